@@ -1,28 +1,66 @@
-use std::{num::NonZeroUsize, time::Duration};
+use std::{
+    collections::HashSet,
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
+use base64::Engine;
 use lru::LruCache;
+use rand::seq::SliceRandom;
 use sdk::slot_subscriber::SlotSubscriber;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
     signature::{Keypair, Signature},
-    transaction::VersionedTransaction,
+    signer::Signer,
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
 };
 
-use crate::types::JitoStrategy;
+use crate::{config::GlobalConfig, types::JitoStrategy};
+
+const DEFAULT_BLOCK_ENGINE_URL: &str = "https://mainnet.block-engine.jito.wtf";
+const DEFAULT_TIP_FLOOR_URL: &str = "https://bundles.jito.wtf/api/v1/bundles/tip_floor";
+/// Jito tip distribution program, a validator running jito-solana creates a
+/// `TipDistributionAccount` for its vote account every epoch
+const TIP_DISTRIBUTION_PROGRAM_ID: &str = "4R3gSG8BpU4t19KYj8CfnbtRpnT8gtk4dvTHxVRwc2r7";
+const TIP_DISTRIBUTION_ACCOUNT_SEED: &[u8] = b"TIP_DISTRIBUTION_ACCOUNT";
 
+const LEADER_SCHEDULE_INTERVAL_MS: u64 = 1_000;
+const CHECK_BUNDLE_RESULTS_INTERVAL_MS: u64 = 2_000;
+const TIP_FLOOR_INTERVAL_MS: u64 = 10_000;
+/// how far ahead to look in the leader schedule for a jito leader
+const LEADER_SCHEDULE_LOOKAHEAD_SLOTS: u64 = 1_000;
+/// block engine accepts at most 5 bundle ids per status request
+const MAX_BUNDLE_IDS_PER_STATUS_REQUEST: usize = 5;
+/// max accounts per getMultipleAccounts request
+const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+/// max signatures per getSignatureStatuses request
+const MAX_SIGNATURES_PER_STATUS_REQUEST: usize = 256;
+/// a tx that hasn't landed this long after its bundle result is considered expired
+const SENT_TX_EXPIRY: Duration = Duration::from_secs(90);
+
+/// Tip percentiles of recently landed bundles, as reported by the jito tip floor endpoint
 #[allow(dead_code)]
-struct TipStream {
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct TipStream {
     time: String,
-    ts: u16,
-    landed_tips_25th_percentile: u16,     // in SOL
-    landed_tips_50th_percentile: u16,     // in SOL
-    landed_tips_75th_percentile: u16,     // in SOL
-    landed_tips_95th_percentile: u16,     // in SOL
-    landed_tips_99th_percentile: u16,     // in SOL
-    ema_landed_tips_50th_percentile: u16, // in SOL
+    landed_tips_25th_percentile: f64,     // in SOL
+    landed_tips_50th_percentile: f64,     // in SOL
+    landed_tips_75th_percentile: f64,     // in SOL
+    landed_tips_95th_percentile: f64,     // in SOL
+    landed_tips_99th_percentile: f64,     // in SOL
+    ema_landed_tips_50th_percentile: f64, // in SOL
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
 enum DropReason {
     Pruned,
     BlockhashExpired,
@@ -30,60 +68,239 @@ enum DropReason {
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone)]
 struct JitoLeader {
     current_slot: u64,
     next_leader_slot: u64,
     next_leader_identity: String,
 }
 
-#[allow(dead_code)]
 struct Bundle {
     tx: String,
-    ts: u16,
+    ts: Instant,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Clone, Default)]
 pub(crate) struct BundleStats {
-    accepted: u16,
-    state_auction_bid_rejected: u16,
-    winning_batch_bid_rejected: u16,
-    simulation_failure: u16,
-    internal_error: u16,
-    dropped_bundle: u16,
+    pub(crate) accepted: u16,
+    pub(crate) state_auction_bid_rejected: u16,
+    pub(crate) winning_batch_bid_rejected: u16,
+    pub(crate) simulation_failure: u16,
+    pub(crate) internal_error: u16,
+    pub(crate) dropped_bundle: u16,
 
     /// extra stats
-    dropped_pruned: u16,
-    dropped_blockhash_expired: u16,
-    dropped_blockhash_bot_found: u16,
+    pub(crate) dropped_pruned: u16,
+    pub(crate) dropped_blockhash_expired: u16,
+    pub(crate) dropped_blockhash_not_found: u16,
 }
 
-#[allow(dead_code)]
-pub struct BundleSender {
-    // ws: Option<WebSocket>,
-    // searcher_client: SearcherClient,
-    leader_schedule_interval_id: Option<Duration>,
-    check_sent_txs_interval_id: Option<Duration>,
-    is_subscribed: bool,
-    shutting_down: bool,
+/// Status of a bundle as reported by the block engine `getInflightBundleStatuses` method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum InflightBundleStatus {
+    /// bundle id not found in the system (5 minute look back)
+    Invalid,
+    /// not failed, not landed yet
+    Pending,
+    /// all regions marked the bundle as failed and it was not forwarded
+    Failed,
+    /// landed on-chain
+    Landed,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InflightBundleResult {
+    bundle_id: String,
+    status: InflightBundleStatus,
+    landed_slot: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcContextValue<T> {
+    value: T,
+}
+
+/// Minimal client for the block engine JSON-RPC bundle API
+#[derive(Clone)]
+struct SearcherClient {
+    client: reqwest::Client,
+    bundles_url: String,
+    tip_floor_url: String,
+}
+
+impl SearcherClient {
+    fn new(block_engine_url: &str, tip_floor_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            bundles_url: format!("{}/api/v1/bundles", block_engine_url.trim_end_matches('/')),
+            tip_floor_url: tip_floor_url.to_string(),
+        }
+    }
+
+    async fn request<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, String> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response = self
+            .client
+            .post(&self.bundles_url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("{method} request failed: {e}"))?;
+
+        let status = response.status();
+        let body_text = response
+            .text()
+            .await
+            .map_err(|e| format!("{method} failed to read body: {e}"))?;
+        if !status.is_success() {
+            return Err(format!("{method} failed with status {status}: {body_text}"));
+        }
+
+        let mut response: Value = serde_json::from_str(&body_text)
+            .map_err(|e| format!("{method} invalid response: {e}"))?;
+        if let Some(error) = response.get("error") {
+            return Err(format!("{method} returned error: {error}"));
+        }
+
+        serde_json::from_value(response["result"].take())
+            .map_err(|e| format!("{method} invalid result: {e}"))
+    }
+
+    async fn get_tip_accounts(&self) -> Result<Vec<Pubkey>, String> {
+        let accounts: Vec<String> = self.request("getTipAccounts", json!([])).await?;
+        accounts
+            .iter()
+            .map(|a| Pubkey::from_str(a).map_err(|e| format!("invalid tip account {a}: {e}")))
+            .collect()
+    }
+
+    /// Submit base64 encoded txs as a bundle, returns the bundle id
+    async fn send_bundle(&self, txs: &[String]) -> Result<String, String> {
+        self.request("sendBundle", json!([txs, { "encoding": "base64" }]))
+            .await
+    }
+
+    async fn get_inflight_bundle_statuses(
+        &self,
+        bundle_ids: &[String],
+    ) -> Result<Vec<InflightBundleResult>, String> {
+        let result: RpcContextValue<Vec<InflightBundleResult>> = self
+            .request("getInflightBundleStatuses", json!([bundle_ids]))
+            .await?;
+        Ok(result.value)
+    }
+
+    async fn get_tip_floor(&self) -> Result<Option<TipStream>, String> {
+        let response = self
+            .client
+            .get(&self.tip_floor_url)
+            .send()
+            .await
+            .map_err(|e| format!("tip floor request failed: {e}"))?;
+
+        let status = response.status();
+        let body_text = response
+            .text()
+            .await
+            .map_err(|e| format!("tip floor failed to read body: {e}"))?;
+        if !status.is_success() {
+            return Err(format!(
+                "tip floor failed with status {status}: {body_text}"
+            ));
+        }
+
+        let tips: Vec<TipStream> = serde_json::from_str(&body_text)
+            .map_err(|e| format!("tip floor invalid response: {e}"))?;
+        Ok(tips.into_iter().next())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleSenderConfig {
+    /// block engine base url, `/api/v1/bundles` is appended for JSON-RPC requests
+    pub block_engine_url: String,
+    /// endpoint returning the latest `TipStream` percentiles
+    pub tip_floor_url: String,
+    pub strategy: JitoStrategy,
+    /// lamports
+    pub min_bundle_tip: u64,
+    /// lamports
+    pub max_bundle_tip: u64,
+    pub max_fail_bundle_count: u16,
+    pub tip_multiplier: u16,
+}
+
+impl Default for BundleSenderConfig {
+    fn default() -> Self {
+        Self {
+            block_engine_url: DEFAULT_BLOCK_ENGINE_URL.to_string(),
+            tip_floor_url: DEFAULT_TIP_FLOOR_URL.to_string(),
+            strategy: JitoStrategy::JitoOnly,
+            min_bundle_tip: 10_000,
+            max_bundle_tip: 100_000,
+            max_fail_bundle_count: 100,
+            tip_multiplier: 3,
+        }
+    }
+}
+
+impl From<&GlobalConfig> for BundleSenderConfig {
+    fn from(config: &GlobalConfig) -> Self {
+        let default = Self::default();
+        Self {
+            block_engine_url: config
+                .jito_block_engine_url
+                .clone()
+                .unwrap_or(default.block_engine_url),
+            tip_floor_url: default.tip_floor_url,
+            strategy: config.jito_strategy.unwrap_or(default.strategy),
+            min_bundle_tip: config
+                .jito_min_bundle_tip
+                .map(u64::from)
+                .unwrap_or(default.min_bundle_tip),
+            max_bundle_tip: config
+                .jito_max_bundle_tip
+                .map(u64::from)
+                .unwrap_or(default.max_bundle_tip),
+            max_fail_bundle_count: config
+                .jito_max_bundle_fail_count
+                .unwrap_or(default.max_fail_bundle_count),
+            tip_multiplier: config.jito_tip_multiplier.unwrap_or(default.tip_multiplier),
+        }
+    }
+}
+
+/// Mutable sender state, shared with the background refresh task
+struct BundleSenderState {
     jito_tip_accounts: Vec<Pubkey>,
+    /// identities of validators running jito-solana, refreshed every epoch
+    jito_leader_identities: HashSet<Pubkey>,
+    jito_leader_identities_epoch: Option<u64>,
     next_jito_leader: Option<JitoLeader>,
-    updating_jito_schedule: bool,
-    checking_sent_txs: bool,
 
-    /// if there is a big difference, probably jito ws connection is bad, should resub
+    /// if there is a big difference, probably jito connection is bad, should resub
     bundles_sent: u16,
 
     bundle_results_received: u16,
 
-    /// `bundleIdToTx` will be populated immediately after sending a bundle.
+    /// `bundle_id_to_tx` will be populated immediately after sending a bundle.
     bundle_id_to_tx: LruCache<String, Bundle>,
 
     /// `sent_tx_cache` will only be populated after a bundle result is received.
     /// reason being that sometimes results come really late (like minutes after sending)
     /// unsure if this is a jito issue or this bot is inefficient and holding onto things
     /// for that long. Check txs from this map to see if they landed.
-    sent_tx_cache: LruCache<String, u16>,
+    sent_tx_cache: LruCache<String, Instant>,
 
     /// -1 for each accepted bundle, +1 for each rejected (due to bid, don't count sim errors).
     fail_bundle_count: u16,
@@ -95,73 +312,702 @@ pub struct BundleSender {
     last_tip_stream: Option<TipStream>,
 
     bundle_stats: BundleStats,
+}
+
+impl BundleSenderState {
+    fn new() -> Self {
+        Self {
+            jito_tip_accounts: Vec::new(),
+            jito_leader_identities: HashSet::new(),
+            jito_leader_identities_epoch: None,
+            next_jito_leader: None,
+            bundles_sent: 0,
+            bundle_results_received: 0,
+            bundle_id_to_tx: LruCache::new(NonZeroUsize::new(500).unwrap()),
+            sent_tx_cache: LruCache::new(NonZeroUsize::new(500).unwrap()),
+            fail_bundle_count: 0,
+            count_landed_bundles: 0,
+            count_dropped_bundles: 0,
+            last_tip_stream: None,
+            bundle_stats: BundleStats::default(),
+        }
+    }
+
+    fn handle_bundle_result(&mut self, result: &InflightBundleResult, max_fail_bundle_count: u16) {
+        if result.status == InflightBundleStatus::Pending {
+            return;
+        }
+
+        self.bundle_results_received = self.bundle_results_received.saturating_add(1);
+        let bundle = self.bundle_id_to_tx.pop(&result.bundle_id);
 
-    // connection: Connection,
-    tip_payer_keypair: Keypair,
+        match result.status {
+            InflightBundleStatus::Landed => {
+                self.bundle_stats.accepted = self.bundle_stats.accepted.saturating_add(1);
+                self.count_landed_bundles = self.count_landed_bundles.saturating_add(1);
+                self.fail_bundle_count = self.fail_bundle_count.saturating_sub(1);
+                if let Some(bundle) = bundle {
+                    log::info!(
+                        "bundle {} landed in slot {:?} (tx: {})",
+                        result.bundle_id,
+                        result.landed_slot,
+                        bundle.tx
+                    );
+                    self.sent_tx_cache.put(bundle.tx, bundle.ts);
+                }
+            }
+            InflightBundleStatus::Failed => {
+                // failed bundles lost the auction, bump the tip for the next ones
+                self.bundle_stats.state_auction_bid_rejected = self
+                    .bundle_stats
+                    .state_auction_bid_rejected
+                    .saturating_add(1);
+                self.count_dropped_bundles = self.count_dropped_bundles.saturating_add(1);
+                self.fail_bundle_count = self
+                    .fail_bundle_count
+                    .saturating_add(1)
+                    .min(max_fail_bundle_count);
+            }
+            InflightBundleStatus::Invalid => {
+                self.record_dropped(DropReason::Pruned);
+            }
+            InflightBundleStatus::Pending => {}
+        }
+    }
+
+    fn record_dropped(&mut self, reason: DropReason) {
+        let stats = &mut self.bundle_stats;
+        stats.dropped_bundle = stats.dropped_bundle.saturating_add(1);
+        match reason {
+            DropReason::Pruned => {
+                stats.dropped_pruned = stats.dropped_pruned.saturating_add(1);
+            }
+            DropReason::BlockhashExpired => {
+                stats.dropped_blockhash_expired = stats.dropped_blockhash_expired.saturating_add(1);
+            }
+            DropReason::BlockhashNotFound => {
+                stats.dropped_blockhash_not_found =
+                    stats.dropped_blockhash_not_found.saturating_add(1);
+            }
+        }
+        self.count_dropped_bundles = self.count_dropped_bundles.saturating_add(1);
+    }
+}
+
+/// Sends transactions to the jito block engine as bundles with a tip transfer attached
+pub struct BundleSender {
+    searcher_client: SearcherClient,
+    rpc_client: Arc<RpcClient>,
+    tip_payer_keypair: Arc<Keypair>,
     slot_subscriber: SlotSubscriber,
+    state: Arc<Mutex<BundleSenderState>>,
+    is_subscribed: bool,
+    unsubscriber: Option<tokio::sync::mpsc::Sender<()>>,
 
     /// tip algo params
     pub(crate) strategy: JitoStrategy,
 
-    // cant be lower than this
-    min_bundle_tip: u16,
+    /// cant be lower than this, lamports
+    min_bundle_tip: u64,
 
+    /// lamports
     max_bundle_tip: u64,
 
     max_fail_bundle_count: u16,
-    // bigger == more superlinear, delay the ramp up to prevent overpaying too soon
+    /// bigger == more superlinear, delay the ramp up to prevent overpaying too soon
     tip_multiplier: u16,
 }
 
 impl BundleSender {
-    pub fn new(tip_payer_keypair: Keypair, slot_subscriber: SlotSubscriber) -> Self {
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        tip_payer_keypair: Keypair,
+        slot_subscriber: SlotSubscriber,
+        config: BundleSenderConfig,
+    ) -> Self {
         Self {
-            leader_schedule_interval_id: None,
-            check_sent_txs_interval_id: None,
-            is_subscribed: false,
-            shutting_down: false,
-            jito_tip_accounts: Vec::new(),
-            next_jito_leader: None,
-            updating_jito_schedule: false,
-            checking_sent_txs: false,
-            bundles_sent: 0,
-            bundle_results_received: 0,
-            bundle_id_to_tx: LruCache::new(NonZeroUsize::new(500).unwrap()),
-            sent_tx_cache: LruCache::new(NonZeroUsize::new(500).unwrap()),
-            fail_bundle_count: 0,
-            count_landed_bundles: 0,
-            count_dropped_bundles: 0,
-            last_tip_stream: None,
-            bundle_stats: BundleStats::default(),
-            tip_payer_keypair,
+            searcher_client: SearcherClient::new(&config.block_engine_url, &config.tip_floor_url),
+            rpc_client,
+            tip_payer_keypair: Arc::new(tip_payer_keypair),
             slot_subscriber,
-            strategy: JitoStrategy::JitoOnly,
-            min_bundle_tip: 10_000,
-            max_bundle_tip: 100_000,
-            max_fail_bundle_count: 100,
-            tip_multiplier: 3,
+            state: Arc::new(Mutex::new(BundleSenderState::new())),
+            is_subscribed: false,
+            unsubscriber: None,
+            strategy: config.strategy,
+            min_bundle_tip: config.min_bundle_tip,
+            max_bundle_tip: config.max_bundle_tip.max(config.min_bundle_tip),
+            max_fail_bundle_count: config.max_fail_bundle_count.max(1),
+            tip_multiplier: config.tip_multiplier,
         }
     }
 
-    pub fn slots_until_next_leader(&self) -> Option<u64> {
-        match &self.next_jito_leader {
-            Some(leader) => Some(leader.next_leader_slot - self.slot_subscriber.current_slot()),
-            None => None,
+    /// Fetch tip accounts and the leader schedule, then keep them, the tip floor and bundle
+    /// results up to date in the background
+    pub async fn subscribe(&mut self) -> Result<(), String> {
+        if self.is_subscribed {
+            return Ok(());
+        }
+
+        let tip_accounts = self.searcher_client.get_tip_accounts().await?;
+        if tip_accounts.is_empty() {
+            return Err(String::from("block engine returned no tip accounts"));
+        }
+        self.state.lock().unwrap().jito_tip_accounts = tip_accounts;
+
+        if let Err(e) =
+            update_jito_leader(&self.rpc_client, &self.slot_subscriber, &self.state).await
+        {
+            log::warn!("failed to load jito leader schedule: {e}");
+        }
+        update_tip_floor(&self.searcher_client, &self.state).await;
+
+        let (unsub_tx, mut unsub_rx) = tokio::sync::mpsc::channel::<()>(1);
+        self.unsubscriber = Some(unsub_tx);
+
+        let searcher_client = self.searcher_client.clone();
+        let rpc_client = self.rpc_client.clone();
+        let slot_subscriber = self.slot_subscriber.clone();
+        let state = self.state.clone();
+        let max_fail_bundle_count = self.max_fail_bundle_count;
+
+        tokio::spawn(async move {
+            let mut leader_interval =
+                tokio::time::interval(Duration::from_millis(LEADER_SCHEDULE_INTERVAL_MS));
+            let mut results_interval =
+                tokio::time::interval(Duration::from_millis(CHECK_BUNDLE_RESULTS_INTERVAL_MS));
+            let mut tip_floor_interval =
+                tokio::time::interval(Duration::from_millis(TIP_FLOOR_INTERVAL_MS));
+
+            loop {
+                tokio::select! {
+                    _ = leader_interval.tick() => {
+                        if let Err(e) = update_jito_leader(&rpc_client, &slot_subscriber, &state).await {
+                            log::warn!("failed to update jito leader schedule: {e}");
+                        }
+                    }
+                    _ = results_interval.tick() => {
+                        check_bundle_results(&searcher_client, &state, max_fail_bundle_count).await;
+                        check_sent_txs(&rpc_client, &state).await;
+                    }
+                    _ = tip_floor_interval.tick() => {
+                        update_tip_floor(&searcher_client, &state).await;
+                    }
+                    _ = unsub_rx.recv() => {
+                        log::debug!("Unsubscribing bundle sender.");
+                        break;
+                    }
+                }
+            }
+        });
+
+        self.is_subscribed = true;
+
+        Ok(())
+    }
+
+    pub async fn unsubscribe(&mut self) -> Result<(), String> {
+        if let Some(unsubscriber) = self.unsubscriber.take() {
+            unsubscriber.send(()).await.map_err(|e| e.to_string())?;
         }
+        self.is_subscribed = false;
+
+        Ok(())
+    }
+
+    pub fn slots_until_next_leader(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state.next_jito_leader.as_ref().map(|leader| {
+            let current_slot = self.slot_subscriber.current_slot().max(leader.current_slot);
+            leader.next_leader_slot.saturating_sub(current_slot)
+        })
+    }
+
+    pub(crate) fn bundle_stats(&self) -> BundleStats {
+        self.state.lock().unwrap().bundle_stats.clone()
+    }
+
+    /// Returns (bundles sent, bundle results received, landed, dropped)
+    pub(crate) fn bundle_counts(&self) -> (u16, u16, u16, u16) {
+        let state = self.state.lock().unwrap();
+        (
+            state.bundles_sent,
+            state.bundle_results_received,
+            state.count_landed_bundles,
+            state.count_dropped_bundles,
+        )
+    }
+
+    /// Tip to attach to the next bundle in lamports
+    pub(crate) fn calculate_current_tip_amount(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        calculate_tip_amount(
+            state.last_tip_stream.as_ref(),
+            state.fail_bundle_count,
+            self.min_bundle_tip,
+            self.max_bundle_tip,
+            self.max_fail_bundle_count,
+            self.tip_multiplier,
+        )
     }
 
     /// Alternatively, don't create the bundle now, but batch them and send them together with 1
     /// tip.
     pub(crate) async fn send_transaction(
         &self,
-        _signed_tx: &VersionedTransaction,
-        _metadata: Option<String>,
-        _tx_sig: Option<Signature>,
+        signed_tx: &VersionedTransaction,
+        metadata: Option<String>,
+        tx_sig: Option<Signature>,
     ) {
         if !self.is_subscribed {
             log::warn!("You should call bundle_sender.subscribe() before send_transaction()");
         }
 
-        todo!()
+        let tip_account = {
+            let state = self.state.lock().unwrap();
+            state
+                .jito_tip_accounts
+                .choose(&mut rand::thread_rng())
+                .copied()
+        };
+        let Some(tip_account) = tip_account else {
+            log::error!("No jito tip accounts loaded, can't send bundle");
+            return;
+        };
+
+        let tip_amount = self.calculate_current_tip_amount();
+        let payer = self.tip_payer_keypair.pubkey();
+        // share the blockhash so the tip can't land without the tx
+        let tip_tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &payer,
+                &tip_account,
+                tip_amount,
+            )],
+            Some(&payer),
+            &[self.tip_payer_keypair.as_ref()],
+            *signed_tx.message.recent_blockhash(),
+        );
+
+        let encoded = match (
+            bincode::serialize(signed_tx),
+            bincode::serialize(&VersionedTransaction::from(tip_tx)),
+        ) {
+            (Ok(tx), Ok(tip_tx)) => vec![
+                base64::engine::general_purpose::STANDARD.encode(tx),
+                base64::engine::general_purpose::STANDARD.encode(tip_tx),
+            ],
+            (Err(e), _) | (_, Err(e)) => {
+                log::error!("Failed to serialize bundle txs: {e}");
+                return;
+            }
+        };
+
+        let tx_sig = tx_sig.unwrap_or(signed_tx.signatures[0]).to_string();
+        let metadata = metadata.unwrap_or_default();
+
+        match self.searcher_client.send_bundle(&encoded).await {
+            Ok(bundle_id) => {
+                log::info!(
+                    "sent bundle {bundle_id} with tip {tip_amount} to {tip_account} (tx: {tx_sig}) {metadata}"
+                );
+                let mut state = self.state.lock().unwrap();
+                state.bundles_sent = state.bundles_sent.saturating_add(1);
+                state.bundle_id_to_tx.put(
+                    bundle_id,
+                    Bundle {
+                        tx: tx_sig,
+                        ts: Instant::now(),
+                    },
+                );
+            }
+            Err(e) => {
+                log::error!("Failed to send bundle (tx: {tx_sig}) {metadata}: {e}");
+                let mut state = self.state.lock().unwrap();
+                state.bundle_stats.internal_error =
+                    state.bundle_stats.internal_error.saturating_add(1);
+            }
+        }
+    }
+}
+
+/// Ramp the tip from the landed tips floor towards `max_bundle_tip` as bundles keep failing
+fn calculate_tip_amount(
+    last_tip_stream: Option<&TipStream>,
+    fail_bundle_count: u16,
+    min_bundle_tip: u64,
+    max_bundle_tip: u64,
+    max_fail_bundle_count: u16,
+    tip_multiplier: u16,
+) -> u64 {
+    let landed_tips_floor = last_tip_stream
+        .map(|t| (t.landed_tips_25th_percentile * LAMPORTS_PER_SOL as f64) as u64)
+        .unwrap_or(0);
+    let mut tip = landed_tips_floor.max(min_bundle_tip);
+
+    if fail_bundle_count > 0 {
+        let fail_ratio = fail_bundle_count.min(max_fail_bundle_count) as f64
+            / max_fail_bundle_count.max(1) as f64;
+        let ramp = (fail_ratio.powi(tip_multiplier as i32) * max_bundle_tip as f64) as u64;
+        tip = tip.max(ramp);
+    }
+
+    tip.clamp(min_bundle_tip, max_bundle_tip)
+}
+
+async fn update_tip_floor(searcher_client: &SearcherClient, state: &Mutex<BundleSenderState>) {
+    match searcher_client.get_tip_floor().await {
+        Ok(Some(tip_stream)) => state.lock().unwrap().last_tip_stream = Some(tip_stream),
+        Ok(None) => {}
+        Err(e) => log::warn!("failed to update jito tip floor: {e}"),
+    }
+}
+
+/// Find the next slot led by a jito-solana validator
+async fn update_jito_leader(
+    rpc_client: &RpcClient,
+    slot_subscriber: &SlotSubscriber,
+    state: &Mutex<BundleSenderState>,
+) -> Result<(), String> {
+    let epoch_info = rpc_client
+        .get_epoch_info()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let identities_epoch = state.lock().unwrap().jito_leader_identities_epoch;
+    if identities_epoch != Some(epoch_info.epoch) {
+        let identities = fetch_jito_leader_identities(rpc_client, epoch_info.epoch).await?;
+        log::info!(
+            "loaded {} jito validators for epoch {}",
+            identities.len(),
+            epoch_info.epoch
+        );
+        let mut state = state.lock().unwrap();
+        state.jito_leader_identities = identities;
+        state.jito_leader_identities_epoch = Some(epoch_info.epoch);
+    }
+
+    let current_slot = match slot_subscriber.current_slot() {
+        0 => epoch_info.absolute_slot,
+        slot => slot,
+    };
+    let leaders = rpc_client
+        .get_slot_leaders(current_slot, LEADER_SCHEDULE_LOOKAHEAD_SLOTS)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut state = state.lock().unwrap();
+    let next_jito_leader = leaders
+        .iter()
+        .position(|leader| state.jito_leader_identities.contains(leader))
+        .map(|offset| JitoLeader {
+            current_slot,
+            next_leader_slot: current_slot + offset as u64,
+            next_leader_identity: leaders[offset].to_string(),
+        });
+    state.next_jito_leader = next_jito_leader;
+
+    Ok(())
+}
+
+/// A validator runs jito-solana if its vote account has a tip distribution account this epoch
+async fn fetch_jito_leader_identities(
+    rpc_client: &RpcClient,
+    epoch: u64,
+) -> Result<HashSet<Pubkey>, String> {
+    let tip_distribution_program = Pubkey::from_str(TIP_DISTRIBUTION_PROGRAM_ID).unwrap();
+    let vote_accounts = rpc_client
+        .get_vote_accounts_with_commitment(CommitmentConfig::confirmed())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let validators: Vec<(Pubkey, Pubkey)> = vote_accounts
+        .current
+        .iter()
+        .chain(vote_accounts.delinquent.iter())
+        .filter_map(|v| {
+            let vote = Pubkey::from_str(&v.vote_pubkey).ok()?;
+            let identity = Pubkey::from_str(&v.node_pubkey).ok()?;
+            Some((vote, identity))
+        })
+        .collect();
+
+    let mut identities = HashSet::new();
+    for chunk in validators.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+        let tip_distribution_accounts: Vec<Pubkey> = chunk
+            .iter()
+            .map(|(vote, _)| {
+                Pubkey::find_program_address(
+                    &[
+                        TIP_DISTRIBUTION_ACCOUNT_SEED,
+                        vote.as_ref(),
+                        &epoch.to_le_bytes(),
+                    ],
+                    &tip_distribution_program,
+                )
+                .0
+            })
+            .collect();
+
+        let accounts = rpc_client
+            .get_multiple_accounts(&tip_distribution_accounts)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for ((_, identity), account) in chunk.iter().zip(accounts) {
+            if account.is_some_and(|a| a.owner == tip_distribution_program) {
+                identities.insert(*identity);
+            }
+        }
+    }
+
+    Ok(identities)
+}
+
+async fn check_bundle_results(
+    searcher_client: &SearcherClient,
+    state: &Mutex<BundleSenderState>,
+    max_fail_bundle_count: u16,
+) {
+    let bundle_ids: Vec<String> = {
+        let state = state.lock().unwrap();
+        state
+            .bundle_id_to_tx
+            .iter()
+            .map(|(id, _)| id.clone())
+            .collect()
+    };
+
+    for ids in bundle_ids.chunks(MAX_BUNDLE_IDS_PER_STATUS_REQUEST) {
+        match searcher_client.get_inflight_bundle_statuses(ids).await {
+            Ok(results) => {
+                let mut state = state.lock().unwrap();
+                for result in &results {
+                    state.handle_bundle_result(result, max_fail_bundle_count);
+                }
+            }
+            Err(e) => {
+                log::warn!("failed to get bundle statuses: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Drop txs of landed bundles from the cache once confirmed, count the ones that never landed
+async fn check_sent_txs(rpc_client: &RpcClient, state: &Mutex<BundleSenderState>) {
+    let sent_txs: Vec<(String, Signature, Instant)> = {
+        let state = state.lock().unwrap();
+        state
+            .sent_tx_cache
+            .iter()
+            .filter_map(|(sig, ts)| Some((sig.clone(), Signature::from_str(sig).ok()?, *ts)))
+            .collect()
+    };
+    if sent_txs.is_empty() {
+        return;
+    }
+
+    for sent_txs in sent_txs.chunks(MAX_SIGNATURES_PER_STATUS_REQUEST) {
+        let signatures: Vec<Signature> = sent_txs.iter().map(|(_, sig, _)| *sig).collect();
+        let statuses = match rpc_client.get_signature_statuses(&signatures).await {
+            Ok(response) => response.value,
+            Err(e) => {
+                log::warn!("failed to get sent tx statuses: {e}");
+                return;
+            }
+        };
+
+        let mut state = state.lock().unwrap();
+        for ((sig, _, ts), status) in sent_txs.iter().zip(statuses) {
+            if status.is_some() {
+                state.sent_tx_cache.pop(sig);
+            } else if ts.elapsed() > SENT_TX_EXPIRY {
+                state.sent_tx_cache.pop(sig);
+                state.record_dropped(DropReason::BlockhashExpired);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdk::http_stub;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    /// Serve JSON-RPC requests with `respond(method, params)`, forwarding each request body to
+    /// the returned receiver
+    async fn stub_block_engine(
+        respond: fn(&str, &Value) -> Value,
+    ) -> (String, mpsc::UnboundedReceiver<Value>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let url = http_stub::serve(move |request| {
+            let body = request.json();
+            let result = respond(body["method"].as_str().unwrap_or_default(), &body["params"]);
+            let _ = tx.send(body);
+            Some(json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string())
+        })
+        .await;
+        (url, rx)
+    }
+
+    fn tip_stream(landed_tips_25th_percentile: f64) -> TipStream {
+        TipStream {
+            time: String::new(),
+            landed_tips_25th_percentile,
+            landed_tips_50th_percentile: 0.0,
+            landed_tips_75th_percentile: 0.0,
+            landed_tips_95th_percentile: 0.0,
+            landed_tips_99th_percentile: 0.0,
+            ema_landed_tips_50th_percentile: 0.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn get_tip_accounts() {
+        let (url, mut requests) = stub_block_engine(|method, _| {
+            assert_eq!(method, "getTipAccounts");
+            json!([
+                "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+                "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"
+            ])
+        })
+        .await;
+        let client = SearcherClient::new(&url, "");
+
+        let accounts = client.get_tip_accounts().await.unwrap();
+        assert_eq!(
+            accounts,
+            vec![
+                Pubkey::from_str("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5").unwrap(),
+                Pubkey::from_str("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe").unwrap(),
+            ]
+        );
+        assert_eq!(requests.recv().await.unwrap()["params"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn send_bundle() {
+        let (url, mut requests) = stub_block_engine(|method, _| {
+            assert_eq!(method, "sendBundle");
+            json!("bundle-1")
+        })
+        .await;
+        let client = SearcherClient::new(&url, "");
+
+        let txs = vec![String::from("dHgx"), String::from("dGlw")];
+        assert_eq!(client.send_bundle(&txs).await.unwrap(), "bundle-1");
+        assert_eq!(
+            requests.recv().await.unwrap()["params"],
+            json!([["dHgx", "dGlw"], { "encoding": "base64" }])
+        );
+    }
+
+    #[tokio::test]
+    async fn get_inflight_bundle_statuses() {
+        let (url, mut requests) = stub_block_engine(|method, _| {
+            assert_eq!(method, "getInflightBundleStatuses");
+            json!({
+                "context": { "slot": 100 },
+                "value": [
+                    { "bundle_id": "landed", "status": "Landed", "landed_slot": 99 },
+                    { "bundle_id": "pending", "status": "Pending", "landed_slot": null },
+                    { "bundle_id": "failed", "status": "Failed", "landed_slot": null }
+                ]
+            })
+        })
+        .await;
+        let client = SearcherClient::new(&url, "");
+
+        let ids = vec![
+            String::from("landed"),
+            String::from("pending"),
+            String::from("failed"),
+        ];
+        let results = client.get_inflight_bundle_statuses(&ids).await.unwrap();
+        assert_eq!(
+            requests.recv().await.unwrap()["params"],
+            json!([["landed", "pending", "failed"]])
+        );
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].status, InflightBundleStatus::Landed);
+        assert_eq!(results[0].landed_slot, Some(99));
+        assert_eq!(results[1].status, InflightBundleStatus::Pending);
+        assert_eq!(results[2].status, InflightBundleStatus::Failed);
+
+        let mut state = BundleSenderState::new();
+        for id in &ids {
+            state.bundle_id_to_tx.put(
+                id.clone(),
+                Bundle {
+                    tx: format!("{id}-tx"),
+                    ts: Instant::now(),
+                },
+            );
+        }
+        for result in &results {
+            state.handle_bundle_result(result, 10);
+        }
+        assert_eq!(state.bundle_results_received, 2);
+        assert_eq!(state.count_landed_bundles, 1);
+        assert_eq!(state.fail_bundle_count, 1);
+        assert!(state.sent_tx_cache.contains("landed-tx"));
+        // pending bundles are checked again later
+        assert!(state.bundle_id_to_tx.contains("pending"));
+    }
+
+    #[tokio::test]
+    async fn block_engine_error() {
+        let (url, _requests) = stub_block_engine(|_, _| Value::Null).await;
+        let client = SearcherClient::new(&url, "");
+
+        // null result doesn't decode as a bundle id
+        assert!(client.send_bundle(&[]).await.is_err());
+    }
+
+    #[test]
+    fn tip_amount_uses_floor_and_bounds() {
+        // no tip stream or failures, min tip
+        assert_eq!(
+            calculate_tip_amount(None, 0, 10_000, 100_000, 100, 3),
+            10_000
+        );
+        // landed tips floor above the min
+        let stream = tip_stream(0.000030517578125);
+        assert_eq!(
+            calculate_tip_amount(Some(&stream), 0, 10_000, 100_000, 100, 3),
+            30_517
+        );
+        // floor above the max is capped
+        let stream = tip_stream(1.0);
+        assert_eq!(
+            calculate_tip_amount(Some(&stream), 0, 10_000, 100_000, 100, 3),
+            100_000
+        );
+    }
+
+    #[test]
+    fn tip_amount_ramps_with_failures() {
+        // (50 / 100)^2 * 100_000
+        assert_eq!(
+            calculate_tip_amount(None, 50, 10_000, 100_000, 100, 2),
+            25_000
+        );
+        // small ramp doesn't go below the min
+        assert_eq!(
+            calculate_tip_amount(None, 1, 10_000, 100_000, 100, 2),
+            10_000
+        );
+        // failures past the max count are clamped
+        assert_eq!(
+            calculate_tip_amount(None, 500, 10_000, 100_000, 100, 2),
+            100_000
+        );
+        // bigger multiplier ramps slower
+        assert!(
+            calculate_tip_amount(None, 50, 10_000, 100_000, 100, 3)
+                < calculate_tip_amount(None, 50, 10_000, 100_000, 100, 2)
+        );
     }
 }
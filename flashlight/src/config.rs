@@ -1,17 +1,40 @@
-use sdk::types::Context as DriftEnv;
-use solana_sdk::pubkey::Pubkey;
-
-use crate::types::JitoStrategy;
-
-#[allow(dead_code)]
-#[derive(Debug)]
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use clap::Args;
+use drift::state::user::MarketType;
+use sdk::{
+    tx::tx_sender::{
+        FastTxSender, RetryTxSender, TxSender, TxSenderConnections, WhileValidTxSender,
+    },
+    types::Context as DriftEnv,
+};
+use serde::{de::IgnoredAny, Deserialize, Deserializer};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use thiserror::Error;
+
+use crate::{
+    types::JitoStrategy,
+    util::{valid_minimum_gas_amount, valid_rebalance_settled_pnl_threshold},
+};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum TxSenderType {
     Fast,
     Retry,
     WhileValid,
 }
 
-#[derive(Debug, Clone, Default)]
+/// `deny_unknown_fields` only takes effect where the config isn't flattened into a bot config,
+/// those collect unknown keys into `unknown_fields` instead which are rejected on load
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
 pub struct BaseBotConfig {
     pub bot_id: String,
 
@@ -22,8 +45,10 @@ pub struct BaseBotConfig {
     pub run_once: Option<bool>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct FillerConfig {
+    #[serde(flatten)]
     pub base_config: BaseBotConfig,
 
     pub filler_polling_interval: Option<u16>,
@@ -32,15 +57,103 @@ pub struct FillerConfig {
 
     pub simulate_tx_for_cu_estimate: Option<bool>,
 
+    /// settle the filler's own perp pnl and swap USDC to SOL when low on gas (mainnet only),
+    /// defaults to `global.rebalanceFiller`
     pub rebalance_filler: Option<bool>,
 
+    /// min USDC to withdraw and swap to SOL when rebalancing, whole number, default: 20
     pub rebalance_settled_pnl_threshold: Option<f64>,
 
     pub min_gas_balance_to_fill: Option<f64>,
+
+    /// keys not matching any field, serde doesn't support `deny_unknown_fields` with `flatten`
+    #[serde(flatten)]
+    pub unknown_fields: HashMap<String, IgnoredAny>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LiquidatorConfig {
+    #[serde(flatten)]
+    pub base_config: BaseBotConfig,
+
+    /// sub-account that takes over liquidated positions, default: 0
+    pub sub_account_id: Option<u16>,
+
+    /// max perp position (BASE_PRECISION) the liquidator will hold, keyed by perp market index
+    pub max_perp_position_size: HashMap<u16, u64>,
+
+    /// max spot liability (token amount) the liquidator will take over, keyed by spot market index
+    pub max_spot_position_size: HashMap<u16, u128>,
+
+    /// skip liquidations smaller than this notional (QUOTE_PRECISION)
+    pub min_liquidation_size: Option<u64>,
+
+    /// don't close inherited positions after liquidating
+    pub disable_auto_derisking: Option<bool>,
+
+    /// keys not matching any field, serde doesn't support `deny_unknown_fields` with `flatten`
+    #[serde(flatten)]
+    pub unknown_fields: HashMap<String, IgnoredAny>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UserPnlSettlerConfig {
+    #[serde(flatten)]
+    pub base_config: BaseBotConfig,
+
+    /// settle perp positions with at least this much unsettled pnl (USDC), default: 10
+    pub min_pnl_to_settle: Option<f64>,
+
+    /// users settled per tx, default: 4
+    pub max_users_per_tx: Option<usize>,
+
+    /// keys not matching any field, serde doesn't support `deny_unknown_fields` with `flatten`
+    #[serde(flatten)]
+    pub unknown_fields: HashMap<String, IgnoredAny>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JitMarketConfig {
+    pub market_index: u16,
+
+    /// `perp` or `spot`
+    #[serde(deserialize_with = "deserialize_market_type")]
+    pub market_type: MarketType,
+
+    /// max distance of the auction price from oracle to respond at (e.g. 0.001 = 10bps)
+    pub spread: f64,
+
+    /// max absolute position (BASE_PRECISION) to hold in this market
+    pub max_position: u64,
+
+    /// sub-account to make from
+    pub sub_account_id: u16,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JitMakerConfig {
+    #[serde(flatten)]
+    pub base_config: BaseBotConfig,
+
+    pub market_configs: Vec<JitMarketConfig>,
+
+    /// stop adding to positions above this leverage (e.g. 2.0 = 2x), default: 1.0
+    pub max_leverage: Option<f64>,
+
+    /// keys not matching any field, serde doesn't support `deny_unknown_fields` with `flatten`
+    #[serde(flatten)]
+    pub unknown_fields: HashMap<String, IgnoredAny>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
 pub struct GlobalConfig {
+    /// `devnet` or `mainnet-beta`
+    #[serde(deserialize_with = "deserialize_drift_env")]
     pub drift_env: Option<DriftEnv>,
 
     pub endpoint: Option<String>,
@@ -50,18 +163,24 @@ pub struct GlobalConfig {
     /// endpoint to use helius priority fee strategy
     pub helius_endpoint: Option<String>,
 
-    ///onal rpc endpoints to send transactions to
+    /// additional rpc endpoints to send transactions to
     pub additional_send_tx_endpoints: Option<Vec<String>>,
 
-    ///nt to confirm txs on
+    /// endpoint to confirm txs on
     pub tx_confirmation_endpoint: Option<String>,
 
-    ///t metrics port to use, will be overridden by {@link BaseBotConfig.metricsPort} if provided
+    /// default metrics port to use, will be overridden by `BaseBotConfig::metrics_port` if provided
     pub metrics_port: Option<u16>,
 
-    ///e all metrics
+    /// disable all metrics
     pub disable_metrics: Option<bool>,
 
+    /// port to serve `/health` and `/ready` on, default: 8888
+    pub health_check_port: Option<u16>,
+
+    /// file the shared user map is saved to on shutdown and warm started from on startup
+    pub user_map_snapshot: Option<String>,
+
     pub priority_fee_method: Option<String>,
 
     pub max_priority_fee_micro_lamports: Option<u16>,
@@ -70,16 +189,21 @@ pub struct GlobalConfig {
 
     pub priority_fee_multiplier: Option<u16>,
 
-    pub keeper_private_key: Option<Pubkey>,
+    /// base58 string, byte array or path to a keypair file
+    pub keeper_private_key: Option<String>,
 
+    /// initialize the keeper's sub-accounts on startup if they don't exist
     pub init_user: Option<bool>,
 
     pub test_liveness: Option<bool>,
 
+    /// cancel the keeper's open orders on startup
     pub cancel_open_orders: Option<bool>,
 
+    /// close the keeper's perp positions with reduce only market orders on startup
     pub close_open_positions: Option<bool>,
 
+    /// USDC to deposit into the keeper's first sub-account on startup
     pub force_deposit: Option<u16>,
 
     pub websocket: Option<bool>,
@@ -102,7 +226,7 @@ pub struct GlobalConfig {
 
     pub jito_block_engine_url: Option<String>,
 
-    pub jito_auth_private_key: Option<Pubkey>,
+    pub jito_auth_private_key: Option<String>,
 
     pub jito_min_bundle_tip: Option<u16>,
 
@@ -124,3 +248,716 @@ pub struct GlobalConfig {
 
     pub rebalance_filler: Option<bool>,
 }
+
+impl GlobalConfig {
+    /// Build the tx sender selected by `tx_sender_type`, defaults to `Fast`
+    pub fn tx_sender(&self, rpc_client: Arc<RpcClient>) -> Arc<dyn TxSender> {
+        let send_config = RpcSendTransactionConfig {
+            skip_preflight: self.tx_skip_preflight.unwrap_or(false),
+            max_retries: self.tx_max_retries.map(usize::from),
+            ..Default::default()
+        };
+        let connections = TxSenderConnections::new(
+            rpc_client,
+            self.additional_send_tx_endpoints
+                .as_deref()
+                .unwrap_or_default(),
+            self.tx_confirmation_endpoint.as_deref(),
+            send_config,
+        );
+
+        match self.tx_sender_type.unwrap_or(TxSenderType::Fast) {
+            TxSenderType::Fast => Arc::new(FastTxSender::new(connections)),
+            TxSenderType::Retry => Arc::new(RetryTxSender::new(
+                connections,
+                self.tx_retry_timeout_ms
+                    .map(|ms| Duration::from_millis(ms as u64)),
+                None,
+            )),
+            TxSenderType::WhileValid => Arc::new(WhileValidTxSender::new(connections, None)),
+        }
+    }
+}
+
+/// Per-bot configs, a bot is enabled when its section is present
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct BotConfigs {
+    pub filler: Option<FillerConfig>,
+
+    pub spot_filler: Option<FillerConfig>,
+
+    pub liquidator: Option<LiquidatorConfig>,
+
+    pub user_pnl_settler: Option<UserPnlSettlerConfig>,
+
+    pub jit_maker: Option<JitMakerConfig>,
+
+    pub trigger: Option<BaseBotConfig>,
+
+    pub funding_rate_updater: Option<BaseBotConfig>,
+
+    pub if_revenue_settler: Option<BaseBotConfig>,
+
+    pub lp_settler: Option<BaseBotConfig>,
+}
+
+impl BotConfigs {
+    /// Base configs of all enabled bots
+    fn base_configs_mut(&mut self) -> Vec<&mut BaseBotConfig> {
+        let mut configs = Vec::new();
+        if let Some(c) = self.filler.as_mut() {
+            configs.push(&mut c.base_config);
+        }
+        if let Some(c) = self.spot_filler.as_mut() {
+            configs.push(&mut c.base_config);
+        }
+        if let Some(c) = self.liquidator.as_mut() {
+            configs.push(&mut c.base_config);
+        }
+        if let Some(c) = self.user_pnl_settler.as_mut() {
+            configs.push(&mut c.base_config);
+        }
+        if let Some(c) = self.jit_maker.as_mut() {
+            configs.push(&mut c.base_config);
+        }
+        if let Some(c) = self.trigger.as_mut() {
+            configs.push(c);
+        }
+        if let Some(c) = self.funding_rate_updater.as_mut() {
+            configs.push(c);
+        }
+        if let Some(c) = self.if_revenue_settler.as_mut() {
+            configs.push(c);
+        }
+        if let Some(c) = self.lp_settler.as_mut() {
+            configs.push(c);
+        }
+        configs
+    }
+
+    /// Unknown keys of bot configs which flatten `BaseBotConfig`, as (section, key)
+    fn unknown_fields(&self) -> Vec<(&'static str, &str)> {
+        let sections: [(&'static str, Option<&HashMap<String, IgnoredAny>>); 5] = [
+            ("filler", self.filler.as_ref().map(|c| &c.unknown_fields)),
+            (
+                "spotFiller",
+                self.spot_filler.as_ref().map(|c| &c.unknown_fields),
+            ),
+            (
+                "liquidator",
+                self.liquidator.as_ref().map(|c| &c.unknown_fields),
+            ),
+            (
+                "userPnlSettler",
+                self.user_pnl_settler.as_ref().map(|c| &c.unknown_fields),
+            ),
+            (
+                "jitMaker",
+                self.jit_maker.as_ref().map(|c| &c.unknown_fields),
+            ),
+        ];
+        let mut unknown: Vec<(&'static str, &str)> = sections
+            .into_iter()
+            .filter_map(|(section, fields)| Some((section, fields?)))
+            .flat_map(|(section, fields)| fields.keys().map(move |key| (section, key.as_str())))
+            .collect();
+        unknown.sort_unstable();
+        unknown
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse config file {path}: {reason}")]
+    Parse { path: PathBuf, reason: String },
+
+    #[error("unsupported config file extension {0:?}, expected .yaml, .yml or .toml")]
+    UnsupportedFormat(PathBuf),
+
+    #[error("invalid value for {field}: {reason}")]
+    Invalid { field: String, reason: String },
+
+    #[error("missing required config {field} (set it in the config file or {env})")]
+    Missing {
+        field: &'static str,
+        env: &'static str,
+    },
+}
+
+/// Config flags that take precedence over the config file and env vars
+#[derive(Debug, Default, Clone, Args)]
+pub struct ConfigOverrides {
+    /// RPC endpoint
+    #[arg(long, global = true)]
+    pub endpoint: Option<String>,
+
+    /// Websocket endpoint
+    #[arg(long, global = true)]
+    pub ws_endpoint: Option<String>,
+
+    /// Keeper private key, base58 string, byte array or path to a keypair file
+    #[arg(long, global = true)]
+    pub private_key: Option<String>,
+
+    /// `devnet` or `mainnet-beta`
+    #[arg(long, global = true)]
+    pub drift_env: Option<String>,
+
+    /// Don't send txs, applies to every enabled bot
+    #[arg(long, global = true)]
+    pub dry_run: Option<bool>,
+
+    /// Run each bot loop once and exit
+    #[arg(long, global = true)]
+    pub run_once: bool,
+
+    /// Default metrics port
+    #[arg(long, global = true)]
+    pub metrics_port: Option<u16>,
+}
+
+/// Top level config file layout
+///
+/// ```yaml
+/// global:
+///   driftEnv: mainnet-beta
+///   endpoint: https://api.mainnet-beta.solana.com
+/// botConfigs:
+///   filler:
+///     botId: filler
+///     dryRun: false
+///     minGasBalanceToFill: 0.2
+///   liquidator:
+///     botId: liquidator
+///     subAccountId: 1
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct Config {
+    pub global: GlobalConfig,
+
+    pub bot_configs: BotConfigs,
+}
+
+impl Config {
+    /// Load the config file at `path`, the format is picked from the extension
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let parse_error = |reason: String| ConfigError::Parse {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        let config: Self = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str(&contents).map_err(|e| parse_error(e.to_string()))?
+            }
+            Some("toml") => toml::from_str(&contents).map_err(|e| parse_error(e.to_string()))?,
+            _ => return Err(ConfigError::UnsupportedFormat(path.to_path_buf())),
+        };
+        if let Some((section, key)) = config.bot_configs.unknown_fields().first() {
+            return Err(parse_error(format!(
+                "botConfigs.{section}: unknown field `{key}`"
+            )));
+        }
+
+        Ok(config)
+    }
+
+    /// Load the config with overrides applied, in order of precedence: CLI flags, env vars, config file
+    pub fn load(path: Option<&Path>, overrides: &ConfigOverrides) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_overrides(overrides)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Layer env vars and then CLI flags on top of the loaded config
+    pub fn apply_overrides(&mut self, overrides: &ConfigOverrides) -> Result<(), ConfigError> {
+        self.apply_env_overrides()?;
+        self.apply_cli_overrides(overrides)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), ConfigError> {
+        let global = &mut self.global;
+        if let Some(endpoint) = env_var("ENDPOINT").or_else(|| env_var("RPC_URL")) {
+            global.endpoint = Some(endpoint);
+        }
+        if let Some(ws_endpoint) = env_var("WS_ENDPOINT").or_else(|| env_var("WEBSOCKET_URL")) {
+            global.ws_endpoint = Some(ws_endpoint);
+        }
+        if let Some(key) = env_var("KEEPER_PRIVATE_KEY").or_else(|| env_var("PRIVATE_KEY")) {
+            global.keeper_private_key = Some(key);
+        }
+        if let Some(drift_env) = env_var("DRIFT_ENV") {
+            global.drift_env = Some(parse_drift_env(&drift_env)?);
+        }
+        if let Some(url) = env_var("JITO_BLOCK_ENGINE_URL") {
+            global.jito_block_engine_url = Some(url);
+        }
+        if let Some(key) = env_var("JITO_AUTH_PRIVATE_KEY") {
+            global.jito_auth_private_key = Some(key);
+        }
+        if let Some(endpoint) = env_var("TX_CONFIRMATION_ENDPOINT") {
+            global.tx_confirmation_endpoint = Some(endpoint);
+        }
+
+        Ok(())
+    }
+
+    fn apply_cli_overrides(&mut self, overrides: &ConfigOverrides) -> Result<(), ConfigError> {
+        let global = &mut self.global;
+        if let Some(endpoint) = &overrides.endpoint {
+            global.endpoint = Some(endpoint.clone());
+        }
+        if let Some(ws_endpoint) = &overrides.ws_endpoint {
+            global.ws_endpoint = Some(ws_endpoint.clone());
+        }
+        if let Some(key) = &overrides.private_key {
+            global.keeper_private_key = Some(key.clone());
+        }
+        if let Some(drift_env) = &overrides.drift_env {
+            global.drift_env = Some(parse_drift_env(drift_env)?);
+        }
+        if let Some(port) = overrides.metrics_port {
+            global.metrics_port = Some(port);
+        }
+        if overrides.run_once {
+            global.run_once = Some(true);
+        }
+
+        let run_once = global.run_once;
+        for base_config in self.bot_configs.base_configs_mut() {
+            if let Some(dry_run) = overrides.dry_run {
+                base_config.dry_run = dry_run;
+            }
+            base_config.run_once = if overrides.run_once {
+                Some(true)
+            } else {
+                base_config.run_once.or(run_once)
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Check required fields are set and values are in range
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let global = &self.global;
+        if global.endpoint.is_none() {
+            return Err(ConfigError::Missing {
+                field: "global.endpoint",
+                env: "RPC_URL",
+            });
+        }
+        if global.ws_endpoint.is_none() {
+            return Err(ConfigError::Missing {
+                field: "global.wsEndpoint",
+                env: "WEBSOCKET_URL",
+            });
+        }
+        if global.keeper_private_key.is_none() {
+            return Err(ConfigError::Missing {
+                field: "global.keeperPrivateKey",
+                env: "PRIVATE_KEY",
+            });
+        }
+        if let (Some(min), Some(max)) = (global.jito_min_bundle_tip, global.jito_max_bundle_tip) {
+            if min > max {
+                return Err(invalid(
+                    "global.jitoMinBundleTip",
+                    format!("{min} is above jitoMaxBundleTip {max}"),
+                ));
+            }
+        }
+        if matches!(global.tx_sender_type, Some(TxSenderType::Retry))
+            && global.tx_retry_timeout_ms == Some(0)
+        {
+            return Err(invalid(
+                "global.txRetryTimeoutMs",
+                "must be positive for the retry tx sender".to_string(),
+            ));
+        }
+
+        let bots = &self.bot_configs;
+        for (name, filler) in [("filler", &bots.filler), ("spotFiller", &bots.spot_filler)] {
+            let Some(filler) = filler else {
+                continue;
+            };
+            if filler.min_gas_balance_to_fill.is_some()
+                && !valid_minimum_gas_amount(filler.min_gas_balance_to_fill)
+            {
+                return Err(invalid(
+                    &format!("botConfigs.{name}.minGasBalanceToFill"),
+                    format!(
+                        "{:?} must be a non-negative SOL amount",
+                        filler.min_gas_balance_to_fill.unwrap()
+                    ),
+                ));
+            }
+            if filler.rebalance_settled_pnl_threshold.is_some()
+                && !valid_rebalance_settled_pnl_threshold(filler.rebalance_settled_pnl_threshold)
+            {
+                return Err(invalid(
+                    &format!("botConfigs.{name}.rebalanceSettledPnlThreshold"),
+                    format!(
+                        "{:?} must be a whole number of at least 1",
+                        filler.rebalance_settled_pnl_threshold.unwrap()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(settler) = &bots.user_pnl_settler {
+            if settler
+                .min_pnl_to_settle
+                .is_some_and(|min| !min.is_finite() || min < 0.0)
+            {
+                return Err(invalid(
+                    "botConfigs.userPnlSettler.minPnlToSettle",
+                    format!(
+                        "{:?} must be a non-negative USDC amount",
+                        settler.min_pnl_to_settle.unwrap()
+                    ),
+                ));
+            }
+            if settler.max_users_per_tx == Some(0) {
+                return Err(invalid(
+                    "botConfigs.userPnlSettler.maxUsersPerTx",
+                    "must be positive".to_string(),
+                ));
+            }
+        }
+
+        if let Some(jit_maker) = &bots.jit_maker {
+            if jit_maker.market_configs.is_empty() {
+                return Err(invalid(
+                    "botConfigs.jitMaker.marketConfigs",
+                    "at least one market is required".to_string(),
+                ));
+            }
+            for (i, market) in jit_maker.market_configs.iter().enumerate() {
+                if market.spread <= 0.0 {
+                    return Err(invalid(
+                        &format!("botConfigs.jitMaker.marketConfigs[{i}].spread"),
+                        format!("{} must be positive", market.spread),
+                    ));
+                }
+            }
+            if jit_maker.max_leverage.is_some_and(|l| l <= 0.0) {
+                return Err(invalid(
+                    "botConfigs.jitMaker.maxLeverage",
+                    format!("{:?} must be positive", jit_maker.max_leverage.unwrap()),
+                ));
+            }
+        }
+
+        // bots taking on positions must not share a sub-account
+        if let (Some(liquidator), Some(jit_maker)) = (&bots.liquidator, &bots.jit_maker) {
+            let liquidator_sub_account = liquidator.sub_account_id.unwrap_or(0);
+            if jit_maker
+                .market_configs
+                .iter()
+                .any(|m| m.sub_account_id == liquidator_sub_account)
+            {
+                return Err(invalid(
+                    "botConfigs.liquidator.subAccountId",
+                    format!("sub-account {liquidator_sub_account} is also used by jitMaker"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// All sub-accounts the enabled bots trade from, these need to be loaded on the drift client
+    pub fn sub_account_ids(&self) -> Vec<u16> {
+        let mut ids: HashSet<u16> = self
+            .global
+            .subaccounts
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        ids.insert(0);
+        if let Some(liquidator) = &self.bot_configs.liquidator {
+            ids.insert(liquidator.sub_account_id.unwrap_or(0));
+        }
+        if let Some(jit_maker) = &self.bot_configs.jit_maker {
+            ids.extend(jit_maker.market_configs.iter().map(|m| m.sub_account_id));
+        }
+
+        let mut ids: Vec<u16> = ids.into_iter().collect();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+fn invalid(field: &str, reason: String) -> ConfigError {
+    ConfigError::Invalid {
+        field: field.to_string(),
+        reason,
+    }
+}
+
+fn parse_drift_env(value: &str) -> Result<DriftEnv, ConfigError> {
+    match value {
+        "devnet" => Ok(DriftEnv::DevNet),
+        "mainnet-beta" | "mainnet" => Ok(DriftEnv::MainNet),
+        _ => Err(invalid(
+            "driftEnv",
+            format!("{value:?}, expected devnet or mainnet-beta"),
+        )),
+    }
+}
+
+fn deserialize_drift_env<'de, D>(deserializer: D) -> Result<Option<DriftEnv>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_drift_env(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn deserialize_market_type<'de, D>(deserializer: D) -> Result<MarketType, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    match value.to_lowercase().as_str() {
+        "perp" => Ok(MarketType::Perp),
+        "spot" => Ok(MarketType::Spot),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid market type {value:?}, expected perp or spot"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// env vars are process wide, tests reading or setting them run one at a time
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    const ENV_VARS: [&str; 6] = [
+        "ENDPOINT",
+        "RPC_URL",
+        "WS_ENDPOINT",
+        "WEBSOCKET_URL",
+        "KEEPER_PRIVATE_KEY",
+        "PRIVATE_KEY",
+    ];
+
+    fn write_config(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("flashlight-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    const YAML: &str = r#"
+global:
+  driftEnv: devnet
+  endpoint: https://file.rpc
+  wsEndpoint: wss://file.rpc
+  keeperPrivateKey: file-key
+  txSenderType: retry
+botConfigs:
+  filler:
+    botId: filler
+    dryRun: true
+    minGasBalanceToFill: 0.5
+  trigger:
+    botId: trigger
+  jitMaker:
+    botId: jit_maker
+    marketConfigs:
+      - marketIndex: 1
+        marketType: spot
+        spread: 0.002
+        maxPosition: 100
+        subAccountId: 2
+"#;
+
+    const TOML: &str = r#"
+[global]
+driftEnv = "devnet"
+endpoint = "https://file.rpc"
+wsEndpoint = "wss://file.rpc"
+keeperPrivateKey = "file-key"
+txSenderType = "retry"
+
+[botConfigs.filler]
+botId = "filler"
+dryRun = true
+minGasBalanceToFill = 0.5
+
+[botConfigs.trigger]
+botId = "trigger"
+
+[botConfigs.jitMaker]
+botId = "jit_maker"
+
+[[botConfigs.jitMaker.marketConfigs]]
+marketIndex = 1
+marketType = "spot"
+spread = 0.002
+maxPosition = 100
+subAccountId = 2
+"#;
+
+    #[test]
+    fn yaml_and_toml_configs_match() {
+        for (name, contents) in [("config.yaml", YAML), ("config.toml", TOML)] {
+            let path = write_config(name, contents);
+            let config = Config::from_file(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(config.global.endpoint.as_deref(), Some("https://file.rpc"));
+            assert!(matches!(config.global.drift_env, Some(DriftEnv::DevNet)));
+            assert!(matches!(
+                config.global.tx_sender_type,
+                Some(TxSenderType::Retry)
+            ));
+            let filler = config.bot_configs.filler.unwrap();
+            assert_eq!(filler.base_config.bot_id, "filler");
+            assert!(filler.base_config.dry_run);
+            assert_eq!(filler.min_gas_balance_to_fill, Some(0.5));
+            assert!(filler.unknown_fields.is_empty());
+            assert_eq!(config.bot_configs.trigger.unwrap().bot_id, "trigger");
+            let jit_maker = config.bot_configs.jit_maker.unwrap();
+            assert_eq!(jit_maker.market_configs.len(), 1);
+            assert_eq!(jit_maker.market_configs[0].market_type, MarketType::Spot);
+            assert_eq!(jit_maker.market_configs[0].sub_account_id, 2);
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let cases = [
+            ("global.yaml", "global:\n  endpont: https://typo.rpc\n", "endpont"),
+            ("bots.yaml", "botConfigs:\n  fillr:\n    botId: x\n", "fillr"),
+            (
+                "filler.yaml",
+                "botConfigs:\n  filler:\n    botId: x\n    minGasBalanceToFil: 1\n",
+                "minGasBalanceToFil",
+            ),
+            (
+                "trigger.toml",
+                "[botConfigs.trigger]\nbotId = \"x\"\ndryrun = true\n",
+                "dryrun",
+            ),
+            (
+                "market.yaml",
+                "botConfigs:\n  jitMaker:\n    marketConfigs:\n      - marketIndex: 0\n        marketType: perp\n        spread: 0.1\n        maxPosition: 1\n        subAccountId: 1\n        maxLeverage: 2\n",
+                "maxLeverage",
+            ),
+        ];
+        for (name, contents, field) in cases {
+            let path = write_config(name, contents);
+            let result = Config::from_file(&path);
+            fs::remove_file(&path).unwrap();
+
+            match result {
+                Err(ConfigError::Parse { reason, .. }) => {
+                    assert!(reason.contains(field), "{name}: {reason}")
+                }
+                other => panic!("{name}: expected parse error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn env_overrides_file_and_cli_overrides_env() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        for var in ENV_VARS {
+            env::remove_var(var);
+        }
+        let path = write_config("precedence.yaml", YAML);
+
+        // file only
+        let config = Config::load(Some(&path), &ConfigOverrides::default()).unwrap();
+        assert_eq!(config.global.endpoint.as_deref(), Some("https://file.rpc"));
+        assert_eq!(config.global.ws_endpoint.as_deref(), Some("wss://file.rpc"));
+        assert_eq!(
+            config.global.keeper_private_key.as_deref(),
+            Some("file-key")
+        );
+
+        // env over file, the primary name wins over its alias
+        env::set_var("RPC_URL", "https://alias.rpc");
+        env::set_var("ENDPOINT", "https://env.rpc");
+        env::set_var("PRIVATE_KEY", "env-key");
+        let config = Config::load(Some(&path), &ConfigOverrides::default()).unwrap();
+        assert_eq!(config.global.endpoint.as_deref(), Some("https://env.rpc"));
+        assert_eq!(config.global.ws_endpoint.as_deref(), Some("wss://file.rpc"));
+        assert_eq!(config.global.keeper_private_key.as_deref(), Some("env-key"));
+
+        // cli over env and file
+        let overrides = ConfigOverrides {
+            endpoint: Some("https://cli.rpc".to_string()),
+            ws_endpoint: Some("wss://cli.rpc".to_string()),
+            dry_run: Some(false),
+            run_once: true,
+            ..ConfigOverrides::default()
+        };
+        let config = Config::load(Some(&path), &overrides).unwrap();
+        assert_eq!(config.global.endpoint.as_deref(), Some("https://cli.rpc"));
+        assert_eq!(config.global.ws_endpoint.as_deref(), Some("wss://cli.rpc"));
+        assert_eq!(config.global.keeper_private_key.as_deref(), Some("env-key"));
+        let filler = config.bot_configs.filler.unwrap();
+        assert!(!filler.base_config.dry_run);
+        assert_eq!(filler.base_config.run_once, Some(true));
+
+        for var in ENV_VARS {
+            env::remove_var(var);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_required_config_without_overrides() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        for var in ENV_VARS {
+            env::remove_var(var);
+        }
+
+        assert!(matches!(
+            Config::load(None, &ConfigOverrides::default()),
+            Err(ConfigError::Missing {
+                field: "global.endpoint",
+                ..
+            })
+        ));
+
+        let overrides = ConfigOverrides {
+            endpoint: Some("https://cli.rpc".to_string()),
+            ws_endpoint: Some("wss://cli.rpc".to_string()),
+            private_key: Some("cli-key".to_string()),
+            ..ConfigOverrides::default()
+        };
+        let config = Config::load(None, &overrides).unwrap();
+        assert_eq!(config.global.endpoint.as_deref(), Some("https://cli.rpc"));
+    }
+}
@@ -6,11 +6,17 @@ use std::{
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use drift::state::{
-    oracle::OracleSource,
-    perp_market::PerpMarket,
-    user::{MarketType, OrderType, User},
+use drift::{
+    math::constants::{QUOTE_PRECISION_U64, QUOTE_SPOT_MARKET_INDEX},
+    state::{
+        oracle::OracleSource,
+        perp_market::PerpMarket,
+        settle_pnl_mode::SettlePnlMode,
+        spot_market::SpotMarket,
+        user::{MarketType, OrderType, User},
+    },
 };
+use futures_util::{future::LocalBoxFuture, FutureExt};
 use log::info;
 use lru::LruCache;
 use rand::{seq::SliceRandom, thread_rng};
@@ -21,19 +27,21 @@ use sdk::{
     constants,
     dlob::{
         dlob::{MarketAccount, NodeToFill, DLOB},
+        dlob_builder::DLOBBuilder,
         dlob_node::{DLOBNode, Node, NodeType},
         dlob_subscriber::DLOBSubscriber,
         types::{DLOBSubscriptionConfig, DlobSource, SlotSource},
     },
     drift_client::DriftClient,
+    events::event_subscriber::{EventSubscriber, EventSubscriberConfig},
     jupiter::JupiterClient,
     math::{
         market::{calculate_ask_price, calculate_bid_price},
         oracle::is_oracle_valid,
         order::{is_fillable_by_vamm, is_order_expired},
     },
-    priority_fee::priority_fee_subscriber::PriorityFeeSubscriber,
     slot_subscriber::SlotSubscriber,
+    tx::tx_sender::TxSender,
     types::{MakerInfo, ReferrerInfo},
     usermap::{user_stats_map::UserStatsMap, UserMap},
     AccountProvider,
@@ -44,6 +52,7 @@ use solana_sdk::{
     commitment_config::{CommitmentConfig, CommitmentLevel},
     compute_budget::ComputeBudgetInstruction,
     instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
     native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
     signature::Signature,
@@ -59,14 +68,15 @@ use crate::{
         is_taker_breached_maintainance_margin_log,
     },
     config::{FillerConfig, GlobalConfig},
+    health::Watchdog,
     maker_selection::select_makers,
-    metrics::RuntimeSpec,
-    types::JitoStrategy,
+    metrics::{registry_for_port, FillerMetrics, RuntimeSpec, DEFAULT_METRICS_PORT},
+    types::{Bot, JitoStrategy, SharedPriorityFeeSubscriber},
     util::{
-        get_fill_signature_from_user_account_and_orader_id, get_node_to_fill_signature,
-        get_node_to_trigger_signature, get_transaction_account_metas, simulate_and_get_tx_with_cus,
-        valid_minimum_gas_amount, valid_rebalance_settled_pnl_threshold,
-        SimulateAndGetTxWithCUsParams, SimulateAndGetTxWithCUsResponse,
+        get_compute_unit_price, get_fill_signature_from_user_account_and_orader_id,
+        get_node_to_fill_signature, get_node_to_trigger_signature, get_transaction_account_metas,
+        simulate_and_get_tx_with_cus, valid_minimum_gas_amount,
+        valid_rebalance_settled_pnl_threshold, SimulateAndGetTxWithCUsResponse,
     },
 };
 
@@ -91,6 +101,11 @@ const SLOTS_UNTIL_JITO_LEADER_TO_SEND: u64 = 4;
 const TX_CONFIRMATION_BATCH_SIZE: usize = 100;
 const TX_TIMEOUT_THRESHOLD_MS: u128 = 60_000; // tx considered stale after this time and give up confirming
 const CONFIRM_TX_RATE_LIMIT_BACKOFF_MS: u64 = 5_000; // wait this long until trying to confirm tx again if rate limited
+const SETTLE_PNL_INTERVAL: Duration = Duration::from_secs(60 * 60); // settle own pnl and rebalance at most this often
+const REBALANCE_SLIPPAGE_BPS: u16 = 50;
+
+const DLOB_BUILD_INTERVAL_MS: u64 = 400; // apply user map diffs to the DLOB this often
+const DLOB_CONSISTENCY_CHECK_SLOTS: u64 = 150; // compare the incremental DLOB against a full rebuild this often
 
 const EXPIRE_ORDER_BUFFER_SEC: i64 = 60; // add extra time before trying to expire orders (want to avoid 6252 error due to clock drift)
 
@@ -114,10 +129,13 @@ where
     simulate_tx_for_cu_estimate: Option<bool>,
     lookup_table_account: Option<AddressLookupTableAccount>,
     bundle_sender: Option<BundleSender>,
+    tx_sender: Arc<dyn TxSender>,
 
     filler_config: FillerConfig,
     global_config: GlobalConfig,
     dlob_subscriber: Option<DLOBSubscriber<T>>,
+    /// order action records applied to the DLOB ahead of user account updates
+    event_subscriber: Option<EventSubscriber>,
 
     user_map: Option<UserMap>,
     user_stats_map: Option<UserStatsMap<T>>,
@@ -125,7 +143,7 @@ where
     // periodic_task_mutex = new Mutex();
 
     // watchdogTimerMutex = new Mutex();
-    watchdog_timer_last_pat_time: Instant,
+    watchdog: Watchdog,
 
     interval_ids: Vec<Instant>,
     throttled_nodes: HashMap<String, Instant>,
@@ -137,7 +155,7 @@ where
     fill_tx_id: u16,
     last_settle_pnl: Instant,
 
-    priority_fee_subscriber: PriorityFeeSubscriber<T>,
+    priority_fee_subscriber: SharedPriorityFeeSubscriber<T>,
     blockhash_subscriber: BlockhashSubscriber,
     /// stores txSigs that need to been confirmed in a slower loop, and the time they were confirmed
     pending_tx_sigs_toconfirm: LruCache<Signature, PendingTxSigsToconfirm>,
@@ -148,9 +166,7 @@ where
     jupiter_client: Option<JupiterClient<'a>>,
 
     // metrics
-    // metrics_initialized: bool,
-    // metrics_port: Option<u16>,
-    // metrics: Option<Metrics>,
+    metrics: Option<FillerMetrics>,
     // boot_time_ms: Option<u16>,
     runtime_spec: RuntimeSpec,
     // runtime_specs_gauge: Option<GaugeValue>,
@@ -192,7 +208,7 @@ where
         runtime_spec: RuntimeSpec,
         global_config: GlobalConfig,
         filler_config: FillerConfig,
-        mut priority_fee_subscriber: PriorityFeeSubscriber<T>,
+        priority_fee_subscriber: SharedPriorityFeeSubscriber<T>,
         blockhash_subscriber: BlockhashSubscriber,
         bundle_sender: Option<BundleSender>,
     ) -> Self {
@@ -233,9 +249,11 @@ where
             bundle_sender.is_some()
         );
 
-        let jupiter_client = if filler_config.rebalance_filler.is_some()
-            && runtime_spec.drift_env == "mainnet-beta"
-        {
+        let rebalance_filler = filler_config
+            .rebalance_filler
+            .or(global_config.rebalance_filler)
+            .unwrap_or(false);
+        let jupiter_client = if rebalance_filler && runtime_spec.drift_env == "mainnet-beta" {
             let client = JupiterClient::new(&drift_client.backend.rpc_client, None);
             Some(client)
         } else {
@@ -275,11 +293,13 @@ where
 
         // Openbook SOL/USDC
         // sol-perp
-        priority_fee_subscriber.update_addresses(&[
+        priority_fee_subscriber.write().await.update_addresses(&[
             Pubkey::from_str("8BnEgHoWFysVcuFFX7QztDmzuH8r5ZFvyP3sYwn1XTh6").unwrap(),
             Pubkey::from_str("8UJgxaiQx5nTrdDgph5FiahMmzduuLTLf5WmsPegYA6W").unwrap(),
         ]);
 
+        let tx_sender = global_config.tx_sender(drift_client.backend.rpc_client.clone());
+
         let pubsub_client = PubsubClient::new(websocket_url)
             .await
             .expect("init pubsub client");
@@ -297,6 +317,7 @@ where
             bulk_account_loader,
             // user_stats_map_subscription_config: &user_stats_map_subscription_config,
             runtime_spec,
+            metrics: None,
             polling_interval_ms: filler_config
                 .filler_polling_interval
                 .unwrap_or(DEFAULT_INTERVAL_MS),
@@ -306,8 +327,9 @@ where
                 filler_config.simulate_tx_for_cu_estimate.unwrap_or(true),
             ),
             bundle_sender,
+            tx_sender,
             jupiter_client,
-            rebalance_filler: filler_config.rebalance_filler.unwrap_or(false),
+            rebalance_filler,
             min_gas_balance_to_fill,
             rebalance_settled_pnl_threshold,
             priority_fee_subscriber,
@@ -317,6 +339,7 @@ where
             confirm_loop_running: false,
             confirm_loop_rate_limit_ts: Instant::now() - Duration::from_secs(5_000),
             dlob_subscriber: None,
+            event_subscriber: None,
             fill_tx_id: 0,
             fill_tx_since_burst_cu: 0,
             filling_nodes: HashMap::new(),
@@ -328,16 +351,76 @@ where
             triggering_nodes: HashMap::new(),
             user_stats_map: None,
             use_burst_cu_limit: false,
-            watchdog_timer_last_pat_time: Instant::now(),
+            watchdog: Watchdog::new(Duration::from_millis(
+                filler_config
+                    .filler_polling_interval
+                    .unwrap_or(DEFAULT_INTERVAL_MS) as u64
+                    * 10,
+            )),
         }
     }
 
     fn record_evicted_tx_sig(&self) {
-        todo!()
+        if let Some(metrics) = &self.metrics {
+            metrics.evicted_tx_sigs.inc();
+        }
+    }
+
+    fn initialize_metrics(&mut self) {
+        if self.global_config.disable_metrics.unwrap_or(false) {
+            log::info!("{} metrics disabled", self.name);
+            return;
+        }
+
+        let port = self
+            .filler_config
+            .base_config
+            .metrics_port
+            .or(self.global_config.metrics_port)
+            .unwrap_or(DEFAULT_METRICS_PORT);
+        let metrics = registry_for_port(port, &self.runtime_spec).and_then(|registry| {
+            FillerMetrics::new(&registry, &self.name)
+                .map_err(|e| format!("failed to register metrics: {e}"))
+        });
+        match metrics {
+            Ok(metrics) => self.metrics = Some(metrics),
+            Err(e) => log::error!("{} {e}", self.name),
+        }
+    }
+
+    fn record_failed_fill(&self, error_type: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.failed_fills.with_label_values(&[error_type]).inc();
+        }
     }
 
-    fn initialize_metrics(&self) {
-        todo!()
+    /// Returns the compute unit price to set on a fill tx
+    async fn priority_fee_micro_lamports(&self) -> u64 {
+        self.priority_fee_subscriber
+            .read()
+            .await
+            .get_custom_strategy_result() as u64
+    }
+
+    /// Record the compute unit price of a tx that was sent
+    fn record_priority_fee(&self, priority_fee: Option<u64>) {
+        if let (Some(metrics), Some(fee)) = (&self.metrics, priority_fee) {
+            metrics.priority_fee.observe(fee as f64);
+        }
+    }
+
+    fn record_dlob_size(&self, dlob: &DLOB) {
+        if let Some(metrics) = &self.metrics {
+            let (perp_size, spot_size) = dlob.size();
+            metrics
+                .dlob_size
+                .with_label_values(&["perp"])
+                .set(perp_size as i64);
+            metrics
+                .dlob_size
+                .with_label_values(&["spot"])
+                .set(spot_size as i64);
+        }
     }
 
     pub async fn base_init(&mut self) {
@@ -349,6 +432,9 @@ where
             .await
             .expect("get sol balance");
         self.has_enough_sol_to_fill = filler_sol_balance as f64 >= self.min_gas_balance_to_fill;
+        if let Some(metrics) = &self.metrics {
+            metrics.set_sol_balance(filler_sol_balance);
+        }
         log::info!(
             "{}: has_enoght_sol_to_fill: {}, balance: {filler_sol_balance}",
             self.name,
@@ -385,14 +471,39 @@ where
     }
 
     pub async fn init(&mut self) {
+        self.initialize_metrics();
         self.base_init().await;
         let drift_client = self.drift_client.clone();
         let user_map = self.user_map.clone().unwrap();
         let slot_subscriber = self.slot_subscriber.clone();
 
+        // the user map and slot subscriber are shared and already subscribed
+        let dlob_builder = Arc::new(tokio::sync::Mutex::new(DLOBBuilder::new(
+            slot_subscriber.clone(),
+            user_map,
+            DLOB_BUILD_INTERVAL_MS,
+            DLOB_CONSISTENCY_CHECK_SLOTS,
+        )));
+        DLOBBuilder::spawn_build_loop(dlob_builder.clone()).await;
+
+        let mut event_subscriber = EventSubscriber::new(
+            &self.drift_client.backend.rpc_client.url(),
+            EventSubscriberConfig::default(),
+        );
+        match event_subscriber.subscribe().await {
+            Ok(()) => {
+                DLOBBuilder::handle_events(dlob_builder.clone(), event_subscriber.stream());
+                self.event_subscriber = Some(event_subscriber);
+            }
+            Err(e) => log::error!(
+                "{} failed to subscribe to events, DLOB updates from accounts only: {e}",
+                self.name
+            ),
+        }
+
         let dlob_subscriber = DLOBSubscriber::new(DLOBSubscriptionConfig {
             drift_client,
-            dlob_source: DlobSource::UserMap(user_map),
+            dlob_source: DlobSource::DLOBBuilder(dlob_builder),
             slot_source: SlotSource::SlotSubscriber(slot_subscriber),
             update_frequency: Duration::from_millis((self.polling_interval_ms - 500) as u64),
         });
@@ -402,35 +513,86 @@ where
             dlob_subscriber.subscribe().await.unwrap();
         }
 
+        if let Some(bundle_sender) = &mut self.bundle_sender {
+            if let Err(e) = bundle_sender.subscribe().await {
+                log::error!("{} failed to subscribe bundle sender: {e}", self.name);
+            }
+        }
+
         log::info!("[{}]: started", self.name);
     }
 
+    /// The user map is shared with other bots and unsubscribed by the runner
     pub async fn reset(&mut self) {
         if let Some(dlob_sub) = &mut self.dlob_subscriber {
             dlob_sub.unsubscribe().await;
         }
-        if let Some(user_map) = &mut self.user_map {
-            user_map.unsubscribe().await.expect("unsubscribe usermap");
+        if let Some(event_subscriber) = &mut self.event_subscriber {
+            if let Err(e) = event_subscriber.unsubscribe().await {
+                log::error!("{} failed to unsubscribe events: {e}", self.name);
+            }
         }
     }
 
-    pub async fn start_interval_loop(&mut self) {
-        self.try_fill().await;
-        self.settle_pnls().await;
-        self.confirm_pending_tx_sigs().await;
+    pub async fn shutdown(&mut self) -> Result<(), String> {
+        self.reset().await;
+        if let Some(bundle_sender) = &mut self.bundle_sender {
+            bundle_sender.unsubscribe().await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
+    pub async fn start_interval_loop(&mut self) {
         log::info!(
             "{} Bot started! (websocket: {})",
             self.name,
             self.bulk_account_loader.is_none()
         );
+
+        let mut interval = tokio::time::interval(self.polling_interval());
+        loop {
+            interval.tick().await;
+            self.try_fill().await;
+            self.settle_pnls().await;
+            self.confirm_pending_tx_sigs().await;
+            self.record_jito_bundle_stats();
+            self.watchdog.pat();
+
+            if self.run_once() {
+                return;
+            }
+        }
+    }
+
+    pub(crate) fn polling_interval(&self) -> Duration {
+        Duration::from_millis(self.polling_interval_ms as u64)
     }
 
-    fn record_jito_bundle_stats() {
-        todo!()
+    pub(crate) fn run_once(&self) -> bool {
+        self.filler_config.base_config.run_once.unwrap_or(false)
+    }
+
+    fn record_jito_bundle_stats(&self) {
+        let Some(sender) = &self.bundle_sender else {
+            return;
+        };
+
+        let stats = sender.bundle_stats();
+        let (bundles_sent, results_received, landed, dropped) = sender.bundle_counts();
+        log::info!(
+            "{} jito bundles sent: {bundles_sent}, results: {results_received}, landed: {landed}, dropped: {dropped}, next tip: {}",
+            self.name,
+            sender.calculate_current_tip_amount()
+        );
+        log::info!("{} jito bundle stats: {:?}", self.name, stats);
     }
 
-    async fn confirm_pending_tx_sigs(&mut self) {
+    pub(crate) async fn confirm_pending_tx_sigs(&mut self) {
         let next_time_can_run =
             self.confirm_loop_rate_limit_ts + Duration::from_secs(CONFIRM_TX_RATE_LIMIT_BACKOFF_MS);
         let now = Instant::now();
@@ -455,7 +617,8 @@ where
         let tx_entries: Vec<(&Signature, &PendingTxSigsToconfirm)> =
             pending_tx_sigs_toconfirm.iter().collect();
         for i in (0..tx_entries.len()).step_by(TX_CONFIRMATION_BATCH_SIZE) {
-            let tx_sigs_batch = &tx_entries[i..i + TX_CONFIRMATION_BATCH_SIZE];
+            let batch_end = (i + TX_CONFIRMATION_BATCH_SIZE).min(tx_entries.len());
+            let tx_sigs_batch = &tx_entries[i..batch_end];
             let sigs: Vec<Signature> = tx_sigs_batch
                 .into_iter()
                 .map(|(sig, _pending_tx)| **sig)
@@ -471,7 +634,7 @@ where
                 let tx_resp = &txs[j];
                 let tx_confirmation_info = tx_sigs_batch[j];
                 let tx_sig = tx_confirmation_info.0;
-                let tx_age = tx_confirmation_info.1.ts - Instant::now();
+                let tx_age = tx_confirmation_info.1.ts.elapsed();
                 let node_filled = &tx_confirmation_info.1.node_filled;
                 let tx_type = &tx_confirmation_info.1.tx_type;
                 let fill_tx_id = tx_confirmation_info.1.fill_tx_id;
@@ -480,12 +643,20 @@ where
                     Ok(tx) => {
                         log::info!("Tx landed (fill_tx_id: {fill_tx_id}) (tx_type: {tx_type:?}): {tx_sig}, tx age: {} s", tx_age.as_secs());
                         self.pending_tx_sigs_toconfirm.pop(tx_sig);
+                        if let Some(metrics) = &self.metrics {
+                            metrics
+                                .tx_confirmation_latency
+                                .observe(tx_age.as_secs_f64());
+                        }
 
                         if matches!(tx_type, TxType::Fill) {
                             if let Some(meta) = &tx.transaction.meta {
                                 if let OptionSerializer::Some(msgs) = &meta.log_messages {
-                                    let _result =
+                                    let (filled_nodes, _exceeded_cus) =
                                         self.handle_transaction_logs(&node_filled, msgs).await;
+                                    if let Some(metrics) = &self.metrics {
+                                        metrics.landed_fills.inc_by(filled_nodes as u64);
+                                    }
                                 }
                             }
                         }
@@ -508,15 +679,17 @@ where
                     }
                 }
             }
-
-            self.confirm_loop_running = false;
         }
+
+        self.confirm_loop_running = false;
     }
 
     pub fn health_check(&self) -> bool {
-        let healthy = false;
+        self.watchdog.is_healthy()
+    }
 
-        healthy
+    pub fn watchdog(&self) -> Watchdog {
+        self.watchdog.clone()
     }
 
     async fn get_user_account_and_slot_from_map(&self, key: Pubkey) -> Option<(User, u64)> {
@@ -611,6 +784,46 @@ where
         Err(String::from("Could not find oracle"))
     }
 
+    /// Return spot `nodes_to_fill`
+    async fn get_spot_nodes_for_market(
+        &self,
+        market: SpotMarket,
+        dlob: &mut DLOB,
+    ) -> Result<Vec<NodeToFill>, String> {
+        let market_index = market.market_index;
+
+        let oracle = self
+            .drift_client
+            .get_oracle_price_data_and_slot_for_spot_market(market_index);
+        if let Some(oracle) = oracle {
+            // spot markets have no vAMM, the oracle price stands in as fallback liquidity and
+            // nodes without makers are filtered out before filling
+            let fallback_price = oracle.data.price as u64;
+            let fill_slot = self.get_max_slot();
+
+            let state_account = self.drift_client.get_state_account();
+            let state = state_account.read().expect("read state account");
+
+            let nodes_to_fill = dlob
+                .find_nodes_to_fill(
+                    market_index,
+                    fallback_price,
+                    fallback_price,
+                    fill_slot,
+                    self.clock_subscriber.get_unix_ts().await - EXPIRE_ORDER_BUFFER_SEC,
+                    MarketType::Spot,
+                    &oracle.data,
+                    &state,
+                    &MarketAccount::SpotMarket(Box::new(market)),
+                )
+                .map_err(|e| e.to_string())?;
+
+            return Ok(nodes_to_fill);
+        }
+
+        Err(String::from("Could not find oracle"))
+    }
+
     /// Check if the node is still throttled, if not, clears it from the throttled_nodes map
     fn is_throttled_node_still_throttled(&mut self, throttle_key: String) -> bool {
         if let Some(last_fill_attempt) = self.throttled_nodes.get(&throttle_key.to_string()) {
@@ -662,6 +875,11 @@ where
 
     fn set_throttled_node(&mut self, sig: &str) {
         self.throttled_nodes.insert(sig.to_string(), Instant::now());
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .throttled_nodes
+                .set(self.throttled_nodes.len() as i64);
+        }
     }
 
     fn prune_throttled_node(&mut self) {
@@ -670,7 +888,12 @@ where
             let duration_threshold = Duration::new(2_u64 * FILL_ORDER_THROTTLE_BACKOFF, 0);
 
             self.throttled_nodes
-                .retain(|_, v| *v + duration_threshold <= now)
+                .retain(|_, v| *v + duration_threshold <= now);
+            if let Some(metrics) = &self.metrics {
+                metrics
+                    .throttled_nodes
+                    .set(self.throttled_nodes.len() as i64);
+            }
         }
     }
 
@@ -737,6 +960,21 @@ where
             return true;
         }
 
+        if matches!(order.market_type, MarketType::Spot) {
+            // spot has no vAMM, without makers the fill needs external liquidity (serum/phoenix)
+            if node_to_fill.get_maker_nodes().is_empty() {
+                log::debug!(
+                    "filtered out spot node without makers on market {} for user {}-{}",
+                    market_index,
+                    user_account,
+                    order.order_id
+                );
+                return false;
+            }
+
+            return true;
+        }
+
         if let Some(oracle_price_data) = oracle {
             let market_info = self
                 .drift_client
@@ -957,17 +1195,28 @@ where
         referrer_info: &Option<ReferrerInfo>,
     ) -> SimulateAndGetTxWithCUsResponse {
         let user_account_pubkey = self.drift_client.wallet().authority();
-        let mut builder = self
+        let builder = self
             .drift_client
             .init_tx(&user_account_pubkey, false)
-            .expect("build tx")
-            .fill_perp_order(
+            .expect("build tx");
+        let order = node_to_fill.get_node().get_order();
+        let mut builder = match order.market_type {
+            MarketType::Perp => builder.fill_perp_order(
                 *user_account_pubkey,
                 taker_user,
-                node_to_fill.get_node().get_order(),
+                order,
                 makers,
                 referrer_info,
-            );
+            ),
+            MarketType::Spot => builder.fill_spot_order(
+                node_to_fill.get_node().get_user_account(),
+                taker_user,
+                order,
+                makers,
+                referrer_info,
+                None,
+            ),
+        };
 
         let sig = get_node_to_fill_signature(node_to_fill);
         self.filling_nodes.insert(sig, Instant::now());
@@ -998,24 +1247,14 @@ where
             .await
             .expect("get recent blockhash");
 
-        let mut params = SimulateAndGetTxWithCUsParams {
-            connection: self.drift_client.backend.rpc_client.clone(),
-            payer: self.drift_client.wallet.signer.clone(),
-            lookup_table_accounts: vec![self
-                .lookup_table_account
-                .clone()
-                .expect("lookup table account")
-                .clone()],
-            ixs: ixs.into(),
-            cu_limit_multiplier: Some(SIM_CU_ESTIMATE_MULTIPLIER),
-            do_simulation: Some(true),
-            recent_blockhash: Some(recent_blockhash),
-            dump_tx: None,
-        };
-
-        let sim_res = simulate_and_get_tx_with_cus(&mut params)
-            .await
-            .expect("simulate");
+        let sim_res = simulate_and_get_tx_with_cus(
+            &self.drift_client,
+            ixs,
+            SIM_CU_ESTIMATE_MULTIPLIER,
+            recent_blockhash,
+        )
+        .await
+        .expect("simulate");
 
         sim_res
     }
@@ -1116,12 +1355,14 @@ where
                     }
                 }
 
+                self.record_failed_fill("order_does_not_exist");
                 error_this_fill_ix = true;
                 continue;
             }
 
             if let Some(margin) = is_maker_breached_maintainance_margin_log(log) {
                 log::error!("Throttling maker breached maintainance margin: {margin}");
+                self.record_failed_fill("maker_breached_maintenance_margin");
                 self.set_throttled_node(&margin);
                 let user_pub = Pubkey::from_str(&margin).unwrap();
                 if let Some((user_account, _slot)) =
@@ -1135,7 +1376,11 @@ where
                         .legacy()
                         .build();
 
-                    match self.drift_client.sign_and_send(msg, false).await {
+                    match self
+                        .drift_client
+                        .sign_and_send_with_sender(self.tx_sender.as_ref(), msg, false)
+                        .await
+                    {
                         Ok(sig) => {
                             log::info!("force_cancel_orders for makers due to breach of maintainance margin. Tx: {sig}");
                         }
@@ -1155,6 +1400,7 @@ where
                     let taker_node_sig = filled_node.get_node().get_user_account();
                     log::error!("taker breach maint. margin, assoc node (ix_idx: {ix_idx}): {}, {}; (throttling {taker_node_sig} and force cancelling orders); {log}", filled_node.get_node().get_user_account(), filled_node.get_node().get_order().order_id);
                     self.set_throttled_node(&taker_node_sig.to_string());
+                    self.record_failed_fill("taker_breached_maintenance_margin");
                     error_this_fill_ix = true;
 
                     let user_pub = filled_node.get_node().get_user_account();
@@ -1169,7 +1415,11 @@ where
                             .legacy()
                             .build();
 
-                        match self.drift_client.sign_and_send(msg, false).await {
+                        match self
+                            .drift_client
+                            .sign_and_send_with_sender(self.tx_sender.as_ref(), msg, false)
+                            .await
+                        {
                             Ok(sig) => {
                                 log::info!("force_cancel_orders for user {user_pub} due to breach of maintainance margin. Tx: {sig}");
                             }
@@ -1192,6 +1442,7 @@ where
                 if let Some(filled_node) = nodes_filled.get(ix_idx) {
                     let assoc_node_sig = get_node_to_fill_signature(filled_node);
                     log::warn!("Throttling node due to fill error. extracted_sig: {extract_sig}, assoc_node_sig: {assoc_node_sig}, assoc_node_idx: {ix_idx}");
+                    self.record_failed_fill("err_filling");
                     error_this_fill_ix = true;
                     continue;
                 }
@@ -1199,6 +1450,7 @@ where
 
             if is_err_stale_oracle(log) {
                 log::error!("Stale oracle error: {log}");
+                self.record_failed_fill("stale_oracle");
                 error_this_fill_ix = true;
                 continue;
             }
@@ -1214,6 +1466,7 @@ where
         if !logs.is_empty() {
             if let Some(last) = logs.last() {
                 if last.contains("exceeded CUs meter at BPF instruction") {
+                    self.record_failed_fill("exceeded_cus");
                     return (success_count, true);
                 }
             }
@@ -1231,10 +1484,13 @@ where
         fill_tx_id: u16,
         tx_type: TxType,
     ) {
-        self.pending_tx_sigs_toconfirm.put(
+        let evicted = self.pending_tx_sigs_toconfirm.push(
             tx_sig,
             PendingTxSigsToconfirm::new(now, node_filled, fill_tx_id, tx_type),
         );
+        if matches!(evicted, Some((sig, _)) if sig != tx_sig) {
+            self.record_evicted_tx_sig();
+        }
     }
 
     fn remove_filling_nodes(&mut self, nodes: &[NodeToFill]) {
@@ -1283,14 +1539,23 @@ where
 
             let tx_start = Instant::now();
             let tx_sig = tx.signatures[0];
+            if let Some(metrics) = &self.metrics {
+                metrics.fill_attempts.inc_by(nodes_sent.len() as u64);
+            }
 
             if build_for_bundle {
                 self.send_tx_through_jito(&tx, &format!("{fill_tx_id}"), Some(tx_sig))
                     .await;
                 self.remove_filling_nodes(nodes_sent);
             } else if self.can_send_outside_jito() {
-                match self.drift_client.sign_and_send(tx.message, false).await {
+                let priority_fee = get_compute_unit_price(&tx.message);
+                match self
+                    .drift_client
+                    .sign_and_send_with_sender(self.tx_sender.as_ref(), tx.message, false)
+                    .await
+                {
                     Ok(resp) => {
+                        self.record_priority_fee(priority_fee);
                         log::info!(
                             "sent tx: {resp}, took: {}ms (fill_tx_id: {fill_tx_id}",
                             tx_start.elapsed().as_millis()
@@ -1321,7 +1586,7 @@ where
         let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
         if !build_for_bundle {
             ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
-                self.priority_fee_subscriber.get_custom_strategy_result() as u64,
+                self.priority_fee_micro_lamports().await,
             ));
         }
 
@@ -1481,7 +1746,7 @@ where
         let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
         if !build_for_bundle {
             ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
-                self.priority_fee_subscriber.get_custom_strategy_result() as u64,
+                self.priority_fee_micro_lamports().await,
             ));
         }
 
@@ -1644,24 +1909,14 @@ where
         //     .expect("get recent blockhash");
         let recent_blockhash = self.blockhash_subscriber.get_latest_blockhash().await;
 
-        let mut params = SimulateAndGetTxWithCUsParams {
-            connection: self.drift_client.backend.rpc_client.clone(),
-            payer: self.drift_client.wallet.signer.clone(),
-            lookup_table_accounts: vec![self
-                .lookup_table_account
-                .clone()
-                .expect("lookup table account")
-                .clone()],
-            ixs: ixs.into(),
-            cu_limit_multiplier: Some(SIM_CU_ESTIMATE_MULTIPLIER),
-            do_simulation: Some(true),
-            recent_blockhash: Some(recent_blockhash),
-            dump_tx: None,
-        };
-
-        let sim_res = simulate_and_get_tx_with_cus(&mut params)
-            .await
-            .expect("simulate");
+        let sim_res = simulate_and_get_tx_with_cus(
+            &self.drift_client,
+            ixs,
+            SIM_CU_ESTIMATE_MULTIPLIER,
+            recent_blockhash,
+        )
+        .await
+        .expect("simulate");
 
         // ERROR:
         if self.simulate_tx_for_cu_estimate.is_some() && sim_res.sim_error.is_some() {
@@ -1695,6 +1950,122 @@ where
         Ok(nodes_sent.len())
     }
 
+    async fn fill_spot_node(
+        &mut self,
+        fill_tx_id: u16,
+        node_to_fill: &NodeToFill,
+        build_for_bundle: bool,
+    ) -> Result<(), String> {
+        let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
+        if !build_for_bundle {
+            ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.priority_fee_micro_lamports().await,
+            ));
+        }
+
+        if let Some((
+            maker_infos,
+            _taker_user_pubkey,
+            taker_user,
+            taker_user_slot,
+            referrer_info,
+            market_type,
+        )) = self.get_node_fill_info(node_to_fill).await
+        {
+            if MarketType::Spot != market_type {
+                return Err(String::from("expected spot market type"));
+            }
+
+            let mut maker_infos_to_use: Vec<MakerInfo> = maker_infos
+                .into_iter()
+                .map(|(_slot, maker_info)| maker_info)
+                .collect();
+
+            let mut sim_res = self
+                .build_tx_with_maker_infos(
+                    &maker_infos_to_use,
+                    &ixs,
+                    node_to_fill,
+                    &taker_user,
+                    &referrer_info,
+                )
+                .await;
+            let mut tx_accounts = sim_res.tx.message.static_account_keys().len();
+            while tx_accounts > MAX_ACCOUNTS_PER_TX && maker_infos_to_use.len() > 1 {
+                log::info!("(fill_tx_id: {fill_tx_id}) Too many accounts, remove 1 and try again (had {} maker and {tx_accounts} accounts)", maker_infos_to_use.len());
+                maker_infos_to_use.pop();
+                sim_res = self
+                    .build_tx_with_maker_infos(
+                        &maker_infos_to_use,
+                        &ixs,
+                        node_to_fill,
+                        &taker_user,
+                        &referrer_info,
+                    )
+                    .await;
+                tx_accounts = sim_res.tx.message.static_account_keys().len();
+            }
+
+            match sim_res.sim_error {
+                Some(err) => {
+                    log::error!("Error simulating spot node (fill_tx_id: {fill_tx_id}): {:?}\nTaker slot: {taker_user_slot}\n", err);
+
+                    if let Some(logs) = sim_res.sim_tx_logs {
+                        let (_filled_nodes, _exceeded_cus) = self
+                            .handle_transaction_logs(&[node_to_fill.clone()], &logs)
+                            .await;
+                    }
+                }
+                None => {
+                    if self.dry_run {
+                        log::info!("dry run, not sending tx (fill_tx_id: {fill_tx_id})");
+                    } else if self.has_enough_sol_to_fill {
+                        self.send_fill_tx_and_parse_logs(
+                            fill_tx_id,
+                            &[node_to_fill.clone()],
+                            sim_res.tx,
+                            build_for_bundle,
+                        )
+                        .await;
+                    } else {
+                        log::info!("not sending tx because we don't have enough SOL to fill (fill_tx_id: {fill_tx_id})");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spot fills are sent one node per tx, the maker/referrer accounts don't pack well
+    async fn try_fill_spot_nodes(
+        &mut self,
+        nodes_to_fill: &[NodeToFill],
+        build_for_bundle: bool,
+    ) -> usize {
+        let mut nodes_sent = 0;
+
+        for node_to_fill in nodes_to_fill {
+            let fill_tx_id = self.fill_tx_id;
+            self.fill_tx_id += 1;
+
+            match self
+                .fill_spot_node(fill_tx_id, node_to_fill, build_for_bundle)
+                .await
+            {
+                Ok(()) => nodes_sent += 1,
+                Err(e) => {
+                    log::error!(
+                        "{}: failed to fill spot node (fill_tx_id: {fill_tx_id}): {e}",
+                        self.name
+                    );
+                }
+            }
+        }
+
+        nodes_sent
+    }
+
     async fn filter_perp_nodes_for_market(
         &mut self,
         fillable_nodes: &[NodeToFill],
@@ -1762,7 +2133,7 @@ where
 
                 let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
                 ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
-                    self.priority_fee_subscriber.get_custom_strategy_result() as u64,
+                    self.priority_fee_micro_lamports().await,
                 ));
 
                 let mut builder = drift_client
@@ -1783,24 +2154,14 @@ where
                     .await
                     .expect("get recent blockhash");
 
-                let mut params = SimulateAndGetTxWithCUsParams {
-                    connection: drift_client.backend.rpc_client.clone(),
-                    payer: drift_client.wallet.signer.clone(),
-                    lookup_table_accounts: vec![self
-                        .lookup_table_account
-                        .clone()
-                        .expect("lookup table account")
-                        .clone()],
-                    ixs: ixs.into(),
-                    cu_limit_multiplier: Some(SIM_CU_ESTIMATE_MULTIPLIER),
-                    do_simulation: Some(true),
-                    recent_blockhash: Some(recent_blockhash),
-                    dump_tx: None,
-                };
-
-                let sim_res = simulate_and_get_tx_with_cus(&mut params)
-                    .await
-                    .expect("simulate");
+                let sim_res = simulate_and_get_tx_with_cus(
+                    &drift_client,
+                    ixs,
+                    SIM_CU_ESTIMATE_MULTIPLIER,
+                    recent_blockhash,
+                )
+                .await
+                .expect("simulate");
 
                 if self.simulate_tx_for_cu_estimate.is_some() && sim_res.sim_error.is_some() {
                     log::error!(
@@ -1829,8 +2190,17 @@ where
                                 )
                                 .await;
                             } else {
-                                match drift_client.sign_and_send(sim_res.tx.message, false).await {
+                                let priority_fee = get_compute_unit_price(&sim_res.tx.message);
+                                match drift_client
+                                    .sign_and_send_with_sender(
+                                        self.tx_sender.as_ref(),
+                                        sim_res.tx.message,
+                                        false,
+                                    )
+                                    .await
+                                {
                                     Ok(sig) => {
+                                        self.record_priority_fee(priority_fee);
                                         log::info!("Signature: {sig}");
                                     }
                                     Err(e) => {
@@ -1847,7 +2217,9 @@ where
         }
     }
 
-    async fn settle_pnls(&mut self) {
+    /// Check the filler has enough SOL to fill, with `rebalance_filler` set also settle its own
+    /// perp pnl and swap settled USDC to SOL when it is low on gas
+    pub(crate) async fn settle_pnls(&mut self) {
         // Check if we have enough SOL to fill
         let authority = self.drift_client.wallet().authority();
         let filler_sol_balance = self
@@ -1862,6 +2234,182 @@ where
             self.min_gas_balance_to_fill
         );
         self.has_enough_sol_to_fill = filler_sol_balance as f64 >= self.min_gas_balance_to_fill;
+        if let Some(metrics) = &self.metrics {
+            metrics.set_sol_balance(filler_sol_balance);
+        }
+
+        if !self.rebalance_filler || self.last_settle_pnl.elapsed() < SETTLE_PNL_INTERVAL {
+            return;
+        }
+        self.last_settle_pnl = Instant::now();
+        self.settle_own_pnl().await;
+        if !self.has_enough_sol_to_fill {
+            self.rebalance_to_sol().await;
+        }
+    }
+
+    /// Settle the pnl of the filler's perp positions, i.e. fill rewards, into its USDC balance
+    async fn settle_own_pnl(&self) {
+        let Some(user) = self.drift_client.get_user(None) else {
+            return;
+        };
+        let user_info = (user.pubkey, user.get_user_account());
+        let market_indexes: Vec<u16> = user_info
+            .1
+            .perp_positions
+            .iter()
+            .filter(|p| !p.is_available() && p.quote_asset_amount != 0)
+            .map(|p| p.market_index)
+            .collect();
+        if market_indexes.is_empty() {
+            return;
+        }
+
+        let builder = match self.drift_client.init_tx(&user_info.0, false) {
+            Ok(builder) => {
+                builder.settle_multiple_pnls(&user_info, &market_indexes, SettlePnlMode::TrySettle)
+            }
+            Err(e) => {
+                log::error!("{} failed to init settle pnl tx: {e}", self.name);
+                return;
+            }
+        };
+        if self.dry_run {
+            log::info!(
+                "{} dry run, not sending settle pnl {market_indexes:?}",
+                self.name
+            );
+            return;
+        }
+
+        match self
+            .drift_client
+            .sign_and_send_with_sender(self.tx_sender.as_ref(), builder.build(), false)
+            .await
+        {
+            Ok(sig) => log::info!("{} settle pnl {market_indexes:?} tx: {sig}", self.name),
+            Err(e) => log::error!("{} failed to settle pnl: {e}", self.name),
+        }
+    }
+
+    /// Withdraw `rebalance_settled_pnl_threshold` USDC and swap it to SOL through Jupiter,
+    /// skipped until the filler holds at least that much USDC
+    async fn rebalance_to_sol(&self) {
+        let Some(jupiter_client) = &self.jupiter_client else {
+            return;
+        };
+        let Some(user) = self.drift_client.get_user(None) else {
+            return;
+        };
+        let Some(usdc_market) = self
+            .drift_client
+            .get_spot_market_account(QUOTE_SPOT_MARKET_INDEX)
+        else {
+            return;
+        };
+        let usdc_amount = user
+            .get_user_account()
+            .get_spot_position(QUOTE_SPOT_MARKET_INDEX)
+            .and_then(|p| p.get_signed_token_amount(&usdc_market))
+            .unwrap_or(0);
+        let swap_amount =
+            (self.rebalance_settled_pnl_threshold * QUOTE_PRECISION_U64 as f64) as u64;
+        if usdc_amount < swap_amount as i128 {
+            log::info!(
+                "{} {usdc_amount} USDC is below the rebalance threshold, not swapping to SOL",
+                self.name
+            );
+            return;
+        }
+
+        let authority = *self.drift_client.wallet().authority();
+        let usdc_token_account =
+            constants::derive_associated_token_account(&authority, &usdc_market.mint);
+        let withdraw = match self.drift_client.init_tx(&user.pubkey, false) {
+            Ok(builder) => builder.withdraw(
+                swap_amount,
+                QUOTE_SPOT_MARKET_INDEX,
+                usdc_token_account,
+                Some(true),
+            ),
+            Err(e) => {
+                log::error!("{} failed to init withdraw tx: {e}", self.name);
+                return;
+            }
+        };
+        if self.dry_run {
+            log::info!(
+                "{} dry run, not withdrawing and swapping {swap_amount} USDC to SOL",
+                self.name
+            );
+            return;
+        }
+
+        // the swap spends the withdrawn USDC so the withdraw must land first
+        if let Err(e) = self.send_and_confirm(withdraw.build()).await {
+            log::error!("{} failed to withdraw USDC for rebalance: {e}", self.name);
+            return;
+        }
+
+        let quote = match jupiter_client
+            .get_quote(
+                usdc_market.mint,
+                constants::NATIVE_MINT,
+                swap_amount,
+                None,
+                REBALANCE_SLIPPAGE_BPS,
+                None,
+                None,
+                None,
+            )
+            .await
+        {
+            Ok(quote) => quote,
+            Err(e) => {
+                log::error!("{} failed to get USDC to SOL quote: {e}", self.name);
+                return;
+            }
+        };
+        let swap_tx = match jupiter_client
+            .get_swap(quote, authority, Some(REBALANCE_SLIPPAGE_BPS))
+            .await
+        {
+            Ok(tx) => tx,
+            Err(e) => {
+                log::error!("{} failed to get USDC to SOL swap: {e}", self.name);
+                return;
+            }
+        };
+
+        match self
+            .drift_client
+            .sign_and_send_with_sender(self.tx_sender.as_ref(), swap_tx.message, false)
+            .await
+        {
+            Ok(sig) => log::info!("{} swapped {swap_amount} USDC to SOL tx: {sig}", self.name),
+            Err(e) => log::error!("{} failed to swap USDC to SOL: {e}", self.name),
+        }
+    }
+
+    /// Sign and send `tx`, waiting until it is confirmed
+    async fn send_and_confirm(&self, tx: VersionedMessage) -> Result<Signature, String> {
+        let blockhash = self
+            .drift_client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| e.to_string())?;
+        let tx = self
+            .drift_client
+            .wallet()
+            .sign_tx(tx, blockhash, false)
+            .map_err(|e| e.to_string())?;
+
+        self.drift_client
+            .backend
+            .rpc_client
+            .send_and_confirm_transaction(&tx)
+            .await
+            .map_err(|e| e.to_string())
     }
 
     fn using_jito(&self) -> bool {
@@ -1918,6 +2466,9 @@ where
         let _user = self.drift_client.get_user(None);
 
         let mut dlob = self.get_dlob().await;
+        if let Some(dlob) = &dlob {
+            self.record_dlob_size(dlob);
+        }
         self.prune_throttled_node();
 
         // 1) get all fillable nodes
@@ -1963,4 +2514,94 @@ where
 
         // ran = true;
     }
+
+    pub(crate) async fn try_fill_spot(&mut self) {
+        if !self.has_enough_sol_to_fill {
+            log::info!("Not enough SOL to fill, skipping spot fill");
+            return;
+        }
+
+        let mut dlob = self.get_dlob().await;
+        if let Some(dlob) = &dlob {
+            self.record_dlob_size(dlob);
+        }
+        self.prune_throttled_node();
+
+        let mut fillable_nodes = Vec::new();
+        for market in self.drift_client.get_spot_market_accounts() {
+            // quote market orders are never placed
+            if market.market_index == QUOTE_SPOT_MARKET_INDEX {
+                continue;
+            }
+
+            if let Some(ref mut dlob) = dlob {
+                match self.get_spot_nodes_for_market(market, dlob).await {
+                    Ok(nodes_to_fill) => fillable_nodes.extend(nodes_to_fill),
+                    Err(e) => {
+                        log::warn!(
+                            "{}: :x: Failed to get fillable nodes for spot market {}, Error: {e}",
+                            self.name,
+                            market.market_index
+                        );
+                        continue;
+                    }
+                }
+            }
+        }
+
+        let (filtered_fillable_nodes, _) = self
+            .filter_perp_nodes_for_market(&fillable_nodes, &[])
+            .await;
+        log::debug!(
+            "filtered fillable spot nodes from {} to {}",
+            fillable_nodes.len(),
+            filtered_fillable_nodes.len()
+        );
+
+        let build_bundle = self.should_build_for_bundle();
+
+        self.try_fill_spot_nodes(&filtered_fillable_nodes, build_bundle)
+            .await;
+    }
+}
+
+impl<'a, T> Bot for FillerBot<'a, T>
+where
+    T: AccountProvider + Clone,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        async move {
+            FillerBot::init(self).await;
+            Ok(())
+        }
+        .boxed_local()
+    }
+
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        async move {
+            FillerBot::reset(self).await;
+            Ok(())
+        }
+        .boxed_local()
+    }
+
+    fn start(&mut self) -> LocalBoxFuture<'_, ()> {
+        self.start_interval_loop().boxed_local()
+    }
+
+    fn health_check(&self) -> bool {
+        FillerBot::health_check(self)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        FillerBot::watchdog(self)
+    }
+
+    fn shutdown(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        FillerBot::shutdown(self).boxed_local()
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    sync::Arc,
     time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
@@ -7,6 +8,7 @@ use drift::{
     math::helpers::on_the_hour_update,
     state::{paused_operations::PerpOperation, perp_market::MarketStatus},
 };
+use futures_util::{future::LocalBoxFuture, FutureExt};
 use log::{error, info, warn};
 use sdk::{
     config::DriftEnv,
@@ -18,11 +20,11 @@ use sdk::{
         priority_fee_subscriber_map::PriorityFeeSubscriberMap,
         types::PriorityFeeSubscriberMapConfig,
     },
+    tx::tx_sender::TxSender,
     types::SdkResult,
     AccountProvider,
 };
 use solana_sdk::{
-    address_lookup_table_account::AddressLookupTableAccount,
     compute_budget::ComputeBudgetInstruction, instruction::InstructionError, pubkey::Pubkey,
     transaction::TransactionError,
 };
@@ -30,10 +32,9 @@ use tokio::{sync::oneshot, task::JoinHandle, time::interval, time::Duration};
 
 use crate::{
     config::BaseBotConfig,
-    util::{
-        get_drift_priority_fee_endpoint, simulate_and_get_tx_with_cus,
-        SimulateAndGetTxWithCUsParams,
-    },
+    health::Watchdog,
+    types::Bot,
+    util::{get_drift_priority_fee_endpoint, simulate_and_get_tx_with_cus},
 };
 
 const ERROR_CODES_TO_SUPPRESS: &[u32] = &[
@@ -55,17 +56,22 @@ pub struct FundingRateUpdaterBot<T: AccountProvider> {
     default_interval_ms: u64,
 
     drift_client: DriftClient<T>,
+    tx_sender: Arc<dyn TxSender>,
     interval_tx: Option<oneshot::Sender<()>>,
     interval_handles: Option<JoinHandle<()>>,
     priority_fee_subscriber_map: PriorityFeeSubscriberMap,
-    lookup_table_account: Option<AddressLookupTableAccount>,
 
-    watchdog_timer_last_par_time: Instant,
+    watchdog: Watchdog,
     in_progress: bool,
 }
 
 impl<T: AccountProvider> FundingRateUpdaterBot<T> {
-    pub fn new(drift_client: DriftClient<T>, config: BaseBotConfig) -> Self {
+    pub fn new(
+        drift_client: DriftClient<T>,
+        tx_sender: Arc<dyn TxSender>,
+        config: BaseBotConfig,
+    ) -> Self {
+        let default_interval_ms = 120000;
         let perp_markets = read_perp_markets(DriftEnv::Devnet);
         let drift_markets = perp_markets
             .iter()
@@ -84,20 +90,19 @@ impl<T: AccountProvider> FundingRateUpdaterBot<T> {
             name: config.bot_id,
             dry_run: config.dry_run,
             run_once: config.run_once.unwrap_or(false),
-            default_interval_ms: 120000,
+            default_interval_ms,
             drift_client,
+            tx_sender,
             interval_tx: None,
             interval_handles: None,
             priority_fee_subscriber_map: PriorityFeeSubscriberMap::new(priority_config),
-            lookup_table_account: None,
-            watchdog_timer_last_par_time: Instant::now(),
+            watchdog: Watchdog::new(Duration::from_millis(default_interval_ms * 10)),
             in_progress: false,
         }
     }
 
     pub async fn init(&mut self) -> SdkResult<()> {
         self.priority_fee_subscriber_map.subscribe().await?;
-        self.lookup_table_account = Some(self.drift_client.fetch_market_lookup_table_account());
 
         info!("{} inited", self.name);
 
@@ -106,12 +111,15 @@ impl<T: AccountProvider> FundingRateUpdaterBot<T> {
 
     pub async fn reset(&mut self) -> Result<(), String> {
         if let Some(interval_tx) = self.interval_tx.take() {
-            interval_tx
-                .send(())
-                .map_err(|_| String::from("failed to send oneshot channel"))?;
-
+            // the receiver is gone if the loop was already dropped
+            let _ = interval_tx.send(());
             self.interval_handles = None;
         }
+        self.in_progress = false;
+        self.priority_fee_subscriber_map
+            .unsubscribe()
+            .await
+            .map_err(|e| e.to_string())?;
 
         Ok(())
     }
@@ -126,24 +134,32 @@ impl<T: AccountProvider> FundingRateUpdaterBot<T> {
         if self.run_once {
             self.try_update_funding_rate().await?;
         } else {
-            self.try_update_funding_rate().await?;
-            self.interval_handles = Some(tokio::spawn(async move {
-                loop {
-                    tokio::select! {
-                        _ = interval.tick() => {
-                            println!("Inteval tick");
-                        }
-                        _ = &mut interval_rx => {
-                            break;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = self.try_update_funding_rate().await {
+                            error!("{} failed to update funding rates: {e}", self.name);
                         }
+                        self.watchdog.pat();
+                    }
+                    _ = &mut interval_rx => {
+                        break;
                     }
                 }
-            }));
+            }
         }
 
         Ok(())
     }
 
+    pub fn health_check(&self) -> bool {
+        self.watchdog.is_healthy()
+    }
+
+    pub fn watchdog(&self) -> Watchdog {
+        self.watchdog.clone()
+    }
+
     pub async fn try_update_funding_rate(&mut self) -> Result<(), String> {
         if self.in_progress {
             info!(
@@ -287,23 +303,12 @@ impl<T: AccountProvider> FundingRateUpdaterBot<T> {
             .get_latest_blockhash()
             .await
             .expect("get recent blockhash");
-        let lookup_table_account = if let Some(lookup) = &self.lookup_table_account {
-            lookup.clone()
-        } else {
-            self.drift_client
-                .fetch_market_lookup_table_account()
-                .clone()
-        };
-        let sim_result = simulate_and_get_tx_with_cus(&mut SimulateAndGetTxWithCUsParams {
-            connection: self.drift_client.backend.rpc_client.clone(),
-            payer: self.drift_client.wallet.signer.clone(),
-            lookup_table_accounts: vec![lookup_table_account.clone()],
-            ixs: ixs.into(),
-            cu_limit_multiplier: Some(CU_EST_MULTIPLIER),
-            do_simulation: Some(true),
-            recent_blockhash: Some(recent_blockhash),
-            dump_tx: None,
-        })
+        let sim_result = simulate_and_get_tx_with_cus(
+            &self.drift_client,
+            ixs,
+            CU_EST_MULTIPLIER,
+            recent_blockhash,
+        )
         .await?;
 
         info!(
@@ -332,7 +337,7 @@ impl<T: AccountProvider> FundingRateUpdaterBot<T> {
         let send_tx_start = Instant::now();
         let tx_sig = self
             .drift_client
-            .sign_and_send(sim_result.tx.message, false)
+            .sign_and_send_with_sender(self.tx_sender.as_ref(), sim_result.tx.message, false)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -346,3 +351,40 @@ impl<T: AccountProvider> FundingRateUpdaterBot<T> {
         Ok((true, true))
     }
 }
+
+impl<T: AccountProvider> Bot for FundingRateUpdaterBot<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        async move {
+            FundingRateUpdaterBot::init(self)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        .boxed_local()
+    }
+
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        FundingRateUpdaterBot::reset(self).boxed_local()
+    }
+
+    fn start(&mut self) -> LocalBoxFuture<'_, ()> {
+        let interval_ms = self.default_interval_ms;
+        async move {
+            if let Err(e) = self.start_interval_loop(interval_ms).await {
+                error!("{} {e}", self.name);
+            }
+        }
+        .boxed_local()
+    }
+
+    fn health_check(&self) -> bool {
+        FundingRateUpdaterBot::health_check(self)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        FundingRateUpdaterBot::watchdog(self)
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use sdk::{oraclemap::OracleMap, slot_subscriber::SlotSubscriber, usermap::UserMap};
+
+use crate::http::{self, Response};
+
+/// Port `/health` and `/ready` are served on when the global config doesn't set one
+pub const DEFAULT_HEALTH_CHECK_PORT: u16 = 8888;
+
+/// unhealthy once the slot subscriber hasn't advanced for this long
+const SLOT_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// unhealthy once a websocket subscription lags the slot subscriber by this many slots (~2 min)
+const MAX_SUBSCRIPTION_SLOT_LAG: u64 = 300;
+
+/// Tracks when a bot's loop last made progress, shared with the health server so it can be
+/// checked while the bot is running
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    last_pat: Arc<Mutex<Instant>>,
+    ready: Arc<AtomicBool>,
+    timeout: Duration,
+}
+
+impl Watchdog {
+    /// `timeout` is how long the loop may go without a `pat` before the bot counts as stalled
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            last_pat: Arc::new(Mutex::new(Instant::now())),
+            ready: Arc::new(AtomicBool::new(false)),
+            timeout,
+        }
+    }
+
+    /// Record progress of the bot loop
+    pub fn pat(&self) {
+        *self.last_pat.lock().expect("acquired") = Instant::now();
+    }
+
+    /// Mark the bot as initialized
+    pub fn set_ready(&self) {
+        self.pat();
+        self.ready.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    pub fn since_last_pat(&self) -> Duration {
+        self.last_pat.lock().expect("acquired").elapsed()
+    }
+
+    /// Returns false once the bot loop has stalled. Bots still initializing count as healthy,
+    /// use `is_ready` to tell them apart
+    pub fn is_healthy(&self) -> bool {
+        !self.is_ready() || self.since_last_pat() < self.timeout
+    }
+}
+
+/// Serves `/health` and `/ready` for every bot running in this process
+///
+/// `/health` fails when a bot loop stalls, the `SlotSubscriber` stops advancing or a `UserMap`
+/// / `OracleMap` subscription falls behind, so liveness probes can restart the process.
+/// `/ready` additionally waits for every bot to finish initializing.
+pub struct HealthServer {
+    bots: Vec<(String, Watchdog)>,
+    slot_subscriber: SlotSubscriber,
+    user_maps: Vec<(String, UserMap)>,
+    oracle_map: Arc<OracleMap>,
+    /// last slot seen and when it was first seen
+    last_slot: Mutex<(u64, Instant)>,
+}
+
+impl HealthServer {
+    pub fn new(slot_subscriber: SlotSubscriber, oracle_map: Arc<OracleMap>) -> Self {
+        Self {
+            bots: vec![],
+            slot_subscriber,
+            user_maps: vec![],
+            oracle_map,
+            last_slot: Mutex::new((0, Instant::now())),
+        }
+    }
+
+    pub fn add_bot(&mut self, name: &str, watchdog: Watchdog) {
+        self.bots.push((name.to_string(), watchdog));
+    }
+
+    pub fn add_user_map(&mut self, name: &str, user_map: UserMap) {
+        self.user_maps.push((name.to_string(), user_map));
+    }
+
+    /// Start serving on `0.0.0.0:port`
+    pub fn serve(self, port: u16) -> Result<(), String> {
+        let listener = http::bind(port).map_err(|e| format!("health check: {e}"))?;
+        log::info!("serving health checks on 0.0.0.0:{port}/health and /ready");
+
+        let server = Arc::new(self);
+        tokio::spawn(http::serve(listener, move |path| match path {
+            "/health" => server.respond(server.check_health()),
+            "/ready" => server.respond(server.check_ready()),
+            _ => Response::not_found(),
+        }));
+
+        Ok(())
+    }
+
+    fn respond(&self, problems: Vec<String>) -> Response {
+        if problems.is_empty() {
+            return Response::ok("text/plain", "ok");
+        }
+
+        log::warn!("health check failed: {}", problems.join(", "));
+        Response::new("503 Service Unavailable", "text/plain", problems.join("\n"))
+    }
+
+    /// Returns the reasons the process is unhealthy, empty if healthy
+    fn check_health(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (name, watchdog) in &self.bots {
+            if !watchdog.is_healthy() {
+                problems.push(format!(
+                    "{name} loop stalled for {}s",
+                    watchdog.since_last_pat().as_secs()
+                ));
+            }
+        }
+
+        let current_slot = self.slot_subscriber.current_slot();
+        {
+            let mut last_slot = self.last_slot.lock().expect("acquired");
+            if current_slot != last_slot.0 {
+                *last_slot = (current_slot, Instant::now());
+            } else if last_slot.1.elapsed() > SLOT_STALL_TIMEOUT {
+                problems.push(format!(
+                    "slot subscriber stalled at slot {current_slot} for {}s",
+                    last_slot.1.elapsed().as_secs()
+                ));
+            }
+        }
+
+        let mut check_lag = |name: &str, latest_slot: u64| {
+            // 0 until the first update arrives, covered by `/ready`
+            if latest_slot != 0
+                && current_slot.saturating_sub(latest_slot) > MAX_SUBSCRIPTION_SLOT_LAG
+            {
+                problems.push(format!(
+                    "{name} is stale, last update at slot {latest_slot}, current slot {current_slot}"
+                ));
+            }
+        };
+        for (name, user_map) in &self.user_maps {
+            check_lag(&format!("{name} user map"), user_map.get_latest_slot());
+        }
+        check_lag("oracle map", self.oracle_map.get_latest_slot());
+
+        problems
+    }
+
+    /// Returns the reasons the process is not ready, empty if ready
+    fn check_ready(&self) -> Vec<String> {
+        let mut problems = self.check_health();
+
+        for (name, watchdog) in &self.bots {
+            if !watchdog.is_ready() {
+                problems.push(format!("{name} is initializing"));
+            }
+        }
+        if self.slot_subscriber.current_slot() == 0 {
+            problems.push("slot subscriber has not received a slot".to_string());
+        }
+        for (name, user_map) in &self.user_maps {
+            if user_map.get_latest_slot() == 0 {
+                problems.push(format!("{name} user map has not received an update"));
+            }
+        }
+
+        problems
+    }
+}
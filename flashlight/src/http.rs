@@ -0,0 +1,91 @@
+//! Minimal HTTP/1.1 server for the metrics and health check endpoints
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+pub(crate) struct Response {
+    status: &'static str,
+    content_type: String,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: &'static str, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            content_type: content_type.to_string(),
+            body: body.into(),
+        }
+    }
+
+    pub fn ok(content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self::new("200 OK", content_type, body)
+    }
+
+    pub fn not_found() -> Self {
+        Self::new("404 Not Found", "text/plain", "not found")
+    }
+}
+
+/// Bind `0.0.0.0:port`, failing immediately if the port is taken
+pub(crate) fn bind(port: u16) -> Result<TcpListener, String> {
+    std::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)))
+        .and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            TcpListener::from_std(listener)
+        })
+        .map_err(|e| format!("failed to bind port {port}: {e}"))
+}
+
+/// Serve requests on `listener`, answering each with `route(path)`
+pub(crate) async fn serve<F>(listener: TcpListener, route: F)
+where
+    F: Fn(&str) -> Response + Send + Sync + 'static,
+{
+    let route = Arc::new(route);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let route = route.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_request(stream, route.as_ref()).await {
+                        log::debug!("http request failed: {e}");
+                    }
+                });
+            }
+            Err(e) => log::warn!("http server failed to accept connection: {e}"),
+        }
+    }
+}
+
+async fn handle_request<F>(mut stream: TcpStream, route: &F) -> std::io::Result<()>
+where
+    F: Fn(&str) -> Response,
+{
+    let mut buf = [0_u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default();
+
+    let response = route(path);
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,63 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use log::{error, info, warn};
+use sdk::{math::insurance::time_until_revenue_settle, AccountProvider};
+
+use crate::settler::{SettleContext, Settler, SettlerBot};
+
+/// Settles spot market revenue pools to their insurance funds once each market's revenue settle
+/// period elapses
+pub type IfRevenueSettlerBot<T> = SettlerBot<T, IfRevenueSettler>;
+
+/// Settles spot market revenue, run by an `IfRevenueSettlerBot`
+#[derive(Debug, Default)]
+pub struct IfRevenueSettler;
+
+impl IfRevenueSettler {
+    async fn try_settle_revenue<T: AccountProvider>(&self, ctx: &SettleContext<T>) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs() as i64;
+        for spot_market in ctx.drift_client.get_spot_market_accounts() {
+            let market_index = spot_market.market_index;
+            if spot_market.revenue_pool.scaled_balance == 0 {
+                continue;
+            }
+
+            match time_until_revenue_settle(&spot_market, now) {
+                Ok(Some(0)) => settle(ctx, market_index).await,
+                Ok(Some(remaining)) => info!(
+                    "{} spot market {market_index}: {remaining}s until revenue settle",
+                    ctx.name
+                ),
+                Ok(None) => {}
+                Err(e) => warn!(
+                    "{} failed to get revenue settle time of spot market {market_index}: {e}",
+                    ctx.name
+                ),
+            }
+        }
+    }
+}
+
+impl<T: AccountProvider> Settler<T> for IfRevenueSettler {
+    fn settle<'a>(&'a mut self, ctx: &'a SettleContext<T>) -> LocalBoxFuture<'a, ()> {
+        self.try_settle_revenue(ctx).boxed_local()
+    }
+}
+
+async fn settle<T: AccountProvider>(ctx: &SettleContext<T>, market_index: u16) {
+    let Some(builder) = ctx.init_tx() else {
+        error!("{} keeper sub-account not found", ctx.name);
+        return;
+    };
+    let label = format!("settle revenue to insurance fund of spot market {market_index}");
+
+    ctx.send(
+        builder.settle_revenue_to_insurance_fund(market_index),
+        &label,
+    )
+    .await;
+}
@@ -0,0 +1,385 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use drift::{
+    controller::position::PositionDirection,
+    math::constants::PRICE_PRECISION,
+    state::{
+        order_params::{OrderParams, PostOnlyParam},
+        user::{MarketType, Order, OrderStatus, OrderType, User},
+    },
+};
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use log::{info, warn};
+use sdk::{
+    drift_client::DriftClient,
+    math::{
+        auction::{get_auction_price, is_auction_complete},
+        leverage::get_leverage,
+    },
+    slot_subscriber::SlotSubscriber,
+    transaction_builder::TransactionBuilder,
+    tx::tx_sender::TxSender,
+    usermap::UserMap,
+    AccountProvider,
+};
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::interval;
+
+use crate::{
+    config::{JitMakerConfig, JitMarketConfig},
+    health::Watchdog,
+    types::Bot,
+    util::get_fill_signature_from_user_account_and_orader_id,
+};
+
+// the time to wait before responding to the same auction again
+const JIT_RESPONSE_COOLDOWN_MS: u64 = 1000;
+// size of responded auctions to get to before pruning the map
+const RESPONDED_AUCTION_SIZE_TO_PRUNE: usize = 1000;
+
+#[allow(dead_code)]
+pub struct JitMakerBot<T: AccountProvider> {
+    name: String,
+    dry_run: bool,
+    run_once: bool,
+    default_interval_ms: u64,
+
+    drift_client: Arc<DriftClient<T>>,
+    tx_sender: Arc<dyn TxSender>,
+    slot_subscriber: SlotSubscriber,
+    user_map: UserMap,
+    market_configs: Vec<JitMarketConfig>,
+    /// PRICE_PRECISION
+    max_leverage: u128,
+    responded_auctions: HashMap<String, Instant>,
+
+    watchdog: Watchdog,
+}
+
+impl<T: AccountProvider> JitMakerBot<T> {
+    pub fn new(
+        drift_client: Arc<DriftClient<T>>,
+        tx_sender: Arc<dyn TxSender>,
+        slot_subscriber: SlotSubscriber,
+        user_map: UserMap,
+        config: JitMakerConfig,
+    ) -> Self {
+        let default_interval_ms = 400;
+        Self {
+            name: config.base_config.bot_id,
+            dry_run: config.base_config.dry_run,
+            run_once: config.base_config.run_once.unwrap_or(false),
+            default_interval_ms,
+            drift_client,
+            tx_sender,
+            slot_subscriber,
+            user_map,
+            market_configs: config.market_configs,
+            max_leverage: (config.max_leverage.unwrap_or(1.0) * PRICE_PRECISION as f64) as u128,
+            responded_auctions: HashMap::new(),
+            watchdog: Watchdog::new(Duration::from_millis(default_interval_ms * 50)),
+        }
+    }
+
+    pub async fn init(&mut self) -> Result<(), String> {
+        for market_config in &self.market_configs {
+            if self
+                .drift_client
+                .get_user(Some(market_config.sub_account_id))
+                .is_none()
+            {
+                return Err(format!(
+                    "{} sub-account {} for market {} is not loaded",
+                    self.name, market_config.sub_account_id, market_config.market_index
+                ));
+            }
+        }
+
+        info!(
+            "{} inited, markets: {}",
+            self.name,
+            self.market_configs.len()
+        );
+
+        Ok(())
+    }
+
+    /// The user map is shared with other bots and unsubscribed by the runner
+    pub async fn reset(&mut self) -> Result<(), String> {
+        self.responded_auctions.clear();
+
+        Ok(())
+    }
+
+    pub async fn start_interval_loop(&mut self, interval_ms: u64) {
+        info!("{} Bot started! run_once {}", self.name, self.run_once);
+
+        if self.run_once {
+            self.try_make().await;
+            return;
+        }
+
+        let mut interval = interval(Duration::from_millis(interval_ms));
+        loop {
+            interval.tick().await;
+            self.try_make().await;
+        }
+    }
+
+    pub fn health_check(&self) -> bool {
+        self.watchdog.is_healthy()
+    }
+
+    pub fn watchdog(&self) -> Watchdog {
+        self.watchdog.clone()
+    }
+
+    async fn try_make(&mut self) {
+        let slot = std::cmp::max(
+            self.slot_subscriber.get_slot(),
+            self.user_map.get_latest_slot(),
+        );
+        self.prune_responded_auctions();
+
+        for (taker, taker_account) in self.user_map.values() {
+            for order in taker_account.orders.iter() {
+                if order.status != OrderStatus::Open
+                    || order.post_only
+                    || is_auction_complete(order, slot)
+                {
+                    continue;
+                }
+
+                let Some(market_config) = self
+                    .market_configs
+                    .iter()
+                    .find(|c| {
+                        c.market_index == order.market_index && c.market_type == order.market_type
+                    })
+                    .cloned()
+                else {
+                    continue;
+                };
+
+                // don't make against our own orders
+                if self
+                    .drift_client
+                    .get_user(Some(market_config.sub_account_id))
+                    .is_some_and(|u| u.pubkey == taker)
+                {
+                    continue;
+                }
+
+                let sig = get_fill_signature_from_user_account_and_orader_id(taker, order.order_id);
+                if self
+                    .responded_auctions
+                    .get(&sig)
+                    .is_some_and(|responded_at| {
+                        responded_at.elapsed() < Duration::from_millis(JIT_RESPONSE_COOLDOWN_MS)
+                    })
+                {
+                    continue;
+                }
+
+                match self
+                    .respond_to_auction(&market_config, &(taker, taker_account), order, slot)
+                    .await
+                {
+                    Ok(()) => {
+                        self.responded_auctions.insert(sig, Instant::now());
+                    }
+                    Err(e) => warn!("{} skipped auction {sig}: {e}", self.name),
+                }
+            }
+        }
+
+        self.watchdog.pat();
+    }
+
+    async fn respond_to_auction(
+        &self,
+        market_config: &JitMarketConfig,
+        taker_info: &(Pubkey, User),
+        order: &Order,
+        slot: u64,
+    ) -> Result<(), String> {
+        let oracle = match order.market_type {
+            MarketType::Perp => self
+                .drift_client
+                .get_oracle_price_data_and_slot_for_perp_market(order.market_index),
+            MarketType::Spot => self
+                .drift_client
+                .get_oracle_price_data_and_slot_for_spot_market(order.market_index),
+        }
+        .ok_or("oracle not found")?;
+        let oracle_price = oracle.data.price;
+
+        let auction_price = get_auction_price(order, slot, oracle_price);
+        let max_distance = (oracle_price as f64 * market_config.spread) as i128;
+        if (auction_price - oracle_price as i128).abs() > max_distance {
+            return Err(format!(
+                "auction price {auction_price} outside spread of oracle {oracle_price}"
+            ));
+        }
+
+        let maker = self
+            .drift_client
+            .get_user(Some(market_config.sub_account_id))
+            .ok_or("maker sub-account not found")?;
+        let maker_account = maker.get_user_account();
+
+        // maker takes the opposite side of the taker
+        let maker_direction = match order.direction {
+            PositionDirection::Long => PositionDirection::Short,
+            PositionDirection::Short => PositionDirection::Long,
+        };
+        let current_position = self.get_position(&maker_account, market_config)?;
+        let remaining = order.base_asset_amount - order.base_asset_amount_filled;
+        let size = max_fill_size(
+            current_position,
+            maker_direction,
+            remaining,
+            market_config.max_position,
+        );
+        if size == 0 {
+            return Err(String::from("max position reached"));
+        }
+
+        let is_reducing = match maker_direction {
+            PositionDirection::Long => current_position < 0,
+            PositionDirection::Short => current_position > 0,
+        };
+        if !is_reducing {
+            let leverage =
+                get_leverage(&self.drift_client, &maker_account).map_err(|e| e.to_string())?;
+            if leverage >= self.max_leverage {
+                return Err(format!(
+                    "leverage {leverage} above max {}",
+                    self.max_leverage
+                ));
+            }
+        }
+
+        let params = OrderParams {
+            order_type: OrderType::Limit,
+            market_type: market_config.market_type,
+            direction: maker_direction,
+            base_asset_amount: size,
+            price: auction_price.max(0) as u64,
+            market_index: market_config.market_index,
+            post_only: PostOnlyParam::MustPostOnly,
+            immediate_or_cancel: true,
+            ..OrderParams::default()
+        };
+
+        let builder = TransactionBuilder::new(
+            self.drift_client.program_data(),
+            maker.pubkey,
+            Cow::Owned(maker_account),
+            false,
+        )
+        .place_and_make(params, taker_info, order.order_id, None, None);
+
+        info!(
+            "{} responding to auction {}-{} on market {}: {:?} {size} @ {auction_price} (oracle: {oracle_price})",
+            self.name, taker_info.0, order.order_id, order.market_index, maker_direction
+        );
+
+        if self.dry_run {
+            info!("{} dry run, not sending place_and_make", self.name);
+            return Ok(());
+        }
+
+        let sig = self
+            .drift_client
+            .sign_and_send_with_sender(self.tx_sender.as_ref(), builder.build(), false)
+            .await
+            .map_err(|e| format!("failed to send place_and_make: {e}"))?;
+        info!("{} place_and_make tx: {sig}", self.name);
+
+        Ok(())
+    }
+
+    /// Return the maker's signed position in the market (perp base amount or spot token amount)
+    fn get_position(
+        &self,
+        maker_account: &User,
+        market_config: &JitMarketConfig,
+    ) -> Result<i128, String> {
+        match market_config.market_type {
+            MarketType::Perp => Ok(maker_account
+                .get_perp_position(market_config.market_index)
+                .map(|p| p.base_asset_amount as i128)
+                .unwrap_or(0)),
+            MarketType::Spot => {
+                let Ok(position) = maker_account.get_spot_position(market_config.market_index)
+                else {
+                    return Ok(0);
+                };
+                let spot_market = self
+                    .drift_client
+                    .get_spot_market_account(market_config.market_index)
+                    .ok_or("spot market not found")?;
+                position
+                    .get_signed_token_amount(&spot_market)
+                    .map_err(|e| format!("{e:?}"))
+            }
+        }
+    }
+
+    fn prune_responded_auctions(&mut self) {
+        if self.responded_auctions.len() > RESPONDED_AUCTION_SIZE_TO_PRUNE {
+            let cooldown = Duration::from_millis(JIT_RESPONSE_COOLDOWN_MS);
+            self.responded_auctions
+                .retain(|_, responded_at| responded_at.elapsed() < cooldown);
+        }
+    }
+}
+
+impl<T: AccountProvider> Bot for JitMakerBot<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        JitMakerBot::init(self).boxed_local()
+    }
+
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        JitMakerBot::reset(self).boxed_local()
+    }
+
+    fn start(&mut self) -> LocalBoxFuture<'_, ()> {
+        let interval_ms = self.default_interval_ms;
+        self.start_interval_loop(interval_ms).boxed_local()
+    }
+
+    fn health_check(&self) -> bool {
+        JitMakerBot::health_check(self)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        JitMakerBot::watchdog(self)
+    }
+}
+
+/// Size the maker can fill without its position exceeding `max_position` in either direction
+fn max_fill_size(
+    current_position: i128,
+    direction: PositionDirection,
+    remaining: u64,
+    max_position: u64,
+) -> u64 {
+    let max_position = max_position as i128;
+    let available = match direction {
+        PositionDirection::Long => max_position - current_position,
+        PositionDirection::Short => max_position + current_position,
+    };
+
+    available.clamp(0, remaining as i128) as u64
+}
@@ -8,8 +8,19 @@ pub mod config;
 pub mod error;
 pub mod filler;
 pub mod funding_rate_updater;
+pub mod health;
+pub(crate) mod http;
+pub mod if_revenue_settler;
+pub mod jit_maker;
+pub mod liquidator;
+pub mod lp_settler;
 pub mod maker_selection;
 pub mod metrics;
+pub mod runner;
+pub mod settler;
+pub mod spot_filler;
 pub mod trigger;
 pub mod types;
+pub mod user_management;
+pub mod user_pnl_settler;
 pub mod util;
@@ -0,0 +1,695 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
+
+use drift::{
+    controller::position::PositionDirection,
+    math::constants::{BASE_PRECISION_I128, QUOTE_SPOT_MARKET_INDEX},
+    state::{
+        order_params::OrderParams,
+        user::{MarketType, OrderType, User},
+    },
+};
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use log::{error, info, warn};
+use sdk::{
+    drift_client::DriftClient,
+    math::liquidation::{calculate_collateral, calculate_margin_requirements, MarginCategory},
+    transaction_builder::TransactionBuilder,
+    tx::tx_sender::TxSender,
+    usermap::UserMap,
+    AccountProvider,
+};
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::{interval, Duration};
+
+use crate::{config::LiquidatorConfig, health::Watchdog, types::Bot};
+
+/// Liquidatee account address and data
+type UserInfo = (Pubkey, User);
+
+/// Markets the liquidator took over positions in, and its own balances before it started
+///
+/// Derisking only unwinds the change against `baseline` in these markets so collateral the
+/// operator deposited into the sub-account is left untouched.
+#[derive(Default)]
+struct InheritedPositions {
+    perp_markets: HashSet<u16>,
+    spot_markets: HashSet<u16>,
+    /// perp base amounts by market at init
+    perp_baseline: HashMap<u16, i64>,
+    /// signed spot token amounts by market at init
+    spot_baseline: HashMap<u16, i128>,
+}
+
+pub struct LiquidatorBot<T: AccountProvider> {
+    name: String,
+    dry_run: bool,
+    run_once: bool,
+    default_interval_ms: u64,
+
+    drift_client: Arc<DriftClient<T>>,
+    tx_sender: Arc<dyn TxSender>,
+    user_map: UserMap,
+    config: LiquidatorConfig,
+    sub_account_id: u16,
+    min_liquidation_size: u128,
+    auto_derisking: bool,
+
+    inherited: InheritedPositions,
+
+    watchdog: Watchdog,
+    in_progress: bool,
+}
+
+impl<T: AccountProvider> LiquidatorBot<T> {
+    pub fn new(
+        drift_client: Arc<DriftClient<T>>,
+        tx_sender: Arc<dyn TxSender>,
+        user_map: UserMap,
+        config: LiquidatorConfig,
+    ) -> Self {
+        let default_interval_ms = 5000;
+        Self {
+            name: config.base_config.bot_id.clone(),
+            dry_run: config.base_config.dry_run,
+            run_once: config.base_config.run_once.unwrap_or(false),
+            default_interval_ms,
+            drift_client,
+            tx_sender,
+            user_map,
+            sub_account_id: config.sub_account_id.unwrap_or(0),
+            min_liquidation_size: config.min_liquidation_size.unwrap_or(0) as u128,
+            auto_derisking: !config.disable_auto_derisking.unwrap_or(false),
+            config,
+            inherited: InheritedPositions::default(),
+            watchdog: Watchdog::new(Duration::from_millis(default_interval_ms * 10)),
+            in_progress: false,
+        }
+    }
+
+    pub async fn init(&mut self) -> Result<(), String> {
+        let Some(liquidator_account) = self.liquidator_account() else {
+            return Err(format!(
+                "{} liquidator sub-account {} is not loaded",
+                self.name, self.sub_account_id
+            ));
+        };
+
+        self.inherited.perp_baseline = liquidator_account
+            .perp_positions
+            .iter()
+            .filter(|p| p.base_asset_amount != 0)
+            .map(|p| (p.market_index, p.base_asset_amount))
+            .collect();
+        self.inherited.spot_baseline = liquidator_account
+            .spot_positions
+            .iter()
+            .filter(|p| !p.is_available())
+            .map(|p| {
+                (
+                    p.market_index,
+                    self.spot_token_amount(&liquidator_account, p.market_index),
+                )
+            })
+            .collect();
+
+        info!(
+            "{} inited, sub_account: {}, auto derisking: {}",
+            self.name, self.sub_account_id, self.auto_derisking
+        );
+
+        Ok(())
+    }
+
+    /// The user map is shared with other bots and unsubscribed by the runner
+    pub async fn reset(&mut self) -> Result<(), String> {
+        self.in_progress = false;
+
+        Ok(())
+    }
+
+    pub async fn start_interval_loop(&mut self, interval_ms: u64) {
+        info!("{} Bot started! run_once {}", self.name, self.run_once);
+
+        if self.run_once {
+            self.try_liquidate().await;
+            return;
+        }
+
+        let mut interval = interval(Duration::from_millis(interval_ms));
+        loop {
+            interval.tick().await;
+            self.try_liquidate().await;
+        }
+    }
+
+    pub fn health_check(&self) -> bool {
+        self.watchdog.is_healthy()
+    }
+
+    pub fn watchdog(&self) -> Watchdog {
+        self.watchdog.clone()
+    }
+
+    async fn try_liquidate(&mut self) {
+        if self.in_progress {
+            info!("{} liquidation already in progress, skipping...", self.name);
+            return;
+        }
+        self.in_progress = true;
+        let start = Instant::now();
+
+        let liquidator = match self.drift_client.get_user(Some(self.sub_account_id)) {
+            Some(user) => user.pubkey,
+            None => {
+                error!("{} liquidator sub-account not found", self.name);
+                self.in_progress = false;
+                return;
+            }
+        };
+
+        for user_info in self.user_map.values() {
+            let (user_pubkey, user) = &user_info;
+            if *user_pubkey == liquidator {
+                continue;
+            }
+
+            if user.is_bankrupt() {
+                warn!("{} user {user_pubkey} is bankrupt, skipping", self.name);
+                continue;
+            }
+
+            match self.can_be_liquidated(user) {
+                Ok(true) => {
+                    info!("{} user {user_pubkey} can be liquidated", self.name);
+                    self.liquidate_user(&user_info).await;
+                }
+                Ok(false) => {}
+                Err(e) => warn!("{} failed margin check for {user_pubkey}: {e}", self.name),
+            }
+        }
+
+        if self.auto_derisking {
+            self.derisk().await;
+        }
+
+        info!(
+            "{} try_liquidate took: {}ms",
+            self.name,
+            start.elapsed().as_millis()
+        );
+        self.watchdog.pat();
+        self.in_progress = false;
+    }
+
+    /// Return true if the user is below maintenance margin (or already being liquidated)
+    fn can_be_liquidated(&self, user: &User) -> Result<bool, String> {
+        can_be_liquidated(user, |user| {
+            let margin_requirement = calculate_margin_requirements(&self.drift_client, user)
+                .map_err(|e| e.to_string())?;
+            let collateral =
+                calculate_collateral(&self.drift_client, user, MarginCategory::Maintenance)
+                    .map_err(|e| e.to_string())?;
+
+            Ok((collateral.total, margin_requirement.maintenance))
+        })
+    }
+
+    async fn liquidate_user(&mut self, user_info: &UserInfo) {
+        let (user_pubkey, user) = user_info;
+        let liquidator_account = match self.liquidator_account() {
+            Some(account) => account,
+            None => return,
+        };
+
+        // spot deposit with the largest notional, used as collateral to liquidate against
+        let deposit_market_index = self.largest_deposit(user);
+
+        for position in user.perp_positions.iter().filter(|p| !p.is_available()) {
+            let market_index = position.market_index;
+
+            if position.base_asset_amount != 0 {
+                let held = liquidator_account
+                    .get_perp_position(market_index)
+                    .map(|p| p.base_asset_amount.unsigned_abs())
+                    .unwrap_or(0);
+                let max_size = self
+                    .config
+                    .max_perp_position_size
+                    .get(&market_index)
+                    .copied()
+                    .unwrap_or(u64::MAX);
+                let base_amount = capped_amount(
+                    position.base_asset_amount.unsigned_abs() as u128,
+                    max_size as u128,
+                    held as u128,
+                ) as u64;
+
+                if base_amount == 0 {
+                    warn!(
+                        "{} max position size reached for perp market {market_index}, skipping {user_pubkey}",
+                        self.name
+                    );
+                    continue;
+                }
+
+                if self.perp_notional(market_index, base_amount) < self.min_liquidation_size {
+                    continue;
+                }
+
+                if let Some(builder) = self.init_tx() {
+                    let builder =
+                        builder.liquidate_perp(market_index, user_info, base_amount, None);
+                    if self
+                        .send(
+                            builder,
+                            &format!("liquidate_perp {user_pubkey}-{market_index}"),
+                        )
+                        .await
+                    {
+                        self.inherited.perp_markets.insert(market_index);
+                    }
+                }
+            } else if position.quote_asset_amount < 0 {
+                let Some(spot_market_index) = deposit_market_index else {
+                    continue;
+                };
+
+                let pnl = position.quote_asset_amount.unsigned_abs() as u128;
+                if pnl < self.min_liquidation_size {
+                    continue;
+                }
+
+                if let Some(builder) = self.init_tx() {
+                    let builder = builder.liquidate_perp_pnl_for_deposit(
+                        market_index,
+                        spot_market_index,
+                        user_info,
+                        pnl,
+                        None,
+                    );
+                    if self
+                        .send(
+                            builder,
+                            &format!("liquidate_perp_pnl_for_deposit {user_pubkey}-{market_index}"),
+                        )
+                        .await
+                    {
+                        self.inherited.spot_markets.insert(spot_market_index);
+                    }
+                }
+            }
+        }
+
+        for position in user.spot_positions.iter().filter(|p| !p.is_available()) {
+            let liability_market_index = position.market_index;
+            let Some(spot_market) = self
+                .drift_client
+                .get_spot_market_account(liability_market_index)
+            else {
+                continue;
+            };
+            let token_amount = match position.get_signed_token_amount(&spot_market) {
+                Ok(amount) => amount,
+                Err(e) => {
+                    warn!("{} failed to get token amount: {e:?}", self.name);
+                    continue;
+                }
+            };
+            if token_amount >= 0 {
+                continue;
+            }
+
+            let max_size = self
+                .config
+                .max_spot_position_size
+                .get(&liability_market_index)
+                .copied()
+                .unwrap_or(u128::MAX);
+            // only the liquidator's borrow counts against the cap, deposits are not a liability
+            let held = self
+                .spot_token_amount(&liquidator_account, liability_market_index)
+                .min(0)
+                .unsigned_abs();
+            let liability_amount = capped_amount(token_amount.unsigned_abs(), max_size, held);
+            if liability_amount == 0 {
+                warn!(
+                    "{} max position size reached for spot market {liability_market_index}, skipping {user_pubkey}",
+                    self.name
+                );
+                continue;
+            }
+            if self.spot_notional(liability_market_index, liability_amount)
+                < self.min_liquidation_size
+            {
+                continue;
+            }
+
+            // spot markets the liquidator takes over balances in
+            let (builder, inherited_markets) = if let Some(asset_market_index) =
+                deposit_market_index.filter(|i| *i != liability_market_index)
+            {
+                (
+                    self.init_tx().map(|builder| {
+                        builder.liquidate_spot(
+                            asset_market_index,
+                            liability_market_index,
+                            user_info,
+                            liability_amount,
+                            None,
+                        )
+                    }),
+                    vec![asset_market_index, liability_market_index],
+                )
+            } else if let Some(perp_position) = user
+                .perp_positions
+                .iter()
+                .find(|p| p.base_asset_amount == 0 && p.quote_asset_amount > 0)
+            {
+                (
+                    self.init_tx().map(|builder| {
+                        builder.liquidate_borrow_for_perp_pnl(
+                            perp_position.market_index,
+                            liability_market_index,
+                            user_info,
+                            liability_amount,
+                            None,
+                        )
+                    }),
+                    vec![liability_market_index],
+                )
+            } else {
+                (None, vec![])
+            };
+
+            if let Some(builder) = builder {
+                if self
+                    .send(
+                        builder,
+                        &format!("liquidate_spot {user_pubkey}-{liability_market_index}"),
+                    )
+                    .await
+                {
+                    self.inherited.spot_markets.extend(inherited_markets);
+                }
+            }
+        }
+    }
+
+    /// Close out positions inherited from liquidations with reduce only market orders
+    ///
+    /// Only markets the liquidator took over positions in are considered and only the change
+    /// against the balances held at init is unwound.
+    async fn derisk(&self) {
+        let Some(liquidator_account) = self.liquidator_account() else {
+            return;
+        };
+
+        let mut orders = Vec::new();
+        for position in liquidator_account.perp_positions.iter().filter(|p| {
+            p.base_asset_amount != 0
+                && p.open_orders == 0
+                && self.inherited.perp_markets.contains(&p.market_index)
+        }) {
+            let baseline = self
+                .inherited
+                .perp_baseline
+                .get(&position.market_index)
+                .copied()
+                .unwrap_or(0);
+            let Some((direction, amount)) =
+                unwind_amount(position.base_asset_amount as i128, baseline as i128)
+            else {
+                continue;
+            };
+            orders.push(OrderParams {
+                order_type: OrderType::Market,
+                market_type: MarketType::Perp,
+                direction,
+                base_asset_amount: amount as u64,
+                market_index: position.market_index,
+                reduce_only: true,
+                ..OrderParams::default()
+            });
+        }
+
+        for position in liquidator_account.spot_positions.iter().filter(|p| {
+            !p.is_available()
+                && p.open_orders == 0
+                && p.market_index != QUOTE_SPOT_MARKET_INDEX
+                && self.inherited.spot_markets.contains(&p.market_index)
+        }) {
+            let token_amount = self.spot_token_amount(&liquidator_account, position.market_index);
+            let baseline = self
+                .inherited
+                .spot_baseline
+                .get(&position.market_index)
+                .copied()
+                .unwrap_or(0);
+            let Some((direction, amount)) = unwind_amount(token_amount, baseline) else {
+                continue;
+            };
+            orders.push(OrderParams {
+                order_type: OrderType::Market,
+                market_type: MarketType::Spot,
+                direction,
+                base_asset_amount: amount as u64,
+                market_index: position.market_index,
+                reduce_only: true,
+                ..OrderParams::default()
+            });
+        }
+
+        if orders.is_empty() {
+            return;
+        }
+
+        info!("{} derisking {} positions", self.name, orders.len());
+        if let Some(builder) = self.init_tx() {
+            self.send(builder.place_orders(orders), "derisk").await;
+        }
+    }
+
+    fn liquidator_account(&self) -> Option<User> {
+        self.drift_client
+            .get_user(Some(self.sub_account_id))
+            .map(|user| user.get_user_account())
+    }
+
+    fn init_tx(&self) -> Option<TransactionBuilder> {
+        let user = self.drift_client.get_user(Some(self.sub_account_id))?;
+
+        Some(TransactionBuilder::new(
+            self.drift_client.program_data(),
+            user.pubkey,
+            Cow::Owned(user.get_user_account()),
+            false,
+        ))
+    }
+
+    /// Send the tx, returning true if it landed
+    async fn send(&self, builder: TransactionBuilder<'_>, label: &str) -> bool {
+        if self.dry_run {
+            info!("{} dry run, not sending {label}", self.name);
+            return false;
+        }
+
+        match self
+            .drift_client
+            .sign_and_send_with_sender(self.tx_sender.as_ref(), builder.build(), false)
+            .await
+        {
+            Ok(sig) => {
+                info!("{} {label} tx: {sig}", self.name);
+                true
+            }
+            Err(e) => {
+                error!("{} failed to send {label}: {e}", self.name);
+                false
+            }
+        }
+    }
+
+    /// Signed token amount of `user`'s balance in a spot market, 0 if there is none
+    fn spot_token_amount(&self, user: &User, market_index: u16) -> i128 {
+        let Some(market) = self.drift_client.get_spot_market_account(market_index) else {
+            return 0;
+        };
+        user.get_spot_position(market_index)
+            .ok()
+            .and_then(|p| p.get_signed_token_amount(&market).ok())
+            .unwrap_or(0)
+    }
+
+    /// Return the market index of the user's largest deposit by notional
+    fn largest_deposit(&self, user: &User) -> Option<u16> {
+        user.spot_positions
+            .iter()
+            .filter(|p| !p.is_available())
+            .filter_map(|p| {
+                let market = self.drift_client.get_spot_market_account(p.market_index)?;
+                let token_amount = p.get_signed_token_amount(&market).ok()?;
+                if token_amount <= 0 {
+                    return None;
+                }
+                Some((
+                    p.market_index,
+                    self.spot_notional(p.market_index, token_amount.unsigned_abs()),
+                ))
+            })
+            .max_by_key(|(_, notional)| *notional)
+            .map(|(market_index, _)| market_index)
+    }
+
+    /// Notional value of a perp base amount (QUOTE_PRECISION)
+    fn perp_notional(&self, market_index: u16, base_amount: u64) -> u128 {
+        let price = self
+            .drift_client
+            .get_oracle_price_data_and_slot_for_perp_market(market_index)
+            .map(|oracle| oracle.data.price.unsigned_abs() as u128)
+            .unwrap_or(0);
+
+        base_amount as u128 * price / BASE_PRECISION_I128 as u128
+    }
+
+    /// Notional value of a spot token amount (QUOTE_PRECISION)
+    fn spot_notional(&self, market_index: u16, token_amount: u128) -> u128 {
+        let Some(market) = self.drift_client.get_spot_market_account(market_index) else {
+            return 0;
+        };
+        let price = self
+            .drift_client
+            .get_oracle_price_data_and_slot_for_spot_market(market_index)
+            .map(|oracle| oracle.data.price.unsigned_abs() as u128)
+            .unwrap_or(0);
+
+        token_amount * price / 10_u128.pow(market.decimals)
+    }
+}
+
+/// Return true if `user` is already being liquidated or `margin` reports its total collateral
+/// below the maintenance margin requirement
+fn can_be_liquidated(
+    user: &User,
+    margin: impl FnOnce(&User) -> Result<(i128, u128), String>,
+) -> Result<bool, String> {
+    if user.is_being_liquidated() {
+        return Ok(true);
+    }
+
+    let (total_collateral, maintenance_requirement) = margin(user)?;
+    Ok(total_collateral < maintenance_requirement as i128)
+}
+
+/// Amount the liquidator may take over given it already holds `held` of a `max_size` limit
+fn capped_amount(amount: u128, max_size: u128, held: u128) -> u128 {
+    amount.min(max_size.saturating_sub(held))
+}
+
+/// Order direction and size to unwind the change from `baseline` to `current`
+///
+/// Returns None when there is nothing inherited to unwind i.e. the position has not grown
+/// away from `baseline` on the same side as `current`.
+fn unwind_amount(current: i128, baseline: i128) -> Option<(PositionDirection, u128)> {
+    let delta = current - baseline;
+    if delta == 0 || delta.signum() != current.signum() {
+        return None;
+    }
+
+    let direction = if current > 0 {
+        PositionDirection::Short
+    } else {
+        PositionDirection::Long
+    };
+    Some((direction, delta.unsigned_abs().min(current.unsigned_abs())))
+}
+
+impl<T: AccountProvider> Bot for LiquidatorBot<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        LiquidatorBot::init(self).boxed_local()
+    }
+
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        LiquidatorBot::reset(self).boxed_local()
+    }
+
+    fn start(&mut self) -> LocalBoxFuture<'_, ()> {
+        let interval_ms = self.default_interval_ms;
+        self.start_interval_loop(interval_ms).boxed_local()
+    }
+
+    fn health_check(&self) -> bool {
+        LiquidatorBot::health_check(self)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        LiquidatorBot::watchdog(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drift::state::user::UserStatus;
+
+    use super::*;
+
+    #[test]
+    fn can_be_liquidated_below_maintenance() {
+        let user = User::default();
+        assert!(can_be_liquidated(&user, |_| Ok((99, 100))).unwrap());
+        assert!(!can_be_liquidated(&user, |_| Ok((100, 100))).unwrap());
+        assert!(can_be_liquidated(&user, |_| Ok((-1, 0))).unwrap());
+        assert!(can_be_liquidated(&user, |_| Err("no oracle".into())).is_err());
+    }
+
+    #[test]
+    fn can_be_liquidated_while_being_liquidated() {
+        let user = User {
+            status: UserStatus::BeingLiquidated as u8,
+            ..Default::default()
+        };
+        assert!(can_be_liquidated(&user, |_| panic!("margin should not be checked")).unwrap());
+    }
+
+    #[test]
+    fn capped_amount_subtracts_held() {
+        assert_eq!(capped_amount(50, u128::MAX, 1_000), 50);
+        assert_eq!(capped_amount(50, 100, 0), 50);
+        assert_eq!(capped_amount(50, 100, 70), 30);
+        assert_eq!(capped_amount(50, 100, 100), 0);
+        assert_eq!(capped_amount(50, 100, 150), 0);
+    }
+
+    #[test]
+    fn unwind_amount_only_inherited() {
+        // nothing taken over
+        assert_eq!(unwind_amount(0, 0), None);
+        assert_eq!(unwind_amount(500, 500), None);
+        // operator deposit 500 + inherited 200
+        assert_eq!(
+            unwind_amount(700, 500),
+            Some((PositionDirection::Short, 200))
+        );
+        // inherited borrow on top of an operator borrow
+        assert_eq!(
+            unwind_amount(-300, -100),
+            Some((PositionDirection::Long, 200))
+        );
+        // inherited borrow against an operator deposit, reduce only can't go past 0
+        assert_eq!(unwind_amount(200, 500), None);
+        assert_eq!(
+            unwind_amount(-100, 500),
+            Some((PositionDirection::Long, 100))
+        );
+        // deposit shrank below the baseline, nothing inherited to sell
+        assert_eq!(unwind_amount(300, 500), None);
+    }
+}
@@ -0,0 +1,92 @@
+use drift::state::user::User;
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use log::{error, info, warn};
+use sdk::{math::lp::calculate_settle_lp_metrics, usermap::UserMap, AccountProvider};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::settler::{SettleContext, Settler, SettlerBot};
+
+const MAX_SETTLES_PER_TX: usize = 4;
+
+/// User account address and data, with the perp market of the LP position to settle
+type LpToSettle = ((Pubkey, User), u16);
+
+/// Settles the LP positions of users in the `UserMap` into their perp positions
+pub type LpSettlerBot<T> = SettlerBot<T, LpSettler>;
+
+/// Settles LP positions, run by an `LpSettlerBot`
+///
+/// A position is settled once its unsettled base reaches the market's order step size, smaller
+/// amounts would only add to the position's remainder.
+pub struct LpSettler {
+    user_map: UserMap,
+}
+
+impl LpSettler {
+    pub fn new(user_map: UserMap) -> Self {
+        Self { user_map }
+    }
+
+    async fn try_settle<T: AccountProvider>(&self, ctx: &SettleContext<T>) {
+        let lps = self.lps_to_settle(ctx);
+        if !lps.is_empty() {
+            info!("{} settling {} LP positions", ctx.name, lps.len());
+        }
+        for chunk in lps.chunks(MAX_SETTLES_PER_TX) {
+            settle(ctx, chunk).await;
+        }
+    }
+
+    /// Return the LP positions with at least an order step of base to settle
+    fn lps_to_settle<T: AccountProvider>(&self, ctx: &SettleContext<T>) -> Vec<LpToSettle> {
+        let mut lps = Vec::new();
+
+        for user_info in self.user_map.values() {
+            let (user_pubkey, user) = &user_info;
+            for position in user.perp_positions.iter().filter(|p| p.is_lp()) {
+                let market_index = position.market_index;
+                let Some(market) = ctx.drift_client.get_perp_market_account(market_index) else {
+                    continue;
+                };
+                match calculate_settle_lp_metrics(&market, position) {
+                    Ok(metrics) if metrics.base_asset_amount != 0 => {
+                        lps.push((user_info.clone(), market_index));
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(
+                        "{} failed to calculate LP position of {user_pubkey}-{market_index}: {e}",
+                        ctx.name
+                    ),
+                }
+            }
+        }
+
+        lps
+    }
+}
+
+impl<T: AccountProvider> Settler<T> for LpSettler {
+    fn settle<'a>(&'a mut self, ctx: &'a SettleContext<T>) -> LocalBoxFuture<'a, ()> {
+        self.try_settle(ctx).boxed_local()
+    }
+}
+
+/// Settle `lps` in one tx, one ix per position
+async fn settle<T: AccountProvider>(ctx: &SettleContext<T>, lps: &[LpToSettle]) {
+    let Some(mut builder) = ctx.init_tx() else {
+        error!("{} keeper sub-account not found", ctx.name);
+        return;
+    };
+    for (user_info, market_index) in lps {
+        builder = builder.settle_lp(user_info, *market_index);
+    }
+    let label = format!(
+        "settle lp {}",
+        lps.iter()
+            .map(|((pubkey, _), market_index)| format!("{pubkey}-{market_index}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    ctx.send(builder, &label).await;
+}
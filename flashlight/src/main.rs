@@ -1,40 +1,97 @@
-use std::{env, sync::Arc, time::Duration};
+use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use dotenv::dotenv;
+use drift::{math::constants::BASE_PRECISION_U64, state::user::MarketType};
 use flashlight::{
-    config::{BaseBotConfig, FillerConfig, GlobalConfig},
+    bundle_sender::{BundleSender, BundleSenderConfig},
+    config::{
+        BaseBotConfig, Config, ConfigError, ConfigOverrides, FillerConfig, JitMakerConfig,
+        JitMarketConfig, LiquidatorConfig, UserPnlSettlerConfig,
+    },
     filler::FillerBot,
     funding_rate_updater::FundingRateUpdaterBot,
+    if_revenue_settler::{IfRevenueSettler, IfRevenueSettlerBot},
+    jit_maker::JitMakerBot,
+    liquidator::LiquidatorBot,
+    lp_settler::{LpSettler, LpSettlerBot},
     metrics::RuntimeSpec,
+    runner::BotRunner,
+    spot_filler::SpotFillerBot,
     trigger::TriggerBot,
+    types::Bot,
+    user_management::UserManager,
+    user_pnl_settler::{UserPnlSettler, UserPnlSettlerBot},
 };
-use log::info;
-use sdk::{
-    blockhash_subscriber::BlockhashSubscriber,
-    drift_client::DriftClient,
-    priority_fee::{
-        priority_fee_subscriber::PriorityFeeSubscriber, types::PriorityFeeSubscriberConfig,
-    },
-    slot_subscriber::SlotSubscriber,
-    types::Context,
-    usermap::UserMap,
-    utils::load_keypair_multi_format,
-    RpcAccountProvider, Wallet,
-};
-use solana_sdk::commitment_config::CommitmentConfig;
+use log::error;
+use sdk::{types::Context, utils::load_keypair_multi_format};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
+    /// Path to a YAML or TOML config file
+    #[arg(long, short, global = true)]
+    config_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    overrides: ConfigOverrides,
+
+    /// Run a single bot, by default every bot enabled in the config file is run
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Initialize user
-    InitUser {},
+    /// Initialize the keeper's user stats and sub-account
+    InitUser {
+        #[arg(long, default_value_t = 0)]
+        sub_account_id: u16,
+
+        /// Account name, defaults to "Main Account" for sub-account 0
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Deposit from the wallet's associated token account
+    Deposit {
+        /// Amount in tokens, e.g. 100.5 USDC
+        #[arg(long)]
+        amount: f64,
+
+        /// Spot market to deposit, default: 0 (USDC)
+        #[arg(long, default_value_t = 0)]
+        market_index: u16,
+
+        #[arg(long, default_value_t = 0)]
+        sub_account_id: u16,
+    },
+
+    /// Withdraw to the wallet's associated token account without opening a borrow
+    Withdraw {
+        /// Amount in tokens, e.g. 100.5 USDC
+        #[arg(long)]
+        amount: f64,
+
+        /// Spot market to withdraw, default: 0 (USDC)
+        #[arg(long, default_value_t = 0)]
+        market_index: u16,
+
+        #[arg(long, default_value_t = 0)]
+        sub_account_id: u16,
+    },
+
+    /// Cancel all open orders
+    CancelOrders {
+        #[arg(long, default_value_t = 0)]
+        sub_account_id: u16,
+    },
+
+    /// Close perp positions with reduce only market orders
+    ClosePositions {
+        #[arg(long, default_value_t = 0)]
+        sub_account_id: u16,
+    },
 
     /// Just In Time Auction Bot
     Jit {},
@@ -42,11 +99,165 @@ enum Commands {
     /// Order Matching Bot
     Filler {},
 
+    /// Spot Order Matching Bot
+    SpotFiller {},
+
     /// Enable Funding Rate updater bot
     FundingRateUpdater {},
 
     /// Enable Triggering bot
     Trigger {},
+
+    /// Enable Liquidator bot
+    Liquidator {},
+
+    /// Enable User PnL Settler bot
+    UserPnlSettler {},
+
+    /// Enable Insurance Fund Revenue Settler bot
+    IfRevenueSettler {},
+
+    /// Enable LP Settler bot
+    LpSettler {},
+}
+
+fn base_config(bot_id: &str) -> BaseBotConfig {
+    BaseBotConfig {
+        bot_id: bot_id.to_string(),
+        ..BaseBotConfig::default()
+    }
+}
+
+/// Keep only the bot selected by `command`, falling back to its default config
+fn select_bot(config: &mut Config, command: &Commands) {
+    let bots = std::mem::take(&mut config.bot_configs);
+    let selected = &mut config.bot_configs;
+    match command {
+        Commands::InitUser { .. }
+        | Commands::Deposit { .. }
+        | Commands::Withdraw { .. }
+        | Commands::CancelOrders { .. }
+        | Commands::ClosePositions { .. } => {}
+        Commands::Jit {} => {
+            selected.jit_maker = Some(bots.jit_maker.unwrap_or_else(|| JitMakerConfig {
+                base_config: base_config("jit_maker"),
+                market_configs: vec![JitMarketConfig {
+                    market_index: 0,
+                    market_type: MarketType::Perp,
+                    spread: 0.001,
+                    max_position: 10 * BASE_PRECISION_U64,
+                    sub_account_id: 0,
+                }],
+                max_leverage: Some(1.0),
+                ..JitMakerConfig::default()
+            }));
+        }
+        Commands::Filler {} => {
+            selected.filler = Some(bots.filler.unwrap_or_else(|| FillerConfig {
+                base_config: base_config("filler"),
+                ..FillerConfig::default()
+            }));
+        }
+        Commands::SpotFiller {} => {
+            selected.spot_filler = Some(bots.spot_filler.unwrap_or_else(|| FillerConfig {
+                base_config: base_config("spot_filler"),
+                ..FillerConfig::default()
+            }));
+        }
+        Commands::FundingRateUpdater {} => {
+            selected.funding_rate_updater = Some(
+                bots.funding_rate_updater
+                    .unwrap_or_else(|| base_config("funding_rate_updater")),
+            );
+        }
+        Commands::Trigger {} => {
+            selected.trigger = Some(bots.trigger.unwrap_or_else(|| base_config("trigger")));
+        }
+        Commands::Liquidator {} => {
+            selected.liquidator = Some(bots.liquidator.unwrap_or_else(|| LiquidatorConfig {
+                base_config: base_config("liquidator"),
+                ..LiquidatorConfig::default()
+            }));
+        }
+        Commands::UserPnlSettler {} => {
+            selected.user_pnl_settler =
+                Some(
+                    bots.user_pnl_settler
+                        .unwrap_or_else(|| UserPnlSettlerConfig {
+                            base_config: base_config("user_pnl_settler"),
+                            ..UserPnlSettlerConfig::default()
+                        }),
+                );
+        }
+        Commands::IfRevenueSettler {} => {
+            selected.if_revenue_settler = Some(
+                bots.if_revenue_settler
+                    .unwrap_or_else(|| base_config("if_revenue_settler")),
+            );
+        }
+        Commands::LpSettler {} => {
+            selected.lp_settler =
+                Some(bots.lp_settler.unwrap_or_else(|| base_config("lp_settler")));
+        }
+    }
+}
+
+/// Run an account management command, see `is_user_command`
+async fn run_user_command(manager: &UserManager, command: &Commands) -> Result<(), String> {
+    match command {
+        Commands::InitUser {
+            sub_account_id,
+            name,
+        } => manager.init_user(*sub_account_id, name.as_deref()).await,
+        Commands::Deposit {
+            amount,
+            market_index,
+            sub_account_id,
+        } => {
+            manager
+                .deposit(*sub_account_id, *market_index, *amount)
+                .await
+        }
+        Commands::Withdraw {
+            amount,
+            market_index,
+            sub_account_id,
+        } => {
+            manager
+                .withdraw(*sub_account_id, *market_index, *amount)
+                .await
+        }
+        Commands::CancelOrders { sub_account_id } => manager.cancel_orders(*sub_account_id).await,
+        Commands::ClosePositions { sub_account_id } => {
+            manager.close_positions(*sub_account_id).await
+        }
+        _ => unreachable!("not an account management command"),
+    }
+}
+
+fn is_user_command(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::InitUser { .. }
+            | Commands::Deposit { .. }
+            | Commands::Withdraw { .. }
+            | Commands::CancelOrders { .. }
+            | Commands::ClosePositions { .. }
+    )
+}
+
+fn load_config(cli: &Cli) -> Result<Config, ConfigError> {
+    let mut config = match &cli.config_file {
+        Some(path) => Config::from_file(path)?,
+        None => Config::default(),
+    };
+    if let Some(command) = &cli.command {
+        select_bot(&mut config, command);
+    }
+    config.apply_overrides(&cli.overrides)?;
+    config.validate()?;
+
+    Ok(config)
 }
 
 #[tokio::main]
@@ -55,121 +266,178 @@ async fn main() {
     dotenv().ok();
     env_logger::init();
 
-    let endpoint = env::var("RPC_URL").expect("RPC_URL must be set");
-    let private_key = env::var("PRIVATE_KEY").expect("SECRET_KEY must be set");
-    let websocket_url = env::var("WEBSOCKET_URL").expect("WEBSOCKET_URL must be set");
-    let wallet = Wallet::new(load_keypair_multi_format(&private_key).expect("valid keypair"));
-    let account_provider = RpcAccountProvider::new(&endpoint);
-
-    let mut drift_client: DriftClient<RpcAccountProvider> =
-        DriftClient::new(Context::DevNet, account_provider, &wallet)
-            .await
-            .expect("fail to construct drift client");
-    drift_client.add_user(0).await.expect("add user");
-    drift_client
-        .subscribe()
-        .await
-        .expect("drift client subscribing");
-
-    let mut slot_subscriber = SlotSubscriber::new(&websocket_url);
-    slot_subscriber.subscribe().await.expect("subscribing slot");
-
-    let lamports_balance = drift_client
-        .backend
-        .rpc_client
-        .get_balance(&wallet.authority())
-        .await
-        .expect("get balance");
-
-    info!("Wallet pubkey: {}", &wallet.authority());
-    info!("SOL balance: {}", lamports_balance / 10 * 9);
-
-    match cli.command {
-        Commands::InitUser {} => {
-            // let keypair = read_keypair_file(&private_key).expect("read keypair");
-            // let secret_key_slices = keypair.secret().to_bytes();
-            // let key = bs58::encode(keypair.secret()).into_string();
-            // let key = String::from_utf8(secret_key_slices.to_vec()).unwrap();
-
-            // println!("{:?}", wallet.signer());
-        }
-        Commands::Jit {} => {}
-        Commands::Filler {} => {
-            let drift_client = Arc::new(drift_client);
-            let base_config = BaseBotConfig {
-                bot_id: "filler".to_string(),
-                dry_run: true,
-                metrics_port: Some(9465),
-                run_once: Some(true),
-            };
-
-            let mut user_map = UserMap::new(CommitmentConfig::confirmed(), &endpoint, true, None);
-            user_map.subscribe().await.expect("subscribing usermap");
-            let priority_fee_subscriber =
-                PriorityFeeSubscriber::new(PriorityFeeSubscriberConfig::new(drift_client.clone()))
-                    .expect("construct PriorityFeeSubscriber");
-
-            let mut bot = FillerBot::new(
-                &websocket_url,
-                slot_subscriber,
-                None,
-                drift_client.clone(),
-                user_map,
-                RuntimeSpec::default(),
-                GlobalConfig::default(),
-                FillerConfig {
-                    base_config,
-                    ..FillerConfig::default()
-                },
-                priority_fee_subscriber,
-                BlockhashSubscriber::new(60, endpoint),
-                None,
-            )
-            .await;
-
-            bot.init().await;
-
-            bot.start_interval_loop().await;
+    let config = match load_config(&cli) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("invalid config: {e}");
+            std::process::exit(1);
         }
-        Commands::FundingRateUpdater {} => {
-            let config = BaseBotConfig {
-                bot_id: "funding_rate_updater".to_string(),
-                dry_run: true,
-                metrics_port: Some(9465),
-                run_once: Some(true),
-            };
-
-            let mut bot: FundingRateUpdaterBot<RpcAccountProvider> =
-                FundingRateUpdaterBot::new(drift_client, config);
-            if let Err(e) = bot.init().await {
-                println!("{e}");
-            }
+    };
+    let global_config = &config.global;
+    // validated by `Config::validate`
+    let endpoint = global_config.endpoint.clone().unwrap();
+    let websocket_url = global_config.ws_endpoint.clone().unwrap();
+    let private_key = global_config.keeper_private_key.clone().unwrap();
+    let drift_env = global_config.drift_env.unwrap_or(Context::DevNet);
 
-            if let Err(e) = bot
-                .start_interval_loop(Duration::from_secs(2).as_millis() as u64)
-                .await
-            {
-                println!("{e}");
-            }
+    let dry_run = cli.overrides.dry_run.unwrap_or(false);
+    if let Some(command) = cli.command.as_ref().filter(|c| is_user_command(c)) {
+        let result = match UserManager::new(global_config, dry_run).await {
+            Ok(manager) => run_user_command(&manager, command).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("{e}");
+            std::process::exit(1);
         }
-        Commands::Trigger {} => {
-            let config = BaseBotConfig {
-                bot_id: "trigger".to_string(),
-                dry_run: true,
-                metrics_port: Some(9465),
-                run_once: Some(true),
-            };
-
-            let mut user_map = UserMap::new(CommitmentConfig::confirmed(), &endpoint, true, None);
-            user_map.subscribe().await.expect("subscribing usermap");
-
-            let mut bot: TriggerBot =
-                TriggerBot::new(Arc::new(drift_client), slot_subscriber, user_map, config);
-            if let Err(e) = bot.init().await {
-                println!("{e}");
+        return;
+    }
+
+    if global_config.init_user.unwrap_or(false)
+        || global_config.force_deposit.is_some()
+        || global_config.cancel_open_orders.unwrap_or(false)
+        || global_config.close_open_positions.unwrap_or(false)
+    {
+        let result = match UserManager::new(global_config, dry_run).await {
+            Ok(manager) => {
+                manager
+                    .run_startup_tasks(global_config, &config.sub_account_ids())
+                    .await
             }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("startup account tasks failed: {e}");
+            std::process::exit(1);
+        }
+    }
+
+    let mut runner = match BotRunner::new(&config).await {
+        Ok(runner) => runner,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let drift_client = runner.drift_client();
+    let slot_subscriber = runner.slot_subscriber();
+    let runtime_spec = RuntimeSpec::new(&endpoint, drift_env, drift_client.wallet().authority());
 
-            bot.start_interval_loop().await;
+    let bundle_sender = || {
+        if !global_config.use_jito.unwrap_or(false) {
+            return None;
         }
+        let tip_payer = global_config
+            .jito_auth_private_key
+            .as_deref()
+            .unwrap_or(&private_key);
+        Some(BundleSender::new(
+            drift_client.backend.rpc_client.clone(),
+            load_keypair_multi_format(tip_payer).expect("valid jito tip payer keypair"),
+            slot_subscriber.clone(),
+            BundleSenderConfig::from(global_config),
+        ))
+    };
+    let new_filler = |filler_config: FillerConfig| {
+        FillerBot::new(
+            &websocket_url,
+            runner.slot_subscriber(),
+            None,
+            runner.drift_client(),
+            runner.user_map(),
+            runtime_spec.clone(),
+            global_config.clone(),
+            filler_config,
+            runner.priority_fee_subscriber(),
+            runner.blockhash_subscriber(),
+            bundle_sender(),
+        )
+    };
+
+    let bots = &config.bot_configs;
+    let mut enabled: Vec<Box<dyn Bot>> = Vec::new();
+
+    if let Some(jit_config) = bots.jit_maker.clone() {
+        enabled.push(Box::new(JitMakerBot::new(
+            runner.drift_client(),
+            runner.tx_sender(),
+            runner.slot_subscriber(),
+            runner.user_map(),
+            jit_config,
+        )));
+    }
+
+    if let Some(filler_config) = bots.filler.clone() {
+        enabled.push(Box::new(new_filler(filler_config).await));
+    }
+
+    if let Some(filler_config) = bots.spot_filler.clone() {
+        enabled.push(Box::new(SpotFillerBot::new(
+            new_filler(filler_config).await,
+        )));
+    }
+
+    if let Some(base_config) = bots.funding_rate_updater.clone() {
+        enabled.push(Box::new(FundingRateUpdaterBot::new(
+            (*runner.drift_client()).clone(),
+            runner.tx_sender(),
+            base_config,
+        )));
+    }
+
+    if let Some(base_config) = bots.trigger.clone() {
+        enabled.push(Box::new(TriggerBot::new(
+            runner.drift_client(),
+            runner.tx_sender(),
+            runner.slot_subscriber(),
+            runner.user_map(),
+            base_config,
+        )));
+    }
+
+    if let Some(liquidator_config) = bots.liquidator.clone() {
+        enabled.push(Box::new(LiquidatorBot::new(
+            runner.drift_client(),
+            runner.tx_sender(),
+            runner.user_map(),
+            liquidator_config,
+        )));
+    }
+
+    if let Some(settler_config) = bots.user_pnl_settler.clone() {
+        enabled.push(Box::new(UserPnlSettlerBot::new(
+            runner.drift_client(),
+            runner.tx_sender(),
+            settler_config.base_config.clone(),
+            UserPnlSettler::new(runner.user_map(), &settler_config),
+        )));
+    }
+
+    if let Some(base_config) = bots.if_revenue_settler.clone() {
+        enabled.push(Box::new(IfRevenueSettlerBot::new(
+            runner.drift_client(),
+            runner.tx_sender(),
+            base_config,
+            IfRevenueSettler,
+        )));
+    }
+
+    if let Some(base_config) = bots.lp_settler.clone() {
+        enabled.push(Box::new(LpSettlerBot::new(
+            runner.drift_client(),
+            runner.tx_sender(),
+            base_config,
+            LpSettler::new(runner.user_map()),
+        )));
+    }
+
+    if enabled.is_empty() {
+        error!("no bots enabled, add a section under botConfigs or pass a bot subcommand");
+        std::process::exit(1);
+    }
+
+    for bot in enabled {
+        runner.add_bot(bot);
     }
+    runner.run().await;
 }
@@ -1,6 +1,26 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use prometheus::{
+    exponential_buckets, Encoder, Gauge, Histogram, HistogramOpts, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use sdk::{constants::PROGRAM_ID, types::Context};
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+
+use crate::http::{self, Response};
+
+/// Port the `/metrics` endpoint is served on when neither the bot nor the global config set one
+pub const DEFAULT_METRICS_PORT: u16 = 9464;
+
+/// registries served by this process, keyed by port
+static REGISTRIES: OnceLock<Mutex<HashMap<u16, Registry>>> = OnceLock::new();
+
 /// RuntimeSpec is the attributes of the runtime environment, used to
 /// distinguish this metric set from others
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct RuntimeSpec {
     pub rpc_endpoint: String,
     pub drift_env: String,
@@ -10,7 +30,159 @@ pub struct RuntimeSpec {
 }
 
 impl RuntimeSpec {
-    pub fn new() -> Self {
-        todo!()
+    pub fn new(rpc_endpoint: &str, drift_env: Context, wallet_authority: &Pubkey) -> Self {
+        // only keep the host, rpc urls commonly carry api keys in their path or query
+        let rpc_endpoint = reqwest::Url::parse(rpc_endpoint)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let drift_env = match drift_env {
+            Context::DevNet => "devnet",
+            Context::MainNet => "mainnet-beta",
+        };
+
+        Self {
+            rpc_endpoint,
+            drift_env: drift_env.to_string(),
+            commit: std::env::var("COMMIT")
+                .unwrap_or_else(|_| option_env!("COMMIT").unwrap_or("unknown").to_string()),
+            drift_pid: PROGRAM_ID.to_string(),
+            wallet_authority: wallet_authority.to_string(),
+        }
+    }
+
+    fn labels(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("rpc_endpoint".to_string(), self.rpc_endpoint.clone()),
+            ("drift_env".to_string(), self.drift_env.clone()),
+            ("commit".to_string(), self.commit.clone()),
+            ("drift_pid".to_string(), self.drift_pid.clone()),
+            (
+                "wallet_authority".to_string(),
+                self.wallet_authority.clone(),
+            ),
+        ])
+    }
+}
+
+/// Returns the registry served on `port`, starting its `/metrics` server on first use
+///
+/// Bots sharing a port share the registry, their metrics are told apart by the `bot_id` label.
+/// Every metric registered carries the `runtime_spec` labels.
+pub fn registry_for_port(port: u16, runtime_spec: &RuntimeSpec) -> Result<Registry, String> {
+    let mut registries = REGISTRIES
+        .get_or_init(Default::default)
+        .lock()
+        .expect("acquired");
+    if let Some(registry) = registries.get(&port) {
+        return Ok(registry.clone());
+    }
+
+    let registry = Registry::new_custom(None, Some(runtime_spec.labels()))
+        .map_err(|e| format!("invalid runtime spec labels: {e}"))?;
+    let listener = http::bind(port).map_err(|e| format!("metrics: {e}"))?;
+    log::info!("serving metrics on 0.0.0.0:{port}/metrics");
+    let served = registry.clone();
+    tokio::spawn(http::serve(listener, move |path| match path {
+        "/metrics" => encode(&served),
+        _ => Response::not_found(),
+    }));
+    registries.insert(port, registry.clone());
+
+    Ok(registry)
+}
+
+fn encode(registry: &Registry) -> Response {
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    match encoder.encode(&registry.gather(), &mut body) {
+        Ok(()) => Response::ok(encoder.format_type(), body),
+        Err(e) => Response::new("500 Internal Server Error", "text/plain", e.to_string()),
+    }
+}
+
+/// Metrics reported by the filler bots
+pub struct FillerMetrics {
+    /// nodes sent in fill txs
+    pub fill_attempts: IntCounter,
+    /// nodes filled by landed fill txs
+    pub landed_fills: IntCounter,
+    /// failed fill ixs, by the error parsed from the tx logs
+    pub failed_fills: IntCounterVec,
+    pub throttled_nodes: IntGauge,
+    /// seconds between sending a tx and seeing it landed
+    pub tx_confirmation_latency: Histogram,
+    /// compute unit price set on fill txs, in micro lamports
+    pub priority_fee: Histogram,
+    pub sol_balance: Gauge,
+    /// orders in the dlob, by market type
+    pub dlob_size: IntGaugeVec,
+    /// pending tx sigs evicted before they could be confirmed
+    pub evicted_tx_sigs: IntCounter,
+}
+
+impl FillerMetrics {
+    pub fn new(registry: &Registry, bot_id: &str) -> prometheus::Result<Self> {
+        let opts = |name: &str, help: &str| Opts::new(name, help).const_label("bot_id", bot_id);
+        let histogram_opts = |name: &str, help: &str, buckets: Vec<f64>| {
+            HistogramOpts::new(name, help)
+                .const_label("bot_id", bot_id)
+                .buckets(buckets)
+        };
+
+        let metrics = Self {
+            fill_attempts: IntCounter::with_opts(opts(
+                "filler_fill_attempts_total",
+                "Nodes sent in fill txs",
+            ))?,
+            landed_fills: IntCounter::with_opts(opts(
+                "filler_landed_fills_total",
+                "Nodes filled by landed fill txs",
+            ))?,
+            failed_fills: IntCounterVec::new(
+                opts("filler_failed_fills_total", "Failed fill ixs by error type"),
+                &["error_type"],
+            )?,
+            throttled_nodes: IntGauge::with_opts(opts(
+                "filler_throttled_nodes",
+                "Nodes currently throttled",
+            ))?,
+            tx_confirmation_latency: Histogram::with_opts(histogram_opts(
+                "filler_tx_confirmation_latency_seconds",
+                "Time from sending a tx to seeing it landed",
+                vec![0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0],
+            ))?,
+            priority_fee: Histogram::with_opts(histogram_opts(
+                "filler_priority_fee_micro_lamports",
+                "Compute unit price set on fill txs",
+                exponential_buckets(10.0, 4.0, 10)?,
+            ))?,
+            sol_balance: Gauge::with_opts(opts("filler_sol_balance", "Filler SOL balance"))?,
+            dlob_size: IntGaugeVec::new(
+                opts("filler_dlob_size", "Orders in the DLOB by market type"),
+                &["market_type"],
+            )?,
+            evicted_tx_sigs: IntCounter::with_opts(opts(
+                "filler_evicted_pending_tx_sigs_total",
+                "Pending tx sigs evicted before they were confirmed",
+            ))?,
+        };
+
+        registry.register(Box::new(metrics.fill_attempts.clone()))?;
+        registry.register(Box::new(metrics.landed_fills.clone()))?;
+        registry.register(Box::new(metrics.failed_fills.clone()))?;
+        registry.register(Box::new(metrics.throttled_nodes.clone()))?;
+        registry.register(Box::new(metrics.tx_confirmation_latency.clone()))?;
+        registry.register(Box::new(metrics.priority_fee.clone()))?;
+        registry.register(Box::new(metrics.sol_balance.clone()))?;
+        registry.register(Box::new(metrics.dlob_size.clone()))?;
+        registry.register(Box::new(metrics.evicted_tx_sigs.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub fn set_sol_balance(&self, lamports: u64) {
+        self.sol_balance
+            .set(lamports as f64 / LAMPORTS_PER_SOL as f64);
     }
 }
@@ -0,0 +1,279 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use log::{error, info, warn};
+use sdk::{
+    blockhash_subscriber::BlockhashSubscriber,
+    drift_client::DriftClient,
+    priority_fee::{
+        priority_fee_subscriber::PriorityFeeSubscriber, types::PriorityFeeSubscriberConfig,
+    },
+    slot_subscriber::SlotSubscriber,
+    tx::tx_sender::TxSender,
+    types::Context,
+    usermap::UserMap,
+    utils::load_keypair_multi_format,
+    RpcAccountProvider, Wallet,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use tokio::{sync::RwLock, time::interval};
+
+use crate::{
+    config::Config,
+    health::{HealthServer, DEFAULT_HEALTH_CHECK_PORT},
+    types::{Bot, SharedPriorityFeeSubscriber},
+};
+
+const BLOCKHASH_REFRESH_INTERVAL_MS: u64 = 1_000;
+const PRIORITY_FEE_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs the bots enabled in one config in a single process
+///
+/// The `DriftClient`, `UserMap`, `SlotSubscriber`, `PriorityFeeSubscriber` and
+/// `BlockhashSubscriber` are subscribed once and shared by every bot. `run` stops the bots on
+/// SIGTERM or ctrl-c and shuts them down before unsubscribing the shared components.
+pub struct BotRunner {
+    drift_client: Arc<DriftClient<RpcAccountProvider>>,
+    user_map: UserMap,
+    user_map_snapshot: Option<String>,
+    slot_subscriber: SlotSubscriber,
+    priority_fee_subscriber: SharedPriorityFeeSubscriber<RpcAccountProvider>,
+    blockhash_subscriber: BlockhashSubscriber,
+    tx_sender: Arc<dyn TxSender>,
+    health_server: Option<HealthServer>,
+    health_check_port: u16,
+    bots: Vec<Box<dyn Bot>>,
+}
+
+impl BotRunner {
+    /// Connect and subscribe the components shared by the bots of `config`
+    pub async fn new(config: &Config) -> Result<Self, String> {
+        let global_config = &config.global;
+        // validated by `Config::validate`
+        let endpoint = global_config.endpoint.clone().unwrap();
+        let websocket_url = global_config.ws_endpoint.clone().unwrap();
+        let private_key = global_config.keeper_private_key.clone().unwrap();
+        let drift_env = global_config.drift_env.unwrap_or(Context::DevNet);
+
+        let wallet = Wallet::new(
+            load_keypair_multi_format(&private_key)
+                .map_err(|e| format!("invalid keeper private key: {e}"))?,
+        );
+        let mut drift_client =
+            DriftClient::new(drift_env, RpcAccountProvider::new(&endpoint), &wallet)
+                .await
+                .map_err(|e| format!("failed to construct drift client: {e}"))?;
+        for sub_account_id in config.sub_account_ids() {
+            drift_client
+                .add_user(sub_account_id)
+                .await
+                .map_err(|e| format!("failed to add sub-account {sub_account_id}: {e}"))?;
+        }
+        drift_client
+            .subscribe()
+            .await
+            .map_err(|e| format!("failed to subscribe drift client: {e}"))?;
+        let drift_client = Arc::new(drift_client);
+
+        let mut slot_subscriber = SlotSubscriber::new(&websocket_url);
+        slot_subscriber
+            .subscribe()
+            .await
+            .map_err(|e| format!("failed to subscribe slots: {e}"))?;
+
+        let mut user_map = UserMap::new(CommitmentConfig::confirmed(), &endpoint, true, None);
+        let user_map_snapshot = global_config.user_map_snapshot.clone();
+        if let Some(path) = user_map_snapshot
+            .as_ref()
+            .filter(|path| Path::new(path).exists())
+        {
+            match user_map.load_snapshot(path) {
+                Ok(slot) => info!(
+                    "loaded {} users from user map snapshot at slot {slot}",
+                    user_map.size()
+                ),
+                Err(e) => warn!("failed to load user map snapshot {path}: {e}"),
+            }
+        }
+        user_map
+            .subscribe()
+            .await
+            .map_err(|e| format!("failed to subscribe user map: {e}"))?;
+
+        let mut priority_fee_subscriber =
+            PriorityFeeSubscriber::new(PriorityFeeSubscriberConfig::new(drift_client.clone()))
+                .map_err(|e| format!("failed to construct priority fee subscriber: {e}"))?;
+        if let Err(e) = priority_fee_subscriber.subscribe().await {
+            warn!("failed to load priority fees: {e}");
+        }
+
+        let mut blockhash_subscriber =
+            BlockhashSubscriber::new(BLOCKHASH_REFRESH_INTERVAL_MS, endpoint);
+        blockhash_subscriber
+            .subscribe()
+            .await
+            .map_err(|e| format!("failed to subscribe blockhashes: {e}"))?;
+
+        let lamports_balance = drift_client
+            .backend
+            .rpc_client
+            .get_balance(wallet.authority())
+            .await
+            .map_err(|e| format!("failed to get balance: {e}"))?;
+        info!("Wallet pubkey: {}", wallet.authority());
+        info!("SOL balance: {}", lamports_balance / 10 * 9);
+
+        let tx_sender = global_config.tx_sender(drift_client.backend.rpc_client.clone());
+
+        let mut health_server = HealthServer::new(
+            slot_subscriber.clone(),
+            drift_client.backend.oracle_map.clone(),
+        );
+        health_server.add_user_map("shared", user_map.clone());
+
+        Ok(Self {
+            drift_client,
+            user_map,
+            user_map_snapshot,
+            slot_subscriber,
+            priority_fee_subscriber: Arc::new(RwLock::new(priority_fee_subscriber)),
+            blockhash_subscriber,
+            tx_sender,
+            health_server: Some(health_server),
+            health_check_port: global_config
+                .health_check_port
+                .unwrap_or(DEFAULT_HEALTH_CHECK_PORT),
+            bots: vec![],
+        })
+    }
+
+    pub fn drift_client(&self) -> Arc<DriftClient<RpcAccountProvider>> {
+        self.drift_client.clone()
+    }
+
+    pub fn user_map(&self) -> UserMap {
+        self.user_map.clone()
+    }
+
+    pub fn slot_subscriber(&self) -> SlotSubscriber {
+        self.slot_subscriber.clone()
+    }
+
+    pub fn priority_fee_subscriber(&self) -> SharedPriorityFeeSubscriber<RpcAccountProvider> {
+        self.priority_fee_subscriber.clone()
+    }
+
+    pub fn blockhash_subscriber(&self) -> BlockhashSubscriber {
+        self.blockhash_subscriber.clone()
+    }
+
+    pub fn tx_sender(&self) -> Arc<dyn TxSender> {
+        self.tx_sender.clone()
+    }
+
+    pub fn add_bot(&mut self, bot: Box<dyn Bot>) {
+        if let Some(health_server) = &mut self.health_server {
+            health_server.add_bot(bot.name(), bot.watchdog());
+        }
+        self.bots.push(bot);
+    }
+
+    /// Init every bot and run their loops until they stop or a shutdown signal is received
+    pub async fn run(mut self) {
+        if let Some(health_server) = self.health_server.take() {
+            if let Err(e) = health_server.serve(self.health_check_port) {
+                error!("{e}");
+            }
+        }
+
+        let mut bots = Vec::with_capacity(self.bots.len());
+        for mut bot in std::mem::take(&mut self.bots) {
+            match bot.init().await {
+                Ok(()) => {
+                    bot.watchdog().set_ready();
+                    bots.push(bot);
+                }
+                Err(e) => error!("{} failed to init: {e}", bot.name()),
+            }
+        }
+
+        if bots.is_empty() {
+            error!("no bots initialized");
+        } else {
+            info!("running {} bot(s)", bots.len());
+            let loops = futures_util::future::join_all(bots.iter_mut().map(|bot| bot.start()));
+            tokio::select! {
+                _ = loops => info!("all bots stopped"),
+                _ = refresh_priority_fees(self.priority_fee_subscriber.clone()) => {}
+                _ = shutdown_signal() => info!("shutdown signal received, stopping bots"),
+            }
+        }
+
+        for bot in bots.iter_mut() {
+            if let Err(e) = bot.shutdown().await {
+                error!("{} failed to shut down: {e}", bot.name());
+            }
+        }
+        self.unsubscribe().await;
+        info!("shut down");
+    }
+
+    async fn unsubscribe(&mut self) {
+        if let Some(path) = &self.user_map_snapshot {
+            match self.user_map.save_snapshot(path) {
+                Ok(()) => info!("saved user map snapshot to {path}"),
+                Err(e) => warn!("failed to save user map snapshot {path}: {e}"),
+            }
+        }
+        if let Err(e) = self.user_map.unsubscribe().await {
+            warn!("failed to unsubscribe user map: {e}");
+        }
+        if let Err(e) = self.slot_subscriber.unsubscribe().await {
+            warn!("failed to unsubscribe slots: {e}");
+        }
+        self.priority_fee_subscriber
+            .write()
+            .await
+            .unsubscribe()
+            .await;
+        if let Err(e) = self.drift_client.unsubscribe().await {
+            warn!("failed to unsubscribe drift client: {e}");
+        }
+    }
+}
+
+async fn refresh_priority_fees(subscriber: SharedPriorityFeeSubscriber<RpcAccountProvider>) {
+    let mut interval = interval(PRIORITY_FEE_REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+        // fetch outside the lock so bots building txs aren't blocked on the rpc
+        let query = subscriber.read().await.query();
+        match query.fetch().await {
+            Ok(sample) => subscriber.write().await.apply(sample),
+            Err(e) => warn!("failed to refresh priority fees: {e}"),
+        }
+    }
+}
+
+/// Resolves on SIGTERM (e.g. from kubernetes) or ctrl-c
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("failed to listen for SIGTERM: {e}");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,210 @@
+use std::{sync::Arc, time::Instant};
+
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use log::{error, info, warn};
+use sdk::{
+    drift_client::DriftClient,
+    transaction_builder::TransactionBuilder,
+    tx::tx_sender::TxSender,
+    types::{ProcessingTxParams, TxParams},
+    AccountProvider,
+};
+use tokio::time::{interval, Duration};
+
+use crate::{config::BaseBotConfig, health::Watchdog, types::Bot};
+
+const DEFAULT_SETTLE_INTERVAL_MS: u64 = 60_000;
+const SETTLE_CU_BUFFER_MULTIPLIER: f64 = 1.2;
+
+/// One kind of settling run periodically by a `SettlerBot`
+pub trait Settler<T: AccountProvider> {
+    /// Called once the keeper sub-account is known to be loaded
+    fn init(&mut self, _ctx: &SettleContext<T>) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Find what needs settling and send the settle txs with `ctx`
+    fn settle<'a>(&'a mut self, ctx: &'a SettleContext<T>) -> LocalBoxFuture<'a, ()>;
+}
+
+/// What a `Settler` needs to build and send its txs
+pub struct SettleContext<T: AccountProvider> {
+    pub name: String,
+    pub dry_run: bool,
+    pub drift_client: Arc<DriftClient<T>>,
+    pub tx_sender: Arc<dyn TxSender>,
+}
+
+impl<T: AccountProvider> SettleContext<T> {
+    /// Start a tx signed by the keeper sub-account
+    pub fn init_tx(&self) -> Option<TransactionBuilder> {
+        let user = self.drift_client.get_user(None)?;
+        self.drift_client.init_tx(&user.pubkey, false).ok()
+    }
+
+    /// Simulate `builder` for its compute units and send it, unless dry running or the
+    /// simulation fails
+    pub async fn send(&self, builder: TransactionBuilder<'_>, label: &str) {
+        if self.dry_run {
+            info!("{} dry run, not sending {label}", self.name);
+            return;
+        }
+
+        let builder = builder.tx_params(TxParams {
+            processing: ProcessingTxParams {
+                use_simulated_compute_units: Some(true),
+                compute_units_buffer_multipler: Some(SETTLE_CU_BUFFER_MULTIPLIER),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let tx = match self.drift_client.build_tx(builder).await {
+            Ok((_, Some(simulation))) if simulation.err.is_some() => {
+                warn!(
+                    "{} {label} failed simulation: {:?}, logs: {:?}",
+                    self.name, simulation.err, simulation.logs
+                );
+                return;
+            }
+            Ok((tx, _)) => tx,
+            Err(e) => {
+                error!("{} failed to build {label}: {e}", self.name);
+                return;
+            }
+        };
+
+        match self
+            .drift_client
+            .sign_and_send_with_sender(self.tx_sender.as_ref(), tx, false)
+            .await
+        {
+            Ok(sig) => info!("{} {label} tx: {sig}", self.name),
+            Err(e) => error!("{} failed to send {label}: {e}", self.name),
+        }
+    }
+}
+
+/// Runs a `Settler` on an interval, skipping a tick while the previous one is still settling
+pub struct SettlerBot<T: AccountProvider, S: Settler<T>> {
+    ctx: SettleContext<T>,
+    run_once: bool,
+    default_interval_ms: u64,
+    settler: S,
+
+    watchdog: Watchdog,
+    in_progress: bool,
+}
+
+impl<T: AccountProvider, S: Settler<T>> SettlerBot<T, S> {
+    pub fn new(
+        drift_client: Arc<DriftClient<T>>,
+        tx_sender: Arc<dyn TxSender>,
+        config: BaseBotConfig,
+        settler: S,
+    ) -> Self {
+        let default_interval_ms = DEFAULT_SETTLE_INTERVAL_MS;
+        Self {
+            ctx: SettleContext {
+                name: config.bot_id,
+                dry_run: config.dry_run,
+                drift_client,
+                tx_sender,
+            },
+            run_once: config.run_once.unwrap_or(false),
+            default_interval_ms,
+            settler,
+            watchdog: Watchdog::new(Duration::from_millis(default_interval_ms * 5)),
+            in_progress: false,
+        }
+    }
+
+    pub async fn init(&mut self) -> Result<(), String> {
+        if self.ctx.drift_client.get_user(None).is_none() {
+            return Err(format!(
+                "{} keeper sub-account is not loaded",
+                self.ctx.name
+            ));
+        }
+        self.settler.init(&self.ctx)?;
+
+        info!("{} inited", self.ctx.name);
+
+        Ok(())
+    }
+
+    /// Anything shared with other bots (e.g. the user map) is unsubscribed by the runner
+    pub async fn reset(&mut self) -> Result<(), String> {
+        self.in_progress = false;
+
+        Ok(())
+    }
+
+    pub async fn start_interval_loop(&mut self, interval_ms: u64) {
+        info!("{} Bot started! run_once {}", self.ctx.name, self.run_once);
+
+        if self.run_once {
+            self.try_settle().await;
+            return;
+        }
+
+        let mut interval = interval(Duration::from_millis(interval_ms));
+        loop {
+            interval.tick().await;
+            self.try_settle().await;
+        }
+    }
+
+    pub fn health_check(&self) -> bool {
+        self.watchdog.is_healthy()
+    }
+
+    pub fn watchdog(&self) -> Watchdog {
+        self.watchdog.clone()
+    }
+
+    async fn try_settle(&mut self) {
+        if self.in_progress {
+            info!("{} settle already in progress, skipping...", self.ctx.name);
+            return;
+        }
+        self.in_progress = true;
+        let start = Instant::now();
+
+        self.settler.settle(&self.ctx).await;
+
+        info!(
+            "{} try_settle took: {}ms",
+            self.ctx.name,
+            start.elapsed().as_millis()
+        );
+        self.watchdog.pat();
+        self.in_progress = false;
+    }
+}
+
+impl<T: AccountProvider, S: Settler<T>> Bot for SettlerBot<T, S> {
+    fn name(&self) -> &str {
+        &self.ctx.name
+    }
+
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        SettlerBot::init(self).boxed_local()
+    }
+
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        SettlerBot::reset(self).boxed_local()
+    }
+
+    fn start(&mut self) -> LocalBoxFuture<'_, ()> {
+        let interval_ms = self.default_interval_ms;
+        self.start_interval_loop(interval_ms).boxed_local()
+    }
+
+    fn health_check(&self) -> bool {
+        SettlerBot::health_check(self)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        SettlerBot::watchdog(self)
+    }
+}
@@ -0,0 +1,97 @@
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use sdk::AccountProvider;
+
+use crate::{filler::FillerBot, health::Watchdog, types::Bot};
+
+/// Fills spot orders against resting makers in the DLOB
+///
+/// Shares throttling, tx log parsing and tx confirmation with the perp `FillerBot`
+pub struct SpotFillerBot<'a, T>
+where
+    T: AccountProvider,
+{
+    filler: FillerBot<'a, T>,
+}
+
+impl<'a, T> SpotFillerBot<'a, T>
+where
+    T: AccountProvider + Clone,
+{
+    pub fn new(filler: FillerBot<'a, T>) -> Self {
+        Self { filler }
+    }
+
+    pub async fn init(&mut self) {
+        self.filler.init().await;
+    }
+
+    pub async fn reset(&mut self) {
+        self.filler.reset().await;
+    }
+
+    pub async fn start_interval_loop(&mut self) {
+        log::info!("spot filler bot started!");
+
+        let mut interval = tokio::time::interval(self.filler.polling_interval());
+        loop {
+            interval.tick().await;
+            self.filler.try_fill_spot().await;
+            self.filler.settle_pnls().await;
+            self.filler.confirm_pending_tx_sigs().await;
+            self.filler.watchdog().pat();
+
+            if self.filler.run_once() {
+                return;
+            }
+        }
+    }
+
+    pub fn health_check(&self) -> bool {
+        self.filler.health_check()
+    }
+
+    pub fn watchdog(&self) -> Watchdog {
+        self.filler.watchdog()
+    }
+}
+
+impl<'a, T> Bot for SpotFillerBot<'a, T>
+where
+    T: AccountProvider + Clone,
+{
+    fn name(&self) -> &str {
+        self.filler.name()
+    }
+
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        async move {
+            SpotFillerBot::init(self).await;
+            Ok(())
+        }
+        .boxed_local()
+    }
+
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        async move {
+            SpotFillerBot::reset(self).await;
+            Ok(())
+        }
+        .boxed_local()
+    }
+
+    fn start(&mut self) -> LocalBoxFuture<'_, ()> {
+        self.start_interval_loop().boxed_local()
+    }
+
+    fn health_check(&self) -> bool {
+        SpotFillerBot::health_check(self)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        SpotFillerBot::watchdog(self)
+    }
+
+    fn shutdown(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        self.filler.shutdown().boxed_local()
+    }
+}
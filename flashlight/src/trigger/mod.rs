@@ -5,74 +5,99 @@ use std::{
 };
 
 use drift::state::{perp_market::PerpMarket, spot_market::SpotMarket, user::MarketType};
+use futures_util::{future::LocalBoxFuture, FutureExt};
 use log::{error, info, warn};
 use sdk::{
     dlob::{
+        dlob_builder::DLOBBuilder,
         dlob_node::DLOBNode,
         dlob_subscriber::DLOBSubscriber,
         types::{DLOBSubscriptionConfig, DlobSource, SlotSource},
     },
     drift_client::DriftClient,
     slot_subscriber::SlotSubscriber,
-    tx::priority_fee_calculator::PriorityFeeCalculator,
+    tx::{priority_fee_calculator::PriorityFeeCalculator, tx_sender::TxSender},
     types::{BaseTxParams, ProcessingTxParams, TxParams},
     usermap::UserMap,
     RpcAccountProvider,
 };
-use tokio::{sync::oneshot, task::JoinHandle};
+use tokio::{sync::oneshot, task::JoinHandle, time::interval};
 
-use crate::{config::BaseBotConfig, util::get_node_to_trigger_signature};
+use crate::{
+    config::BaseBotConfig, health::Watchdog, types::Bot, util::get_node_to_trigger_signature,
+};
 
 // time to wait between triggering an order
 const TRIGGER_ORDER_COOLDOWN_MS: u64 = 10000;
+const DLOB_BUILD_INTERVAL_MS: u64 = 400; // apply user map diffs to the DLOB this often
+const DLOB_CONSISTENCY_CHECK_SLOTS: u64 = 150; // compare the incremental DLOB against a full rebuild this often
 
 #[allow(dead_code)]
 pub struct TriggerBot {
     name: String,
     dry_run: bool,
+    run_once: bool,
     default_interval_ms: u64,
 
     drift_client: Arc<DriftClient<RpcAccountProvider>>,
+    tx_sender: Arc<dyn TxSender>,
     slot_subscriber: SlotSubscriber,
     dlob_subscriber: Option<DLOBSubscriber<RpcAccountProvider>>,
-    triggering_nodes: HashMap<String, Instant>,
+    /// when each node was last triggered, shared across ticks for the cooldown
+    triggering_nodes: Arc<Mutex<HashMap<String, Instant>>>,
     periodic_task_mutex: Arc<Mutex<()>>,
     interval_tx: Option<oneshot::Sender<()>>,
     interval_handles: Option<JoinHandle<()>>,
     user_map: UserMap,
 
     priority_fee_calculator: PriorityFeeCalculator,
+
+    watchdog: Watchdog,
 }
 
 impl TriggerBot {
     pub fn new(
         drift_client: Arc<DriftClient<RpcAccountProvider>>,
+        tx_sender: Arc<dyn TxSender>,
         slot_subscriber: SlotSubscriber,
         user_map: UserMap,
         config: BaseBotConfig,
     ) -> Self {
+        let default_interval_ms = 1000;
         Self {
             name: config.bot_id,
             dry_run: config.dry_run,
-            default_interval_ms: 1000,
+            run_once: config.run_once.unwrap_or(false),
+            default_interval_ms,
             drift_client,
+            tx_sender,
             slot_subscriber,
             dlob_subscriber: None,
-            triggering_nodes: HashMap::new(),
+            triggering_nodes: Default::default(),
             periodic_task_mutex: Arc::new(Mutex::new(())),
             interval_tx: None,
             interval_handles: None,
             user_map,
             priority_fee_calculator: PriorityFeeCalculator::new(Instant::now(), None),
+            watchdog: Watchdog::new(Duration::from_millis(default_interval_ms * 20)),
         }
     }
 
     pub async fn init(&mut self) -> Result<(), String> {
         info!("{} initing", self.name);
 
+        // the user map and slot subscriber are shared and already subscribed
+        let dlob_builder = Arc::new(tokio::sync::Mutex::new(DLOBBuilder::new(
+            self.slot_subscriber.clone(),
+            self.user_map.clone(),
+            DLOB_BUILD_INTERVAL_MS,
+            DLOB_CONSISTENCY_CHECK_SLOTS,
+        )));
+        DLOBBuilder::spawn_build_loop(dlob_builder.clone()).await;
+
         self.dlob_subscriber = Some(DLOBSubscriber::new(DLOBSubscriptionConfig {
             drift_client: self.drift_client.clone(),
-            dlob_source: DlobSource::UserMap(self.user_map.clone()),
+            dlob_source: DlobSource::DLOBBuilder(dlob_builder),
             update_frequency: Duration::from_millis(self.default_interval_ms - 500),
             slot_source: SlotSource::SlotSubscriber(self.slot_subscriber.clone()),
         }));
@@ -83,21 +108,37 @@ impl TriggerBot {
         Ok(())
     }
 
+    /// The user map is shared with other bots and unsubscribed by the runner
     pub async fn reset(&mut self) -> Result<(), String> {
         if let Some(subscriber) = &mut self.dlob_subscriber {
             subscriber.unsubscribe().await;
         }
 
-        self.user_map
-            .unsubscribe()
-            .await
-            .map_err(|e| e.to_string())?;
-
         Ok(())
     }
 
     pub async fn start_interval_loop(&mut self) {
-        self.try_trigger().await;
+        info!("{} Bot started! run_once {}", self.name, self.run_once);
+
+        if self.run_once {
+            self.try_trigger().await;
+            return;
+        }
+
+        let mut interval = interval(Duration::from_millis(self.default_interval_ms));
+        loop {
+            interval.tick().await;
+            self.try_trigger().await;
+            self.watchdog.pat();
+        }
+    }
+
+    pub fn health_check(&self) -> bool {
+        self.watchdog.is_healthy()
+    }
+
+    pub fn watchdog(&self) -> Watchdog {
+        self.watchdog.clone()
     }
 
     async fn try_trigger(&mut self) {
@@ -106,51 +147,55 @@ impl TriggerBot {
 
         match self.periodic_task_mutex.clone().try_lock() {
             Ok(_guard) => {
-                let _perp_markets = self.drift_client.get_perp_market_accounts();
+                let perp_markets = self.drift_client.get_perp_market_accounts();
                 let spot_markets = self.drift_client.get_spot_market_accounts();
                 let user_map = self.user_map.clone();
 
                 let drift_client = &self.drift_client;
-                let triggering_nodes = Arc::new(Mutex::new(self.triggering_nodes.clone()));
+                let triggering_nodes = &self.triggering_nodes;
                 let priority_fee_calculator =
                     Arc::new(Mutex::new(self.priority_fee_calculator.clone()));
 
                 if let Some(subscriber) = &self.dlob_subscriber {
                     let subscriber = Arc::new(subscriber.clone());
-                    // let trigger_perp_markets: Vec<_> = perp_markets
-                    //     .into_iter()
-                    //     .map(|market| {
-                    //         try_trigger_for_perp_market(
-                    //             drift_client.clone(),
-                    //             subscriber.clone(),
-                    //             triggering_nodes.clone(),
-                    //             user_map.clone(),
-                    //             market,
-                    //         )
-                    //     })
-                    //     .collect();
+                    let trigger_perp_markets: Vec<_> = perp_markets
+                        .into_iter()
+                        .map(|market| {
+                            try_trigger_for_perp_market(
+                                drift_client.clone(),
+                                self.tx_sender.clone(),
+                                subscriber.clone(),
+                                triggering_nodes.clone(),
+                                user_map.clone(),
+                                priority_fee_calculator.clone(),
+                                market,
+                            )
+                            .boxed_local()
+                        })
+                        .collect();
 
                     let trigger_spot_markets: Vec<_> = spot_markets
                         .into_iter()
                         .map(|market| {
                             try_trigger_trigger_fro_spot_market(
                                 drift_client.clone(),
+                                self.tx_sender.clone(),
                                 subscriber.clone(),
                                 triggering_nodes.clone(),
                                 user_map.clone(),
                                 priority_fee_calculator.clone(),
                                 market,
                             )
+                            .boxed_local()
                         })
                         .collect();
 
-                    // let all_futures = trigger_perp_markets
-                    //     .into_iter()
-                    //     .chain(trigger_spot_markets.into_iter())
-                    //     .collect::<Vec<_>>();
+                    let all_futures = trigger_perp_markets
+                        .into_iter()
+                        .chain(trigger_spot_markets)
+                        .collect::<Vec<_>>();
 
-                    // perp_market.iter().map(|m| )
-                    let results = futures_util::future::join_all(trigger_spot_markets).await;
+                    let results = futures_util::future::join_all(all_futures).await;
                     for result in results {
                         match result {
                             Ok(()) => log::info!("success triggering"),
@@ -166,12 +211,59 @@ impl TriggerBot {
     }
 }
 
-#[allow(dead_code)]
+impl Bot for TriggerBot {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        TriggerBot::init(self).boxed_local()
+    }
+
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        TriggerBot::reset(self).boxed_local()
+    }
+
+    fn start(&mut self) -> LocalBoxFuture<'_, ()> {
+        self.start_interval_loop().boxed_local()
+    }
+
+    fn health_check(&self) -> bool {
+        TriggerBot::health_check(self)
+    }
+
+    fn watchdog(&self) -> Watchdog {
+        TriggerBot::watchdog(self)
+    }
+}
+
+/// Priority fee params for a trigger tx, None if priority fees are off
+fn trigger_tx_params(priority_fee_calculator: &Mutex<PriorityFeeCalculator>) -> Option<TxParams> {
+    // TODO: modify tx_time_count
+    let mut priority_fee_calculator = priority_fee_calculator.lock().unwrap();
+    if !priority_fee_calculator.update_priority_fee(Instant::now(), 0) {
+        return None;
+    }
+
+    let compute_units = 100_000;
+    let compute_unit_price =
+        priority_fee_calculator.calculate_compute_unit_price(compute_units, 1_000_000_000);
+    Some(TxParams {
+        base: BaseTxParams {
+            compute_units: Some(compute_units),
+            compute_units_price: Some(compute_unit_price),
+        },
+        processing: ProcessingTxParams::default(),
+    })
+}
+
 async fn try_trigger_for_perp_market(
     drift_client: Arc<DriftClient<RpcAccountProvider>>,
+    tx_sender: Arc<dyn TxSender>,
     subscriber: Arc<DLOBSubscriber<RpcAccountProvider>>,
     triggering_nodes: Arc<Mutex<HashMap<String, Instant>>>,
     user_map: UserMap,
+    priority_fee_calculator: Arc<Mutex<PriorityFeeCalculator>>,
     market: PerpMarket,
 ) -> Result<(), String> {
     let market_index = market.market_index;
@@ -226,21 +318,19 @@ async fn try_trigger_for_perp_market(
             .await
             .map_err(|e| e.to_string())?;
 
-        let sub_account = drift_client.wallet().authority();
-        let user_account = drift_client.get_user(Some(0)).ok_or("failed to get user")?;
-        let msg = drift_client
-            .init_tx(&sub_account, false)
-            .map_err(|e| e.to_string())?
-            .trigger_order_ix(
+        let tx_params = trigger_tx_params(&priority_fee_calculator);
+
+        match drift_client
+            .trigger_order(
+                tx_sender.as_ref(),
                 &node_to_trigger.get_user_account(),
-                &user,
+                user,
                 node_to_trigger.get_order(),
-                Some(&user_account.pubkey),
-                vec![],
+                tx_params,
+                None,
             )
-            .build();
-
-        match drift_client.sign_and_send(msg, false).await {
+            .await
+        {
             Ok(sig) => {
                 info!(
                     "Triggered perp user (account: {}) perp order: {}",
@@ -263,6 +353,7 @@ async fn try_trigger_for_perp_market(
 
 async fn try_trigger_trigger_fro_spot_market(
     drift_client: Arc<DriftClient<RpcAccountProvider>>,
+    tx_sender: Arc<dyn TxSender>,
     subscriber: Arc<DLOBSubscriber<RpcAccountProvider>>,
     _triggering_nodes: Arc<Mutex<HashMap<String, Instant>>>,
     user_map: UserMap,
@@ -298,27 +389,11 @@ async fn try_trigger_trigger_fro_spot_market(
             .await
             .map_err(|e| e.to_string())?;
 
-        // TODO: modify tx_time_count
-        let mut priority_fee_calculator = priority_fee_calculator.lock().unwrap();
-        let user_priority_fee = priority_fee_calculator.update_priority_fee(Instant::now(), 0);
-
-        let tx_params: Option<TxParams> = if user_priority_fee {
-            let compute_units = 100_000;
-            let compute_unit_price =
-                priority_fee_calculator.calculate_compute_unit_price(compute_units, 1_000_000_000);
-            Some(TxParams {
-                base: BaseTxParams {
-                    compute_units: Some(compute_units),
-                    compute_units_price: Some(compute_unit_price),
-                },
-                processing: ProcessingTxParams::default(),
-            })
-        } else {
-            None
-        };
+        let tx_params = trigger_tx_params(&priority_fee_calculator);
 
         match drift_client
             .trigger_order(
+                tx_sender.as_ref(),
                 &node_to_trigger.get_user_account(),
                 user,
                 node_to_trigger.get_order(),
@@ -1,18 +1,42 @@
-pub(crate) trait Bot {
+use std::sync::Arc;
+
+use futures_util::future::LocalBoxFuture;
+use sdk::priority_fee::priority_fee_subscriber::PriorityFeeSubscriber;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::health::Watchdog;
+
+/// `PriorityFeeSubscriber` shared by the bots of one runner, refreshed by the runner
+pub type SharedPriorityFeeSubscriber<T> = Arc<RwLock<PriorityFeeSubscriber<T>>>;
+
+pub trait Bot {
+    /// Unique name of the bot, its `bot_id`
+    fn name(&self) -> &str;
+
     /// Initialize the bot
-    async fn init();
+    fn init(&mut self) -> LocalBoxFuture<'_, Result<(), String>>;
 
     /// Reset the bot. This is called to reset the bot to a fresh state (pre-init).
-    async fn reset();
+    fn reset(&mut self) -> LocalBoxFuture<'_, Result<(), String>>;
 
-    /// Start the bot loop. This is generally a polling loop.
-    async fn start_interval_loop(interval_ms: u16);
+    /// Start the bot loop. This is generally a polling loop, returning once it stops.
+    fn start(&mut self) -> LocalBoxFuture<'_, ()>;
 
     /// Returns true if bot is healthy, else false. Typically used for monitoring liveness.
-    async fn health_check() -> bool;
+    fn health_check(&self) -> bool;
+
+    /// Handle to the bot's liveness, readable while `start` is running
+    fn watchdog(&self) -> Watchdog;
+
+    /// Stop the bot after its loop was dropped, releasing anything it subscribed to
+    fn shutdown(&mut self) -> LocalBoxFuture<'_, Result<(), String>> {
+        self.reset()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum JitoStrategy {
     JitoOnly,
     NonJitoOnly,
@@ -0,0 +1,326 @@
+//! Management of the keeper's own drift account: init, deposit, withdraw, cancel orders and
+//! close positions
+
+use std::{borrow::Cow, sync::Arc};
+
+use drift::{
+    controller::position::PositionDirection,
+    math::constants::QUOTE_SPOT_MARKET_INDEX,
+    state::{
+        order_params::OrderParams,
+        user::{MarketType, OrderStatus, OrderType, User},
+    },
+};
+use log::info;
+use sdk::{
+    constants::derive_associated_token_account, drift_client::DriftClient,
+    transaction_builder::TransactionBuilder, tx::tx_sender::TxSender, types::Context,
+    utils::load_keypair_multi_format, RpcAccountProvider, Wallet,
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::config::GlobalConfig;
+
+/// Sends the account management txs of the keeper wallet
+///
+/// With `dry_run` set the txs are built and logged instead of sent.
+pub struct UserManager {
+    drift_client: DriftClient<RpcAccountProvider>,
+    tx_sender: Arc<dyn TxSender>,
+    dry_run: bool,
+}
+
+impl UserManager {
+    /// Connect with the keeper wallet of `global_config`
+    ///
+    /// The drift client is not subscribed, accounts are fetched when needed.
+    pub async fn new(global_config: &GlobalConfig, dry_run: bool) -> Result<Self, String> {
+        // validated by `Config::validate`
+        let endpoint = global_config.endpoint.clone().unwrap();
+        let private_key = global_config.keeper_private_key.clone().unwrap();
+        let drift_env = global_config.drift_env.unwrap_or(Context::DevNet);
+
+        let wallet = Wallet::new(
+            load_keypair_multi_format(&private_key)
+                .map_err(|e| format!("invalid keeper private key: {e}"))?,
+        );
+        let drift_client = DriftClient::new(drift_env, RpcAccountProvider::new(&endpoint), &wallet)
+            .await
+            .map_err(|e| format!("failed to construct drift client: {e}"))?;
+        let tx_sender = global_config.tx_sender(drift_client.backend.rpc_client.clone());
+
+        Ok(Self {
+            drift_client,
+            tx_sender,
+            dry_run,
+        })
+    }
+
+    /// Run the account tasks enabled by the `initUser`, `forceDeposit`, `cancelOpenOrders` and
+    /// `closeOpenPositions` flags before the bots start
+    ///
+    /// `forceDeposit` is an amount of USDC deposited into the first of `sub_account_ids`
+    pub async fn run_startup_tasks(
+        &self,
+        global_config: &GlobalConfig,
+        sub_account_ids: &[u16],
+    ) -> Result<(), String> {
+        if global_config.init_user.unwrap_or(false) {
+            for sub_account_id in sub_account_ids {
+                self.init_user(*sub_account_id, None).await?;
+            }
+        }
+        if let Some(amount) = global_config.force_deposit {
+            let sub_account_id = sub_account_ids.first().copied().unwrap_or(0);
+            self.deposit(sub_account_id, QUOTE_SPOT_MARKET_INDEX, amount as f64)
+                .await?;
+        }
+        if global_config.cancel_open_orders.unwrap_or(false) {
+            for sub_account_id in sub_account_ids {
+                self.cancel_orders(*sub_account_id).await?;
+            }
+        }
+        if global_config.close_open_positions.unwrap_or(false) {
+            for sub_account_id in sub_account_ids {
+                self.close_positions(*sub_account_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Initialize the wallet's user stats, if missing, and the sub-account
+    ///
+    /// `name` defaults to "Main Account" for sub-account 0 and "Subaccount <id>" otherwise
+    pub async fn init_user(&self, sub_account_id: u16, name: Option<&str>) -> Result<(), String> {
+        let authority = *self.drift_client.wallet().authority();
+        let user_pubkey = self.user_pubkey(sub_account_id);
+        if self
+            .drift_client
+            .get_user_account(&user_pubkey)
+            .await
+            .is_ok()
+        {
+            info!("sub-account {sub_account_id} ({user_pubkey}) already initialized");
+            return Ok(());
+        }
+
+        let mut builder = self.drift_client.init_new_account_tx(sub_account_id);
+        if self.drift_client.get_user_stats(&authority).await.is_err() {
+            builder = builder.initialize_user_stats();
+        }
+        let name = match name {
+            Some(name) => name.to_string(),
+            None if sub_account_id == 0 => "Main Account".to_string(),
+            None => format!("Subaccount {sub_account_id}"),
+        };
+
+        self.send(
+            builder.initialize_user(sub_account_id, &name, None),
+            &format!("initialize sub-account {sub_account_id} ({user_pubkey}) as {name:?}"),
+        )
+        .await
+    }
+
+    /// Deposit `amount` tokens of spot market `market_index` from the wallet's associated token
+    /// account
+    pub async fn deposit(
+        &self,
+        sub_account_id: u16,
+        market_index: u16,
+        amount: f64,
+    ) -> Result<(), String> {
+        let (token_amount, user_token_account) = self.token_amount(market_index, amount)?;
+        let builder = self.init_tx(sub_account_id).await?.deposit(
+            token_amount,
+            market_index,
+            user_token_account,
+            None,
+        );
+
+        self.send(
+            builder,
+            &format!(
+                "deposit {amount} of spot market {market_index} into sub-account {sub_account_id}"
+            ),
+        )
+        .await
+    }
+
+    /// Withdraw `amount` tokens of spot market `market_index` to the wallet's associated token
+    /// account, reduce only so the withdrawal never opens a borrow
+    pub async fn withdraw(
+        &self,
+        sub_account_id: u16,
+        market_index: u16,
+        amount: f64,
+    ) -> Result<(), String> {
+        let (token_amount, user_token_account) = self.token_amount(market_index, amount)?;
+        let builder = self.init_tx(sub_account_id).await?.withdraw(
+            token_amount,
+            market_index,
+            user_token_account,
+            Some(true),
+        );
+
+        self.send(
+            builder,
+            &format!(
+                "withdraw {amount} of spot market {market_index} from sub-account {sub_account_id}"
+            ),
+        )
+        .await
+    }
+
+    /// Cancel all open orders of the sub-account
+    pub async fn cancel_orders(&self, sub_account_id: u16) -> Result<(), String> {
+        let builder = self.init_tx(sub_account_id).await?;
+        let open_orders = builder
+            .account_data()
+            .orders
+            .iter()
+            .filter(|o| o.status == OrderStatus::Open)
+            .count();
+        if open_orders == 0 {
+            info!("sub-account {sub_account_id} has no open orders");
+            return Ok(());
+        }
+
+        self.send(
+            builder.cancel_all_orders(),
+            &format!("cancel {open_orders} open orders of sub-account {sub_account_id}"),
+        )
+        .await
+    }
+
+    /// Close the sub-account's perp positions with reduce only market orders
+    pub async fn close_positions(&self, sub_account_id: u16) -> Result<(), String> {
+        let builder = self.init_tx(sub_account_id).await?;
+        let orders: Vec<OrderParams> = builder
+            .account_data()
+            .perp_positions
+            .iter()
+            .filter(|p| p.base_asset_amount != 0)
+            .map(|position| OrderParams {
+                order_type: OrderType::Market,
+                market_type: MarketType::Perp,
+                direction: if position.base_asset_amount > 0 {
+                    PositionDirection::Short
+                } else {
+                    PositionDirection::Long
+                },
+                base_asset_amount: position.base_asset_amount.unsigned_abs(),
+                market_index: position.market_index,
+                reduce_only: true,
+                ..OrderParams::default()
+            })
+            .collect();
+        if orders.is_empty() {
+            info!("sub-account {sub_account_id} has no open perp positions");
+            return Ok(());
+        }
+
+        let label = format!(
+            "close perp positions of sub-account {sub_account_id} in markets {:?}",
+            orders.iter().map(|o| o.market_index).collect::<Vec<_>>()
+        );
+        self.send(builder.place_orders(orders), &label).await
+    }
+
+    fn user_pubkey(&self, sub_account_id: u16) -> Pubkey {
+        Wallet::derive_user_account(
+            self.drift_client.wallet().authority(),
+            sub_account_id,
+            &drift::ID,
+        )
+    }
+
+    /// Fetch the sub-account and start a tx for it
+    async fn init_tx(&self, sub_account_id: u16) -> Result<TransactionBuilder, String> {
+        let user_pubkey = self.user_pubkey(sub_account_id);
+        let user: User = self
+            .drift_client
+            .get_user_account(&user_pubkey)
+            .await
+            .map_err(|e| {
+                format!("failed to fetch sub-account {sub_account_id} ({user_pubkey}): {e}")
+            })?;
+
+        Ok(TransactionBuilder::new(
+            self.drift_client.program_data(),
+            user_pubkey,
+            Cow::Owned(user),
+            false,
+        ))
+    }
+
+    /// Convert `amount` into the spot market's token precision, returns it with the wallet's
+    /// associated token account for the market mint
+    fn token_amount(&self, market_index: u16, amount: f64) -> Result<(u64, Pubkey), String> {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(format!("invalid amount {amount}"));
+        }
+        let spot_market = self
+            .drift_client
+            .program_data()
+            .spot_market_config_by_index(market_index)
+            .ok_or_else(|| format!("unknown spot market {market_index}"))?;
+        let token_amount = to_token_amount(amount, spot_market.decimals);
+        let user_token_account = derive_associated_token_account(
+            self.drift_client.wallet().authority(),
+            &spot_market.mint,
+        );
+
+        Ok((token_amount, user_token_account))
+    }
+
+    async fn send(&self, builder: TransactionBuilder<'_>, label: &str) -> Result<(), String> {
+        if self.dry_run {
+            info!("dry run, not sending: {label}");
+            for (i, ix) in builder.instructions().iter().enumerate() {
+                info!(
+                    "  ix {i}: program {}, data {}",
+                    ix.program_id,
+                    bs58::encode(&ix.data).into_string()
+                );
+                for account in &ix.accounts {
+                    info!(
+                        "    {}{}{}",
+                        account.pubkey,
+                        if account.is_writable { " writable" } else { "" },
+                        if account.is_signer { " signer" } else { "" }
+                    );
+                }
+            }
+            return Ok(());
+        }
+
+        let sig = self
+            .drift_client
+            .sign_and_send_with_sender(self.tx_sender.as_ref(), builder.build(), false)
+            .await
+            .map_err(|e| format!("failed to {label}: {e}"))?;
+        info!("{label} tx: {sig}");
+
+        Ok(())
+    }
+}
+
+/// Scale `amount` to `decimals`, rounding to the nearest unit so e.g. 0.3 isn't truncated to
+/// 0.299999
+fn to_token_amount(amount: f64, decimals: u32) -> u64 {
+    (amount * 10_f64.powi(decimals as i32)).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_amount_rounds_to_nearest_unit() {
+        assert_eq!(to_token_amount(0.3, 6), 300_000);
+        assert_eq!(to_token_amount(1.1, 6), 1_100_000);
+        assert_eq!(to_token_amount(0.000_000_001, 9), 1);
+        assert_eq!(to_token_amount(12.5, 0), 13);
+    }
+}
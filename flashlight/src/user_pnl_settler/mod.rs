@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use drift::{
+    math::constants::{QUOTE_PRECISION_I128, QUOTE_SPOT_MARKET_INDEX},
+    state::{
+        paused_operations::PerpOperation, settle_pnl_mode::SettlePnlMode, spot_market::SpotMarket,
+        user::User,
+    },
+};
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use log::{error, info, warn};
+use sdk::{
+    math::{liquidation::calculate_unsettled_pnl, market::calculate_pnl_pool_amount},
+    usermap::UserMap,
+    AccountProvider,
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    config::UserPnlSettlerConfig,
+    settler::{SettleContext, Settler, SettlerBot},
+};
+
+const DEFAULT_MIN_PNL_TO_SETTLE: f64 = 10.0;
+const DEFAULT_MAX_USERS_PER_TX: usize = 4;
+
+/// User account address and data, with the perp markets to settle
+type UserToSettle = ((Pubkey, User), Vec<u16>);
+
+/// Settles the perp pnl of users in the `UserMap` into their quote balances
+pub type UserPnlSettlerBot<T> = SettlerBot<T, UserPnlSettler>;
+
+/// Settles user perp pnl, run by a `UserPnlSettlerBot`
+///
+/// Positions are settled once their unsettled pnl reaches `minPnlToSettle`. Positive pnl is paid
+/// out of the market's pnl pool so it is only settled while the pool can cover it.
+pub struct UserPnlSettler {
+    user_map: UserMap,
+    /// QUOTE_PRECISION
+    min_pnl_to_settle: u128,
+    max_users_per_tx: usize,
+}
+
+impl UserPnlSettler {
+    pub fn new(user_map: UserMap, config: &UserPnlSettlerConfig) -> Self {
+        let min_pnl_to_settle = config
+            .min_pnl_to_settle
+            .unwrap_or(DEFAULT_MIN_PNL_TO_SETTLE);
+        Self {
+            user_map,
+            min_pnl_to_settle: (min_pnl_to_settle * QUOTE_PRECISION_I128 as f64) as u128,
+            max_users_per_tx: config.max_users_per_tx.unwrap_or(DEFAULT_MAX_USERS_PER_TX),
+        }
+    }
+
+    async fn try_settle<T: AccountProvider>(&self, ctx: &SettleContext<T>) {
+        let users = self.users_to_settle(ctx);
+        if !users.is_empty() {
+            info!(
+                "{} settling {} positions of {} users",
+                ctx.name,
+                users
+                    .iter()
+                    .map(|(_, markets)| markets.len())
+                    .sum::<usize>(),
+                users.len()
+            );
+        }
+        for chunk in users.chunks(self.max_users_per_tx) {
+            settle(ctx, chunk).await;
+        }
+    }
+
+    /// Return the users with positions to settle
+    fn users_to_settle<T: AccountProvider>(&self, ctx: &SettleContext<T>) -> Vec<UserToSettle> {
+        let Some(quote_market) = ctx
+            .drift_client
+            .get_spot_market_account(QUOTE_SPOT_MARKET_INDEX)
+        else {
+            error!("{} quote spot market not loaded", ctx.name);
+            return vec![];
+        };
+
+        let users = self
+            .user_map
+            .values()
+            .into_iter()
+            .filter(|(_, user)| !user.is_being_liquidated() && !user.is_bankrupt())
+            .map(|user_info| {
+                let (user_pubkey, user) = &user_info;
+                let pnls = user
+                    .perp_positions
+                    .iter()
+                    .filter(|p| !p.is_available())
+                    .filter_map(|position| {
+                        let market_index = position.market_index;
+                        match calculate_unsettled_pnl(&ctx.drift_client, user, market_index) {
+                            Ok(pnl) => Some((market_index, pnl)),
+                            Err(e) => {
+                                warn!(
+                                    "{} failed to calculate pnl of {user_pubkey}-{market_index}: {e}",
+                                    ctx.name
+                                );
+                                None
+                            }
+                        }
+                    })
+                    .collect();
+                (user_info, pnls)
+            });
+
+        select_positions(users, self.min_pnl_to_settle, |market_index| {
+            pnl_pool(ctx, market_index, &quote_market)
+        })
+    }
+}
+
+impl<T: AccountProvider> Settler<T> for UserPnlSettler {
+    fn init(&mut self, ctx: &SettleContext<T>) -> Result<(), String> {
+        info!(
+            "{} min pnl to settle: {}, max users per tx: {}",
+            ctx.name, self.min_pnl_to_settle, self.max_users_per_tx
+        );
+
+        Ok(())
+    }
+
+    fn settle<'a>(&'a mut self, ctx: &'a SettleContext<T>) -> LocalBoxFuture<'a, ()> {
+        self.try_settle(ctx).boxed_local()
+    }
+}
+
+/// Pick the positions to settle from each user's unsettled pnl by perp market (QUOTE_PRECISION)
+///
+/// `pnl_pool` returns a market's pnl pool, None if it can't be settled, and is called once per
+/// market. The pool is drawn down by the settle amount of each position picked, profit or loss,
+/// and a market's positions are skipped once it is exhausted.
+fn select_positions<U>(
+    users: impl IntoIterator<Item = (U, Vec<(u16, i128)>)>,
+    min_pnl_to_settle: u128,
+    mut pnl_pool: impl FnMut(u16) -> Option<u128>,
+) -> Vec<(U, Vec<u16>)> {
+    // remaining pnl pool by perp market, None if settling is paused
+    let mut pnl_pools = HashMap::<u16, Option<u128>>::new();
+    let mut selected = Vec::new();
+
+    for (user, pnls) in users {
+        let mut market_indexes = Vec::new();
+        for (market_index, pnl) in pnls {
+            if pnl == 0 || pnl.unsigned_abs() < min_pnl_to_settle {
+                continue;
+            }
+
+            let Some(remaining) = pnl_pools
+                .entry(market_index)
+                .or_insert_with(|| pnl_pool(market_index))
+            else {
+                continue;
+            };
+            if *remaining == 0 {
+                continue;
+            }
+            *remaining = remaining.saturating_sub(pnl.unsigned_abs());
+            market_indexes.push(market_index);
+        }
+
+        if !market_indexes.is_empty() {
+            selected.push((user, market_indexes));
+        }
+    }
+
+    selected
+}
+
+/// Pnl pool balance of the perp market, None if it can't be settled
+fn pnl_pool<T: AccountProvider>(
+    ctx: &SettleContext<T>,
+    market_index: u16,
+    quote_market: &SpotMarket,
+) -> Option<u128> {
+    let market = ctx.drift_client.get_perp_market_account(market_index)?;
+    if market.is_operation_paused(PerpOperation::SettlePnl) {
+        info!(
+            "{} settle pnl paused for perp market {market_index}",
+            ctx.name
+        );
+        return None;
+    }
+
+    match calculate_pnl_pool_amount(&market, quote_market) {
+        Ok(amount) => Some(amount),
+        Err(e) => {
+            warn!(
+                "{} failed to get pnl pool of perp market {market_index}: {e}",
+                ctx.name
+            );
+            None
+        }
+    }
+}
+
+/// Settle `users` in one tx, each user's markets with a single ix
+async fn settle<T: AccountProvider>(ctx: &SettleContext<T>, users: &[UserToSettle]) {
+    let Some(mut builder) = ctx.init_tx() else {
+        error!("{} keeper sub-account not found", ctx.name);
+        return;
+    };
+    for (user_info, market_indexes) in users {
+        // skip markets that can't be settled instead of failing the tx
+        builder = builder.settle_multiple_pnls(user_info, market_indexes, SettlePnlMode::TrySettle);
+    }
+    let label = format!(
+        "settle pnl {}",
+        users
+            .iter()
+            .map(|((pubkey, _), markets)| format!("{pubkey}-{markets:?}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    ctx.send(builder, &label).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_PNL: u128 = 10;
+
+    #[test]
+    fn skips_pnl_below_min() {
+        let users = vec![
+            ("a", vec![(0, 9), (1, -9), (2, 0)]),
+            ("b", vec![(0, 10), (1, -10)]),
+        ];
+
+        let selected = select_positions(users, MIN_PNL, |_| Some(1_000));
+
+        assert_eq!(selected, vec![("b", vec![0, 1])]);
+    }
+
+    #[test]
+    fn draws_down_pnl_pool() {
+        let users = vec![
+            ("a", vec![(0, 60)]),
+            ("b", vec![(0, 50), (1, 50)]),
+            // pool 0 is exhausted
+            ("c", vec![(0, 20)]),
+            ("d", vec![(0, -20)]),
+        ];
+        let mut lookups = Vec::new();
+
+        let selected = select_positions(users, MIN_PNL, |market_index| {
+            lookups.push(market_index);
+            Some(100)
+        });
+
+        assert_eq!(selected, vec![("a", vec![0]), ("b", vec![0, 1])]);
+        assert_eq!(lookups, vec![0, 1]);
+    }
+
+    #[test]
+    fn losses_draw_down_pnl_pool() {
+        let users = vec![("a", vec![(0, -50)]), ("b", vec![(0, 20)])];
+
+        let selected = select_positions(users, MIN_PNL, |_| Some(50));
+
+        assert_eq!(selected, vec![("a", vec![0])]);
+    }
+
+    #[test]
+    fn users_exceeding_pnl_pool_together() {
+        // a and b together exceed the pool, b is the last pick
+        let users = vec![
+            ("a", vec![(0, -60)]),
+            ("b", vec![(0, -60)]),
+            ("c", vec![(0, -20)]),
+            ("d", vec![(0, 20)]),
+        ];
+
+        let selected = select_positions(users, MIN_PNL, |_| Some(100));
+
+        assert_eq!(selected, vec![("a", vec![0]), ("b", vec![0])]);
+    }
+
+    #[test]
+    fn skips_markets_that_cannot_be_settled() {
+        let users = vec![("a", vec![(0, 50), (1, -50)]), ("b", vec![(1, 50)])];
+
+        let selected = select_positions(users, MIN_PNL, |market_index| {
+            (market_index == 0).then_some(100)
+        });
+
+        assert_eq!(selected, vec![("a", vec![0])]);
+    }
+}
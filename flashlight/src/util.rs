@@ -1,28 +1,22 @@
-use std::{
-    collections::VecDeque,
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::time::Duration;
 
-use base64::Engine;
-use log::info;
 use sdk::{
     config::DriftEnv,
     dlob::{
         dlob::NodeToFill,
         dlob_node::{get_order_signature, DLOBNode, Node},
     },
+    drift_client::DriftClient,
+    types::{ProcessingTxParams, TxParams},
+    AccountProvider,
 };
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     address_lookup_table_account::AddressLookupTableAccount,
-    compute_budget::{ComputeBudgetInstruction, ID as ComputeBudgetProgramId},
+    compute_budget::ID as ComputeBudgetProgramId,
     hash::Hash,
     instruction::{AccountMeta, Instruction},
-    message::{v0::Message, VersionedMessage},
+    message::VersionedMessage,
     pubkey::Pubkey,
-    signature::Keypair,
-    signer::Signer,
     transaction::{TransactionError, VersionedTransaction},
 };
 
@@ -50,48 +44,17 @@ pub fn is_set_compute_units_ix(ix: &Instruction) -> bool {
     false
 }
 
-fn get_versioned_transaction(
-    payer: &Keypair,
-    ixs: &[Instruction],
-    lookup_table_accounts: &[AddressLookupTableAccount],
-    recent_blockhash: Hash,
-) -> Result<VersionedTransaction, String> {
-    let message = Message::try_compile(
-        &payer.pubkey(),
-        ixs,
-        lookup_table_accounts,
-        recent_blockhash,
-    )
-    .map_err(|e| e.to_string())?;
-    let tx = VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer])
-        .map_err(|e| e.to_string())?;
-
-    Ok(tx)
-}
-
-// const PLACEHOLDER_BLOCKHASH: &[u8] = b"Fdum64WVeej6DeL85REV9NvfSxEJNPZ74DBk7A8kTrKP";
-
-pub struct SimulateAndGetTxWithCUsParams {
-    pub connection: Arc<RpcClient>,
-    pub payer: Arc<Keypair>,
-    pub lookup_table_accounts: Vec<AddressLookupTableAccount>,
-
-    /// instructions to simulate and create transaction from
-    pub ixs: VecDeque<Instruction>,
-
-    /// multiplier to apply to the estimated CU usage, default: 1.0
-    pub cu_limit_multiplier: Option<f64>,
-
-    /// set false to only create a tx without simulating for CU estimate
-    pub do_simulation: Option<bool>,
-
-    /// recentBlockhash to use in the final tx. If undefined, PLACEHOLDER_BLOCKHASH
-    /// will be used for simulation, the final tx will have an empty blockhash so
-    /// attempts to sign it will throw.
-    pub recent_blockhash: Option<Hash>,
-
-    /// set true to dump base64 transaction before and after simulating for CUs
-    pub dump_tx: Option<bool>,
+/// Return the compute unit price (micro lamports) set by `message`, if any
+pub fn get_compute_unit_price(message: &VersionedMessage) -> Option<u64> {
+    let account_keys = message.static_account_keys();
+    message.instructions().iter().find_map(|ix| {
+        let program_id = account_keys.get(ix.program_id_index as usize)?;
+        // `ComputeBudgetInstruction::SetComputeUnitPrice`, borsh tag 3 then the u64 price
+        if *program_id != ComputeBudgetProgramId || ix.data.first() != Some(&3) {
+            return None;
+        }
+        Some(u64::from_le_bytes(ix.data.get(1..9)?.try_into().ok()?))
+    })
 }
 
 pub struct SimulateAndGetTxWithCUsResponse {
@@ -103,110 +66,47 @@ pub struct SimulateAndGetTxWithCUsResponse {
 }
 
 /// Simulates the instructions in order to determine how many CUs it needs,
-/// applies `cuLimitMulitplier` to the estimate and inserts or modifies
-/// the CU limit request ix.
+/// applies `cu_limit_multiplier` to the estimate and inserts or modifies
+/// the CU limit request ix, see `DriftClient::build_tx`.
 ///
-/// If `recentBlockhash` is provided, it is used as is to generate the final
-/// tx. If it is undefined, uses `PLACEHOLDER_BLOCKHASH` which is a valid
-/// blockhash to perform simulation and removes it from the final tx. Signing
-/// a tx without a valid blockhash will throw.
-pub async fn simulate_and_get_tx_with_cus(
-    params: &mut SimulateAndGetTxWithCUsParams,
+/// The returned tx is signed by the drift client wallet with `recent_blockhash`.
+pub async fn simulate_and_get_tx_with_cus<T: AccountProvider>(
+    drift_client: &DriftClient<T>,
+    ixs: Vec<Instruction>,
+    cu_limit_multiplier: f64,
+    recent_blockhash: Hash,
 ) -> Result<SimulateAndGetTxWithCUsResponse, String> {
-    if params.ixs.is_empty() {
+    if ixs.is_empty() {
         return Err("cannot simulate empty tx".to_string());
     }
 
-    let mut set_cu_limit_ix_idx = -1;
-    for (index, ix) in params.ixs.iter().enumerate() {
-        if is_set_compute_units_ix(ix) {
-            set_cu_limit_ix_idx = index as isize;
-            break;
-        }
-    }
-
-    // if we don't have a set CU limit ix, add one to the beginning
-    // otherwise the default CU limit for sim is 400k, which may be too low
-    if set_cu_limit_ix_idx == -1 {
-        params
-            .ixs
-            .push_front(ComputeBudgetInstruction::set_compute_unit_limit(1_400_000));
-        set_cu_limit_ix_idx = 0;
-    }
-
-    // let sim_tx_duration: Duration = 0;
-    // let place_holder = Hash::new(PLACEHOLDER_BLOCKHASH);
-    let tx = get_versioned_transaction(
-        &params.payer,
-        &Vec::from(params.ixs.clone()),
-        &params.lookup_table_accounts,
-        params.recent_blockhash.unwrap(),
-    )?;
-    if params.do_simulation.is_none() {
-        return Ok(SimulateAndGetTxWithCUsResponse {
-            cu_estimate: -1,
-            sim_tx_logs: None,
-            sim_error: None,
-            sim_tx_duration: Duration::new(0, 0),
-            tx,
+    let builder = drift_client
+        .init_tx(drift_client.wallet().authority(), false)
+        .map_err(|e| e.to_string())?
+        .extend_ix(ixs)
+        .tx_params(TxParams {
+            processing: ProcessingTxParams {
+                use_simulated_compute_units: Some(true),
+                compute_units_buffer_multipler: Some(cu_limit_multiplier),
+                ..Default::default()
+            },
+            ..Default::default()
         });
-    }
-    if params.dump_tx.is_some() {
-        info!("===== Simulating The following transaction =====");
-        let serizlied_tx =
-            base64::engine::general_purpose::STANDARD.encode(&tx.message.serialize());
-        info!("{}", serizlied_tx);
-        info!("================================================");
-    }
-
-    let start = Instant::now();
-    let resp = params
-        .connection
-        .simulate_transaction(&tx)
+    let (message, simulation) = drift_client
+        .build_tx(builder)
         .await
-        .map_err(|e| format!("Failed to simulate transaction: {}", e.to_string()))?;
-    let sim_tx_duration = start.elapsed();
-
-    let sim_tx_logs = resp.value.logs;
-    let cu_estimate = match resp.value.units_consumed {
-        Some(estimate) => estimate,
-        None => {
-            return Err(String::from(
-                "Failed to get units comsumed from simulateTransaction",
-            ))
-        }
-    };
-    let cu_to_use = cu_estimate as f64 * (params.cu_limit_multiplier.unwrap_or(1.0));
-    params.ixs[set_cu_limit_ix_idx as usize] =
-        ComputeBudgetInstruction::set_compute_unit_limit(cu_to_use as u32);
-
-    let recent_blockhash = params.recent_blockhash.unwrap();
-    let mut tx_with_cus = get_versioned_transaction(
-        &params.payer,
-        &Vec::from(params.ixs.clone()),
-        &params.lookup_table_accounts,
-        recent_blockhash,
-    )?;
-    if params.dump_tx.is_some() {
-        info!("== Simulation result, cuEstimate: {cu_estimate}, using: {cu_to_use}, blockhash: {recent_blockhash} ==");
-        let serizlied_tx =
-            base64::engine::general_purpose::STANDARD.encode(&tx.message.serialize());
-        info!("{}", serizlied_tx);
-        info!("================================================");
-    }
-
-    // strip out the placeholder blockhash so user doesn't try to send the tx.
-    // sending a tx with placeholder blockhash will cause `blockhash not found error`
-    // which is suppressed if flight checks are skipped.
-    if params.recent_blockhash.is_none() {
-        tx_with_cus.message.set_recent_blockhash(Hash::new(&[0]));
-    }
+        .map_err(|e| format!("Failed to simulate transaction: {e}"))?;
+    let simulation = simulation.expect("simulated");
+    let tx = drift_client
+        .wallet()
+        .sign_tx(message, recent_blockhash, false)
+        .map_err(|e| e.to_string())?;
 
     Ok(SimulateAndGetTxWithCUsResponse {
-        cu_estimate: cu_estimate as i64,
-        sim_tx_logs,
-        sim_error: resp.value.err,
-        sim_tx_duration,
+        cu_estimate: simulation.compute_units as i64,
+        sim_tx_logs: Some(simulation.logs),
+        sim_error: simulation.err,
+        sim_tx_duration: simulation.duration,
         tx,
     })
 }
@@ -273,3 +173,37 @@ pub fn valid_rebalance_settled_pnl_threshold(amount: Option<f64>) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{compute_budget::ComputeBudgetInstruction, message::v0, system_instruction};
+
+    use super::*;
+
+    #[test]
+    fn compute_unit_price_from_message() {
+        let payer = Pubkey::new_unique();
+        let message = |ixs: &[Instruction]| {
+            VersionedMessage::V0(
+                v0::Message::try_compile(&payer, ixs, &[], Hash::default()).unwrap(),
+            )
+        };
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        assert_eq!(
+            get_compute_unit_price(&message(&[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                ComputeBudgetInstruction::set_compute_unit_price(12_345),
+                transfer.clone(),
+            ])),
+            Some(12_345)
+        );
+        assert_eq!(
+            get_compute_unit_price(&message(&[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                transfer,
+            ])),
+            None
+        );
+    }
+}
@@ -28,6 +28,8 @@ pub struct BufferAndSlot {
     slot: u64,
 }
 
+/// Polls accounts in bulk, clones share the accounts and the polling task
+#[derive(Clone)]
 pub struct BulkAccountLoader {
     pub client: Arc<RpcClient>,
     pub commitment: CommitmentConfig,
@@ -35,7 +37,8 @@ pub struct BulkAccountLoader {
     accounts_to_load: Arc<Mutex<HashMap<String, AccountToLoad>>>,
     buffer_and_slot_map: Arc<Mutex<HashMap<String, BufferAndSlot>>>,
     error_callbacks: Arc<Mutex<HashMap<String, Box<dyn ErrorCallback>>>>,
-    interval_handle: Option<tokio::task::JoinHandle<()>>,
+    /// polling task, shared so only one runs however many clones add accounts
+    interval_handle: Arc<std::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
 }
 
 impl BulkAccountLoader {
@@ -51,7 +54,7 @@ impl BulkAccountLoader {
             accounts_to_load: Arc::new(Mutex::new(HashMap::new())),
             buffer_and_slot_map: Arc::new(Mutex::new(HashMap::new())),
             error_callbacks: Arc::new(Mutex::new(HashMap::new())),
-            interval_handle: None,
+            interval_handle: Default::default(),
         }
     }
 
@@ -80,9 +83,7 @@ impl BulkAccountLoader {
             }
         }
 
-        if self.accounts_to_load.lock().await.len() == 1 {
-            self.start_polling().await;
-        }
+        self.start_polling();
 
         callback_id
     }
@@ -138,11 +139,11 @@ impl BulkAccountLoader {
         let commitment = self.commitment;
 
         let pubkeys: Vec<Pubkey> = chunk.iter().map(|a| a.public_key).collect();
-        let responses = match client
+        let (slot, responses) = match client
             .get_multiple_accounts_with_commitment(&pubkeys, commitment)
             .await
         {
-            Ok(response) => response.value,
+            Ok(response) => (response.context.slot, response.value),
             Err(e) => {
                 self.handle_error(Arc::new(e)).await;
                 return;
@@ -153,7 +154,6 @@ impl BulkAccountLoader {
             if let Some(account_data) = response {
                 let account_to_load = &mut chunk[i];
                 let buffer = account_data.data.clone();
-                let slot = account_data.lamports;
 
                 let mut buffer_and_slot_map = self.buffer_and_slot_map.lock().await;
                 let old_data = buffer_and_slot_map.get(&account_to_load.public_key.to_string());
@@ -182,39 +182,123 @@ impl BulkAccountLoader {
         }
     }
 
-    async fn start_polling(&mut self) {
+    /// Start the polling task unless it is already running
+    fn start_polling(&self) {
+        let mut interval_handle = self.interval_handle.lock().expect("lock");
+        if interval_handle.is_some() {
+            return;
+        }
+
         let polling_frequency = self.polling_frequency;
         let loader = self.clone();
-
-        let handle = tokio::spawn(async move {
+        *interval_handle = Some(tokio::spawn(async move {
             let mut interval = interval(polling_frequency);
             loop {
                 interval.tick().await;
                 loader.load().await;
             }
-        });
-
-        self.interval_handle = Some(handle);
+        }));
     }
 
-    fn stop_polling(&mut self) {
-        if let Some(handle) = &self.interval_handle {
+    fn stop_polling(&self) {
+        if let Some(handle) = self.interval_handle.lock().expect("lock").take() {
             handle.abort();
-            self.interval_handle = None;
         }
     }
+
+    /// Whether the polling task is running
+    pub fn is_polling(&self) -> bool {
+        self.interval_handle.lock().expect("lock").is_some()
+    }
 }
 
-impl Clone for BulkAccountLoader {
-    fn clone(&self) -> Self {
-        BulkAccountLoader {
-            client: self.client.clone(),
-            commitment: self.commitment,
-            polling_frequency: self.polling_frequency,
-            accounts_to_load: self.accounts_to_load.clone(),
-            buffer_and_slot_map: self.buffer_and_slot_map.clone(),
-            error_callbacks: self.error_callbacks.clone(),
-            interval_handle: None,
-        }
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use base64::Engine;
+    use drift::state::user::User;
+    use tokio::time::{sleep, timeout};
+
+    use super::*;
+    use crate::{
+        accounts::{AccountSubscriber, PollingAccountSubscriber},
+        http_stub,
+        utils::zero_account_to_bytes,
+    };
+
+    /// Serve `getAccountInfo` and `getMultipleAccounts` on a local port, every account has `data`
+    ///
+    /// Each response is from the slot after the last one. Returns the server url and the number
+    /// of requests received.
+    pub(crate) async fn mock_rpc(data: Vec<u8>) -> (String, Arc<AtomicU64>) {
+        let requests = Arc::new(AtomicU64::new(0));
+        let account = serde_json::json!({
+            "lamports": 1_000_000,
+            "data": [base64::engine::general_purpose::STANDARD.encode(&data), "base64"],
+            "owner": drift::ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        });
+
+        let received = Arc::clone(&requests);
+        let url = http_stub::serve_json_rpc(move |method, params| {
+            let slot = received.fetch_add(1, Ordering::Relaxed) + 1;
+            let value = match method {
+                "getAccountInfo" => account.clone(),
+                "getMultipleAccounts" => serde_json::Value::Array(
+                    params[0]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|_| account.clone())
+                        .collect(),
+                ),
+                method => panic!("unexpected method {method}"),
+            };
+            serde_json::json!({ "context": { "slot": slot }, "value": value })
+        })
+        .await;
+
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn clones_share_one_polling_task() {
+        let (url, requests) = mock_rpc(zero_account_to_bytes(User::default())).await;
+        let loader = BulkAccountLoader::new(
+            Arc::new(RpcClient::new(url)),
+            CommitmentConfig::confirmed(),
+            Duration::from_millis(10),
+        );
+        let mut subscriber_1 =
+            PollingAccountSubscriber::<User>::new(Pubkey::new_unique(), loader.clone());
+        let mut subscriber_2 =
+            PollingAccountSubscriber::<User>::new(Pubkey::new_unique(), loader.clone());
+
+        subscriber_1.subscribe(None).await.unwrap();
+        subscriber_2.subscribe(None).await.unwrap();
+        assert!(loader.is_polling());
+
+        let subscribed_slot = subscriber_1.data_and_slot().unwrap().slot;
+        timeout(Duration::from_secs(5), async {
+            while subscriber_1.data_and_slot().unwrap().slot == subscribed_slot {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("polled update");
+
+        subscriber_1.unsubscribe().await.unwrap();
+        assert!(loader.is_polling());
+        subscriber_2.unsubscribe().await.unwrap();
+        assert!(!loader.is_polling());
+
+        // let an in flight poll finish, nothing polls after that
+        sleep(Duration::from_millis(50)).await;
+        let stopped_at = requests.load(Ordering::Relaxed);
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(requests.load(Ordering::Relaxed), stopped_at);
     }
 }
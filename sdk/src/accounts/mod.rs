@@ -1,7 +1,9 @@
 pub mod bulk_account_loader;
-pub mod polling_user_stats_account_subscriber;
+pub mod one_shot_account_subscriber;
+pub mod polling_account_subscriber;
 pub mod types;
-pub mod web_socket_user_stats_account_subscriber;
 
 pub use bulk_account_loader::*;
+pub use one_shot_account_subscriber::OneShotAccountSubscriber;
+pub use polling_account_subscriber::PollingAccountSubscriber;
 pub use types::*;
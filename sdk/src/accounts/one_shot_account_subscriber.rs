@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use anchor_lang::AccountDeserialize;
+use futures_util::{future::BoxFuture, FutureExt};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{AccountSubscriber, OnUpdate, SubscribedAccount};
+use crate::types::{DataAndSlot, SdkResult};
+
+/// Fetches an account once on subscribe, it is only updated again by explicit `fetch`es
+///
+/// For accounts that rarely change or when a snapshot is sufficient
+#[derive(Clone)]
+pub struct OneShotAccountSubscriber<T>
+where
+    T: AccountDeserialize,
+{
+    account: SubscribedAccount<T>,
+    rpc_client: Arc<RpcClient>,
+    subscribed: bool,
+}
+
+impl<T> OneShotAccountSubscriber<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    pub fn new(pubkey: Pubkey, rpc_client: Arc<RpcClient>) -> Self {
+        Self {
+            account: SubscribedAccount::new(pubkey),
+            rpc_client,
+            subscribed: false,
+        }
+    }
+
+    async fn subscribe_impl(&mut self, on_update: Option<OnUpdate<T>>) -> SdkResult<()> {
+        if self.subscribed {
+            return Ok(());
+        }
+        self.account.set_on_update(on_update);
+        self.account.fetch(&self.rpc_client).await?;
+        self.subscribed = true;
+
+        Ok(())
+    }
+}
+
+impl<T> AccountSubscriber<T> for OneShotAccountSubscriber<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    fn subscribe(&mut self, on_update: Option<OnUpdate<T>>) -> BoxFuture<SdkResult<()>> {
+        self.subscribe_impl(on_update).boxed()
+    }
+    fn fetch(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.account.fetch(&self.rpc_client).boxed()
+    }
+    fn unsubscribe(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.subscribed = false;
+        futures_util::future::ok(()).boxed()
+    }
+    fn set_data(&self, data: T, slot: u64) {
+        self.account.update(data, slot);
+    }
+    fn data_and_slot(&self) -> Option<DataAndSlot<T>> {
+        self.account.get()
+    }
+    fn is_subscribed(&self) -> bool {
+        self.subscribed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use drift::state::user::User;
+    use tokio::time::{sleep, Duration};
+
+    use super::*;
+    use crate::{accounts::bulk_account_loader::tests::mock_rpc, utils::zero_account_to_bytes};
+
+    #[tokio::test]
+    async fn fetches_on_subscribe_and_fetch_only() {
+        let (url, requests) = mock_rpc(zero_account_to_bytes(User::default())).await;
+        let mut subscriber = OneShotAccountSubscriber::<User>::new(
+            Pubkey::new_unique(),
+            Arc::new(RpcClient::new(url)),
+        );
+
+        subscriber.subscribe(None).await.unwrap();
+        assert!(subscriber.is_subscribed());
+        let subscribed_slot = subscriber.data_and_slot().unwrap().slot;
+
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(subscriber.data_and_slot().unwrap().slot, subscribed_slot);
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        subscriber.fetch().await.unwrap();
+        assert!(subscriber.data_and_slot().unwrap().slot > subscribed_slot);
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+    }
+}
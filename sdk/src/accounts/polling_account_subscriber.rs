@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use anchor_lang::AccountDeserialize;
+use futures_util::{future::BoxFuture, FutureExt};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::Mutex;
+
+use super::{AccountSubscriber, BulkAccountLoader, OnUpdate, SubscribedAccount};
+use crate::types::{DataAndSlot, SdkResult};
+
+/// Subscribes to an account by polling it with a `BulkAccountLoader`
+///
+/// The loader may be shared by many subscribers, their accounts are fetched in bulk each poll
+#[derive(Clone)]
+pub struct PollingAccountSubscriber<T>
+where
+    T: AccountDeserialize,
+{
+    account: SubscribedAccount<T>,
+    account_loader: BulkAccountLoader,
+    callback_id: Option<String>,
+    subscribed: bool,
+}
+
+impl<T> PollingAccountSubscriber<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    pub fn new(pubkey: Pubkey, account_loader: BulkAccountLoader) -> Self {
+        Self {
+            account: SubscribedAccount::new(pubkey),
+            account_loader,
+            callback_id: None,
+            subscribed: false,
+        }
+    }
+
+    async fn subscribe_impl(&mut self, on_update: Option<OnUpdate<T>>) -> SdkResult<()> {
+        if self.subscribed {
+            return Ok(());
+        }
+        self.account.set_on_update(on_update);
+        self.add_to_account_loader().await;
+        if self.account.get().is_none() {
+            self.fetch_impl().await?;
+        }
+        self.subscribed = true;
+
+        Ok(())
+    }
+
+    /// Add the account to the loader's polls without an initial fetch
+    pub(crate) async fn add_to_account_loader(&mut self) {
+        if self.callback_id.is_some() {
+            return;
+        }
+
+        let account = self.account.clone();
+        self.callback_id = Some(
+            self.account_loader
+                .add_account(
+                    account.pubkey,
+                    Arc::new(Mutex::new(move |buffer: Vec<u8>, slot: u64| {
+                        if buffer.is_empty() {
+                            return;
+                        }
+                        if let Err(e) = account.update_raw(&buffer, slot) {
+                            log::error!("Error decoding account {}: {e}", account.pubkey);
+                        }
+                    })),
+                )
+                .await,
+        );
+    }
+
+    async fn fetch_impl(&mut self) -> SdkResult<()> {
+        self.account.fetch(&self.account_loader.client).await
+    }
+
+    async fn unsubscribe_impl(&mut self) -> SdkResult<()> {
+        if let Some(callback_id) = self.callback_id.take() {
+            self.account_loader
+                .remove_account(self.account.pubkey, callback_id)
+                .await;
+        }
+        self.subscribed = false;
+
+        Ok(())
+    }
+}
+
+impl<T> AccountSubscriber<T> for PollingAccountSubscriber<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    fn subscribe(&mut self, on_update: Option<OnUpdate<T>>) -> BoxFuture<SdkResult<()>> {
+        self.subscribe_impl(on_update).boxed()
+    }
+    fn fetch(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.fetch_impl().boxed()
+    }
+    fn unsubscribe(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.unsubscribe_impl().boxed()
+    }
+    fn set_data(&self, data: T, slot: u64) {
+        self.account.update(data, slot);
+    }
+    fn data_and_slot(&self) -> Option<DataAndSlot<T>> {
+        self.account.get()
+    }
+    fn is_subscribed(&self) -> bool {
+        self.subscribed
+    }
+}
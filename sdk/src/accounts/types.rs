@@ -1,23 +1,245 @@
+use std::sync::{Arc, RwLock};
+
+use anchor_lang::AccountDeserialize;
 use drift::state::user::User as UserAccount;
+use futures_util::future::BoxFuture;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
 
 use crate::{
     error::SdkError,
+    event_emitter::EventEmitter,
+    grpc::{GrpcAccountSubscriber, GrpcConnectionOpts},
     types::{DataAndSlot, UserStatsAccount},
+    utils::get_ws_url,
+    websocket_account_subscriber::WebsocketAccountSubscriber,
     SdkResult,
 };
 
 use super::{
-    polling_user_stats_account_subscriber::PollingUserStatsAccountSubscriber,
-    web_socket_user_stats_account_subscriber::WebSocketUserStatsAccountSubscriber,
+    one_shot_account_subscriber::OneShotAccountSubscriber,
+    polling_account_subscriber::PollingAccountSubscriber, BulkAccountLoader,
 };
 
-// #[async_trait]
-// pub trait AccountSubscriber<T> {
-//     async fn subscribe<F: FnMut(T) + std::marker::Send>(&mut self, on_change: F);
-//     async fn fetch(&mut self) -> SdkResult<()>;
-//     async fn unsubscribe(&self);
-//     fn set_data(&mut self, user_account: T, slot: Option<u64>);
-// }
+/// Called with the pubkey and new value of an account each time its subscriber applies an update
+pub type OnUpdate<T> = Arc<dyn Fn(&Pubkey, &DataAndSlot<T>) + Send + Sync>;
+
+/// Keeps the latest value of a single account
+///
+/// Updates older than the current value are dropped, whichever way they arrive
+pub trait AccountSubscriber<T>: Send + Sync
+where
+    T: AccountDeserialize,
+{
+    /// Start receiving account updates, calling `on_update` with each one applied
+    ///
+    /// This is a no-op if already subscribed
+    fn subscribe(&mut self, on_update: Option<OnUpdate<T>>) -> BoxFuture<SdkResult<()>>;
+    /// Fetch the account from RPC
+    fn fetch(&mut self) -> BoxFuture<SdkResult<()>>;
+    /// Stop receiving account updates
+    fn unsubscribe(&mut self) -> BoxFuture<SdkResult<()>>;
+    /// Set the account value e.g. from a previous fetch, ignored if older than the current value
+    fn set_data(&self, data: T, slot: u64);
+    /// The latest account value, None until subscribed, fetched or set
+    fn data_and_slot(&self) -> Option<DataAndSlot<T>>;
+    fn is_subscribed(&self) -> bool;
+}
+
+/// Latest value of a subscribed account, shared between a subscriber and its update task
+pub(crate) struct SubscribedAccount<T>
+where
+    T: AccountDeserialize,
+{
+    pub pubkey: Pubkey,
+    data_and_slot: Arc<RwLock<Option<DataAndSlot<T>>>>,
+    on_update: Arc<RwLock<Option<OnUpdate<T>>>>,
+}
+
+impl<T> Clone for SubscribedAccount<T>
+where
+    T: AccountDeserialize,
+{
+    fn clone(&self) -> Self {
+        Self {
+            pubkey: self.pubkey,
+            data_and_slot: Arc::clone(&self.data_and_slot),
+            on_update: Arc::clone(&self.on_update),
+        }
+    }
+}
+
+impl<T> SubscribedAccount<T>
+where
+    T: AccountDeserialize + Clone,
+{
+    pub fn new(pubkey: Pubkey) -> Self {
+        Self {
+            pubkey,
+            data_and_slot: Default::default(),
+            on_update: Default::default(),
+        }
+    }
+
+    pub fn set_on_update(&self, on_update: Option<OnUpdate<T>>) {
+        *self.on_update.write().expect("lock") = on_update;
+    }
+
+    /// Apply `data` unless the current value is from a later slot
+    ///
+    /// Returns whether `data` was applied
+    pub fn update(&self, data: T, slot: u64) -> bool {
+        let data_and_slot = DataAndSlot { slot, data };
+        {
+            let mut current = self.data_and_slot.write().expect("lock");
+            if current.as_ref().is_some_and(|current| current.slot > slot) {
+                return false;
+            }
+            *current = Some(data_and_slot.clone());
+        }
+        if let Some(on_update) = self.on_update.read().expect("lock").as_ref() {
+            on_update(&self.pubkey, &data_and_slot);
+        }
+
+        true
+    }
+
+    /// Decode and apply raw account `data`
+    pub fn update_raw(&self, data: &[u8], slot: u64) -> SdkResult<bool> {
+        let data = T::try_deserialize(&mut &data[..]).map_err(|_| SdkError::Deserializing)?;
+        Ok(self.update(data, slot))
+    }
+
+    pub fn get(&self) -> Option<DataAndSlot<T>> {
+        self.data_and_slot.read().expect("lock").clone()
+    }
+
+    /// Fetch the account from `rpc_client` and apply it
+    pub async fn fetch(&self, rpc_client: &RpcClient) -> SdkResult<()> {
+        let response = rpc_client
+            .get_account_with_commitment(&self.pubkey, rpc_client.commitment())
+            .await?;
+        match response.value {
+            Some(account) => {
+                self.update_raw(&account.data, response.context.slot)?;
+                Ok(())
+            }
+            None => Err(SdkError::Generic(format!(
+                "account {} does not exist",
+                self.pubkey
+            ))),
+        }
+    }
+}
+
+/// Account subscription over any of the subscriber backends
+#[derive(Clone)]
+pub enum AccountSubscription<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    WebSocket(WebsocketAccountSubscriber<T>),
+    Grpc(GrpcAccountSubscriber<T>),
+    Polling(PollingAccountSubscriber<T>),
+    OneShot(OneShotAccountSubscriber<T>),
+}
+
+impl<T> AccountSubscription<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    /// Subscribe to `pubkey` over websocket
+    ///
+    /// `rpc_client` is used for fetches, its URL for the websocket connection
+    pub fn websocket(
+        subscription_name: &'static str,
+        pubkey: Pubkey,
+        rpc_client: Arc<RpcClient>,
+        commitment: CommitmentConfig,
+    ) -> SdkResult<Self> {
+        let url = get_ws_url(&rpc_client.url())
+            .map_err(|e| SdkError::Generic(format!("valid url: {e}")))?;
+        let subscriber = WebsocketAccountSubscriber::new(
+            subscription_name,
+            &url,
+            pubkey,
+            commitment,
+            EventEmitter::new(),
+        )
+        .with_rpc_client(rpc_client);
+
+        Ok(Self::WebSocket(subscriber))
+    }
+
+    /// Subscribe to `pubkey` over Yellowstone gRPC, `rpc_client` is used for fetches
+    pub fn grpc(
+        subscription_name: &'static str,
+        pubkey: Pubkey,
+        connection: GrpcConnectionOpts,
+        rpc_client: Arc<RpcClient>,
+        commitment: CommitmentConfig,
+    ) -> Self {
+        Self::Grpc(GrpcAccountSubscriber::new(
+            subscription_name,
+            connection,
+            pubkey,
+            rpc_client,
+            commitment,
+        ))
+    }
+
+    /// Poll `pubkey` with `account_loader`
+    pub fn polling(pubkey: Pubkey, account_loader: BulkAccountLoader) -> Self {
+        Self::Polling(PollingAccountSubscriber::new(pubkey, account_loader))
+    }
+
+    /// Fetch `pubkey` once on subscribe and on each `fetch`
+    pub fn one_shot(pubkey: Pubkey, rpc_client: Arc<RpcClient>) -> Self {
+        Self::OneShot(OneShotAccountSubscriber::new(pubkey, rpc_client))
+    }
+
+    fn inner(&self) -> &dyn AccountSubscriber<T> {
+        match self {
+            Self::WebSocket(subscriber) => subscriber,
+            Self::Grpc(subscriber) => subscriber,
+            Self::Polling(subscriber) => subscriber,
+            Self::OneShot(subscriber) => subscriber,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn AccountSubscriber<T> {
+        match self {
+            Self::WebSocket(subscriber) => subscriber,
+            Self::Grpc(subscriber) => subscriber,
+            Self::Polling(subscriber) => subscriber,
+            Self::OneShot(subscriber) => subscriber,
+        }
+    }
+}
+
+impl<T> AccountSubscriber<T> for AccountSubscription<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    fn subscribe(&mut self, on_update: Option<OnUpdate<T>>) -> BoxFuture<SdkResult<()>> {
+        self.inner_mut().subscribe(on_update)
+    }
+    fn fetch(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.inner_mut().fetch()
+    }
+    fn unsubscribe(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.inner_mut().unsubscribe()
+    }
+    fn set_data(&self, data: T, slot: u64) {
+        self.inner().set_data(data, slot)
+    }
+    fn data_and_slot(&self) -> Option<DataAndSlot<T>> {
+        self.inner().data_and_slot()
+    }
+    fn is_subscribed(&self) -> bool {
+        self.inner().is_subscribed()
+    }
+}
 
 #[allow(dead_code)]
 enum UserAccountEvents {
@@ -26,12 +248,7 @@ enum UserAccountEvents {
     Error { e: String },
 }
 
-#[derive(Debug, Clone)]
-pub enum UserAccountSubscriber {
-    Basic,
-    WebSocket,
-    Polling,
-}
+pub type UserAccountSubscriber = AccountSubscription<UserAccount>;
 
 pub struct ResubOpts {
     pub resub_timeout_ms: Option<u64>,
@@ -44,52 +261,50 @@ pub trait UserStatsAccountEvents {
     fn error(&self, e: SdkError);
 }
 
-pub enum UserStatsAccountSubscriber {
-    Polling(PollingUserStatsAccountSubscriber),
-    WebSocket(WebSocketUserStatsAccountSubscriber),
-}
+pub type UserStatsAccountSubscriber = AccountSubscription<UserStatsAccount>;
 
-impl UserStatsAccountSubscriber {
-    pub(crate) async fn subscribe(
-        &mut self,
-        user_stats_account: Option<UserStatsAccount>,
-    ) -> SdkResult<bool> {
-        match self {
-            UserStatsAccountSubscriber::Polling(polling) => {
-                polling.subscribe(user_stats_account).await
-            }
-            UserStatsAccountSubscriber::WebSocket(websocket) => {
-                websocket.subscribe(user_stats_account).await
-            }
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
 
-    pub(crate) async fn fetch(&mut self) -> SdkResult<()> {
-        match self {
-            UserStatsAccountSubscriber::Polling(polling) => polling.fetch().await,
-            UserStatsAccountSubscriber::WebSocket(websocket) => websocket.fetch().await,
-        }
-    }
+    use super::*;
 
-    pub(crate) async fn unsubscribe(&mut self) -> SdkResult<()> {
-        match self {
-            UserStatsAccountSubscriber::Polling(polling) => polling.unsubscribe().await,
-            UserStatsAccountSubscriber::WebSocket(websocket) => websocket.unsubscribe().await?,
-        }
+    #[test]
+    fn subscribed_account_drops_older_updates() {
+        let account = SubscribedAccount::<UserAccount>::new(Pubkey::new_unique());
+        let seen = Arc::new(Mutex::new(Vec::<(Pubkey, u64)>::new()));
+        account.set_on_update(Some(Arc::new({
+            let seen = Arc::clone(&seen);
+            move |_pubkey: &Pubkey, update: &DataAndSlot<UserAccount>| {
+                seen.lock()
+                    .unwrap()
+                    .push((update.data.authority, update.slot));
+            }
+        })));
+        let user = |authority| UserAccount {
+            authority,
+            ..Default::default()
+        };
+        let (authority_1, authority_2, authority_3) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
 
-        Ok(())
-    }
+        assert!(account.get().is_none());
+        assert!(account.update(user(authority_1), 10));
+        assert!(!account.update(user(authority_2), 9));
+        assert!(account.update(user(authority_3), 10));
+        assert!(matches!(
+            account.update_raw(&[0; 8], 11),
+            Err(SdkError::Deserializing)
+        ));
 
-    pub(crate) fn get_user_stats_account_and_slot(
-        &self,
-    ) -> SdkResult<Option<DataAndSlot<UserStatsAccount>>> {
-        match self {
-            UserStatsAccountSubscriber::Polling(polling) => {
-                polling.get_user_stats_account_and_slot()
-            }
-            UserStatsAccountSubscriber::WebSocket(websocket) => {
-                websocket.get_user_stats_account_and_slot()
-            }
-        }
+        let latest = account.get().unwrap();
+        assert_eq!((latest.data.authority, latest.slot), (authority_3, 10));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(authority_1, 10), (authority_3, 10)]
+        );
     }
 }
@@ -25,6 +25,12 @@ static STATE_ACCOUNT: OnceLock<Pubkey> = OnceLock::new();
 pub const TOKEN_PROGRAM_ID: Pubkey =
     solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
 
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Mint of wrapped SOL
+pub const NATIVE_MINT: Pubkey = solana_sdk::pubkey!("So11111111111111111111111111111111111111112");
+
 /// Return the market lookup table
 pub(crate) const fn market_lookup_table(context: Context) -> Pubkey {
     match context {
@@ -73,12 +79,43 @@ pub fn derive_spot_market_vault(market_index: u16) -> Pubkey {
     account
 }
 
+/// calculate the PDA for a drift spot market's insurance fund vault given index
+pub fn derive_insurance_fund_vault(market_index: u16) -> Pubkey {
+    let (account, _seed) = Pubkey::find_program_address(
+        &[&b"insurance_fund_vault"[..], &market_index.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    account
+}
+
+/// calculate the PDA for an authority's insurance fund stake in a spot market given index
+pub fn derive_insurance_fund_stake(authority: &Pubkey, market_index: u16) -> Pubkey {
+    let (account, _seed) = Pubkey::find_program_address(
+        &[
+            &b"insurance_fund_stake"[..],
+            authority.as_ref(),
+            &market_index.to_le_bytes(),
+        ],
+        &PROGRAM_ID,
+    );
+    account
+}
+
 /// calculate the PDA for the drift signer
 pub fn derive_drift_signer() -> Pubkey {
     let (account, _seed) = Pubkey::find_program_address(&[&b"drift_signer"[..]], &PROGRAM_ID);
     account
 }
 
+/// calculate the associated token account of `owner` for `mint`
+pub fn derive_associated_token_account(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let (account, _seed) = Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    );
+    account
+}
+
 /// Helper methods for market data structs
 pub trait MarketExt {
     fn market_type(&self) -> &'static str;
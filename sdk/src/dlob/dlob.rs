@@ -2,15 +2,16 @@
 
 use dashmap::DashSet;
 use drift::controller::position::PositionDirection;
+use drift::state::events::{OrderAction, OrderActionRecord};
 use drift::state::oracle::OraclePriceData;
 use drift::state::perp_market::PerpMarket;
 use drift::state::spot_market::SpotMarket;
 use drift::state::state::{ExchangeStatus, State};
-use drift::state::user::{MarketType, Order, OrderStatus, OrderTriggerCondition, OrderType};
+use drift::state::user::{MarketType, Order, OrderStatus, OrderTriggerCondition, OrderType, User};
 use rayon::prelude::*;
 use solana_sdk::pubkey::Pubkey;
 use std::any::Any;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::ops::Sub;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -159,6 +160,183 @@ impl DLOB {
         None
     }
 
+    /// Remove an order from the book, returns false if it wasn't in the book
+    pub fn delete(&self, order: &Order, user_account: Pubkey) -> bool {
+        let order_signature = get_order_signature(order.order_id, user_account);
+        let market = match order.market_type {
+            MarketType::Perp => self.exchange.perp.get_mut(&order.market_index),
+            MarketType::Spot => self.exchange.spot.get_mut(&order.market_index),
+        };
+        let Some(mut market) = market else {
+            return false;
+        };
+
+        let mut removed = false;
+        for order_list in market.order_lists_mut() {
+            removed |= order_list.remove(&order_signature).is_some();
+        }
+        removed
+    }
+
+    /// Replace an order already in the book, e.g. after a partial fill or trigger
+    ///
+    /// The order is removed if it is no longer open
+    pub fn update(&self, order: &Order, user_account: Pubkey, slot: u64) {
+        self.delete(order, user_account);
+        if order.status != OrderStatus::Init {
+            self.insert_order(order, user_account, slot);
+        }
+    }
+
+    /// Apply the order changes between two versions of a user account
+    ///
+    /// `old` is None for a new user, `new` is None for a removed user
+    pub fn apply_user_update(
+        &self,
+        user_account: Pubkey,
+        old: Option<&User>,
+        new: Option<&User>,
+        slot: u64,
+    ) {
+        let open_orders = |user: Option<&User>| -> HashMap<u32, Order> {
+            user.map(|user| {
+                user.orders
+                    .iter()
+                    .filter(|order| order.status != OrderStatus::Init)
+                    .map(|order| (order.order_id, *order))
+                    .collect()
+            })
+            .unwrap_or_default()
+        };
+        let old_orders = open_orders(old);
+        let new_orders = open_orders(new);
+
+        for (order_id, order) in old_orders.iter() {
+            if !new_orders.contains_key(order_id) {
+                self.delete(order, user_account);
+            }
+        }
+
+        for (order_id, order) in new_orders.iter() {
+            match old_orders.get(order_id) {
+                Some(old_order) if old_order == order => {}
+                Some(_) => self.update(order, user_account, slot),
+                None => self.insert_order(order, user_account, slot),
+            }
+        }
+    }
+
+    /// Apply fills, cancels, expiries and triggers from an `OrderActionRecord`
+    ///
+    /// Placed orders are not included in the record and must come from user account updates
+    pub fn handle_order_action_record(&self, record: &OrderActionRecord, slot: u64) {
+        let sides = [
+            (
+                record.taker,
+                record.taker_order_id,
+                record.taker_order_cumulative_base_asset_amount_filled,
+            ),
+            (
+                record.maker,
+                record.maker_order_id,
+                record.maker_order_cumulative_base_asset_amount_filled,
+            ),
+        ];
+
+        for (user_account, order_id, cumulative_filled) in sides {
+            let (Some(user_account), Some(order_id)) = (user_account, order_id) else {
+                continue;
+            };
+            let Some(mut order) = self.get_order(order_id, user_account) else {
+                continue;
+            };
+
+            match record.action {
+                OrderAction::Cancel | OrderAction::Expire => {
+                    self.delete(&order, user_account);
+                }
+                OrderAction::Fill => {
+                    if let Some(cumulative_filled) = cumulative_filled {
+                        order.base_asset_amount_filled = cumulative_filled;
+                    }
+                    if order.base_asset_amount_filled >= order.base_asset_amount {
+                        self.delete(&order, user_account);
+                    } else {
+                        self.update(&order, user_account, slot);
+                    }
+                }
+                OrderAction::Trigger => {
+                    order.trigger_condition = match order.trigger_condition {
+                        OrderTriggerCondition::Above => OrderTriggerCondition::TriggeredAbove,
+                        OrderTriggerCondition::Below => OrderTriggerCondition::TriggeredBelow,
+                        condition => condition,
+                    };
+                    self.update(&order, user_account, slot);
+                }
+                OrderAction::Place => {}
+            }
+        }
+    }
+
+    /// All orders in the book keyed by order signature
+    pub fn get_orders(&self) -> HashMap<String, Order> {
+        self.exchange
+            .get_order_lists()
+            .iter()
+            .flat_map(|order_list| {
+                order_list
+                    .order_sigs
+                    .iter()
+                    .map(|node| (node.key().clone(), *node.value().get_order()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Orders in each node list keyed by (market type, market index, node type), then by order
+    /// signature with whether the order is on the bid side of the list
+    fn get_node_lists(&self) -> HashMap<(String, u16, NodeType), HashMap<String, (Order, bool)>> {
+        let mut node_lists = HashMap::new();
+        for (market_type, markets) in [("perp", &self.exchange.perp), ("spot", &self.exchange.spot)]
+        {
+            for market in markets.iter() {
+                for (node_type, order_list) in market.value().order_lists() {
+                    let bids: HashSet<String> = order_list
+                        .bids
+                        .iter()
+                        .map(|directional| {
+                            get_order_signature(
+                                directional.node.get_order().order_id,
+                                directional.node.get_user_account(),
+                            )
+                        })
+                        .collect();
+                    let orders: HashMap<String, (Order, bool)> = order_list
+                        .order_sigs
+                        .iter()
+                        .map(|node| {
+                            (
+                                node.key().clone(),
+                                (*node.value().get_order(), bids.contains(node.key())),
+                            )
+                        })
+                        .collect();
+                    if !orders.is_empty() {
+                        node_lists
+                            .insert((market_type.to_string(), *market.key(), node_type), orders);
+                    }
+                }
+            }
+        }
+
+        node_lists
+    }
+
+    /// Return true if both books hold the same orders in the same node lists and sides
+    pub fn is_consistent_with(&self, other: &DLOB) -> bool {
+        self.get_node_lists() == other.get_node_lists()
+    }
+
     pub fn get_list_for_order(&self, order: &Order, slot: u64) -> Option<Orderlist> {
         let is_inactive_trigger_order = must_be_triggered(order) && !is_triggered(order);
 
@@ -732,9 +910,6 @@ impl DLOB {
     }
 
     fn update_resting_limit_orders_for_market_type(&mut self, slot: u64, market_type: MarketType) {
-        let mut new_taking_asks: BinaryHeap<DirectionalNode> = BinaryHeap::new();
-        let mut new_taking_bids: BinaryHeap<DirectionalNode> = BinaryHeap::new();
-
         let market = match market_type {
             MarketType::Perp => &self.exchange.perp,
             MarketType::Spot => &self.exchange.spot,
@@ -742,6 +917,8 @@ impl DLOB {
 
         for mut market_ref in market.iter_mut() {
             let market = market_ref.value_mut();
+            let mut new_taking_asks: BinaryHeap<DirectionalNode> = BinaryHeap::new();
+            let mut new_taking_bids: BinaryHeap<DirectionalNode> = BinaryHeap::new();
 
             for directional_node in market.taking_limit_orders.bids.iter() {
                 if is_resting_limit_order(directional_node.node.get_order(), slot) {
@@ -763,8 +940,23 @@ impl DLOB {
                 }
             }
 
-            market.taking_limit_orders.bids = new_taking_bids.clone();
-            market.taking_limit_orders.asks = new_taking_asks.clone();
+            // moved orders are no longer in the taking list
+            let taking_sigs: HashSet<String> = new_taking_bids
+                .iter()
+                .chain(new_taking_asks.iter())
+                .map(|directional| {
+                    get_order_signature(
+                        directional.node.get_order().order_id,
+                        directional.node.get_user_account(),
+                    )
+                })
+                .collect();
+            market
+                .taking_limit_orders
+                .order_sigs
+                .retain(|order_sig, _| taking_sigs.contains(order_sig));
+            market.taking_limit_orders.bids = new_taking_bids;
+            market.taking_limit_orders.asks = new_taking_asks;
         }
     }
 
@@ -1584,4 +1776,166 @@ mod tests {
         assert_eq!(resting_limit_bids[1].get_order().order_id, 2);
         assert_eq!(resting_limit_bids[2].get_order().order_id, 1);
     }
+
+    #[test]
+    fn test_dlob_delete_and_update() {
+        let dlob = DLOB::new();
+        let user_account = Pubkey::new_unique();
+        let order = Order {
+            order_id: 1,
+            slot: 1,
+            market_index: 0,
+            market_type: MarketType::Perp,
+            status: OrderStatus::Open,
+            base_asset_amount: 100,
+            ..Order::default()
+        };
+
+        dlob.insert_order(&order, user_account, 1);
+        assert_eq!(dlob.size(), (1, 0));
+
+        let partially_filled = Order {
+            base_asset_amount_filled: 40,
+            ..order
+        };
+        dlob.update(&partially_filled, user_account, 1);
+        assert_eq!(dlob.size(), (1, 0));
+        assert_eq!(
+            dlob.get_order(1, user_account)
+                .unwrap()
+                .base_asset_amount_filled,
+            40
+        );
+
+        assert!(dlob.delete(&order, user_account));
+        assert!(!dlob.delete(&order, user_account));
+        assert_eq!(dlob.size(), (0, 0));
+        assert!(dlob.get_order(1, user_account).is_none());
+    }
+
+    #[test]
+    fn test_apply_user_update() {
+        let dlob = DLOB::new();
+        let user_account = Pubkey::new_unique();
+        let order = |order_id: u32| Order {
+            order_id,
+            slot: 1,
+            market_index: 0,
+            market_type: MarketType::Perp,
+            status: OrderStatus::Open,
+            base_asset_amount: 100,
+            ..Order::default()
+        };
+
+        let mut old = User::default();
+        old.orders[0] = order(1);
+        old.orders[1] = order(2);
+        dlob.apply_user_update(user_account, None, Some(&old), 1);
+        assert_eq!(dlob.size(), (2, 0));
+
+        // order 1 partially filled, order 2 canceled, order 3 placed
+        let mut new = User::default();
+        new.orders[0] = Order {
+            base_asset_amount_filled: 50,
+            ..order(1)
+        };
+        new.orders[2] = order(3);
+        dlob.apply_user_update(user_account, Some(&old), Some(&new), 2);
+        assert_eq!(dlob.size(), (2, 0));
+        assert_eq!(
+            dlob.get_order(1, user_account)
+                .unwrap()
+                .base_asset_amount_filled,
+            50
+        );
+        assert!(dlob.get_order(2, user_account).is_none());
+        assert!(dlob.get_order(3, user_account).is_some());
+
+        let rebuilt = DLOB::new();
+        for order in new.orders.iter() {
+            if order.status != OrderStatus::Init {
+                rebuilt.insert_order(order, user_account, 2);
+            }
+        }
+        assert!(dlob.is_consistent_with(&rebuilt));
+
+        dlob.apply_user_update(user_account, Some(&new), None, 3);
+        assert_eq!(dlob.size(), (0, 0));
+        assert!(!dlob.is_consistent_with(&rebuilt));
+    }
+
+    #[test]
+    fn test_is_consistent_with_compares_node_lists() {
+        let mut dlob = DLOB::new();
+        let rebuilt = DLOB::new();
+        let user_account = Pubkey::new_unique();
+        let order = Order {
+            order_id: 1,
+            slot: 1,
+            market_index: 0,
+            market_type: MarketType::Perp,
+            status: OrderStatus::Open,
+            base_asset_amount: 100,
+            auction_duration: 1,
+            ..Order::default()
+        };
+
+        // same order, taking in one book and resting in the other
+        dlob.insert_order(&order, user_account, 1);
+        rebuilt.insert_order(&order, user_account, 5);
+        assert_eq!(dlob.get_orders(), rebuilt.get_orders());
+        assert!(!dlob.is_consistent_with(&rebuilt));
+
+        dlob.update_resting_limit_orders(5);
+        assert!(dlob.is_consistent_with(&rebuilt));
+    }
+
+    #[test]
+    fn test_handle_order_action_record() {
+        let dlob = DLOB::new();
+        let taker = Pubkey::new_unique();
+        let maker = Pubkey::new_unique();
+        let taker_order = Order {
+            order_id: 1,
+            slot: 1,
+            market_index: 0,
+            market_type: MarketType::Perp,
+            status: OrderStatus::Open,
+            base_asset_amount: 100,
+            ..Order::default()
+        };
+        let maker_order = Order {
+            order_id: 2,
+            direction: drift::controller::position::PositionDirection::Short,
+            ..taker_order
+        };
+        dlob.insert_order(&taker_order, taker, 1);
+        dlob.insert_order(&maker_order, maker, 1);
+
+        let fill = OrderActionRecord {
+            action: OrderAction::Fill,
+            taker: Some(taker),
+            taker_order_id: Some(1),
+            taker_order_cumulative_base_asset_amount_filled: Some(100),
+            maker: Some(maker),
+            maker_order_id: Some(2),
+            maker_order_cumulative_base_asset_amount_filled: Some(30),
+            ..OrderActionRecord::default()
+        };
+        dlob.handle_order_action_record(&fill, 2);
+        assert!(dlob.get_order(1, taker).is_none());
+        assert_eq!(
+            dlob.get_order(2, maker).unwrap().base_asset_amount_filled,
+            30
+        );
+
+        let cancel = OrderActionRecord {
+            action: OrderAction::Cancel,
+            maker: Some(maker),
+            maker_order_id: Some(2),
+            ..OrderActionRecord::default()
+        };
+        dlob.handle_order_action_record(&cancel, 3);
+        assert_eq!(dlob.size(), (0, 0));
+    }
 }
@@ -1,14 +1,28 @@
 use crate::{
-    dlob::dlob::DLOB, event_emitter::EventEmitter, slot_subscriber::SlotSubscriber,
-    usermap::UserMap, SdkResult,
+    dlob::dlob::DLOB, event_emitter::EventEmitter, events::types::EventMap,
+    slot_subscriber::SlotSubscriber, usermap::UserMap, SdkResult,
+};
+use drift::state::{events::OrderActionRecord, user::User};
+use futures_util::{Stream, StreamExt};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
 };
-use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Keeps a `DLOB` up to date by applying `UserMap` account diffs instead of rebuilding it every tick
 pub struct DLOBBuilder {
     slot_subscriber: SlotSubscriber,
     usermap: UserMap,
     rebuild_frequency: u64,
+    /// compare the incremental book against a full rebuild every this many slots
+    consistency_check_slots: u64,
+    last_consistency_check_slot: u64,
+    /// user accounts the book was last updated from
+    users: HashMap<String, User>,
+    initialized: bool,
     dlob: DLOB,
     event_emitter: EventEmitter,
 }
@@ -16,11 +30,20 @@ pub struct DLOBBuilder {
 impl DLOBBuilder {
     pub const SUBSCRIPTION_ID: &'static str = "dlob_update";
 
-    pub fn new(slot_subscriber: SlotSubscriber, usermap: UserMap, rebuild_frequency: u64) -> Self {
+    pub fn new(
+        slot_subscriber: SlotSubscriber,
+        usermap: UserMap,
+        rebuild_frequency: u64,
+        consistency_check_slots: u64,
+    ) -> Self {
         DLOBBuilder {
             slot_subscriber,
             usermap,
             rebuild_frequency,
+            consistency_check_slots,
+            last_consistency_check_slot: 0,
+            users: HashMap::new(),
+            initialized: false,
             dlob: DLOB::new(),
             event_emitter: EventEmitter::new(),
         }
@@ -28,11 +51,20 @@ impl DLOBBuilder {
 
     pub async fn start_building(builder: Arc<Mutex<Self>>) -> SdkResult<()> {
         let mut locked_builder = builder.lock().await;
-        let rebuild_frequency = locked_builder.rebuild_frequency;
         locked_builder.slot_subscriber.subscribe().await?;
         locked_builder.usermap.subscribe().await?;
         drop(locked_builder);
 
+        Self::spawn_build_loop(builder).await;
+
+        Ok(())
+    }
+
+    /// Build on an interval from a slot subscriber and user map which are already subscribed,
+    /// e.g. when they are shared with other bots
+    pub async fn spawn_build_loop(builder: Arc<Mutex<Self>>) {
+        let rebuild_frequency = builder.lock().await.rebuild_frequency;
+
         tokio::task::spawn(async move {
             let mut timer =
                 tokio::time::interval(tokio::time::Duration::from_millis(rebuild_frequency));
@@ -44,20 +76,180 @@ impl DLOBBuilder {
                 let _ = timer.tick().await;
             }
         });
+    }
 
-        Ok(())
+    /// Apply order action records from `events` (e.g. `EventSubscriber::stream`) to the book as
+    /// they arrive
+    pub fn handle_events(
+        builder: Arc<Mutex<Self>>,
+        events: impl Stream<Item = EventMap> + Send + 'static,
+    ) {
+        tokio::task::spawn(Self::apply_events(builder, events));
+    }
+
+    /// Apply order action records from `events` until the stream ends
+    async fn apply_events(builder: Arc<Mutex<Self>>, events: impl Stream<Item = EventMap>) {
+        futures_util::pin_mut!(events);
+        while let Some(event) = events.next().await {
+            if let EventMap::OrderActionRecord(record) = event {
+                builder
+                    .lock()
+                    .await
+                    .handle_order_action_record(&record.data);
+            }
+        }
     }
 
     pub fn build(&mut self) {
-        self.dlob
-            .build_from_usermap(&self.usermap, self.slot_subscriber.current_slot());
+        let slot = self.slot_subscriber.current_slot();
+
+        if self.initialized {
+            self.apply_usermap_diffs(slot);
+            if slot >= self.last_consistency_check_slot + self.consistency_check_slots {
+                self.check_consistency(slot);
+            }
+        } else {
+            self.rebuild(slot);
+            self.initialized = true;
+        }
+
         self.event_emitter
             .emit(DLOBBuilder::SUBSCRIPTION_ID, Box::new(self.dlob.clone()));
     }
 
+    /// Apply fills, cancels and triggers as soon as their events arrive, ahead of the account updates
+    pub fn handle_order_action_record(&mut self, record: &OrderActionRecord) {
+        self.dlob
+            .handle_order_action_record(record, self.slot_subscriber.current_slot());
+    }
+
     pub fn get_dlob(&self) -> DLOB {
         self.dlob.clone()
     }
+
+    fn rebuild(&mut self, slot: u64) {
+        self.dlob.build_from_usermap(&self.usermap, slot);
+        self.users = self
+            .usermap
+            .usermap
+            .iter()
+            .map(|user| (user.key().clone(), *user.value()))
+            .collect();
+        self.last_consistency_check_slot = slot;
+    }
+
+    fn apply_usermap_diffs(&mut self, slot: u64) {
+        let mut seen = HashSet::with_capacity(self.users.len());
+
+        for user_ref in self.usermap.usermap.iter() {
+            let key = user_ref.key();
+            let user = user_ref.value();
+            seen.insert(key.clone());
+
+            let old = self.users.get(key);
+            if old.is_some_and(|old| old.orders == user.orders) {
+                continue;
+            }
+
+            let user_pubkey = Pubkey::from_str(key).expect("Valid pubkey");
+            self.dlob
+                .apply_user_update(user_pubkey, old, Some(user), slot);
+            self.users.insert(key.clone(), *user);
+        }
+
+        let dlob = &self.dlob;
+        self.users.retain(|key, old| {
+            if seen.contains(key) {
+                return true;
+            }
+            let user_pubkey = Pubkey::from_str(key).expect("Valid pubkey");
+            dlob.apply_user_update(user_pubkey, Some(old), None, slot);
+            false
+        });
+    }
+
+    fn check_consistency(&mut self, slot: u64) {
+        // a full rebuild classifies taking limit orders as resting by the current slot
+        self.dlob.update_resting_limit_orders(slot);
+        let mut rebuilt = DLOB::new();
+        rebuilt.build_from_usermap(&self.usermap, slot);
+
+        if !self.dlob.is_consistent_with(&rebuilt) {
+            log::warn!("incremental DLOB diverged from full rebuild at slot {slot}, resetting");
+            self.rebuild(slot);
+        }
+        self.last_consistency_check_slot = slot;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drift::state::{
+        events::OrderAction,
+        user::{MarketType, Order, OrderStatus},
+    };
+    use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+
+    use super::*;
+    use crate::events::types::Event;
+
+    #[tokio::test]
+    async fn apply_events_handles_order_action_records() {
+        let builder = DLOBBuilder::new(
+            SlotSubscriber::new("ws://localhost:8900"),
+            UserMap::new(
+                CommitmentConfig::confirmed(),
+                "http://localhost:8899",
+                false,
+                None,
+            ),
+            100,
+            100,
+        );
+        let user = Pubkey::new_unique();
+        let order = Order {
+            order_id: 1,
+            slot: 1,
+            market_type: MarketType::Perp,
+            status: OrderStatus::Open,
+            base_asset_amount: 100,
+            ..Order::default()
+        };
+        builder.dlob.insert_order(&order, user, 1);
+        let builder = Arc::new(Mutex::new(builder));
+
+        let event = |action, filled| {
+            EventMap::OrderActionRecord(Event {
+                tx_sig: Signature::default(),
+                slot: 2,
+                tx_sig_index: 0,
+                data: OrderActionRecord {
+                    action,
+                    taker: Some(user),
+                    taker_order_id: Some(1),
+                    taker_order_cumulative_base_asset_amount_filled: filled,
+                    ..OrderActionRecord::default()
+                },
+            })
+        };
+        DLOBBuilder::apply_events(
+            builder.clone(),
+            futures_util::stream::iter([event(OrderAction::Fill, Some(40))]),
+        )
+        .await;
+        let dlob = builder.lock().await.get_dlob();
+        assert_eq!(
+            dlob.get_order(1, user).unwrap().base_asset_amount_filled,
+            40
+        );
+
+        DLOBBuilder::apply_events(
+            builder.clone(),
+            futures_util::stream::iter([event(OrderAction::Cancel, None)]),
+        )
+        .await;
+        assert!(builder.lock().await.get_dlob().get_order(1, user).is_none());
+    }
 }
 
 // #[cfg(test)]
@@ -85,7 +277,7 @@ impl DLOBBuilder {
 //             true,
 //             Some(vec![get_user_with_order_filter()]),
 //         );
-//         let dlob_builder = DLOBBuilder::new(slot_subscriber, usermap, 5);
+//         let dlob_builder = DLOBBuilder::new(slot_subscriber, usermap, 5, 100);
 //
 //         dlob_builder
 //             .event_emitter
@@ -121,7 +313,7 @@ impl DLOBBuilder {
 //         let _ = slot_subscriber.subscribe().await;
 //         let _ = usermap.subscribe().await;
 //
-//         let mut dlob_builder = DLOBBuilder::new(slot_subscriber, usermap, 30);
+//         let mut dlob_builder = DLOBBuilder::new(slot_subscriber, usermap, 30, 100);
 //
 //         let start = std::time::Instant::now();
 //         dlob_builder.build();
@@ -2,7 +2,7 @@ use crate::math::order::get_limit_price;
 use drift::state::{oracle::OraclePriceData, user::Order};
 use solana_sdk::pubkey::Pubkey;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum NodeType {
     TakingLimit,
     RestingLimit,
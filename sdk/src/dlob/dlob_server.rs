@@ -0,0 +1,542 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
+
+use dashmap::DashMap;
+use drift::state::user::MarketType;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, error, warn};
+use reqwest::StatusCode;
+use serde::{de, Deserialize, Deserializer};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::{
+    error::SdkError,
+    grpc::reconnect_delay,
+    types::SdkResult,
+    utils::{dlob_subscribe_ws_json, market_type_to_string},
+};
+
+use super::order_book_levels::{L2Level, L2OrderBook, L3Level, L3OrderBook, LiquiditySource};
+
+/// The DLOB server serializes big numbers as strings
+fn from_str_or_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => s.parse().map_err(de::Error::custom),
+        Value::Number(n) => n.to_string().parse().map_err(de::Error::custom),
+        other => Err(de::Error::custom(format!("unexpected value: {other}"))),
+    }
+}
+
+fn liquidity_source_from_str(source: &str) -> Option<LiquiditySource> {
+    match source {
+        "serum" => Some(LiquiditySource::Serum),
+        "vamm" => Some(LiquiditySource::Vamm),
+        "dlob" => Some(LiquiditySource::Dlob),
+        "phoenix" => Some(LiquiditySource::Phoenix),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct L2LevelResponse {
+    #[serde(deserialize_with = "from_str_or_number")]
+    pub price: u128,
+    #[serde(deserialize_with = "from_str_or_number")]
+    pub size: i128,
+    #[serde(default)]
+    pub sources: HashMap<String, Value>,
+}
+
+impl From<L2LevelResponse> for L2Level {
+    fn from(level: L2LevelResponse) -> Self {
+        let sources = level
+            .sources
+            .iter()
+            .filter_map(|(source, size)| {
+                let size = match size {
+                    Value::String(s) => s.parse().ok(),
+                    Value::Number(n) => n.to_string().parse().ok(),
+                    _ => None,
+                }?;
+                Some((liquidity_source_from_str(source)?, size))
+            })
+            .collect();
+        L2Level::new(level.price, level.size, sources)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L2OrderBookResponse {
+    pub bids: Vec<L2LevelResponse>,
+    pub asks: Vec<L2LevelResponse>,
+    pub slot: u64,
+    pub market_type: Option<String>,
+    pub market_index: Option<u16>,
+}
+
+impl From<L2OrderBookResponse> for L2OrderBook {
+    fn from(book: L2OrderBookResponse) -> Self {
+        L2OrderBook {
+            bids: book.bids.into_iter().map(L2Level::from).collect(),
+            asks: book.asks.into_iter().map(L2Level::from).collect(),
+            slot: book.slot,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L3LevelResponse {
+    #[serde(deserialize_with = "from_str_or_number")]
+    pub price: u64,
+    #[serde(deserialize_with = "from_str_or_number")]
+    pub size: u64,
+    #[serde(deserialize_with = "from_str_or_number")]
+    pub maker: Pubkey,
+    pub order_id: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct L3OrderBookResponse {
+    pub bids: Vec<L3LevelResponse>,
+    pub asks: Vec<L3LevelResponse>,
+    pub slot: u64,
+}
+
+impl From<L3OrderBookResponse> for L3OrderBook {
+    fn from(book: L3OrderBookResponse) -> Self {
+        let to_level = |level: L3LevelResponse| L3Level {
+            price: level.price,
+            size: level.size,
+            maker: level.maker,
+            order_id: level.order_id,
+        };
+        L3OrderBook {
+            bids: book.bids.into_iter().map(to_level).collect(),
+            asks: book.asks.into_iter().map(to_level).collect(),
+            slot: book.slot,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WsMessage {
+    channel: String,
+    data: Option<String>,
+}
+
+/// Client for the Drift DLOB server
+///
+/// L2 books are served from the `orderbook` websocket channel once subscribed, otherwise from `/l2`
+#[derive(Clone)]
+pub struct DlobServerClient {
+    url: String,
+    ws_url: String,
+    client: reqwest::Client,
+    /// latest websocket L2 book per (market type, market index)
+    orderbooks: Arc<DashMap<(String, u16), L2OrderBookResponse>>,
+    unsubscriber: Option<tokio::sync::mpsc::Sender<()>>,
+}
+
+impl DlobServerClient {
+    pub fn new(url: &str, ws_url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            ws_url: ws_url.to_string(),
+            client: reqwest::Client::new(),
+            orderbooks: Arc::new(DashMap::new()),
+            unsubscriber: None,
+        }
+    }
+
+    /// `include_vamm` is forwarded to `/l2`, websocket books include vAMM liquidity so it is
+    /// removed from them when false
+    pub async fn get_l2(
+        &self,
+        market_type: MarketType,
+        market_index: u16,
+        depth: usize,
+        include_vamm: bool,
+    ) -> SdkResult<L2OrderBook> {
+        let market_type = market_type_to_string(&market_type);
+        if let Some(book) = self.orderbooks.get(&(market_type.clone(), market_index)) {
+            let mut book: L2OrderBook = book.value().clone().into();
+            if !include_vamm {
+                remove_vamm_liquidity(&mut book.bids);
+                remove_vamm_liquidity(&mut book.asks);
+            }
+            book.bids.truncate(depth);
+            book.asks.truncate(depth);
+            return Ok(book);
+        }
+
+        let url = format!(
+            "{}/l2?marketType={market_type}&marketIndex={market_index}&depth={depth}&includeVamm={include_vamm}",
+            self.url
+        );
+        let book: L2OrderBookResponse = self.get(&url).await?;
+        Ok(book.into())
+    }
+
+    pub async fn get_l3(
+        &self,
+        market_type: MarketType,
+        market_index: u16,
+    ) -> SdkResult<L3OrderBook> {
+        let market_type = market_type_to_string(&market_type);
+        let url = format!(
+            "{}/l3?marketType={market_type}&marketIndex={market_index}",
+            self.url
+        );
+        let book: L3OrderBookResponse = self.get(&url).await?;
+        Ok(book.into())
+    }
+
+    /// Subscribe to the `orderbook` channel of `markets` (e.g. "SOL-PERP")
+    ///
+    /// The websocket is reconnected with backoff if it closes, books are served from `/l2` until
+    /// the channel delivers them again
+    pub async fn subscribe(&mut self, markets: &[&str]) -> SdkResult<()> {
+        if self.unsubscriber.is_some() {
+            return Ok(());
+        }
+
+        let markets: Vec<String> = markets.iter().map(ToString::to_string).collect();
+        let mut ws_stream = connect_orderbook_ws(&self.ws_url, &markets).await?;
+
+        let (unsub_tx, mut unsub_rx) = tokio::sync::mpsc::channel::<()>(1);
+        self.unsubscriber = Some(unsub_tx);
+
+        let ws_url = self.ws_url.clone();
+        let orderbooks = self.orderbooks.clone();
+        tokio::spawn(async move {
+            'subscription: loop {
+                let (mut write, mut read) = ws_stream.split();
+                loop {
+                    tokio::select! {
+                        message = read.next() => {
+                            match message {
+                                Some(Ok(Message::Text(text))) => handle_ws_message(&orderbooks, &text),
+                                Some(Ok(Message::Ping(payload))) => {
+                                    let _ = write.send(Message::Pong(payload)).await;
+                                }
+                                Some(Ok(Message::Close(_))) | None => {
+                                    warn!("DLOB server websocket closed, reconnecting");
+                                    break;
+                                }
+                                Some(Ok(_)) => {}
+                                Some(Err(e)) => {
+                                    error!("DLOB server websocket error: {e}, reconnecting");
+                                    break;
+                                }
+                            }
+                        }
+                        _ = unsub_rx.recv() => {
+                            debug!("Unsubscribing.");
+                            let _ = write.close().await;
+                            break 'subscription;
+                        }
+                    }
+                }
+                // books go stale while disconnected
+                orderbooks.clear();
+
+                let mut attempt = 0;
+                ws_stream = loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(reconnect_delay(attempt)) => {}
+                        _ = unsub_rx.recv() => break 'subscription,
+                    }
+                    match connect_orderbook_ws(&ws_url, &markets).await {
+                        Ok(ws_stream) => break ws_stream,
+                        Err(e) => {
+                            warn!("DLOB server websocket reconnect failed: {e}");
+                            attempt += 1;
+                        }
+                    }
+                };
+            }
+            orderbooks.clear();
+        });
+
+        Ok(())
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.unsubscriber.is_some()
+    }
+
+    pub async fn unsubscribe(&mut self) -> SdkResult<()> {
+        if let Some(unsubscriber) = self.unsubscriber.take() {
+            unsubscriber.send(()).await?;
+        }
+        Ok(())
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, url: &str) -> SdkResult<T> {
+        let response = self.client.get(url).send().await?;
+        let status: StatusCode = response.status();
+        let body_text: String = response.text().await.unwrap_or_default();
+
+        if !status.is_success() {
+            return Err(SdkError::Generic(format!(
+                "Status: {status}, Url: {url}, Body Text: {body_text}"
+            )));
+        }
+
+        serde_json::from_str(&body_text).map_err(|e| {
+            SdkError::Generic(format!("Deserialization Error: {e}, Raw JSON: {body_text}"))
+        })
+    }
+}
+
+/// Connect to the DLOB server websocket and subscribe to the `orderbook` channel of `markets`
+async fn connect_orderbook_ws(
+    ws_url: &str,
+    markets: &[String],
+) -> SdkResult<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let (mut ws_stream, _) = connect_async(ws_url).await?;
+    for market in markets {
+        ws_stream
+            .send(Message::Text(dlob_subscribe_ws_json(market)))
+            .await?;
+    }
+    Ok(ws_stream)
+}
+
+fn handle_ws_message(orderbooks: &DashMap<(String, u16), L2OrderBookResponse>, text: &str) {
+    let message: WsMessage = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => {
+            warn!("unexpected DLOB server message: {e}, {text}");
+            return;
+        }
+    };
+    if !message.channel.starts_with("orderbook") {
+        return;
+    }
+    let Some(data) = message.data else {
+        return;
+    };
+
+    match serde_json::from_str::<L2OrderBookResponse>(&data) {
+        Ok(book) => {
+            if let (Some(market_type), Some(market_index)) =
+                (book.market_type.clone(), book.market_index)
+            {
+                orderbooks.insert((market_type, market_index), book);
+            }
+        }
+        Err(e) => warn!("failed to parse DLOB server orderbook: {e}"),
+    }
+}
+
+/// Remove vAMM liquidity from `levels`, dropping levels with no other source
+fn remove_vamm_liquidity(levels: &mut Vec<L2Level>) {
+    for level in levels.iter_mut() {
+        if let Some(vamm_size) = level.sources.remove(&LiquiditySource::Vamm) {
+            level.size -= vamm_size;
+        }
+    }
+    levels.retain(|level| level.size > 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::http_stub;
+
+    /// Serve `body` to every HTTP request, returning the server url
+    async fn mock_http_server(body: String) -> String {
+        http_stub::serve(move |_| Some(body.clone())).await
+    }
+
+    /// Serve `body` to every HTTP request, forwarding each request line to the returned receiver
+    async fn recording_http_server(
+        body: String,
+    ) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let url = http_stub::serve(move |request| {
+            let _ = tx.send(request.request_line.clone());
+            Some(body.clone())
+        })
+        .await;
+        (url, rx)
+    }
+
+    /// Push `messages` to the first websocket client after it subscribes, returning the server url
+    async fn mock_ws_server(messages: Vec<String>) -> String {
+        mock_ws_server_with_reconnects(vec![messages]).await
+    }
+
+    /// Push the nth entry of `connections` to the nth websocket client after it subscribes,
+    /// closing every connection but the last
+    async fn mock_ws_server_with_reconnects(connections: Vec<Vec<String>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let count = connections.len();
+            for (i, messages) in connections.into_iter().enumerate() {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let subscribe = ws.next().await.unwrap().unwrap();
+                assert!(subscribe.to_text().unwrap().contains("orderbook"));
+                for message in messages {
+                    ws.send(Message::Text(message)).await.unwrap();
+                }
+                if i + 1 < count {
+                    ws.close(None).await.unwrap();
+                } else {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        });
+        format!("ws://{addr}")
+    }
+
+    fn l2_json() -> Value {
+        json!({
+            "marketName": "SOL-PERP",
+            "marketType": "perp",
+            "marketIndex": 0,
+            "slot": 100,
+            "bids": [
+                { "price": "150000000", "size": "2000000000", "sources": { "dlob": "1000000000", "vamm": "1000000000" } },
+                { "price": "149000000", "size": "1000000000", "sources": { "vamm": "1000000000" } }
+            ],
+            "asks": [
+                { "price": "151000000", "size": "3000000000", "sources": { "dlob": "3000000000" } }
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_l2() {
+        let url = mock_http_server(l2_json().to_string()).await;
+        let client = DlobServerClient::new(&url, "");
+
+        let book = client.get_l2(MarketType::Perp, 0, 10, true).await.unwrap();
+
+        assert_eq!(book.slot, 100);
+        assert_eq!(book.bids.len(), 2);
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.bids[0].price, 150_000_000);
+        assert_eq!(book.bids[0].size, 2_000_000_000);
+        assert_eq!(
+            book.bids[0].sources.get(&LiquiditySource::Vamm),
+            Some(&1_000_000_000)
+        );
+        assert_eq!(book.asks[0].price, 151_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_get_l2_forwards_include_vamm() {
+        let (url, mut requests) = recording_http_server(l2_json().to_string()).await;
+        let client = DlobServerClient::new(&url, "");
+
+        client.get_l2(MarketType::Perp, 0, 10, false).await.unwrap();
+        let request = requests.recv().await.unwrap();
+        assert!(request.starts_with("GET /l2?"));
+        assert!(request.contains("marketType=perp"));
+        assert!(request.contains("depth=10"));
+        assert!(request.contains("includeVamm=false"));
+
+        client.get_l2(MarketType::Perp, 0, 10, true).await.unwrap();
+        assert!(requests.recv().await.unwrap().contains("includeVamm=true"));
+    }
+
+    #[test]
+    fn test_remove_vamm_liquidity() {
+        let response: L2OrderBookResponse = serde_json::from_value(l2_json()).unwrap();
+        let mut book: L2OrderBook = response.into();
+
+        remove_vamm_liquidity(&mut book.bids);
+        remove_vamm_liquidity(&mut book.asks);
+
+        // vamm only level is dropped, mixed level keeps its dlob size
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!(book.bids[0].size, 1_000_000_000);
+        assert!(!book.bids[0].sources.contains_key(&LiquiditySource::Vamm));
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.asks[0].size, 3_000_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_get_l3() {
+        let maker = Pubkey::new_unique();
+        let body = json!({
+            "marketName": "SOL-PERP",
+            "marketType": "perp",
+            "marketIndex": 0,
+            "slot": 100,
+            "bids": [{ "price": "150000000", "size": "1000000000", "maker": maker.to_string(), "orderId": 7 }],
+            "asks": []
+        });
+        let url = mock_http_server(body.to_string()).await;
+        let client = DlobServerClient::new(&url, "");
+
+        let book = client.get_l3(MarketType::Perp, 0).await.unwrap();
+
+        assert_eq!(book.slot, 100);
+        assert_eq!(book.bids.len(), 1);
+        assert!(book.asks.is_empty());
+        assert_eq!(book.bids[0].maker, maker);
+        assert_eq!(book.bids[0].order_id, 7);
+        assert_eq!(book.bids[0].size, 1_000_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_orderbook() {
+        let messages = vec![
+            json!({ "channel": "heartbeat" }).to_string(),
+            json!({ "channel": "orderbook_perp_0", "data": l2_json().to_string() }).to_string(),
+        ];
+        let ws_url = mock_ws_server(messages).await;
+        // REST is unreachable, so the book must come from the websocket
+        let mut client = DlobServerClient::new("http://127.0.0.1:1", &ws_url);
+        client.subscribe(&["SOL-PERP"]).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let book = client.get_l2(MarketType::Perp, 0, 1, false).await.unwrap();
+        assert_eq!(book.slot, 100);
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!(book.asks.len(), 1);
+
+        client.unsubscribe().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_reconnects_after_close() {
+        let book = |slot: u64| {
+            let mut book = l2_json();
+            book["slot"] = json!(slot);
+            json!({ "channel": "orderbook_perp_0", "data": book.to_string() }).to_string()
+        };
+        let ws_url = mock_ws_server_with_reconnects(vec![vec![book(100)], vec![book(101)]]).await;
+        let mut client = DlobServerClient::new("http://127.0.0.1:1", &ws_url);
+        client.subscribe(&["SOL-PERP"]).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+
+        assert!(client.is_subscribed());
+        let book = client.get_l2(MarketType::Perp, 0, 1, false).await.unwrap();
+        assert_eq!(book.slot, 101);
+
+        client.unsubscribe().await.unwrap();
+        assert!(!client.is_subscribed());
+    }
+}
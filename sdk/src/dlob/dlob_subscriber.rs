@@ -109,9 +109,10 @@ where
     }
 
     async fn update_dlob(&self) -> SdkResult<()> {
-        let slot = self.slot_source.get_slot();
+        let Some(dlob_source) = self.dlob_source.get_dlob().await else {
+            return Ok(());
+        };
         let mut dlob = self.dlob.lock().await;
-        let dlob_source = self.dlob_source.get_dlob(slot);
 
         info!("DLOB: {} {}", dlob_source.size().0, dlob_source.size().1);
 
@@ -159,6 +160,11 @@ where
 
         let market_type = market_type.unwrap();
         let market_index = market_index.unwrap();
+        if let DlobSource::DlobServer(client) = &self.dlob_source {
+            return client
+                .get_l2(market_type, market_index, depth, include_vamm)
+                .await;
+        }
         let is_perp = market_type == MarketType::Perp;
 
         let oracle_price_data = if is_perp {
@@ -239,6 +245,9 @@ where
 
         let market_type = market_type.unwrap();
         let market_index = market_index.unwrap();
+        if let DlobSource::DlobServer(client) = &self.dlob_source {
+            return client.get_l3(market_type, market_index).await;
+        }
         let is_perp = market_type == MarketType::Perp;
 
         let oracle_price_data = if is_perp {
@@ -64,6 +64,26 @@ impl Market {
         .clone()
     }
 
+    pub(crate) fn order_lists(&self) -> [(NodeType, &Orderlist); 5] {
+        [
+            (NodeType::RestingLimit, &self.resting_limit_orders),
+            (NodeType::FloatingLimit, &self.floating_limit_orders),
+            (NodeType::TakingLimit, &self.taking_limit_orders),
+            (NodeType::Market, &self.market_orders),
+            (NodeType::Trigger, &self.trigger_orders),
+        ]
+    }
+
+    pub(crate) fn order_lists_mut(&mut self) -> [&mut Orderlist; 5] {
+        [
+            &mut self.resting_limit_orders,
+            &mut self.floating_limit_orders,
+            &mut self.taking_limit_orders,
+            &mut self.market_orders,
+            &mut self.trigger_orders,
+        ]
+    }
+
     /// for debugging
     pub fn print_all_orders(&self) {
         self.resting_limit_orders.print();
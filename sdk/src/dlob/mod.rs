@@ -1,6 +1,7 @@
 pub mod dlob;
 pub mod dlob_builder;
 pub mod dlob_node;
+pub mod dlob_server;
 pub mod dlob_subscriber;
 pub mod market;
 pub(crate) mod order_book_levels;
@@ -66,6 +66,20 @@ impl Orderlist {
         None
     }
 
+    /// Remove the node with `order_sig` from both sides of the list
+    pub fn remove(&mut self, order_sig: &str) -> Option<Node> {
+        let (_, node) = self.order_sigs.remove(order_sig)?;
+        let is_other = |directional: &DirectionalNode| {
+            get_order_signature(
+                directional.node.get_order().order_id,
+                directional.node.get_user_account(),
+            ) != order_sig
+        };
+        self.bids.retain(is_other);
+        self.asks.retain(is_other);
+        Some(node)
+    }
+
     pub fn get_node(&self, order_sig: &String) -> Option<Node> {
         self.order_sigs.get(order_sig).map(|node| *node)
     }
@@ -210,4 +224,31 @@ mod tests {
         assert_eq!(orderlist.get_best_ask().unwrap().get_order().slot, 4);
         assert_eq!(orderlist.get_best_ask().unwrap().get_order().slot, 5);
     }
+
+    #[test]
+    fn test_remove() {
+        let mut orderlist = Orderlist::new(SortDirection::Ascending, SortDirection::Ascending);
+        let user_account = Pubkey::new_unique();
+        let order_1 = Order {
+            order_id: 1,
+            slot: 1,
+            ..Order::default()
+        };
+        let order_2 = Order {
+            order_id: 2,
+            slot: 2,
+            ..Order::default()
+        };
+
+        orderlist.insert_bid(create_node(NodeType::TakingLimit, order_1, user_account));
+        orderlist.insert_bid(create_node(NodeType::TakingLimit, order_2, user_account));
+
+        let removed = orderlist.remove(&get_order_signature(1, user_account));
+        assert_eq!(removed.unwrap().get_order().order_id, 1);
+        assert!(orderlist
+            .remove(&get_order_signature(1, user_account))
+            .is_none());
+        assert_eq!(orderlist.size(), 1);
+        assert_eq!(orderlist.get_best_bid().unwrap().get_order().order_id, 2);
+    }
 }
@@ -1,12 +1,10 @@
 use std::sync::Arc;
 
-use tokio::time::Duration;
+use tokio::{sync::Mutex, time::Duration};
 
-use crate::{
-    drift_client::DriftClient, slot_subscriber::SlotSubscriber, usermap::UserMap, AccountProvider,
-};
+use crate::{drift_client::DriftClient, slot_subscriber::SlotSubscriber, AccountProvider};
 
-use super::dlob::DLOB;
+use super::{dlob::DLOB, dlob_builder::DLOBBuilder, dlob_server::DlobServerClient};
 
 pub struct DLOBSubscriptionConfig<T: AccountProvider + Clone> {
     pub drift_client: Arc<DriftClient<T>>,
@@ -22,13 +20,18 @@ pub(crate) trait DLOBSubscriberEvents {
 
 #[derive(Clone)]
 pub enum DlobSource {
-    UserMap(UserMap),
+    /// DLOB maintained incrementally by a running `DLOBBuilder`
+    DLOBBuilder(Arc<Mutex<DLOBBuilder>>),
+    /// L2/L3 books are read from the DLOB server instead of building a local DLOB
+    DlobServer(DlobServerClient),
 }
 
 impl DlobSource {
-    pub fn get_dlob(&self, slot: u64) -> DLOB {
+    /// Return None if the source doesn't build a local DLOB
+    pub async fn get_dlob(&self) -> Option<DLOB> {
         match self {
-            DlobSource::UserMap(usermap) => usermap.get_dlob(slot),
+            DlobSource::DLOBBuilder(builder) => Some(builder.lock().await.get_dlob()),
+            DlobSource::DlobServer(_) => None,
         }
     }
 }
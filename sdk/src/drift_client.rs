@@ -1,9 +1,10 @@
-use std::{borrow::Cow, collections::HashMap, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, sync::Arc, time::Instant};
 
 use anchor_lang::{AccountDeserialize, Discriminator};
 use drift::{
     math::constants::QUOTE_SPOT_MARKET_INDEX,
     state::{
+        insurance_fund_stake::InsuranceFundStake,
         oracle::{get_oracle_price, OracleSource},
         perp_market::PerpMarket,
         spot_market::SpotMarket,
@@ -11,12 +12,14 @@ use drift::{
         user::{MarketType, Order, OrderStatus, PerpPosition, SpotPosition, User, UserStats},
     },
 };
-use futures_util::TryFutureExt;
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::{
     client_error::ClientErrorKind,
     nonblocking::rpc_client::RpcClient,
-    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig},
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+        RpcSimulateTransactionConfig,
+    },
     rpc_filter::{Memcmp, RpcFilterType},
     rpc_request::{RpcError, RpcResponseErrorData},
 };
@@ -28,6 +31,7 @@ use solana_sdk::{
     message::VersionedMessage,
     pubkey::Pubkey,
     signature::Signature,
+    transaction::VersionedTransaction,
 };
 use tokio::sync::RwLock;
 
@@ -40,10 +44,13 @@ use crate::{
     drift_client_config::ClientOpts,
     error::SdkError,
     event_emitter::EventEmitter,
+    jupiter::{JupiterClient, JupiterSwapParams, QuoteResponse},
     marketmap::MarketMap,
+    math::insurance::calculate_staker_value,
     oraclemap::{Oracle, OracleMap},
-    transaction_builder::TransactionBuilder,
-    types::{Context, DataAndSlot, MarketId, SdkResult, TxParams},
+    transaction_builder::{TransactionBuilder, MAX_COMPUTE_UNITS},
+    tx::tx_sender::TxSender,
+    types::{Context, DataAndSlot, MarketId, SdkResult, SimulatedTx, TxParams},
     user::DriftUser,
     user_config::UserSubscriptionConfig,
     utils::{self, decode, get_ws_url},
@@ -95,7 +102,8 @@ where
     ) -> SdkResult<Self> {
         Ok(Self {
             backend: Box::leak(Box::new(
-                DriftClientBackend::new(context, account_provider).await?,
+                DriftClientBackend::new(context, account_provider, opts.account_subscription())
+                    .await?,
             )),
             wallet: wallet.clone(),
             active_sub_account_id: opts.active_sub_account_id(),
@@ -578,6 +586,117 @@ where
             .map_err(|err| err.to_out_of_sol_error().unwrap_or(err))
     }
 
+    /// Sign and send a tx to the network using `tx_sender`, e.g. to retry until confirmed
+    ///
+    /// Returns the signature on success
+    pub async fn sign_and_send_with_sender(
+        &self,
+        tx_sender: &dyn TxSender,
+        tx: VersionedMessage,
+        additional_signers: bool,
+    ) -> SdkResult<Signature> {
+        let rpc_client = &self.backend.rpc_client;
+        let (recent_block_hash, last_valid_block_height) = rpc_client
+            .get_latest_blockhash_with_commitment(rpc_client.commitment())
+            .await?;
+        let tx = self
+            .wallet()
+            .sign_tx(tx, recent_block_hash, additional_signers)?;
+        tx_sender
+            .send_tx(tx, last_valid_block_height)
+            .await
+            .map_err(|err| err.to_out_of_sol_error().unwrap_or(err))
+    }
+
+    /// Simulate `tx` against the latest blockhash, signatures are not verified
+    ///
+    /// A tx that fails in simulation is not an `Err`, see `SimulatedTx::err`
+    pub async fn simulate_tx(&self, tx: VersionedMessage) -> SdkResult<SimulatedTx> {
+        let rpc_client = &self.backend.rpc_client;
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default(); tx.header().num_required_signatures as usize],
+            message: tx,
+        };
+
+        let start = Instant::now();
+        let result = rpc_client
+            .simulate_transaction_with_config(
+                &tx,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    commitment: Some(rpc_client.commitment()),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .value;
+
+        Ok(SimulatedTx {
+            compute_units: result.units_consumed.ok_or_else(|| {
+                SdkError::Generic("simulation did not return units consumed".to_string())
+            })?,
+            logs: result.logs.unwrap_or_default(),
+            err: result.err,
+            duration: start.elapsed(),
+        })
+    }
+
+    /// Build the tx of `builder` applying its `TxParams`
+    ///
+    /// With `use_simulated_compute_units` set the tx is simulated and its compute unit limit set to
+    /// the consumed units times `compute_units_buffer_multipler`. `get_cu_price_from_compute_units`
+    /// then derives the compute unit price from the simulated units if
+    /// `use_simulated_compute_units_for_cu_price_calculation` is set.
+    ///
+    /// Returns the message and the simulation result, if it was simulated
+    pub async fn build_tx(
+        &self,
+        mut builder: TransactionBuilder<'_>,
+    ) -> SdkResult<(VersionedMessage, Option<SimulatedTx>)> {
+        if !builder
+            .tx_params
+            .processing
+            .use_simulated_compute_units
+            .unwrap_or(false)
+        {
+            return Ok((builder.build(), None));
+        }
+
+        // simulate with the max limit so the simulation can't run out of CUs
+        let requested_units = builder.tx_params.base.compute_units;
+        builder.tx_params.base.compute_units = Some(MAX_COMPUTE_UNITS);
+        builder.apply_tx_params();
+        let simulation = self.simulate_tx(builder.compile()).await?;
+
+        let TxParams { base, processing } = &mut builder.tx_params;
+        let simulated_price = processing
+            .use_simulated_compute_units_for_cu_price_calculation
+            .unwrap_or(false);
+        let price_units = if simulated_price {
+            Some(simulation.compute_units)
+        } else {
+            requested_units.map(u64::from)
+        };
+        if simulated_price || base.compute_units_price.is_none() {
+            if let Some(price) = processing
+                .get_cu_price_from_compute_units
+                .zip(price_units)
+                .map(|(cu_price, units)| cu_price(units))
+            {
+                base.compute_units_price = Some(price.min(u32::MAX as u64) as u32);
+            }
+        }
+        // a tx failing before consuming CUs keeps the max limit
+        if simulation.compute_units > 0 {
+            let buffered = simulation.compute_units as f64
+                * processing.compute_units_buffer_multipler.unwrap_or(1.0);
+            base.compute_units = Some((buffered as u32).min(MAX_COMPUTE_UNITS));
+        }
+
+        Ok((builder.build(), Some(simulation)))
+    }
+
     /// Get live info of a spot market
     pub async fn get_spot_market_info(&self, market_index: u16) -> SdkResult<SpotMarket> {
         let market = derive_spot_market_account(market_index);
@@ -590,6 +709,42 @@ where
         self.backend.get_account(&market).await
     }
 
+    /// Get the insurance fund stake account of `authority` in a spot market
+    pub async fn get_insurance_fund_stake(
+        &self,
+        authority: &Pubkey,
+        market_index: u16,
+    ) -> SdkResult<InsuranceFundStake> {
+        let stake = constants::derive_insurance_fund_stake(authority, market_index);
+        self.backend.get_account(&stake).await
+    }
+
+    /// Get the token balance of a spot market's insurance fund vault
+    pub async fn get_insurance_fund_vault_balance(&self, market_index: u16) -> SdkResult<u64> {
+        let vault = constants::derive_insurance_fund_vault(market_index);
+        let balance = self
+            .backend
+            .rpc_client
+            .get_token_account_balance(&vault)
+            .await?;
+        balance.amount.parse().map_err(|_| SdkError::Deserializing)
+    }
+
+    /// Get the token amount the insurance fund stake of `authority` in a spot market is worth
+    pub async fn get_insurance_fund_stake_value(
+        &self,
+        authority: &Pubkey,
+        market_index: u16,
+    ) -> SdkResult<u64> {
+        let stake = self
+            .get_insurance_fund_stake(authority, market_index)
+            .await?;
+        let spot_market = self.get_spot_market_info(market_index).await?;
+        let vault_balance = self.get_insurance_fund_vault_balance(market_index).await?;
+
+        calculate_staker_value(&stake, &spot_market, vault_balance)
+    }
+
     /// Lookup a market by symbol
     ///
     /// This operation is not free so lookups should be reused/cached by the caller
@@ -649,6 +804,105 @@ where
         }
     }
 
+    /// Initialize a transaction for a sub-account of the wallet that may not exist yet
+    ///
+    /// ```ignore
+    /// let tx = client
+    ///     .init_new_account_tx(0)
+    ///     .initialize_user_stats()
+    ///     .initialize_user(0, "Main Account", None)
+    ///     .build();
+    /// ```
+    /// Returns a `TransactionBuilder` for composing the tx
+    pub fn init_new_account_tx(&self, sub_account_id: u16) -> TransactionBuilder {
+        let account_data = User {
+            authority: *self.wallet.authority(),
+            sub_account_id,
+            ..Default::default()
+        };
+        TransactionBuilder::new(
+            self.program_data(),
+            self.wallet.sub_account(sub_account_id),
+            Cow::Owned(account_data),
+            false,
+        )
+    }
+
+    /// Swap between spot market balances of the active sub-account through a Jupiter route
+    ///
+    /// Jupiter's swap ix is wrapped between drift's `begin_swap` and `end_swap` so the tokens move
+    /// through the wallet's associated token accounts, which must exist. With `SwapMode::ExactOut`
+    /// the slippage adjusted maximum input is withdrawn and the unused input deposited back.
+    ///
+    /// Returns the v0 message using Jupiter's and drift's lookup tables, with the route's quote
+    pub async fn jupiter_swap(
+        &self,
+        jupiter_client: &JupiterClient<'_>,
+        params: JupiterSwapParams,
+    ) -> SdkResult<(VersionedMessage, QuoteResponse)> {
+        let JupiterSwapParams {
+            in_market_index,
+            out_market_index,
+            amount,
+            slippage_bps,
+            swap_mode,
+            reduce_only,
+            quote,
+            tx_params,
+        } = params;
+        let spot_market = |market_index: u16| {
+            self.program_data()
+                .spot_market_config_by_index(market_index)
+                .ok_or_else(|| SdkError::Generic(format!("unknown spot market {market_index}")))
+        };
+        let in_mint = spot_market(in_market_index)?.mint;
+        let out_mint = spot_market(out_market_index)?.mint;
+
+        let quote = match quote {
+            Some(quote) => quote,
+            None => {
+                jupiter_client
+                    .get_quote(
+                        in_mint,
+                        out_mint,
+                        amount,
+                        None,
+                        slippage_bps,
+                        Some(swap_mode),
+                        None,
+                        None,
+                    )
+                    .await?
+            }
+        };
+        let authority = *self.wallet.authority();
+        let swap_instructions = jupiter_client
+            .get_swap_instructions(quote.clone(), authority)
+            .await?;
+        let lookup_tables = jupiter_client
+            .get_lookup_tables(&swap_instructions.address_lookup_table_addresses)
+            .await?;
+
+        // Jupiter's compute budget, setup and cleanup ixs are dropped, drift moves the tokens
+        let builder = self
+            .init_tx(&self.wallet.sub_account(self.active_sub_account_id), false)?
+            .lookup_tables(&lookup_tables)
+            .swap(
+                in_market_index,
+                out_market_index,
+                quote.max_in_amount(),
+                constants::derive_associated_token_account(&authority, &in_mint),
+                constants::derive_associated_token_account(&authority, &out_mint),
+                vec![swap_instructions.swap_instruction],
+                None,
+                reduce_only,
+            )
+            .tx_params(tx_params);
+        let (message, _) = self.build_tx(builder).await?;
+
+        Ok((message, quote))
+    }
+
     pub async fn get_recent_priority_fees(
         &self,
         writable_markets: &[MarketId],
@@ -725,30 +979,33 @@ where
             .get_oracle_price_data_and_slot_for_spot_market(market_index)
     }
 
+    /// Trigger `order` of `user_account`, sending the tx with `tx_sender`
     pub async fn trigger_order(
         &self,
+        tx_sender: &dyn TxSender,
         user_account_pubkey: &Pubkey,
         user_account: User,
         order: &Order,
-        _trigger_order: Option<TxParams>,
+        tx_params: Option<TxParams>,
         filler_pubkey: Option<&Pubkey>,
     ) -> SdkResult<Signature> {
         let ix = self
             .get_trigger_order_ix(user_account_pubkey, user_account, order, filler_pubkey)
             .await?;
 
-        let msg = TransactionBuilder::new(
+        let mut builder = TransactionBuilder::new(
             self.program_data(),
             self.wallet.default_sub_account(),
             Cow::Owned(user_account),
             false,
         )
-        .extend_ix(vec![ix])
-        .build();
-
-        let sig = self.sign_and_send(msg, true).await?;
+        .extend_ix(vec![ix]);
+        if let Some(tx_params) = tx_params {
+            builder = builder.tx_params(tx_params);
+        }
+        let (msg, _) = self.build_tx(builder).await?;
 
-        Ok(sig)
+        self.sign_and_send_with_sender(tx_sender, msg, false).await
     }
 
     pub async fn get_trigger_order_ix(
@@ -870,7 +1127,13 @@ pub struct DriftClientBackend<T: AccountProvider> {
 
 impl<T: AccountProvider> DriftClientBackend<T> {
     /// Initialize a new `DriftClientBackend`
-    async fn new(context: Context, account_provider: T) -> SdkResult<Self> {
+    ///
+    /// `account_subscription` how oracles are subscribed, websocket if unset or one-shot
+    async fn new(
+        context: Context,
+        account_provider: T,
+        account_subscription: Option<UserSubscriptionConfig>,
+    ) -> SdkResult<Self> {
         let rpc_client = RpcClient::new_with_commitment(
             account_provider.endpoint(),
             account_provider.commitment_config(),
@@ -890,25 +1153,30 @@ impl<T: AccountProvider> DriftClientBackend<T> {
         let lookup_table_address = market_lookup_table(context);
 
         let (_, _, lut, state) = tokio::try_join!(
-            perp_market_map.sync(),
-            spot_market_map.sync(),
-            rpc_client
-                .get_account(&lookup_table_address)
-                .map_err(Into::into),
-            rpc_client.get_account(state_account()).map_err(Into::into),
+            perp_market_map.sync_with(&account_provider),
+            spot_market_map.sync_with(&account_provider),
+            account_provider.get_account(lookup_table_address),
+            account_provider.get_account(*state_account()),
         )?;
         let lookup_table = utils::deserialize_alt(lookup_table_address, &lut)?;
 
         let perp_oracles = perp_market_map.oracles();
         let spot_oracles = spot_market_map.oracles();
 
-        let oracle_map = OracleMap::new(
+        let mut oracle_map = OracleMap::new(
             account_provider.commitment_config(),
             account_provider.endpoint(),
             true,
             &perp_oracles,
             &spot_oracles,
         );
+        match account_subscription {
+            // one-shot oracles would go stale, they stay on websocket
+            Some(UserSubscriptionConfig::OneShot) | None => {}
+            Some(account_subscription) => {
+                oracle_map = oracle_map.with_subscription(account_subscription);
+            }
+        }
 
         let blockhash_subscriber = Arc::new(RwLock::new(BlockhashSubscriber::new(
             2,
@@ -1111,22 +1379,19 @@ impl<T: AccountProvider> DriftClientBackend<T> {
     async fn get_program_accounts<U: AccountDeserialize + Discriminator>(
         &self,
     ) -> SdkResult<Vec<U>> {
-        let accounts = self
-            .rpc_client
-            .get_program_accounts_with_config(
-                &constants::PROGRAM_ID,
-                RpcProgramAccountsConfig {
-                    filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                        0,
-                        U::DISCRIMINATOR.to_vec(),
-                    ))]),
-                    account_config: RpcAccountInfoConfig {
-                        encoding: Some(UiAccountEncoding::Base64Zstd),
-                        ..Default::default()
-                    },
+        let (_, accounts) = self
+            .account_provider
+            .get_program_accounts(RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    0,
+                    U::DISCRIMINATOR.to_vec(),
+                ))]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64Zstd),
                     ..Default::default()
                 },
-            )
+                ..Default::default()
+            })
             .await?;
 
         accounts
@@ -1251,3 +1516,241 @@ impl<T: AccountProvider> DriftClientBackend<T> {
         Ok(price_data.price)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::InstructionData;
+
+    use futures_util::{future::BoxFuture, FutureExt};
+    use serde_json::{json, Value};
+    use solana_sdk::signature::Keypair;
+
+    use super::*;
+    use crate::{
+        fixture_account_provider::{drift_account, drift_program_fixtures},
+        http_stub,
+        jupiter::{
+            tests::{
+                mock_jupiter_api, JUPITER_LOOKUP_TABLES, JUPITER_PROGRAM, NATIVE_MINT, TEST_WALLET,
+                USDC_MINT,
+            },
+            SwapMode,
+        },
+        utils::zero_account_to_bytes,
+    };
+
+    #[tokio::test]
+    async fn loads_state_and_markets_with_account_provider() {
+        let perp_markets = [0, 1].map(|market_index| PerpMarket {
+            market_index,
+            ..Default::default()
+        });
+        let spot_markets = [SpotMarket::default()];
+        let provider = drift_program_fixtures(Context::MainNet, &perp_markets, &spot_markets).await;
+
+        let client = DriftClient::new(
+            Context::MainNet,
+            provider,
+            &Wallet::read_only(Pubkey::new_unique()),
+        )
+        .await
+        .unwrap();
+
+        let state = client.get_state_account();
+        assert_eq!(state.read().unwrap().number_of_markets, 2);
+        assert_eq!(state.read().unwrap().number_of_spot_markets, 1);
+        assert_eq!(client.get_perp_market_account(1).unwrap().market_index, 1);
+        assert_eq!(client.get_spot_market_accounts().len(), 1);
+        assert_eq!(
+            client.fetch_market_lookup_table_account().addresses,
+            vec![
+                derive_perp_market_account(0),
+                derive_perp_market_account(1),
+                derive_spot_market_account(0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn jupiter_swap_wraps_route_in_begin_and_end_swap() {
+        let spot_markets =
+            [(0, USDC_MINT), (1, NATIVE_MINT)].map(|(market_index, mint)| SpotMarket {
+                market_index,
+                mint,
+                ..Default::default()
+            });
+        let provider =
+            drift_program_fixtures(Context::MainNet, &[PerpMarket::default()], &spot_markets).await;
+        let wallet = Wallet::read_only(TEST_WALLET);
+        let user = User {
+            authority: TEST_WALLET,
+            ..Default::default()
+        };
+        provider
+            .insert(
+                wallet.sub_account(0),
+                drift_account(zero_account_to_bytes(user)),
+            )
+            .await;
+        let mut client = DriftClient::new(Context::MainNet, provider, &wallet)
+            .await
+            .unwrap();
+        let user = DriftUser::new(wallet.sub_account(0), &client, Some(0))
+            .await
+            .unwrap();
+        client.users.push(user);
+
+        let (url, _) = mock_jupiter_api().await;
+        let rpc_client = RpcClient::new(url.clone());
+        let jupiter_client = JupiterClient::new(&rpc_client, Some(url));
+        let (message, quote) = client
+            .jupiter_swap(
+                &jupiter_client,
+                JupiterSwapParams {
+                    in_market_index: 0,
+                    out_market_index: 1,
+                    amount: 10_000_000,
+                    slippage_bps: 50,
+                    swap_mode: SwapMode::ExactOut,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // exact out withdraws the slippage adjusted maximum input
+        assert_eq!(quote.max_in_amount(), 1_459_572);
+
+        // Jupiter's compute budget, setup and cleanup ixs are dropped
+        let VersionedMessage::V0(message) = message else {
+            panic!("expected a v0 message");
+        };
+        let program_ids: Vec<Pubkey> = message
+            .instructions
+            .iter()
+            .map(|ix| message.account_keys[ix.program_id_index as usize])
+            .collect();
+        assert_eq!(program_ids, vec![drift::ID, JUPITER_PROGRAM, drift::ID]);
+        assert_eq!(
+            message.instructions[0].data,
+            drift::instruction::BeginSwap {
+                in_market_index: 0,
+                out_market_index: 1,
+                amount_in: 1_459_572,
+            }
+            .data()
+        );
+        assert_eq!(
+            message.instructions[2].data,
+            drift::instruction::EndSwap {
+                in_market_index: 0,
+                out_market_index: 1,
+                limit_price: None,
+                reduce_only: None,
+            }
+            .data()
+        );
+
+        // Jupiter's lookup tables are merged with drift's
+        let lookup_tables: Vec<Pubkey> = message
+            .address_table_lookups
+            .iter()
+            .map(|lookup| lookup.account_key)
+            .collect();
+        for table in JUPITER_LOOKUP_TABLES
+            .iter()
+            .chain([market_lookup_table(Context::MainNet)].iter())
+        {
+            assert!(lookup_tables.contains(table), "{table} not used");
+        }
+    }
+
+    /// Records the txs it sends instead of sending them
+    #[derive(Default)]
+    struct RecordingTxSender {
+        sent: std::sync::Mutex<Vec<VersionedTransaction>>,
+    }
+
+    impl TxSender for RecordingTxSender {
+        fn send_tx(
+            &self,
+            tx: VersionedTransaction,
+            _last_valid_block_height: u64,
+        ) -> BoxFuture<SdkResult<Signature>> {
+            let signature = tx.signatures[0];
+            self.sent.lock().unwrap().push(tx);
+            async move { Ok(signature) }.boxed()
+        }
+    }
+
+    #[tokio::test]
+    async fn trigger_order_sends_with_tx_sender() {
+        let blockhash = Hash::new_unique();
+        let url = http_stub::serve_json_rpc(move |method, _| match method {
+            "getLatestBlockhash" => json!({
+                "context": { "slot": 1 },
+                "value": { "blockhash": blockhash.to_string(), "lastValidBlockHeight": 100 },
+            }),
+            _ => Value::Null,
+        })
+        .await;
+        let provider = drift_program_fixtures(
+            Context::MainNet,
+            &[PerpMarket::default()],
+            &[SpotMarket::default()],
+        )
+        .await
+        .with_endpoint(&url);
+        let wallet = Wallet::from(Keypair::new());
+        let filler = User {
+            authority: *wallet.authority(),
+            ..Default::default()
+        };
+        provider
+            .insert(
+                wallet.sub_account(0),
+                drift_account(zero_account_to_bytes(filler)),
+            )
+            .await;
+        let mut client = DriftClient::new(Context::MainNet, provider, &wallet)
+            .await
+            .unwrap();
+        let filler = DriftUser::new(wallet.sub_account(0), &client, Some(0))
+            .await
+            .unwrap();
+        client.users.push(filler);
+
+        let order = Order {
+            order_id: 7,
+            market_type: MarketType::Perp,
+            market_index: 0,
+            ..Default::default()
+        };
+        let tx_sender = RecordingTxSender::default();
+        let signature = client
+            .trigger_order(
+                &tx_sender,
+                &Pubkey::new_unique(),
+                User::default(),
+                &order,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let sent = tx_sender.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].signatures[0], signature);
+        assert_eq!(*sent[0].message.recent_blockhash(), blockhash);
+        let trigger_ix = sent[0].message.instructions().last().expect("trigger ix");
+        assert_eq!(
+            sent[0].message.static_account_keys()[trigger_ix.program_id_index as usize],
+            drift::ID
+        );
+        assert_eq!(
+            trigger_ix.data,
+            drift::instruction::TriggerOrder { order_id: 7 }.data()
+        );
+    }
+}
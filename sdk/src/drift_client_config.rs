@@ -1,6 +1,8 @@
 use solana_sdk::commitment_config::CommitmentLevel;
 
-use crate::{accounts::BulkAccountLoader, user_config::UserSubscriptionConfig};
+use crate::{
+    accounts::BulkAccountLoader, grpc::GrpcConnectionOpts, user_config::UserSubscriptionConfig,
+};
 
 #[derive(Clone)]
 pub struct ClientOpts {
@@ -54,11 +56,19 @@ impl ClientOpts {
                     log_resub_messages: *log_resub_messages,
                     commitment: *commitment,
                 }),
+                DriftClientSubscriptionConfig::Grpc {
+                    connection,
+                    commitment,
+                } => Some(UserSubscriptionConfig::Grpc {
+                    connection: connection.clone(),
+                    commitment: *commitment,
+                }),
                 DriftClientSubscriptionConfig::Polling { account_loader } => {
                     Some(UserSubscriptionConfig::Polling {
                         account_loader: account_loader.clone(),
                     })
                 }
+                DriftClientSubscriptionConfig::OneShot => Some(UserSubscriptionConfig::OneShot),
             },
             None => None,
         }
@@ -72,7 +82,13 @@ pub enum DriftClientSubscriptionConfig {
         log_resub_messages: bool,
         commitment: CommitmentLevel,
     },
+    Grpc {
+        connection: GrpcConnectionOpts,
+        commitment: CommitmentLevel,
+    },
     Polling {
         account_loader: BulkAccountLoader,
     },
+    /// Fetch accounts once on subscribe
+    OneShot,
 }
@@ -26,6 +26,12 @@ impl From<SinkError> for SdkError {
     }
 }
 
+impl From<tonic::Status> for SdkError {
+    fn from(status: tonic::Status) -> Self {
+        Self::Grpc(Box::new(status))
+    }
+}
+
 impl From<drift::error::ErrorCode> for SdkError {
     fn from(value: drift::error::ErrorCode) -> Self {
         Self::DriftProgramError(value)
@@ -120,6 +126,21 @@ pub enum SdkError {
 
     #[error("Market not fund: {0}")]
     MarketNotFound(u16),
+
+    #[error("gRPC error: {0}")]
+    Grpc(Box<tonic::Status>),
+
+    #[error("gRPC connection failed: {0}")]
+    GrpcTransport(#[from] tonic::transport::Error),
+
+    #[error("no account fixture for {0}")]
+    FixtureNotFound(solana_sdk::pubkey::Pubkey),
+
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("tx {0} was not confirmed in time")]
+    TxNotConfirmed(solana_sdk::signature::Signature),
 }
 
 impl SdkError {
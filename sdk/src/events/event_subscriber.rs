@@ -0,0 +1,647 @@
+use std::{
+    any::Any,
+    collections::{HashSet, VecDeque},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::Engine;
+use drift::state::events::{
+    CurveRecord, DepositRecord, FundingPaymentRecord, FundingRateRecord, InsuranceFundRecord,
+    InsuranceFundStakeRecord, LPRecord, LiquidationRecord, NewUserRecord, OrderActionRecord,
+    OrderRecord, SettlePnlRecord, SpotInterestRecord, SwapRecord,
+};
+use futures_util::{Stream, StreamExt};
+use log::{debug, error, warn};
+use serde_json::{json, Value};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    rpc_request::RpcRequest,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+
+use crate::{
+    constants,
+    error::SdkError,
+    event_emitter::{self, EventEmitter},
+    events::types::{Event, EventMap},
+    types::SdkResult,
+    utils::get_ws_url,
+};
+
+const PROGRAM_LOG: &str = "Program log: ";
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// How the subscriber receives program logs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogProviderType {
+    /// `logsSubscribe`, falls back to polling if the websocket fails
+    Websocket,
+    /// `getSignaturesForAddress` + `getTransaction`
+    Polling,
+}
+
+#[derive(Clone, Debug)]
+pub struct EventSubscriberConfig {
+    pub commitment: CommitmentConfig,
+    /// number of tx signatures to remember for deduping
+    pub max_tx: usize,
+    pub log_provider: LogProviderType,
+    /// polling interval, also used by the websocket fallback
+    pub polling_frequency: Duration,
+    /// max signatures to fetch per poll
+    pub polling_batch_size: usize,
+}
+
+impl Default for EventSubscriberConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            max_tx: 4096,
+            log_provider: LogProviderType::Websocket,
+            polling_frequency: Duration::from_secs(1),
+            polling_batch_size: 100,
+        }
+    }
+}
+
+impl event_emitter::Event for EventMap {
+    fn box_clone(&self) -> Box<dyn event_emitter::Event> {
+        Box::new((*self).clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Bounded set of tx signatures which have already been processed
+struct SignatureCache {
+    capacity: usize,
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+impl SignatureCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Return false if `sig` was already seen
+    fn insert(&mut self, sig: &str) -> bool {
+        if self.seen.contains(sig) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(sig.to_string());
+        self.seen.insert(sig.to_string());
+        true
+    }
+}
+
+/// Shared state handed to the log provider tasks
+#[derive(Clone)]
+struct EventContext {
+    event_emitter: EventEmitter,
+    sender: broadcast::Sender<EventMap>,
+    seen: Arc<Mutex<SignatureCache>>,
+}
+
+impl EventContext {
+    fn handle_tx_logs(&self, tx_sig: &str, slot: u64, logs: &[String]) {
+        if !self.seen.lock().unwrap().insert(tx_sig) {
+            return;
+        }
+
+        let Ok(signature) = Signature::from_str(tx_sig) else {
+            warn!("invalid tx signature: {tx_sig}");
+            return;
+        };
+
+        for event in parse_logs(&constants::PROGRAM_ID, signature, slot, logs) {
+            self.event_emitter
+                .emit(EventSubscriber::SUBSCRIPTION_ID, Box::new(event.clone()));
+            // no receivers is fine
+            let _ = self.sender.send(event);
+        }
+    }
+}
+
+/// Decodes Drift program logs into `EventMap`s
+///
+/// To receive events, subscribe to the event_emitter's "event" event type or consume `stream()`
+pub struct EventSubscriber {
+    rpc: Arc<RpcClient>,
+    url: String,
+    config: EventSubscriberConfig,
+    pub event_emitter: EventEmitter,
+    sender: broadcast::Sender<EventMap>,
+    seen: Arc<Mutex<SignatureCache>>,
+    subscribed: bool,
+    unsubscriber: Option<mpsc::Sender<()>>,
+}
+
+impl EventSubscriber {
+    pub const SUBSCRIPTION_ID: &'static str = "event";
+
+    pub fn new(endpoint: &str, config: EventSubscriberConfig) -> Self {
+        let (sender, _) = broadcast::channel(config.max_tx.max(1));
+        Self {
+            rpc: Arc::new(RpcClient::new_with_commitment(
+                endpoint.to_string(),
+                config.commitment,
+            )),
+            url: get_ws_url(endpoint).unwrap(),
+            seen: Arc::new(Mutex::new(SignatureCache::new(config.max_tx))),
+            config,
+            event_emitter: EventEmitter::new(),
+            sender,
+            subscribed: false,
+            unsubscriber: None,
+        }
+    }
+
+    pub async fn subscribe(&mut self) -> SdkResult<()> {
+        if self.subscribed {
+            return Ok(());
+        }
+
+        let (unsub_tx, mut unsub_rx) = mpsc::channel::<()>(1);
+        self.unsubscriber = Some(unsub_tx);
+
+        let ctx = EventContext {
+            event_emitter: self.event_emitter.clone(),
+            sender: self.sender.clone(),
+            seen: self.seen.clone(),
+        };
+        let rpc = self.rpc.clone();
+        let url = self.url.clone();
+        let config = self.config.clone();
+
+        tokio::spawn(async move {
+            if config.log_provider == LogProviderType::Websocket {
+                match subscribe_logs_ws(&url, config.commitment, &ctx, &mut unsub_rx).await {
+                    Ok(true) => return,
+                    Ok(false) => warn!("log stream ended, falling back to polling"),
+                    Err(e) => warn!("logsSubscribe failed: {e}, falling back to polling"),
+                }
+            }
+            poll_logs(rpc, &config, &ctx, &mut unsub_rx).await;
+        });
+
+        self.subscribed = true;
+        Ok(())
+    }
+
+    pub async fn unsubscribe(&mut self) -> SdkResult<()> {
+        if self.subscribed && self.unsubscriber.is_some() {
+            if let Err(e) = self.unsubscriber.as_ref().unwrap().send(()).await {
+                error!("Failed to send unsubscribe signal: {:?}", e);
+                return Err(SdkError::CouldntUnsubscribe(e));
+            }
+            self.subscribed = false;
+        }
+        Ok(())
+    }
+
+    /// Stream of decoded events, lagging receivers skip the events they missed
+    pub fn stream(&self) -> impl Stream<Item = EventMap> {
+        futures_util::stream::unfold(self.sender.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(RecvError::Lagged(n)) => warn!("event stream lagged by {n} events"),
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+/// Return Ok(true) if unsubscribed, Ok(false) if the stream ended
+async fn subscribe_logs_ws(
+    url: &str,
+    commitment: CommitmentConfig,
+    ctx: &EventContext,
+    unsub_rx: &mut mpsc::Receiver<()>,
+) -> SdkResult<bool> {
+    let pubsub = PubsubClient::new(url).await?;
+    let (mut logs, unsubscriber) = pubsub
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![constants::PROGRAM_ID.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(commitment),
+            },
+        )
+        .await?;
+
+    loop {
+        tokio::select! {
+            message = logs.next() => {
+                match message {
+                    Some(message) => {
+                        if message.value.err.is_some() {
+                            continue;
+                        }
+                        ctx.handle_tx_logs(&message.value.signature, message.context.slot, &message.value.logs);
+                    }
+                    None => {
+                        unsubscriber().await;
+                        return Ok(false);
+                    }
+                }
+            }
+            _ = unsub_rx.recv() => {
+                debug!("Unsubscribing.");
+                unsubscriber().await;
+                return Ok(true);
+            }
+        }
+    }
+}
+
+async fn poll_logs(
+    rpc: Arc<RpcClient>,
+    config: &EventSubscriberConfig,
+    ctx: &EventContext,
+    unsub_rx: &mut mpsc::Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(config.polling_frequency);
+    // None until the newest signature is known, only txs after it are emitted
+    let mut last_signature: Option<Option<Signature>> = None;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let Some(until) = last_signature else {
+                    match newest_signature(&rpc, config).await {
+                        Ok(newest) => last_signature = Some(newest),
+                        Err(e) => warn!("failed to get newest signature: {e}"),
+                    }
+                    continue;
+                };
+                match fetch_new_logs(&rpc, config, until).await {
+                    Ok(Some((newest, tx_logs))) => {
+                        for (sig, slot, logs) in tx_logs {
+                            ctx.handle_tx_logs(&sig, slot, &logs);
+                        }
+                        last_signature = Some(Some(newest));
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("failed to poll logs: {e}"),
+                }
+            }
+            _ = unsub_rx.recv() => {
+                debug!("Unsubscribing.");
+                return;
+            }
+        }
+    }
+}
+
+/// Return the signature of the program's newest tx, None if it has none
+async fn newest_signature(
+    rpc: &RpcClient,
+    config: &EventSubscriberConfig,
+) -> SdkResult<Option<Signature>> {
+    let signatures = rpc
+        .get_signatures_for_address_with_config(
+            &constants::PROGRAM_ID,
+            GetConfirmedSignaturesForAddress2Config {
+                before: None,
+                until: None,
+                limit: Some(1),
+                commitment: Some(config.commitment),
+            },
+        )
+        .await?;
+
+    signatures
+        .first()
+        .map(|status| parse_signature(&status.signature))
+        .transpose()
+}
+
+fn parse_signature(signature: &str) -> SdkResult<Signature> {
+    Signature::from_str(signature).map_err(|e| SdkError::Generic(format!("invalid signature: {e}")))
+}
+
+type TxLogs = (String, u64, Vec<String>);
+
+/// Return the newest signature and the logs of all txs after `until`, oldest first
+///
+/// Every tx since `until` is fetched however many landed, `until` None fetches the whole history
+async fn fetch_new_logs(
+    rpc: &RpcClient,
+    config: &EventSubscriberConfig,
+    until: Option<Signature>,
+) -> SdkResult<Option<(Signature, Vec<TxLogs>)>> {
+    // pages are newest first, page backwards until reaching `until`
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                &constants::PROGRAM_ID,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(config.polling_batch_size),
+                    commitment: Some(config.commitment),
+                },
+            )
+            .await?;
+        let last_page = page.len() < config.polling_batch_size;
+        before = page
+            .last()
+            .map(|status| parse_signature(&status.signature))
+            .transpose()?;
+        signatures.extend(page);
+        if last_page || before.is_none() {
+            break;
+        }
+    }
+
+    let Some(newest) = signatures.first() else {
+        return Ok(None);
+    };
+    let newest = parse_signature(&newest.signature)?;
+
+    let mut tx_logs = Vec::with_capacity(signatures.len());
+    for status in signatures.iter().rev() {
+        if status.err.is_some() {
+            continue;
+        }
+        let tx: Value = rpc
+            .send(
+                RpcRequest::GetTransaction,
+                json!([
+                    status.signature,
+                    {
+                        "encoding": "json",
+                        "commitment": config.commitment.commitment,
+                        "maxSupportedTransactionVersion": 0,
+                    }
+                ]),
+            )
+            .await?;
+        let Some(logs) = tx["meta"]["logMessages"].as_array() else {
+            continue;
+        };
+        let logs = logs
+            .iter()
+            .filter_map(|log| log.as_str().map(str::to_string))
+            .collect();
+        tx_logs.push((status.signature.clone(), status.slot, logs));
+    }
+
+    Ok(Some((newest, tx_logs)))
+}
+
+/// Parse the events emitted by `program_id` from a tx's logs
+///
+/// Only `Program data:` lines logged while `program_id` is the innermost invoked program are decoded
+pub fn parse_logs(
+    program_id: &Pubkey,
+    tx_sig: Signature,
+    slot: u64,
+    logs: &[String],
+) -> Vec<EventMap> {
+    let program_id = program_id.to_string();
+    let mut call_stack: Vec<&str> = vec![];
+    let mut events = vec![];
+
+    for log in logs {
+        if log.starts_with(PROGRAM_LOG) {
+            continue;
+        }
+
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+            if call_stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data) else {
+                continue;
+            };
+            if let Some(event) = decode_event(&bytes, tx_sig, slot, events.len() as u64) {
+                events.push(event);
+            }
+            continue;
+        }
+
+        let mut parts = log.split_whitespace();
+        if parts.next() != Some("Program") {
+            continue;
+        }
+        let (Some(program), Some(action)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        match action {
+            "invoke" => call_stack.push(program),
+            "success" => {
+                call_stack.pop();
+            }
+            _ if action.starts_with("failed") => {
+                call_stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
+
+/// Decode an anchor event (discriminator + borsh data) into an `EventMap`
+fn decode_event(data: &[u8], tx_sig: Signature, slot: u64, tx_sig_index: u64) -> Option<EventMap> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (discriminator, mut data) = data.split_at(8);
+
+    macro_rules! decode {
+        ($($record:ident),*) => {
+            $(
+                if discriminator == $record::DISCRIMINATOR {
+                    return $record::deserialize(&mut data).ok().map(|data| {
+                        EventMap::$record(Event {
+                            tx_sig,
+                            slot,
+                            tx_sig_index,
+                            data,
+                        })
+                    });
+                }
+            )*
+        };
+    }
+
+    decode!(
+        DepositRecord,
+        FundingPaymentRecord,
+        LiquidationRecord,
+        FundingRateRecord,
+        OrderRecord,
+        OrderActionRecord,
+        SettlePnlRecord,
+        NewUserRecord,
+        LPRecord,
+        InsuranceFundRecord,
+        SpotInterestRecord,
+        InsuranceFundStakeRecord,
+        CurveRecord,
+        SwapRecord
+    );
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::Event as _;
+
+    use super::*;
+    use crate::http_stub;
+
+    /// Serve `getSignaturesForAddress` and `getTransaction` of the program's txs, newest first
+    async fn mock_rpc(signatures: Arc<Mutex<Vec<Signature>>>) -> RpcClient {
+        let url = http_stub::serve_json_rpc(move |method, params| match method {
+            "getSignaturesForAddress" => {
+                let config = &params[1];
+                let signatures: Vec<String> = signatures
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                let start = config["before"]
+                    .as_str()
+                    .map(|before| signatures.iter().position(|s| s == before).unwrap() + 1)
+                    .unwrap_or(0);
+                let page: Vec<Value> = signatures[start..]
+                    .iter()
+                    .take_while(|s| config["until"].as_str() != Some(s.as_str()))
+                    .take(config["limit"].as_u64().unwrap_or(1_000) as usize)
+                    .map(|s| json!({ "signature": s, "slot": 1, "err": null, "memo": null, "blockTime": null }))
+                    .collect();
+                json!(page)
+            }
+            "getTransaction" => json!({
+                "slot": 1,
+                "meta": { "logMessages": [format!("Program log: {}", params[0].as_str().unwrap())] },
+            }),
+            method => panic!("unexpected method {method}"),
+        })
+        .await;
+
+        RpcClient::new(url)
+    }
+
+    #[tokio::test]
+    async fn test_poll_fetches_every_tx_since_last_poll() {
+        let config = EventSubscriberConfig {
+            polling_batch_size: 2,
+            ..Default::default()
+        };
+        let history = Signature::new_unique();
+        let signatures = Arc::new(Mutex::new(vec![history]));
+        let rpc = mock_rpc(Arc::clone(&signatures)).await;
+
+        // txs before the first poll aren't replayed
+        let until = newest_signature(&rpc, &config).await.unwrap();
+        assert_eq!(until, Some(history));
+        assert!(fetch_new_logs(&rpc, &config, until)
+            .await
+            .unwrap()
+            .is_none());
+
+        // more txs than a batch land between polls
+        let new: Vec<Signature> = (0..5).map(|_| Signature::new_unique()).collect();
+        signatures
+            .lock()
+            .unwrap()
+            .splice(0..0, new.iter().rev().copied());
+
+        let (newest, tx_logs) = fetch_new_logs(&rpc, &config, until).await.unwrap().unwrap();
+        assert_eq!(newest, new[4]);
+        assert_eq!(
+            tx_logs
+                .iter()
+                .map(|(sig, _, logs)| (sig.clone(), logs.clone()))
+                .collect::<Vec<_>>(),
+            new.iter()
+                .map(|sig| (sig.to_string(), vec![format!("Program log: {sig}")]))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_logs() {
+        let program_id = constants::PROGRAM_ID.to_string();
+        let other_program = Pubkey::new_unique().to_string();
+        let record = OrderRecord {
+            ts: 1,
+            user: Pubkey::new_unique(),
+            ..OrderRecord::default()
+        };
+        let data = format!(
+            "{PROGRAM_DATA}{}",
+            base64::engine::general_purpose::STANDARD.encode(record.data())
+        );
+
+        let logs = vec![
+            format!("Program {program_id} invoke [1]"),
+            format!("{PROGRAM_LOG}Instruction: PlaceAndTakePerpOrder"),
+            data.clone(),
+            format!("Program {other_program} invoke [2]"),
+            data.clone(),
+            format!("Program {other_program} success"),
+            data,
+            format!("Program {program_id} consumed 1000 of 200000 compute units"),
+            format!("Program {program_id} success"),
+        ];
+
+        let tx_sig = Signature::new_unique();
+        let events = parse_logs(&constants::PROGRAM_ID, tx_sig, 42, &logs);
+
+        assert_eq!(events.len(), 2);
+        for (i, event) in events.iter().enumerate() {
+            match event {
+                EventMap::OrderRecord(event) => {
+                    assert_eq!(event.tx_sig, tx_sig);
+                    assert_eq!(event.slot, 42);
+                    assert_eq!(event.tx_sig_index, i as u64);
+                    assert_eq!(event.data.user, record.user);
+                }
+                _ => panic!("unexpected event"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_signature_cache() {
+        let mut cache = SignatureCache::new(2);
+        assert!(cache.insert("a"));
+        assert!(!cache.insert("a"));
+        assert!(cache.insert("b"));
+        assert!(cache.insert("c"));
+        // "a" was evicted
+        assert!(cache.insert("a"));
+        assert!(!cache.insert("c"));
+    }
+}
@@ -1 +1,2 @@
+pub mod event_subscriber;
 pub mod types;
@@ -5,6 +5,7 @@ use drift::state::events::{
 };
 use solana_sdk::signature::Signature;
 
+#[derive(Clone)]
 pub struct Event<E> {
     pub tx_sig: Signature,
 
@@ -16,10 +17,13 @@ pub struct Event<E> {
     pub data: E,
 }
 
+#[derive(Clone)]
 pub struct WrappedEvent<E> {
     pub event: Event<E>,
     pub event_type: EventMap,
 }
+
+#[derive(Clone)]
 pub enum EventMap {
     DepositRecord(Event<DepositRecord>),
     FundingPaymentRecord(Event<FundingPaymentRecord>),
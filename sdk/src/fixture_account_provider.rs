@@ -0,0 +1,477 @@
+//! Account providers for offline tests
+//!
+//! `RecordingAccountProvider` writes every account it fetches to a fixture directory which
+//! `FixtureAccountProvider` serves from, without a network connection.
+//!
+//! Both serve `get_program_accounts` too, the fixture provider by filtering every drift owned
+//! fixture.
+//!
+//! Fixtures are stored one file per account, named by pubkey, as either:
+//! - `<pubkey>.json` keyed account JSON, the same format as `solana account <pubkey> --output json`
+//! - `<pubkey>.bin` bincode serialized `Account`
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use fnv::FnvHashMap;
+use futures_util::{future::BoxFuture, FutureExt};
+use log::warn;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{rpc_config::RpcProgramAccountsConfig, rpc_response::RpcKeyedAccount};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    clock::Slot,
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+};
+use tokio::sync::RwLock;
+
+use crate::{AccountProvider, RpcAccountProvider, SdkError, SdkResult};
+
+/// Endpoint reported by `FixtureAccountProvider` by default, a local test validator
+const DEFAULT_FIXTURE_ENDPOINT: &str = "http://127.0.0.1:8899";
+/// Slot fixtures are served at, they aren't recorded with one
+const FIXTURE_SLOT: Slot = 0;
+
+/// File format of account fixtures
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FixtureFormat {
+    /// keyed account JSON with base64 data
+    #[default]
+    Json,
+    /// bincode serialized `Account`
+    Bincode,
+}
+
+impl FixtureFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Bincode => "bin",
+        }
+    }
+}
+
+/// Account provider serving accounts from a directory of fixtures
+///
+/// Accounts are loaded from disk on first request and cached, fixtures in either format are
+/// served. Requesting an account without a fixture returns `SdkError::FixtureNotFound`.
+#[derive(Clone)]
+pub struct FixtureAccountProvider {
+    dir: PathBuf,
+    endpoint: String,
+    commitment: CommitmentConfig,
+    accounts: Arc<RwLock<FnvHashMap<Pubkey, Account>>>,
+}
+
+impl FixtureAccountProvider {
+    /// Serve accounts from fixtures in `dir`
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            endpoint: DEFAULT_FIXTURE_ENDPOINT.to_string(),
+            commitment: CommitmentConfig::confirmed(),
+            accounts: Default::default(),
+        }
+    }
+    /// Set the endpoint reported to consumers that connect on their own e.g. market maps
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+    /// Serve `account` at `pubkey`, overriding its fixture if any
+    pub async fn insert(&self, pubkey: Pubkey, account: Account) {
+        self.accounts.write().await.insert(pubkey, account);
+    }
+    async fn get_account_impl(&self, pubkey: Pubkey) -> SdkResult<Account> {
+        if let Some(account) = self.accounts.read().await.get(&pubkey) {
+            return Ok(account.clone());
+        }
+
+        let account = read_fixture(&self.dir, &pubkey).await?;
+        self.accounts.write().await.insert(pubkey, account.clone());
+
+        Ok(account)
+    }
+    async fn get_program_accounts_impl(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> SdkResult<(Slot, Vec<(Pubkey, Account)>)> {
+        // load every fixture, not only those requested so far
+        for pubkey in fixture_pubkeys(&self.dir).await? {
+            self.get_account_impl(pubkey).await?;
+        }
+
+        let filters = config.filters.unwrap_or_default();
+        let accounts = self
+            .accounts
+            .read()
+            .await
+            .iter()
+            .filter(|(_, account)| {
+                let shared = AccountSharedData::from((*account).clone());
+                account.owner == drift::ID && filters.iter().all(|filter| filter.allows(&shared))
+            })
+            .map(|(pubkey, account)| (*pubkey, account.clone()))
+            .collect();
+
+        Ok((FIXTURE_SLOT, accounts))
+    }
+}
+
+impl AccountProvider for FixtureAccountProvider {
+    fn get_account(&self, account: Pubkey) -> BoxFuture<SdkResult<Account>> {
+        self.get_account_impl(account).boxed()
+    }
+    fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+    fn commitment_config(&self) -> CommitmentConfig {
+        self.commitment
+    }
+    fn get_program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>> {
+        self.get_program_accounts_impl(config).boxed()
+    }
+}
+
+/// Account provider writing a fixture of every account fetched by the wrapped provider
+///
+/// Failing to write a fixture is logged, the fetched account is returned regardless
+#[derive(Clone)]
+pub struct RecordingAccountProvider<T: AccountProvider = RpcAccountProvider> {
+    inner: T,
+    dir: PathBuf,
+    format: FixtureFormat,
+}
+
+impl<T: AccountProvider> RecordingAccountProvider<T> {
+    /// Record accounts fetched by `inner` into `dir` as `format` fixtures
+    ///
+    /// `dir` is created if it doesn't exist
+    pub fn new(inner: T, dir: impl AsRef<Path>, format: FixtureFormat) -> SdkResult<Self> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            inner,
+            dir: dir.as_ref().to_path_buf(),
+            format,
+        })
+    }
+    async fn get_account_impl(&self, pubkey: Pubkey) -> SdkResult<Account> {
+        let account = self.inner.get_account(pubkey).await?;
+        if let Err(err) = write_fixture(&self.dir, self.format, &pubkey, &account).await {
+            warn!(target: "account", "failed to record {pubkey:?}: {err:?}");
+        }
+
+        Ok(account)
+    }
+    async fn get_program_accounts_impl(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> SdkResult<(Slot, Vec<(Pubkey, Account)>)> {
+        let (slot, accounts) = self.inner.get_program_accounts(config).await?;
+        for (pubkey, account) in accounts.iter() {
+            if let Err(err) = write_fixture(&self.dir, self.format, pubkey, account).await {
+                warn!(target: "account", "failed to record {pubkey:?}: {err:?}");
+            }
+        }
+
+        Ok((slot, accounts))
+    }
+}
+
+impl<T: AccountProvider> AccountProvider for RecordingAccountProvider<T> {
+    fn get_account(&self, account: Pubkey) -> BoxFuture<SdkResult<Account>> {
+        self.get_account_impl(account).boxed()
+    }
+    fn endpoint(&self) -> String {
+        self.inner.endpoint()
+    }
+    fn commitment_config(&self) -> CommitmentConfig {
+        self.inner.commitment_config()
+    }
+    fn get_program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>> {
+        self.get_program_accounts_impl(config).boxed()
+    }
+}
+
+fn fixture_path(dir: &Path, pubkey: &Pubkey, format: FixtureFormat) -> PathBuf {
+    dir.join(format!("{pubkey}.{}", format.extension()))
+}
+
+/// Pubkeys of the fixtures in `dir`, none if it doesn't exist
+async fn fixture_pubkeys(dir: &Path) -> SdkResult<Vec<Pubkey>> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut pubkeys = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_fixture = path.extension().is_some_and(|extension| {
+            extension == FixtureFormat::Json.extension()
+                || extension == FixtureFormat::Bincode.extension()
+        });
+        if let (true, Some(pubkey)) = (
+            is_fixture,
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<Pubkey>().ok()),
+        ) {
+            pubkeys.push(pubkey);
+        }
+    }
+
+    Ok(pubkeys)
+}
+
+/// Read the fixture of `pubkey` from `dir`, in whichever format exists
+async fn read_fixture(dir: &Path, pubkey: &Pubkey) -> SdkResult<Account> {
+    let json_path = fixture_path(dir, pubkey, FixtureFormat::Json);
+    if let Ok(json) = tokio::fs::read(&json_path).await {
+        let keyed_account: RpcKeyedAccount =
+            serde_json::from_slice(&json).map_err(|_| SdkError::Deserializing)?;
+        return keyed_account
+            .account
+            .decode::<Account>()
+            .ok_or(SdkError::Deserializing);
+    }
+
+    let bincode_path = fixture_path(dir, pubkey, FixtureFormat::Bincode);
+    match tokio::fs::read(&bincode_path).await {
+        Ok(bytes) => bincode::deserialize(&bytes).map_err(|_| SdkError::Deserializing),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            Err(SdkError::FixtureNotFound(*pubkey))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Write `account` as the fixture of `pubkey` in `dir`
+async fn write_fixture(
+    dir: &Path,
+    format: FixtureFormat,
+    pubkey: &Pubkey,
+    account: &Account,
+) -> SdkResult<()> {
+    let bytes = match format {
+        FixtureFormat::Json => {
+            let keyed_account = RpcKeyedAccount {
+                pubkey: pubkey.to_string(),
+                account: UiAccount::encode(pubkey, account, UiAccountEncoding::Base64, None, None),
+            };
+            serde_json::to_vec_pretty(&keyed_account)
+                .map_err(|err| SdkError::Generic(err.to_string()))?
+        }
+        FixtureFormat::Bincode => {
+            bincode::serialize(account).map_err(|err| SdkError::Generic(err.to_string()))?
+        }
+    };
+    tokio::fs::write(fixture_path(dir, pubkey, format), bytes).await?;
+
+    Ok(())
+}
+
+/// Directory of the recorded account fixtures used by tests
+#[cfg(test)]
+pub(crate) const TEST_FIXTURES_DIR: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/accounts");
+
+/// Drift program owned account holding `data`
+#[cfg(test)]
+pub(crate) fn drift_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner: drift::ID,
+        executable: false,
+        rent_epoch: u64::MAX,
+    }
+}
+
+/// Serve the test fixtures with a drift state, `perp_markets`, `spot_markets` and the market
+/// lookup table of `context` on top, enough to create a `DriftClient`
+#[cfg(test)]
+pub(crate) async fn drift_program_fixtures(
+    context: crate::Context,
+    perp_markets: &[drift::state::perp_market::PerpMarket],
+    spot_markets: &[drift::state::spot_market::SpotMarket],
+) -> FixtureAccountProvider {
+    use anchor_lang::AccountSerialize;
+
+    use crate::{
+        constants::{
+            derive_perp_market_account, derive_spot_market_account, market_lookup_table,
+            state_account,
+        },
+        utils::zero_account_to_bytes,
+    };
+
+    let provider = FixtureAccountProvider::new(TEST_FIXTURES_DIR);
+
+    let state = drift::state::state::State {
+        number_of_markets: perp_markets.len() as u16,
+        number_of_spot_markets: spot_markets.len() as u16,
+        ..Default::default()
+    };
+    let mut state_data = Vec::new();
+    state
+        .try_serialize(&mut state_data)
+        .expect("serialize state");
+    provider
+        .insert(*state_account(), drift_account(state_data))
+        .await;
+
+    let mut lookup_table = vec![0_u8; 56];
+    lookup_table[0] = 1;
+    for market in perp_markets {
+        let pubkey = derive_perp_market_account(market.market_index);
+        provider
+            .insert(pubkey, drift_account(zero_account_to_bytes(*market)))
+            .await;
+        lookup_table.extend_from_slice(pubkey.as_ref());
+    }
+    for market in spot_markets {
+        let pubkey = derive_spot_market_account(market.market_index);
+        provider
+            .insert(pubkey, drift_account(zero_account_to_bytes(*market)))
+            .await;
+        lookup_table.extend_from_slice(pubkey.as_ref());
+    }
+    provider
+        .insert(
+            market_lookup_table(context),
+            Account {
+                lamports: 1_000_000,
+                data: lookup_table,
+                owner: solana_address_lookup_table_program::ID,
+                executable: false,
+                rent_epoch: u64::MAX,
+            },
+        )
+        .await;
+
+    provider
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("drift-fixtures-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn serves_solana_cli_json() {
+        let provider = FixtureAccountProvider::new(TEST_FIXTURES_DIR);
+        let pubkey = Pubkey::from_str("GrDJZX1zzNzf1RYKsmV2bJK2SqNwgWbd1WHsBvoeuZqC").unwrap();
+
+        let fixture = provider.get_account(pubkey).await.unwrap();
+        assert_eq!(fixture.lamports, 2_039_280);
+        assert_eq!(fixture.data, vec![1, 2, 3, 4]);
+        assert_eq!(fixture.owner, drift::ID);
+
+        assert!(matches!(
+            provider.get_account(Pubkey::new_unique()).await,
+            Err(SdkError::FixtureNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn recorded_accounts_replay() {
+        let source = FixtureAccountProvider::new(temp_dir());
+        let (json_account, bincode_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        source
+            .insert(json_account, drift_account(vec![1, 2, 3]))
+            .await;
+        source
+            .insert(bincode_account, drift_account(vec![4, 5, 6]))
+            .await;
+
+        let dir = temp_dir();
+        let json_recorder =
+            RecordingAccountProvider::new(source.clone(), &dir, FixtureFormat::Json).unwrap();
+        let bincode_recorder =
+            RecordingAccountProvider::new(source.clone(), &dir, FixtureFormat::Bincode).unwrap();
+        assert_eq!(
+            json_recorder.get_account(json_account).await.unwrap(),
+            drift_account(vec![1, 2, 3])
+        );
+        assert_eq!(
+            bincode_recorder.get_account(bincode_account).await.unwrap(),
+            drift_account(vec![4, 5, 6])
+        );
+        assert!(dir.join(format!("{json_account}.json")).exists());
+        assert!(dir.join(format!("{bincode_account}.bin")).exists());
+
+        let replay = FixtureAccountProvider::new(&dir);
+        assert_eq!(
+            replay.get_account(json_account).await.unwrap(),
+            drift_account(vec![1, 2, 3])
+        );
+        assert_eq!(
+            replay.get_account(bincode_account).await.unwrap(),
+            drift_account(vec![4, 5, 6])
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn program_accounts_match_filters() {
+        use anchor_lang::Discriminator;
+        use drift::state::user::User;
+        use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+
+        use crate::memcmp::get_user_filter;
+
+        let provider = FixtureAccountProvider::new(TEST_FIXTURES_DIR);
+        let user = Pubkey::new_unique();
+        let user_data = [User::discriminator().as_slice(), &[0_u8; 16]].concat();
+        provider.insert(user, drift_account(user_data)).await;
+        let mut not_drift = drift_account(vec![1, 2, 3, 4]);
+        not_drift.owner = Pubkey::new_unique();
+        provider.insert(Pubkey::new_unique(), not_drift).await;
+
+        let (_, accounts) = provider
+            .get_program_accounts(RpcProgramAccountsConfig {
+                filters: Some(vec![get_user_filter()]),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].0, user);
+
+        // fixtures on disk are matched without being requested first
+        let (_, accounts) = provider
+            .get_program_accounts(RpcProgramAccountsConfig {
+                filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    0,
+                    vec![1, 2, 3, 4],
+                ))]),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            accounts
+                .iter()
+                .map(|(pubkey, _)| *pubkey)
+                .collect::<Vec<_>>(),
+            vec![Pubkey::from_str("GrDJZX1zzNzf1RYKsmV2bJK2SqNwgWbd1WHsBvoeuZqC").unwrap()]
+        );
+    }
+}
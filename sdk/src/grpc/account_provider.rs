@@ -0,0 +1,359 @@
+use std::{collections::HashMap, sync::Arc};
+
+use fnv::FnvHashMap;
+use futures_util::{future::BoxFuture, FutureExt};
+use log::{debug, warn};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcProgramAccountsConfig};
+use solana_sdk::{
+    account::Account, clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey,
+};
+use tokio::sync::{
+    mpsc,
+    watch::{self, Receiver},
+};
+use yellowstone_grpc_proto::prelude::{SubscribeRequest, SubscribeRequestFilterAccounts};
+
+use super::{
+    commitment_level, reconnect_delay, GeyserStream, GrpcAccountUpdate, GrpcConnectionOpts,
+};
+use crate::{utils::get_program_accounts, AccountCache, AccountProvider, SdkResult};
+
+type AccountSender = watch::Sender<(Account, Slot)>;
+
+/// Account provider using a Yellowstone gRPC subscription to receive and cache account updates
+///
+/// Accounts are fetched from RPC on first request and streamed thereafter, all accounts share one
+/// gRPC subscription. Updates are applied in slot order and the stream reconnects indefinitely,
+/// re-fetching the cached accounts from RPC after each reconnect.
+#[derive(Clone)]
+pub struct GrpcAccountProvider {
+    rpc_client: Arc<RpcClient>,
+    /// map from account pubkey to (account data, last modified slot)
+    account_cache: AccountCache,
+    /// sink for accounts to add to the subscription
+    new_accounts: mpsc::UnboundedSender<(Pubkey, AccountSender)>,
+}
+
+impl GrpcAccountProvider {
+    /// Create a new GrpcAccountProvider
+    ///
+    /// `url` RPC endpoint used for initial account fetches
+    /// `grpc` Yellowstone gRPC endpoint for account updates
+    pub async fn new(url: &str, grpc: GrpcConnectionOpts) -> SdkResult<Self> {
+        Self::new_with_commitment(url, grpc, CommitmentConfig::confirmed()).await
+    }
+    /// Create a new GrpcAccountProvider with provided commitment level
+    pub async fn new_with_commitment(
+        url: &str,
+        grpc: GrpcConnectionOpts,
+        commitment: CommitmentConfig,
+    ) -> SdkResult<Self> {
+        let rpc_client = Arc::new(RpcClient::new_with_commitment(url.to_string(), commitment));
+        let (new_accounts, new_accounts_rx) = mpsc::unbounded_channel();
+        tokio::spawn(
+            AccountStream {
+                grpc,
+                commitment,
+                rpc_client: Arc::clone(&rpc_client),
+                senders: Default::default(),
+                latest: Default::default(),
+            }
+            .run(new_accounts_rx),
+        );
+
+        Ok(Self {
+            rpc_client,
+            account_cache: Default::default(),
+            new_accounts,
+        })
+    }
+    /// Cache `account` with its `initial` value and add it to the gRPC subscription
+    async fn watch_account(
+        &self,
+        account: Pubkey,
+        initial: (Account, Slot),
+    ) -> Receiver<(Account, Slot)> {
+        let (tx, rx) = watch::channel(initial);
+        {
+            let mut cache = self.account_cache.write().await;
+            cache.insert(account, rx.clone());
+        }
+        if self.new_accounts.send((account, tx)).is_err() {
+            warn!(target: "account", "grpc stream task ended, {account:?} will not update");
+        }
+
+        rx
+    }
+    /// Fetch an account and initiate subscription for future updates
+    async fn get_account_impl(&self, account: Pubkey) -> SdkResult<Account> {
+        {
+            let cache = self.account_cache.read().await;
+            if let Some(account_data_rx) = cache.get(&account) {
+                let (account_data, _slot) = account_data_rx.borrow().clone();
+                return Ok(account_data);
+            }
+        }
+
+        // fetch initial account data, stream only updates on changes
+        let account_data: Account = self.rpc_client.get_account(&account).await?;
+        self.watch_account(account, (account_data.clone(), 0)).await;
+
+        Ok(account_data)
+    }
+}
+
+impl AccountProvider for GrpcAccountProvider {
+    fn get_account(&self, account: Pubkey) -> BoxFuture<SdkResult<Account>> {
+        self.get_account_impl(account).boxed()
+    }
+    fn endpoint(&self) -> String {
+        self.rpc_client.url()
+    }
+    fn commitment_config(&self) -> CommitmentConfig {
+        self.rpc_client.commitment()
+    }
+    fn get_program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>> {
+        get_program_accounts(&self.rpc_client, config).boxed()
+    }
+}
+
+/// Streams updates of the provider's accounts into their cache entries
+struct AccountStream {
+    grpc: GrpcConnectionOpts,
+    commitment: CommitmentConfig,
+    rpc_client: Arc<RpcClient>,
+    senders: FnvHashMap<Pubkey, AccountSender>,
+    /// (slot, write version) of the last stream update applied per account
+    latest: FnvHashMap<Pubkey, (Slot, u64)>,
+}
+
+impl AccountStream {
+    /// The subscription filter id
+    const FILTER_ID: &'static str = "accounts";
+
+    /// Run until the provider is dropped
+    async fn run(mut self, mut new_accounts: mpsc::UnboundedReceiver<(Pubkey, AccountSender)>) {
+        let mut attempt = 0;
+        loop {
+            // nothing to subscribe to until an account is requested
+            if self.senders.is_empty() {
+                match new_accounts.recv().await {
+                    Some((account, tx)) => {
+                        self.senders.insert(account, tx);
+                    }
+                    None => return,
+                }
+            }
+            self.add_pending(&mut new_accounts);
+
+            let mut stream = match GeyserStream::connect(&self.grpc, self.request()).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!(target: "account", "grpc connect failed: {err:?}");
+                    tokio::time::sleep(reconnect_delay(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+            };
+            debug!(target: "account", "start grpc account stream");
+            if attempt > 0 {
+                // updates may have been missed while disconnected
+                self.backfill().await;
+            }
+            attempt = 0;
+
+            loop {
+                tokio::select! {
+                    biased;
+                    new_account = new_accounts.recv() => {
+                        let Some((account, tx)) = new_account else {
+                            return;
+                        };
+                        self.senders.insert(account, tx);
+                        self.add_pending(&mut new_accounts);
+                        if stream.update(self.request()).is_err() {
+                            break;
+                        }
+                    }
+                    update = stream.next_account() => {
+                        match update {
+                            Ok(Some(update)) => self.apply_update(update),
+                            Ok(None) => {
+                                warn!(target: "account", "grpc account stream closed");
+                                break;
+                            }
+                            Err(err) => {
+                                warn!(target: "account", "grpc account stream failed: {err:?}");
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            attempt += 1;
+            tokio::time::sleep(reconnect_delay(attempt)).await;
+        }
+    }
+    /// Add accounts requested since the last subscription update, so they share one update
+    fn add_pending(&mut self, new_accounts: &mut mpsc::UnboundedReceiver<(Pubkey, AccountSender)>) {
+        while let Ok((account, tx)) = new_accounts.try_recv() {
+            self.senders.insert(account, tx);
+        }
+    }
+    /// The subscription request for all watched accounts
+    fn request(&self) -> SubscribeRequest {
+        SubscribeRequest {
+            accounts: HashMap::from([(
+                Self::FILTER_ID.to_string(),
+                SubscribeRequestFilterAccounts {
+                    account: self.senders.keys().map(ToString::to_string).collect(),
+                    ..Default::default()
+                },
+            )]),
+            commitment: Some(commitment_level(self.commitment) as i32),
+            ..Default::default()
+        }
+    }
+    fn apply_update(&mut self, update: GrpcAccountUpdate) {
+        let Some(tx) = self.senders.get(&update.pubkey) else {
+            return;
+        };
+        let version = (update.slot, update.write_version);
+        if self
+            .latest
+            .get(&update.pubkey)
+            .is_some_and(|latest| *latest >= version)
+        {
+            debug!(target: "account", "stream update old");
+            return;
+        }
+        // the cached slot may be from an RPC fetch
+        let applied = tx.send_if_modified(|current| {
+            if update.slot >= current.1 {
+                debug!(target: "account", "stream update writing to cache");
+                *current = (update.account, update.slot);
+                true
+            } else {
+                debug!(target: "account", "stream update old");
+                false
+            }
+        });
+        if applied {
+            self.latest.insert(update.pubkey, version);
+        }
+    }
+    /// Refresh all watched accounts from RPC
+    async fn backfill(&self) {
+        let accounts: Vec<Pubkey> = self.senders.keys().copied().collect();
+        // getMultipleAccounts is limited to 100 accounts per request
+        for chunk in accounts.chunks(100) {
+            let response = match self
+                .rpc_client
+                .get_multiple_accounts_with_commitment(chunk, self.commitment)
+                .await
+            {
+                Ok(response) => response,
+                Err(err) => {
+                    warn!(target: "account", "grpc reconnect backfill failed: {err:?}");
+                    continue;
+                }
+            };
+            let slot = response.context.slot;
+            for (account, account_data) in chunk.iter().zip(response.value) {
+                let (Some(tx), Some(account_data)) = (self.senders.get(account), account_data)
+                else {
+                    continue;
+                };
+                tx.send_if_modified(|current| {
+                    // only update with polled value if its newer
+                    if slot > current.1 {
+                        *current = (account_data, slot);
+                        true
+                    } else {
+                        false
+                    }
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::grpc::stub::{account_update, serve};
+
+    /// Wait until the account's cached slot reaches `slot`
+    async fn wait_for_slot(rx: &mut Receiver<(Account, Slot)>, slot: Slot) -> Account {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if rx.borrow_and_update().1 >= slot {
+                    return rx.borrow().0.clone();
+                }
+                rx.changed().await.unwrap();
+            }
+        })
+        .await
+        .expect("account update")
+    }
+
+    #[tokio::test]
+    async fn streams_slot_ordered_updates_across_reconnects() {
+        let owner = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let marker = Pubkey::new_unique();
+        let (url, mut requests) = serve(vec![
+            vec![
+                account_update(&account, &owner, vec![1], 10, 2),
+                // older write in the same slot
+                account_update(&account, &owner, vec![5], 10, 1),
+                // late write from an older slot
+                account_update(&account, &owner, vec![2], 9, 2),
+                account_update(&marker, &owner, vec![3], 10, 3),
+            ],
+            vec![account_update(&account, &owner, vec![4], 11, 4)],
+        ])
+        .await;
+
+        // RPC is unreachable, reconnect backfills fail and are skipped
+        let provider = GrpcAccountProvider::new_with_commitment(
+            "http://127.0.0.1:1",
+            GrpcConnectionOpts::new(&url, None),
+            CommitmentConfig::processed(),
+        )
+        .await
+        .unwrap();
+        let mut account_rx = provider
+            .watch_account(account, (Account::default(), 0))
+            .await;
+        let mut marker_rx = provider
+            .watch_account(marker, (Account::default(), 0))
+            .await;
+
+        let request = requests.recv().await.unwrap();
+        let filter = &request.accounts[AccountStream::FILTER_ID];
+        assert_eq!(filter.account.len(), 2);
+        assert!(filter.account.contains(&account.to_string()));
+        assert!(filter.account.contains(&marker.to_string()));
+        assert_eq!(
+            request.commitment,
+            Some(commitment_level(CommitmentConfig::processed()) as i32)
+        );
+
+        // the marker is streamed after the older write
+        wait_for_slot(&mut marker_rx, 10).await;
+        assert_eq!(account_rx.borrow().0.data, vec![1]);
+        assert_eq!(account_rx.borrow().1, 10);
+
+        // stream resumes after the server closes it
+        let account_data = wait_for_slot(&mut account_rx, 11).await;
+        assert_eq!(account_data.data, vec![4]);
+        assert_eq!(account_data.owner, owner);
+        assert_eq!(provider.get_account(account).await.unwrap().data, vec![4]);
+    }
+}
@@ -0,0 +1,264 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anchor_lang::AccountDeserialize;
+use futures_util::{future::BoxFuture, FutureExt};
+use log::{debug, error, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use yellowstone_grpc_proto::prelude::{SubscribeRequest, SubscribeRequestFilterAccounts};
+
+use super::{commitment_level, reconnect_delay, GeyserStream, GrpcConnectionOpts};
+use crate::{
+    accounts::{AccountSubscriber, OnUpdate, SubscribedAccount},
+    error::SdkError,
+    types::{DataAndSlot, SdkResult},
+};
+
+/// Subscribes to a single account over Yellowstone gRPC
+///
+/// Updates are applied in slot order, the stream reconnects indefinitely and re-fetches the account
+/// from RPC after each reconnect.
+#[derive(Clone)]
+pub struct GrpcAccountSubscriber<T>
+where
+    T: AccountDeserialize,
+{
+    subscription_name: &'static str,
+    connection: GrpcConnectionOpts,
+    commitment: CommitmentConfig,
+    rpc_client: Arc<RpcClient>,
+    account: SubscribedAccount<T>,
+    pub subscribed: bool,
+    unsubscriber: Option<tokio::sync::mpsc::Sender<()>>,
+}
+
+impl<T> GrpcAccountSubscriber<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    /// `rpc_client` fetches the account, the stream is subscribed at `commitment`
+    pub fn new(
+        subscription_name: &'static str,
+        connection: GrpcConnectionOpts,
+        pubkey: Pubkey,
+        rpc_client: Arc<RpcClient>,
+        commitment: CommitmentConfig,
+    ) -> Self {
+        GrpcAccountSubscriber {
+            subscription_name,
+            connection,
+            commitment,
+            rpc_client,
+            account: SubscribedAccount::new(pubkey),
+            subscribed: false,
+            unsubscriber: None,
+        }
+    }
+
+    async fn subscribe_impl(&mut self, on_update: Option<OnUpdate<T>>) -> SdkResult<()> {
+        if self.subscribed {
+            return Ok(());
+        }
+        self.account.set_on_update(on_update);
+        self.subscribed = true;
+
+        let (unsub_tx, mut unsub_rx) = tokio::sync::mpsc::channel::<()>(1);
+        self.unsubscriber = Some(unsub_tx);
+
+        let request = self.request();
+        let connection = self.connection.clone();
+        let rpc_client = Arc::clone(&self.rpc_client);
+        let account = self.account.clone();
+        let subscription_name = self.subscription_name;
+        tokio::spawn(async move {
+            // (slot, write version) of the last update applied
+            let mut latest: Option<(Slot, u64)> = None;
+            let mut attempt = 0;
+            let mut reconnect = false;
+            loop {
+                match GeyserStream::connect(&connection, request.clone()).await {
+                    Ok(mut stream) => {
+                        if reconnect {
+                            // updates may have been missed while disconnected
+                            if let Err(e) = account.fetch(&rpc_client).await {
+                                warn!("{} reconnect fetch failed: {e}", subscription_name);
+                            }
+                        }
+                        reconnect = true;
+                        attempt = 0;
+                        loop {
+                            tokio::select! {
+                                update = stream.next_account() => {
+                                    match update {
+                                        Ok(Some(update)) => {
+                                            let version = (update.slot, update.write_version);
+                                            if latest.is_some_and(|latest| latest >= version) {
+                                                continue;
+                                            }
+                                            match account.update_raw(&update.account.data, update.slot) {
+                                                Ok(_) => latest = Some(version),
+                                                Err(e) => error!("{} error decoding account data {e}", subscription_name),
+                                            }
+                                        }
+                                        Ok(None) => {
+                                            warn!("{} stream ended", subscription_name);
+                                            break;
+                                        }
+                                        Err(e) => {
+                                            warn!("{} stream failed: {e}", subscription_name);
+                                            break;
+                                        }
+                                    }
+                                }
+                                _ = unsub_rx.recv() => {
+                                    debug!("Unsubscribing.");
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to subscribe to account stream, retrying. {e}");
+                    }
+                }
+
+                let delay_duration = reconnect_delay(attempt);
+                debug!(
+                    "{}: Reconnecting in {:?}",
+                    subscription_name, delay_duration
+                );
+                attempt += 1;
+                tokio::select! {
+                    _ = tokio::time::sleep(delay_duration) => {}
+                    _ = unsub_rx.recv() => {
+                        debug!("Unsubscribing.");
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn unsubscribe_impl(&mut self) -> SdkResult<()> {
+        if self.subscribed && self.unsubscriber.is_some() {
+            if let Err(e) = self.unsubscriber.as_ref().unwrap().send(()).await {
+                error!("Failed to send unsubscribe signal: {:?}", e);
+                return Err(SdkError::CouldntUnsubscribe(e));
+            }
+            self.subscribed = false;
+        }
+        Ok(())
+    }
+
+    /// The subscription request for the account
+    fn request(&self) -> SubscribeRequest {
+        SubscribeRequest {
+            accounts: HashMap::from([(
+                self.subscription_name.to_string(),
+                SubscribeRequestFilterAccounts {
+                    account: vec![self.account.pubkey.to_string()],
+                    ..Default::default()
+                },
+            )]),
+            commitment: Some(commitment_level(self.commitment) as i32),
+            ..Default::default()
+        }
+    }
+}
+
+impl<T> AccountSubscriber<T> for GrpcAccountSubscriber<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    fn subscribe(&mut self, on_update: Option<OnUpdate<T>>) -> BoxFuture<SdkResult<()>> {
+        self.subscribe_impl(on_update).boxed()
+    }
+    fn fetch(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.account.fetch(&self.rpc_client).boxed()
+    }
+    fn unsubscribe(&mut self) -> BoxFuture<SdkResult<()>> {
+        self.unsubscribe_impl().boxed()
+    }
+    fn set_data(&self, data: T, slot: u64) {
+        self.account.update(data, slot);
+    }
+    fn data_and_slot(&self) -> Option<DataAndSlot<T>> {
+        self.account.get()
+    }
+    fn is_subscribed(&self) -> bool {
+        self.subscribed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use drift::state::user::User;
+
+    use super::*;
+    use crate::grpc::stub::{account_update, serve, user_account_data};
+
+    #[tokio::test]
+    async fn applies_slot_ordered_updates() {
+        let user = Pubkey::new_unique();
+        let (authority_1, authority_2, authority_3) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (url, mut requests) = serve(vec![vec![
+            account_update(&user, &drift::ID, user_account_data(authority_1), 10, 2),
+            // older write in the same slot
+            account_update(&user, &drift::ID, user_account_data(authority_2), 10, 1),
+            // not a user account
+            account_update(&user, &drift::ID, vec![0; 8], 11, 3),
+            account_update(&user, &drift::ID, user_account_data(authority_3), 12, 4),
+        ]])
+        .await;
+
+        // RPC is unreachable, only the stream updates the account
+        let mut subscriber = GrpcAccountSubscriber::<User>::new(
+            "test",
+            GrpcConnectionOpts::new(&url, None),
+            user,
+            Arc::new(RpcClient::new("http://127.0.0.1:1".to_string())),
+            CommitmentConfig::confirmed(),
+        );
+        let seen = Arc::new(Mutex::new(Vec::<(Pubkey, Pubkey, Slot)>::new()));
+        let on_update: OnUpdate<User> = Arc::new({
+            let seen = Arc::clone(&seen);
+            move |pubkey: &Pubkey, update: &DataAndSlot<User>| {
+                seen.lock()
+                    .unwrap()
+                    .push((*pubkey, update.data.authority, update.slot));
+            }
+        });
+        subscriber.subscribe(Some(on_update)).await.unwrap();
+        assert!(subscriber.is_subscribed());
+
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request.accounts["test"].account, vec![user.to_string()]);
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while seen.lock().unwrap().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("updates");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(user, authority_1, 10), (user, authority_3, 12)]
+        );
+        let latest = subscriber.data_and_slot().unwrap();
+        assert_eq!((latest.data.authority, latest.slot), (authority_3, 12));
+
+        AccountSubscriber::unsubscribe(&mut subscriber)
+            .await
+            .unwrap();
+        assert!(!subscriber.is_subscribed());
+    }
+}
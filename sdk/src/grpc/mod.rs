@@ -0,0 +1,256 @@
+//! Yellowstone (Geyser) gRPC account streaming
+//!
+//! Lower latency alternative to the JSON-RPC websocket subscriptions, requires a node running the
+//! yellowstone-grpc plugin
+
+use std::time::Duration;
+
+use solana_client::rpc_filter::RpcFilterType;
+use solana_sdk::{
+    account::Account, clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey,
+};
+use tokio::sync::mpsc;
+use tonic::{
+    metadata::AsciiMetadataValue,
+    service::Interceptor,
+    transport::{ClientTlsConfig, Endpoint},
+    Request, Status, Streaming,
+};
+use yellowstone_grpc_proto::prelude::{
+    geyser_client::GeyserClient, subscribe_request_filter_accounts_filter::Filter,
+    subscribe_request_filter_accounts_filter_memcmp::Data as MemcmpData,
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+    SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterMemcmp,
+    SubscribeRequestPing, SubscribeUpdate, SubscribeUpdateAccount,
+};
+
+use crate::{SdkError, SdkResult};
+
+pub mod account_provider;
+pub mod account_subscriber;
+pub mod program_account_subscriber;
+#[cfg(test)]
+pub(crate) mod stub;
+
+pub use account_provider::GrpcAccountProvider;
+pub use account_subscriber::GrpcAccountSubscriber;
+pub use program_account_subscriber::GrpcProgramAccountSubscriber;
+
+/// Upper bound of the delay between reconnect attempts
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Yellowstone gRPC connection options
+#[derive(Clone, Debug)]
+pub struct GrpcConnectionOpts {
+    /// gRPC endpoint URL e.g. `https://grpc.example.com:443`
+    pub endpoint: String,
+    /// access token, sent as `x-token` metadata
+    pub x_token: Option<String>,
+}
+
+impl GrpcConnectionOpts {
+    pub fn new(endpoint: &str, x_token: Option<&str>) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            x_token: x_token.map(ToString::to_string),
+        }
+    }
+}
+
+/// Adds the `x-token` auth header to requests
+#[derive(Clone)]
+struct XTokenInterceptor(Option<AsciiMetadataValue>);
+
+impl Interceptor for XTokenInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(ref x_token) = self.0 {
+            request.metadata_mut().insert("x-token", x_token.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// An account write received from the geyser stream
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct GrpcAccountUpdate {
+    pub pubkey: Pubkey,
+    pub account: Account,
+    pub slot: Slot,
+    /// orders writes to the account within a slot
+    pub write_version: u64,
+}
+
+impl GrpcAccountUpdate {
+    fn try_from_proto(update: SubscribeUpdateAccount) -> Option<Self> {
+        let info = update.account?;
+        Some(Self {
+            pubkey: Pubkey::try_from(info.pubkey.as_slice()).ok()?,
+            account: Account {
+                lamports: info.lamports,
+                data: info.data,
+                owner: Pubkey::try_from(info.owner.as_slice()).ok()?,
+                executable: info.executable,
+                rent_epoch: info.rent_epoch,
+            },
+            slot: update.slot,
+            write_version: info.write_version,
+        })
+    }
+}
+
+/// A geyser `Subscribe` stream
+///
+/// The subscription is bidirectional, sending a new request replaces the active filters
+pub(crate) struct GeyserStream {
+    requests: mpsc::UnboundedSender<SubscribeRequest>,
+    updates: Streaming<SubscribeUpdate>,
+}
+
+impl GeyserStream {
+    /// Connect to the geyser endpoint and subscribe with `request`
+    pub async fn connect(opts: &GrpcConnectionOpts, request: SubscribeRequest) -> SdkResult<Self> {
+        let mut endpoint = Endpoint::from_shared(opts.endpoint.clone())?
+            .connect_timeout(Duration::from_secs(10))
+            .tcp_keepalive(Some(Duration::from_secs(30)));
+        if opts.endpoint.starts_with("https") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+        }
+        let x_token = match opts.x_token {
+            Some(ref x_token) => Some(
+                x_token
+                    .parse::<AsciiMetadataValue>()
+                    .map_err(|_| SdkError::Generic("invalid gRPC x-token".to_string()))?,
+            ),
+            None => None,
+        };
+        let channel = endpoint.connect().await?;
+        let mut client = GeyserClient::with_interceptor(channel, XTokenInterceptor(x_token))
+            // program accounts can be up to 10MiB
+            .max_decoding_message_size(64 * 1024 * 1024);
+
+        let (requests, rx) = mpsc::unbounded_channel();
+        requests.send(request).expect("receiver alive");
+        let request_stream = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|request| (request, rx))
+        });
+        let updates = client.subscribe(request_stream).await?.into_inner();
+
+        Ok(Self { requests, updates })
+    }
+
+    /// Replace the subscription filters with `request`
+    pub fn update(&self, request: SubscribeRequest) -> SdkResult<()> {
+        self.requests
+            .send(request)
+            .map_err(|_| SdkError::Generic("geyser stream closed".to_string()))
+    }
+
+    /// Return the next account update, None once the server ends the stream
+    ///
+    /// Server pings are answered, other updates are skipped
+    pub async fn next_account(&mut self) -> SdkResult<Option<GrpcAccountUpdate>> {
+        loop {
+            let Some(update) = self.updates.message().await? else {
+                return Ok(None);
+            };
+            match update.update_oneof {
+                Some(UpdateOneof::Account(account)) => {
+                    if let Some(update) = GrpcAccountUpdate::try_from_proto(account) {
+                        return Ok(Some(update));
+                    }
+                }
+                Some(UpdateOneof::Ping(_)) => {
+                    // keeps load balancers from dropping idle connections
+                    self.update(SubscribeRequest {
+                        ping: Some(SubscribeRequestPing { id: 1 }),
+                        ..Default::default()
+                    })?;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Convert an RPC commitment into its geyser equivalent
+pub(crate) fn commitment_level(commitment: CommitmentConfig) -> CommitmentLevel {
+    if commitment.is_finalized() {
+        CommitmentLevel::Finalized
+    } else if commitment.is_confirmed() {
+        CommitmentLevel::Confirmed
+    } else {
+        CommitmentLevel::Processed
+    }
+}
+
+/// Convert an RPC program account filter into its geyser equivalent
+pub(crate) fn account_filter(
+    filter: &RpcFilterType,
+) -> SdkResult<SubscribeRequestFilterAccountsFilter> {
+    let filter = match filter {
+        RpcFilterType::DataSize(size) => Filter::Datasize(*size),
+        RpcFilterType::Memcmp(memcmp) => {
+            let bytes = memcmp
+                .bytes()
+                .ok_or_else(|| SdkError::Generic("invalid memcmp filter bytes".to_string()))?;
+            Filter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+                offset: memcmp.offset as u64,
+                data: Some(MemcmpData::Bytes(bytes.into_owned())),
+            })
+        }
+        RpcFilterType::TokenAccountState => Filter::TokenAccountState(true),
+    };
+
+    Ok(SubscribeRequestFilterAccountsFilter {
+        filter: Some(filter),
+    })
+}
+
+/// Delay before reconnect `attempt`, doubling from 100ms up to `MAX_RECONNECT_DELAY`
+pub(crate) fn reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_millis(100 << attempt.min(8)).min(MAX_RECONNECT_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client::rpc_filter::Memcmp;
+
+    use super::*;
+
+    #[test]
+    fn rpc_filters_to_geyser() {
+        let memcmp = account_filter(&RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            8,
+            vec![1, 2, 3],
+        )))
+        .unwrap();
+        assert_eq!(
+            memcmp.filter,
+            Some(Filter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+                offset: 8,
+                data: Some(MemcmpData::Bytes(vec![1, 2, 3])),
+            }))
+        );
+        assert_eq!(
+            account_filter(&RpcFilterType::DataSize(4_376))
+                .unwrap()
+                .filter,
+            Some(Filter::Datasize(4_376))
+        );
+        assert_eq!(
+            commitment_level(CommitmentConfig::processed()),
+            CommitmentLevel::Processed
+        );
+        assert_eq!(
+            commitment_level(CommitmentConfig::finalized()),
+            CommitmentLevel::Finalized
+        );
+    }
+
+    #[test]
+    fn reconnect_delay_is_capped() {
+        assert_eq!(reconnect_delay(0), Duration::from_millis(100));
+        assert_eq!(reconnect_delay(3), Duration::from_millis(800));
+        assert_eq!(reconnect_delay(100), MAX_RECONNECT_DELAY);
+    }
+}
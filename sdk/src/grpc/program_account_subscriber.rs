@@ -0,0 +1,442 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anchor_lang::AccountDeserialize;
+use fnv::FnvHashMap;
+use log::{debug, error, warn};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+};
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+use yellowstone_grpc_proto::prelude::{SubscribeRequest, SubscribeRequestFilterAccounts};
+
+use super::{
+    account_filter, commitment_level, reconnect_delay, GeyserStream, GrpcAccountUpdate,
+    GrpcConnectionOpts,
+};
+use crate::{
+    error::SdkError,
+    event_emitter::EventEmitter,
+    types::{DataAndSlot, SdkResult},
+    utils::get_program_accounts,
+    websocket_program_account_subscriber::{ProgramAccountUpdate, WebsocketProgramAccountOptions},
+};
+
+/// Subscribes to drift program accounts over Yellowstone gRPC
+///
+/// Emits the same `ProgramAccountUpdate`s as `WebsocketProgramAccountSubscriber`, `options.encoding`
+/// is unused as gRPC streams raw account data.
+/// Updates are emitted in slot order per account, the stream reconnects indefinitely and
+/// re-fetches the matching accounts from RPC after each reconnect.
+#[derive(Clone)]
+pub struct GrpcProgramAccountSubscriber {
+    subscription_name: &'static str,
+    connection: GrpcConnectionOpts,
+    rpc_client: Arc<RpcClient>,
+    pub(crate) options: WebsocketProgramAccountOptions,
+    pub subscribed: bool,
+    pub event_emitter: EventEmitter,
+    unsubscriber: Option<tokio::sync::mpsc::Sender<()>>,
+}
+
+impl GrpcProgramAccountSubscriber {
+    /// `rpc_client` re-fetches the accounts after a reconnect
+    pub fn new(
+        subscription_name: &'static str,
+        connection: GrpcConnectionOpts,
+        rpc_client: Arc<RpcClient>,
+        options: WebsocketProgramAccountOptions,
+        event_emitter: EventEmitter,
+    ) -> Self {
+        GrpcProgramAccountSubscriber {
+            subscription_name,
+            connection,
+            rpc_client,
+            options,
+            subscribed: false,
+            event_emitter,
+            unsubscriber: None,
+        }
+    }
+
+    pub async fn subscribe<T>(&mut self) -> SdkResult<()>
+    where
+        T: AccountDeserialize + Clone + Send + 'static,
+    {
+        if self.subscribed {
+            return Ok(());
+        }
+        let request = self.request()?;
+        self.subscribed = true;
+
+        let (unsub_tx, mut unsub_rx) = tokio::sync::mpsc::channel::<()>(1);
+        self.unsubscriber = Some(unsub_tx);
+
+        let connection = self.connection.clone();
+        let rpc_client = Arc::clone(&self.rpc_client);
+        let options = self.options.clone();
+        let event_emitter = self.event_emitter.clone();
+        let subscription_name = self.subscription_name;
+        tokio::spawn(async move {
+            // (slot, write version) of the last update emitted per account
+            let mut latest = FnvHashMap::<Pubkey, (Slot, u64)>::default();
+            let mut attempt = 0;
+            let mut reconnect = false;
+            loop {
+                match GeyserStream::connect(&connection, request.clone()).await {
+                    Ok(mut stream) => {
+                        if reconnect {
+                            // updates may have been missed while disconnected
+                            match fetch_accounts(&rpc_client, &options).await {
+                                Ok(updates) => {
+                                    for update in updates {
+                                        emit_update::<T>(
+                                            &event_emitter,
+                                            subscription_name,
+                                            &mut latest,
+                                            update,
+                                        );
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("{} reconnect fetch failed: {e}", subscription_name)
+                                }
+                            }
+                        }
+                        reconnect = true;
+                        attempt = 0;
+                        loop {
+                            tokio::select! {
+                                update = stream.next_account() => {
+                                    match update {
+                                        Ok(Some(update)) => {
+                                            emit_update::<T>(&event_emitter, subscription_name, &mut latest, update);
+                                        }
+                                        Ok(None) => {
+                                            warn!("{} stream ended", subscription_name);
+                                            break;
+                                        }
+                                        Err(e) => {
+                                            warn!("{} stream failed: {e}", subscription_name);
+                                            break;
+                                        }
+                                    }
+                                }
+                                _ = unsub_rx.recv() => {
+                                    debug!("Unsubscribing.");
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to subscribe to program stream, retrying. {e}");
+                    }
+                }
+
+                let delay_duration = reconnect_delay(attempt);
+                debug!(
+                    "{}: Reconnecting in {:?}",
+                    subscription_name, delay_duration
+                );
+                attempt += 1;
+                tokio::select! {
+                    _ = tokio::time::sleep(delay_duration) => {}
+                    _ = unsub_rx.recv() => {
+                        debug!("Unsubscribing.");
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    pub async fn unsubscribe(&mut self) -> SdkResult<()> {
+        if self.subscribed && self.unsubscriber.is_some() {
+            if let Err(e) = self.unsubscriber.as_ref().unwrap().send(()).await {
+                error!("Failed to send unsubscribe signal: {:?}", e);
+                return Err(SdkError::CouldntUnsubscribe(e));
+            }
+            self.subscribed = false;
+        }
+        Ok(())
+    }
+
+    /// The subscription request for drift program accounts matching `options`
+    fn request(&self) -> SdkResult<SubscribeRequest> {
+        let filters = self
+            .options
+            .filters
+            .iter()
+            .map(account_filter)
+            .collect::<SdkResult<Vec<_>>>()?;
+
+        Ok(SubscribeRequest {
+            accounts: HashMap::from([(
+                self.subscription_name.to_string(),
+                SubscribeRequestFilterAccounts {
+                    owner: vec![drift::ID.to_string()],
+                    filters,
+                    ..Default::default()
+                },
+            )]),
+            commitment: Some(commitment_level(self.options.commitment) as i32),
+            ..Default::default()
+        })
+    }
+}
+
+/// Fetch the accounts matching `options` from RPC, as updates at the response's context slot
+async fn fetch_accounts(
+    rpc_client: &RpcClient,
+    options: &WebsocketProgramAccountOptions,
+) -> SdkResult<Vec<GrpcAccountUpdate>> {
+    let gpa_config = RpcProgramAccountsConfig {
+        filters: Some(options.filters.clone()),
+        account_config: RpcAccountInfoConfig {
+            commitment: Some(options.commitment),
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        with_context: Some(true),
+    };
+    let (slot, accounts) = get_program_accounts(rpc_client, gpa_config).await?;
+
+    Ok(accounts
+        .into_iter()
+        .map(|(pubkey, account)| GrpcAccountUpdate {
+            pubkey,
+            account,
+            slot,
+            // ordered before any stream write in the same slot
+            write_version: 0,
+        })
+        .collect())
+}
+
+/// Decode and emit `update` unless a newer write to the account was already emitted
+fn emit_update<T>(
+    event_emitter: &EventEmitter,
+    subscription_name: &'static str,
+    latest: &mut FnvHashMap<Pubkey, (Slot, u64)>,
+    update: GrpcAccountUpdate,
+) where
+    T: AccountDeserialize + Clone + Send + 'static,
+{
+    let version = (update.slot, update.write_version);
+    if latest
+        .get(&update.pubkey)
+        .is_some_and(|latest| *latest >= version)
+    {
+        return;
+    }
+
+    match T::try_deserialize(&mut update.account.data.as_slice()) {
+        Ok(data) => {
+            latest.insert(update.pubkey, version);
+            let data_and_slot = DataAndSlot::<T> {
+                slot: update.slot,
+                data,
+            };
+            event_emitter.emit(
+                subscription_name,
+                Box::new(ProgramAccountUpdate::new(
+                    update.pubkey.to_string(),
+                    data_and_slot,
+                    std::time::Instant::now(),
+                )),
+            );
+        }
+        Err(e) => {
+            error!("Error decoding account data {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use drift::state::user::User;
+    use solana_sdk::commitment_config::CommitmentConfig;
+
+    use super::*;
+    use crate::{
+        grpc::stub::{account_update, serve, serve_program_accounts, user_account_data},
+        memcmp::{get_non_idle_user_filter, get_user_filter},
+    };
+
+    #[tokio::test]
+    async fn emits_slot_ordered_updates_across_reconnects() {
+        let user = Pubkey::new_unique();
+        let marker = Pubkey::new_unique();
+        let (authority_1, authority_2, authority_3) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (url, mut requests) = serve(vec![
+            vec![
+                account_update(&user, &drift::ID, user_account_data(authority_1), 10, 2),
+                // older write in the same slot
+                account_update(&user, &drift::ID, user_account_data(authority_2), 10, 1),
+                account_update(&marker, &drift::ID, user_account_data(authority_2), 10, 3),
+                // not a user account
+                account_update(&marker, &drift::ID, vec![0; 8], 11, 4),
+            ],
+            vec![account_update(
+                &user,
+                &drift::ID,
+                user_account_data(authority_3),
+                12,
+                5,
+            )],
+        ])
+        .await;
+
+        let options = WebsocketProgramAccountOptions {
+            filters: vec![get_user_filter(), get_non_idle_user_filter()],
+            commitment: CommitmentConfig::confirmed(),
+            encoding: UiAccountEncoding::Base64,
+        };
+        let mut subscriber = GrpcProgramAccountSubscriber::new(
+            "test",
+            GrpcConnectionOpts::new(&url, Some("token")),
+            Arc::new(RpcClient::new("http://127.0.0.1:1".to_string())),
+            options,
+            EventEmitter::new(),
+        );
+        let seen = Arc::new(Mutex::new(Vec::<(String, Pubkey, Slot)>::new()));
+        subscriber.event_emitter.subscribe("test", {
+            let seen = Arc::clone(&seen);
+            move |event| {
+                if let Some(update) = event.as_any().downcast_ref::<ProgramAccountUpdate<User>>() {
+                    seen.lock().unwrap().push((
+                        update.pubkey.clone(),
+                        update.data_and_slot.data.authority,
+                        update.data_and_slot.slot,
+                    ));
+                }
+            }
+        });
+        subscriber.subscribe::<User>().await.unwrap();
+
+        let request = requests.recv().await.unwrap();
+        let filter = &request.accounts["test"];
+        assert_eq!(filter.owner, vec![drift::ID.to_string()]);
+        assert_eq!(filter.filters.len(), 2);
+        assert_eq!(
+            request.commitment,
+            Some(commitment_level(CommitmentConfig::confirmed()) as i32)
+        );
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while seen.lock().unwrap().len() < 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("updates");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (user.to_string(), authority_1, 10),
+                (marker.to_string(), authority_2, 10),
+                (user.to_string(), authority_3, 12),
+            ]
+        );
+
+        subscriber.unsubscribe().await.unwrap();
+        assert!(!subscriber.subscribed);
+    }
+
+    #[tokio::test]
+    async fn resyncs_from_rpc_after_reconnect() {
+        let (user, other, stale) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (authority_1, authority_2, authority_3) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (url, _requests) = serve(vec![
+            vec![
+                account_update(&user, &drift::ID, user_account_data(authority_1), 10, 1),
+                account_update(&stale, &drift::ID, user_account_data(authority_1), 20, 2),
+            ],
+            vec![account_update(
+                &other,
+                &drift::ID,
+                user_account_data(authority_3),
+                21,
+                3,
+            )],
+        ])
+        .await;
+        // missed while disconnected, `stale` was already updated past the fetch
+        let rpc_url = serve_program_accounts(
+            15,
+            vec![
+                (user, user_account_data(authority_2)),
+                (other, user_account_data(authority_2)),
+                (stale, user_account_data(authority_2)),
+            ],
+        )
+        .await;
+
+        let mut subscriber = GrpcProgramAccountSubscriber::new(
+            "test",
+            GrpcConnectionOpts::new(&url, None),
+            Arc::new(RpcClient::new(rpc_url)),
+            WebsocketProgramAccountOptions {
+                filters: vec![get_user_filter()],
+                commitment: CommitmentConfig::confirmed(),
+                encoding: UiAccountEncoding::Base64,
+            },
+            EventEmitter::new(),
+        );
+        let seen = Arc::new(Mutex::new(Vec::<(String, Pubkey, Slot)>::new()));
+        subscriber.event_emitter.subscribe("test", {
+            let seen = Arc::clone(&seen);
+            move |event| {
+                if let Some(update) = event.as_any().downcast_ref::<ProgramAccountUpdate<User>>() {
+                    seen.lock().unwrap().push((
+                        update.pubkey.clone(),
+                        update.data_and_slot.data.authority,
+                        update.data_and_slot.slot,
+                    ));
+                }
+            }
+        });
+        subscriber.subscribe::<User>().await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while seen.lock().unwrap().len() < 5 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("updates");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (user.to_string(), authority_1, 10),
+                (stale.to_string(), authority_1, 20),
+                (user.to_string(), authority_2, 15),
+                (other.to_string(), authority_2, 15),
+                (other.to_string(), authority_3, 21),
+            ]
+        );
+
+        subscriber.unsubscribe().await.unwrap();
+    }
+}
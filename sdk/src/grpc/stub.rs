@@ -0,0 +1,210 @@
+//! In-process geyser gRPC server for tests
+
+use std::{collections::VecDeque, pin::Pin, sync::Mutex};
+
+use anchor_lang::Discriminator;
+use drift::state::user::User;
+use futures_util::Stream;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::rpc_response::RpcKeyedAccount;
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+use tokio::{net::TcpListener, sync::mpsc};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+use yellowstone_grpc_proto::prelude::{
+    geyser_server::{Geyser, GeyserServer},
+    subscribe_update::UpdateOneof,
+    GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+    GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+    GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+    PongResponse, SubscribeRequest, SubscribeUpdate, SubscribeUpdateAccount,
+    SubscribeUpdateAccountInfo,
+};
+
+use crate::http_stub;
+
+/// Geyser server replaying scripted sessions of updates
+///
+/// Each `Subscribe` call takes the next session, waits for the client's first request, then sends
+/// the session's updates. The stream is ended after every session but the last, which is held open
+/// so clients are forced to reconnect between sessions.
+struct GeyserStub {
+    sessions: Mutex<VecDeque<Vec<SubscribeUpdate>>>,
+    requests: mpsc::UnboundedSender<SubscribeRequest>,
+}
+
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Geyser for GeyserStub {
+    type SubscribeStream = UpdateStream;
+
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let mut client_requests = request.into_inner();
+        let (session, last) = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let session = sessions.pop_front().unwrap_or_default();
+            (session, sessions.is_empty())
+        };
+        let requests = self.requests.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            match client_requests.message().await {
+                Ok(Some(request)) => {
+                    let _ = requests.send(request);
+                }
+                _ => return,
+            }
+            for update in session {
+                let _ = tx.send(Ok(update));
+            }
+            if last {
+                while let Ok(Some(request)) = client_requests.message().await {
+                    let _ = requests.send(request);
+                }
+            }
+        });
+
+        let updates = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|update| (update, rx))
+        });
+        Ok(Response::new(Box::pin(updates)))
+    }
+
+    async fn ping(&self, _request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        _request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_block_height(
+        &self,
+        _request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_slot(
+        &self,
+        _request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        _request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+
+    async fn get_version(
+        &self,
+        _request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        Err(Status::unimplemented("stub"))
+    }
+}
+
+/// Serve `sessions` on a local port
+///
+/// Returns the server URL and a receiver of the subscribe requests sent by clients
+pub(crate) async fn serve(
+    sessions: Vec<Vec<SubscribeUpdate>>,
+) -> (String, mpsc::UnboundedReceiver<SubscribeRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let incoming = futures_util::stream::unfold(listener, |listener| async move {
+        let stream = listener.accept().await.map(|(stream, _)| stream);
+        Some((stream, listener))
+    });
+
+    let (requests, requests_rx) = mpsc::unbounded_channel();
+    let stub = GeyserStub {
+        sessions: Mutex::new(sessions.into()),
+        requests,
+    };
+    tokio::spawn(
+        Server::builder()
+            .add_service(GeyserServer::new(stub))
+            .serve_with_incoming(incoming),
+    );
+
+    (url, requests_rx)
+}
+
+/// Serve `getProgramAccounts` on a local port, answering every request with `accounts` at `slot`
+///
+/// Returns the server URL
+pub(crate) async fn serve_program_accounts(slot: Slot, accounts: Vec<(Pubkey, Vec<u8>)>) -> String {
+    let value: Vec<RpcKeyedAccount> = accounts
+        .into_iter()
+        .map(|(pubkey, data)| {
+            let account = Account {
+                lamports: 1_000_000,
+                data,
+                owner: drift::ID,
+                executable: false,
+                rent_epoch: 0,
+            };
+            RpcKeyedAccount {
+                pubkey: pubkey.to_string(),
+                account: UiAccount::encode(
+                    &pubkey,
+                    &account,
+                    UiAccountEncoding::Base64,
+                    None,
+                    None,
+                ),
+            }
+        })
+        .collect();
+
+    http_stub::serve_json_rpc(move |method, _params| {
+        assert_eq!(method, "getProgramAccounts");
+        serde_json::json!({ "context": { "slot": slot }, "value": value })
+    })
+    .await
+}
+
+/// Build an account write update
+pub(crate) fn account_update(
+    pubkey: &Pubkey,
+    owner: &Pubkey,
+    data: Vec<u8>,
+    slot: Slot,
+    write_version: u64,
+) -> SubscribeUpdate {
+    SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: pubkey.to_bytes().to_vec(),
+                lamports: 1_000_000,
+                owner: owner.to_bytes().to_vec(),
+                data,
+                write_version,
+                ..Default::default()
+            }),
+            slot,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+/// Serialized drift user account of `authority`
+pub(crate) fn user_account_data(authority: Pubkey) -> Vec<u8> {
+    let user = User {
+        authority,
+        ..Default::default()
+    };
+    [User::discriminator().as_slice(), bytemuck::bytes_of(&user)].concat()
+}
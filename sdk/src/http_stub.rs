@@ -0,0 +1,118 @@
+//! In-process HTTP and JSON-RPC servers for tests
+
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+/// HTTP request received by a stub server
+#[derive(Clone, Debug)]
+pub struct Request {
+    /// e.g. `GET /quote?amount=1 HTTP/1.1`
+    pub request_line: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Request target e.g. `/quote?amount=1`
+    pub fn path(&self) -> &str {
+        self.request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+    }
+
+    /// The body as JSON, `Value::Null` if it isn't JSON
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_default()
+    }
+}
+
+/// Serve HTTP requests on a local port
+///
+/// Each request is answered with the JSON body returned by `respond`, 404 if it returns None.
+/// Returns the server url.
+pub async fn serve<F>(respond: F) -> String
+where
+    F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let respond = Arc::new(respond);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let respond = Arc::clone(&respond);
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                let Some(request) = read_request(&mut stream).await else {
+                    return;
+                };
+                let response = match respond(&request) {
+                    Some(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string(),
+                };
+                let _ = stream.get_mut().write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    url
+}
+
+/// Serve JSON-RPC requests on a local port
+///
+/// Each request is answered with the result returned by `respond(method, params)`. Returns the
+/// server url.
+pub async fn serve_json_rpc<F>(respond: F) -> String
+where
+    F: Fn(&str, &Value) -> Value + Send + Sync + 'static,
+{
+    serve(move |request| {
+        let request = request.json();
+        let result = respond(
+            request["method"].as_str().unwrap_or_default(),
+            &request["params"],
+        );
+        Some(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }).to_string())
+    })
+    .await
+}
+
+/// Read the request line, headers and body of a single request, None if the client hung up
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut request_line = String::new();
+    if stream.read_line(&mut request_line).await.ok()? == 0 {
+        return None;
+    }
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if stream.read_line(&mut header).await.ok()? == 0 {
+            return None;
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().ok()?;
+            }
+        }
+    }
+    let mut body = vec![0_u8; content_length];
+    stream.read_exact(&mut body).await.ok()?;
+
+    Some(Request {
+        request_line: request_line.trim().to_string(),
+        body,
+    })
+}
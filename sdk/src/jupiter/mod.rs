@@ -1,5 +1,6 @@
 use std::{collections::HashMap, str::FromStr};
 
+use drift::instructions::SwapReduceOnly;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig};
@@ -14,10 +15,14 @@ use solana_sdk::{
     transaction::VersionedTransaction,
 };
 
-use crate::{jupiter::serde_helpers::field_as_string, types::SdkResult, SdkError};
+use crate::{
+    jupiter::serde_helpers::field_as_string,
+    types::{SdkResult, TxParams},
+    SdkError,
+};
 
 use self::{
-    swap::{SwapInstructionsResponse, SwapInstructionsResponseInternal, SwapRequest, SwapResponse},
+    swap::{SwapInstructionsResponseInternal, SwapRequest, SwapResponse},
     transaction_config::TransactionConfig,
 };
 
@@ -25,6 +30,8 @@ mod serde_helpers;
 mod swap;
 mod transaction_config;
 
+pub use swap::SwapInstructionsResponse;
+
 #[derive(Serialize, Deserialize, Default, PartialEq, Clone, Debug)]
 pub enum SwapMode {
     #[default]
@@ -134,6 +141,39 @@ pub struct QuoteResponse {
     pub time_taken: f64,
 }
 
+impl QuoteResponse {
+    /// Maximum amount of the input token the route may use
+    ///
+    /// The quoted input for `SwapMode::ExactIn`, the slippage adjusted input for
+    /// `SwapMode::ExactOut`
+    pub fn max_in_amount(&self) -> u64 {
+        match self.swap_mode {
+            SwapMode::ExactIn => self.in_amount,
+            SwapMode::ExactOut => self.other_amount_threshold,
+        }
+    }
+}
+
+/// Params of a spot market swap routed by Jupiter, see `DriftClient::jupiter_swap`
+#[derive(Default)]
+pub struct JupiterSwapParams {
+    /// spot market swapped from
+    pub in_market_index: u16,
+    /// spot market swapped to
+    pub out_market_index: u16,
+    /// input token amount for `SwapMode::ExactIn`, output token amount for `SwapMode::ExactOut`
+    pub amount: u64,
+    /// Allowed slippage in basis points
+    pub slippage_bps: u16,
+    pub swap_mode: SwapMode,
+    /// only allow the swap to reduce the in market deposit or the out market borrow
+    pub reduce_only: Option<SwapReduceOnly>,
+    /// route of the swap, quoted from `amount` and `slippage_bps` if not set
+    pub quote: Option<QuoteResponse>,
+    /// compute unit limit and price of the tx
+    pub tx_params: TxParams,
+}
+
 pub struct JupiterClient<'a> {
     url: String,
     rpc_client: &'a RpcClient,
@@ -305,6 +345,19 @@ impl<'a> JupiterClient<'a> {
         Ok((tx, lookup_tables))
     }
 
+    /// Fetch the lookup tables at `addresses`, skipping any that don't exist
+    pub async fn get_lookup_tables(
+        &self,
+        addresses: &[Pubkey],
+    ) -> SdkResult<Vec<AddressLookupTableAccount>> {
+        let tables = futures_util::future::try_join_all(
+            addresses.iter().map(|key| self.get_lookup_table(*key)),
+        )
+        .await?;
+
+        Ok(tables.into_iter().flatten().collect())
+    }
+
     async fn get_lookup_table(
         &self,
         account_key: Pubkey,
@@ -377,19 +430,22 @@ impl<'a> JupiterClient<'a> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
     use solana_client::nonblocking::rpc_client::RpcClient;
     use solana_sdk::pubkey;
     use solana_sdk::pubkey::Pubkey;
 
+    use crate::http_stub;
     use crate::jupiter::JupiterClient;
     use crate::types::SdkResult;
 
-    use super::QuoteResponse;
+    use super::{QuoteResponse, SwapMode};
 
-    const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
-    const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
-    const TEST_WALLET: Pubkey = pubkey!("2AQdpHJ2JpcEgPiATUXjQxA8QmafFegfQwSLWSprPicm");
+    pub(crate) const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+    pub(crate) const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+    pub(crate) const TEST_WALLET: Pubkey = pubkey!("2AQdpHJ2JpcEgPiATUXjQxA8QmafFegfQwSLWSprPicm");
 
     async fn request_get_quote(client: &JupiterClient<'_>) -> SdkResult<QuoteResponse> {
         let quote_response = client
@@ -408,6 +464,157 @@ mod tests {
         quote_response
     }
 
+    pub(crate) const JUPITER_PROGRAM: Pubkey =
+        pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+    /// lookup tables of the recorded swap ixs, the accounts of the swap ix split between them
+    pub(crate) const JUPITER_LOOKUP_TABLES: [Pubkey; 2] = [
+        pubkey!("yr1haxJmGWhXXnyUUN6ZFYicUKrtU6cWbCjYAiQCTyE"),
+        pubkey!("EottXojYKqY8VDiFEehP21aiR3AAgGvyj65KDhXVS5Xf"),
+    ];
+    const LOOKUP_TABLE_FIXTURES: [&str; 2] = [
+        include_str!("fixtures/yr1haxJmGWhXXnyUUN6ZFYicUKrtU6cWbCjYAiQCTyE.json"),
+        include_str!("fixtures/EottXojYKqY8VDiFEehP21aiR3AAgGvyj65KDhXVS5Xf.json"),
+    ];
+
+    /// Answer a JSON-RPC `getAccountInfo` request with the recorded lookup table fixtures
+    fn get_account_info_response(body: &[u8]) -> Option<String> {
+        let request: serde_json::Value = serde_json::from_slice(body).ok()?;
+        if request["method"] != "getAccountInfo" {
+            return None;
+        }
+        let account = LOOKUP_TABLE_FIXTURES
+            .iter()
+            .map(|fixture| serde_json::from_str::<serde_json::Value>(fixture).unwrap())
+            .find(|fixture| fixture["pubkey"] == request["params"][0])
+            .map(|fixture| fixture["account"].clone())
+            .unwrap_or_default();
+
+        Some(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "result": { "context": { "slot": 286543120 }, "value": account },
+                "id": request["id"],
+            })
+            .to_string(),
+        )
+    }
+
+    /// Serve the recorded Jupiter API responses in `fixtures/` on a local port, along with
+    /// `getAccountInfo` of the recorded lookup tables for a `RpcClient` at the same url
+    ///
+    /// Returns the server url and the request line and body of each request received
+    pub(crate) async fn mock_jupiter_api() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        let url = http_stub::serve(move |request| {
+            received.lock().unwrap().push((
+                request.request_line.clone(),
+                String::from_utf8(request.body.clone()).unwrap(),
+            ));
+            let path = request.path();
+            if path.starts_with("/quote") {
+                Some(include_str!("fixtures/quote_exact_out.json").to_string())
+            } else if path.starts_with("/swap-instructions") {
+                Some(include_str!("fixtures/swap_instructions.json").to_string())
+            } else if path == "/" {
+                get_account_info_response(&request.body)
+            } else {
+                None
+            }
+        })
+        .await;
+
+        (url, requests)
+    }
+
+    async fn request_exact_out_quote(client: &JupiterClient<'_>) -> SdkResult<QuoteResponse> {
+        client
+            .get_quote(
+                USDC_MINT,
+                NATIVE_MINT,
+                10_000_000,
+                None,
+                50,
+                Some(SwapMode::ExactOut),
+                None,
+                None,
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn get_quote_exact_out_recorded() {
+        let (url, requests) = mock_jupiter_api().await;
+        let rpc_client = RpcClient::new("".to_string());
+        let jupiter_client = JupiterClient::new(&rpc_client, Some(url));
+
+        let quote = request_exact_out_quote(&jupiter_client)
+            .await
+            .expect("quote");
+
+        assert_eq!(quote.swap_mode, SwapMode::ExactOut);
+        assert_eq!(quote.input_mint, USDC_MINT);
+        assert_eq!(quote.output_mint, NATIVE_MINT);
+        assert_eq!(quote.out_amount, 10_000_000);
+        // exact out withdraws up to the slippage adjusted input
+        assert_eq!(quote.max_in_amount(), 1_459_572);
+        assert_eq!(
+            QuoteResponse {
+                swap_mode: SwapMode::ExactIn,
+                ..quote
+            }
+            .max_in_amount(),
+            1_452_310
+        );
+
+        let requests = requests.lock().unwrap();
+        let (request_line, _) = &requests[0];
+        assert!(request_line.starts_with("GET /quote?"));
+        for param in ["amount=10000000", "swapMode=ExactOut", "slippageBps=50"] {
+            assert!(
+                request_line.contains(param),
+                "{param} not in {request_line}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn get_swap_instructions_recorded() {
+        let (url, requests) = mock_jupiter_api().await;
+        let rpc_client = RpcClient::new("".to_string());
+        let jupiter_client = JupiterClient::new(&rpc_client, Some(url));
+
+        let quote = request_exact_out_quote(&jupiter_client)
+            .await
+            .expect("quote");
+        let swap_instructions = jupiter_client
+            .get_swap_instructions(quote, TEST_WALLET)
+            .await
+            .expect("swap instructions");
+
+        assert_eq!(
+            swap_instructions.swap_instruction.program_id,
+            JUPITER_PROGRAM
+        );
+        assert!(swap_instructions
+            .swap_instruction
+            .accounts
+            .iter()
+            .any(|a| a.pubkey == TEST_WALLET && a.is_signer));
+        assert_eq!(swap_instructions.compute_budget_instructions.len(), 1);
+        assert_eq!(swap_instructions.setup_instructions.len(), 1);
+        assert!(swap_instructions.cleanup_instruction.is_some());
+        assert_eq!(swap_instructions.address_lookup_table_addresses.len(), 2);
+
+        let requests = requests.lock().unwrap();
+        let (request_line, body) = &requests[1];
+        assert!(request_line.starts_with("POST /swap-instructions"));
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["userPublicKey"], TEST_WALLET.to_string());
+        assert_eq!(body["quoteResponse"]["swapMode"], "ExactOut");
+    }
+
     #[tokio::test]
     async fn test_get_quote() {
         let rpc_client = RpcClient::new("".to_string());
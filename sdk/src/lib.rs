@@ -8,7 +8,7 @@ use log::{debug, warn};
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::{
     nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
-    rpc_config::RpcAccountInfoConfig,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
 };
 use solana_sdk::{
     account::Account,
@@ -44,12 +44,17 @@ pub mod drift_client_config;
 pub mod error;
 pub mod event_emitter;
 pub mod events;
+pub mod fixture_account_provider;
+pub mod grpc;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod http_stub;
 pub mod jupiter;
 pub mod marketmap;
 pub mod math;
 pub mod memcmp;
 pub mod oraclemap;
 pub mod priority_fee;
+pub mod program_account_subscriber;
 pub mod slot_subscriber;
 pub mod transaction_builder;
 pub mod tx;
@@ -63,6 +68,11 @@ pub mod utils;
 pub mod websocket_account_subscriber;
 pub mod websocket_program_account_subscriber;
 
+pub use fixture_account_provider::{
+    FixtureAccountProvider, FixtureFormat, RecordingAccountProvider,
+};
+pub use grpc::GrpcAccountProvider;
+
 type AccountCache = Arc<RwLock<FnvHashMap<Pubkey, Receiver<(Account, Slot)>>>>;
 
 /// Provides solana Account fetching API
@@ -74,6 +84,16 @@ pub trait AccountProvider: 'static + Sized + Send + Sync {
     fn endpoint(&self) -> String;
     /// return configured commitment level of the provider
     fn commitment_config(&self) -> CommitmentConfig;
+    /// Return the drift program accounts matching `config` and the slot they were fetched at
+    ///
+    /// Fetches from RPC at the provider's endpoint by default
+    fn get_program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>> {
+        let rpc_client = RpcClient::new_with_commitment(self.endpoint(), self.commitment_config());
+        async move { utils::get_program_accounts(&rpc_client, config).await }.boxed()
+    }
 }
 
 /// Account provider that always fetches from RPC
@@ -111,6 +131,12 @@ impl AccountProvider for RpcAccountProvider {
     fn commitment_config(&self) -> CommitmentConfig {
         self.client.commitment()
     }
+    fn get_program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>> {
+        utils::get_program_accounts(&self.client, config).boxed()
+    }
 }
 
 /// Account provider using websocket subscriptions to receive and cache account updates
@@ -277,6 +303,12 @@ impl AccountProvider for WsAccountProvider {
     fn commitment_config(&self) -> CommitmentConfig {
         self.rpc_client.commitment()
     }
+    fn get_program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>> {
+        utils::get_program_accounts(&self.rpc_client, config).boxed()
+    }
 }
 
 /// Drift wallet
@@ -7,24 +7,23 @@ use drift::state::oracle::OracleSource;
 use drift::state::perp_market::PerpMarket;
 use drift::state::spot_market::SpotMarket;
 use drift::state::user::MarketType;
-use serde_json::json;
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
-use solana_client::rpc_request::RpcRequest;
-use solana_client::rpc_response::{OptionalContext, RpcKeyedAccount};
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 use tokio::sync::RwLock;
 
 use crate::{
     event_emitter::EventEmitter,
+    grpc::{GrpcConnectionOpts, GrpcProgramAccountSubscriber},
     memcmp::get_market_filter,
-    utils::{decode, get_ws_url},
+    program_account_subscriber::ProgramAccountSubscriber,
+    utils::get_ws_url,
     websocket_program_account_subscriber::{
         ProgramAccountUpdate, WebsocketProgramAccountOptions, WebsocketProgramAccountSubscriber,
     },
-    DataAndSlot, SdkResult,
+    AccountProvider, DataAndSlot, RpcAccountProvider, SdkError, SdkResult,
 };
 
 pub trait Market {
@@ -59,12 +58,12 @@ impl Market for SpotMarket {
 
 pub struct MarketMap<T: AccountDeserialize> {
     subscribed: AtomicBool,
-    subscription: RwLock<WebsocketProgramAccountSubscriber>,
+    subscription: RwLock<ProgramAccountSubscriber>,
     pub marketmap: Arc<DashMap<u16, DataAndSlot<T>>>,
     sync_lock: Option<Mutex<()>>,
     latest_slot: Arc<AtomicU64>,
     commitment: CommitmentConfig,
-    rpc: RpcClient,
+    rpc: RpcAccountProvider,
     synced: bool,
 }
 
@@ -75,26 +74,58 @@ where
     pub const SUBSCRIPTION_ID: &'static str = "marketmap";
 
     pub fn new(commitment: CommitmentConfig, endpoint: &str, sync: bool) -> Self {
-        let filters = vec![get_market_filter(T::MARKET_TYPE)];
-        let options = WebsocketProgramAccountOptions {
-            filters,
-            commitment,
-            encoding: UiAccountEncoding::Base64,
-        };
-        let event_emitter = EventEmitter::new();
-
         let url = get_ws_url(endpoint).unwrap();
 
         let subscription = WebsocketProgramAccountSubscriber::new(
             MarketMap::<T>::SUBSCRIPTION_ID,
             url,
-            options,
-            event_emitter,
+            Self::options(commitment),
+            EventEmitter::new(),
+        );
+
+        Self::with_subscription(commitment, endpoint, sync, subscription.into())
+    }
+
+    /// Create a new MarketMap streaming market account updates over Yellowstone gRPC
+    ///
+    /// `endpoint` RPC endpoint used for syncing
+    pub fn new_grpc(
+        commitment: CommitmentConfig,
+        endpoint: &str,
+        grpc: GrpcConnectionOpts,
+        sync: bool,
+    ) -> Self {
+        let subscription = GrpcProgramAccountSubscriber::new(
+            MarketMap::<T>::SUBSCRIPTION_ID,
+            grpc,
+            Arc::new(RpcClient::new_with_commitment(
+                endpoint.to_string(),
+                commitment,
+            )),
+            Self::options(commitment),
+            EventEmitter::new(),
         );
 
+        Self::with_subscription(commitment, endpoint, sync, subscription.into())
+    }
+
+    fn options(commitment: CommitmentConfig) -> WebsocketProgramAccountOptions {
+        WebsocketProgramAccountOptions {
+            filters: vec![get_market_filter(T::MARKET_TYPE)],
+            commitment,
+            encoding: UiAccountEncoding::Base64,
+        }
+    }
+
+    fn with_subscription(
+        commitment: CommitmentConfig,
+        endpoint: &str,
+        sync: bool,
+        subscription: ProgramAccountSubscriber,
+    ) -> Self {
         let marketmap = Arc::new(DashMap::new());
 
-        let rpc = RpcClient::new_with_commitment(endpoint.to_string(), commitment);
+        let rpc = RpcAccountProvider::with_commitment(endpoint, commitment);
 
         let sync_lock = if sync { Some(Mutex::new(())) } else { None };
 
@@ -124,7 +155,7 @@ where
             let marketmap = self.marketmap.clone();
             let latest_slot = self.latest_slot.clone();
 
-            subscription_writer.event_emitter.subscribe(
+            subscription_writer.event_emitter().subscribe(
                 MarketMap::<T>::SUBSCRIPTION_ID,
                 move |event| {
                     if let Some(update) = event.as_any().downcast_ref::<ProgramAccountUpdate<T>>() {
@@ -188,8 +219,13 @@ where
             .map(|market| market.clone())
     }
 
-    #[allow(clippy::await_holding_lock)]
     pub(crate) async fn sync(&self) -> SdkResult<()> {
+        self.sync_with(&self.rpc).await
+    }
+
+    /// Load all markets with `provider`
+    #[allow(clippy::await_holding_lock)]
+    pub(crate) async fn sync_with<P: AccountProvider>(&self, provider: &P) -> SdkResult<()> {
         if self.synced {
             return Ok(());
         }
@@ -202,7 +238,7 @@ where
         };
 
         let subscription_reader = self.subscription.read().await;
-        let options = subscription_reader.options.clone();
+        let options = subscription_reader.options().clone();
         drop(subscription_reader);
 
         let account_config = RpcAccountInfoConfig {
@@ -217,26 +253,14 @@ where
             with_context: Some(true),
         };
 
-        let response = self
-            .rpc
-            .send::<OptionalContext<Vec<RpcKeyedAccount>>>(
-                RpcRequest::GetProgramAccounts,
-                json!([drift::id().to_string(), gpa_config]),
-            )
-            .await?;
-
-        if let OptionalContext::Context(accounts) = response {
-            for account in accounts.value {
-                let slot = accounts.context.slot;
-                let market_data = account.account.data;
-                let data = decode::<T>(market_data)?;
-                self.marketmap
-                    .insert(data.market_index(), DataAndSlot { data, slot });
-            }
-
-            self.latest_slot
-                .store(accounts.context.slot, Ordering::Relaxed);
+        let (slot, accounts) = provider.get_program_accounts(gpa_config).await?;
+        for (_, account) in accounts {
+            let data = T::try_deserialize(&mut account.data.as_slice())
+                .map_err(|err| SdkError::Anchor(Box::new(err)))?;
+            self.marketmap
+                .insert(data.market_index(), DataAndSlot { data, slot });
         }
+        self.latest_slot.store(slot, Ordering::Relaxed);
 
         drop(lock);
         Ok(())
@@ -250,64 +274,47 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixture_account_provider::drift_program_fixtures;
     use crate::marketmap::MarketMap;
+    use crate::Context;
     use drift::state::perp_market::PerpMarket;
     use drift::state::spot_market::SpotMarket;
     use solana_sdk::commitment_config::CommitmentConfig;
-    use solana_sdk::commitment_config::CommitmentLevel;
 
     #[tokio::test]
     async fn test_marketmap_perp() {
-        let endpoint = "https://api.devnet.solana.com".to_string();
-        let commitment = CommitmentConfig {
-            commitment: CommitmentLevel::Processed,
-        };
-
-        let marketmap = MarketMap::<PerpMarket>::new(commitment, &endpoint, true);
-        marketmap.subscribe().await.unwrap();
-
-        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-
-        dbg!(marketmap.size());
-        if endpoint.contains("devnet") {
-            let keys = marketmap.keys();
-            eprintln!("Market indexes: {:?}", keys);
-            assert_eq!(marketmap.size(), 25);
-        } else {
-        }
-
-        dbg!(marketmap.get_latest_slot());
-
-        marketmap.unsubscribe().await.unwrap();
-
-        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-
-        assert_eq!(marketmap.size(), 0);
-        assert_eq!(marketmap.subscribed.load(Ordering::Relaxed), false);
+        let perp_markets = [0, 1, 2].map(|market_index| PerpMarket {
+            market_index,
+            ..Default::default()
+        });
+        let provider =
+            drift_program_fixtures(Context::MainNet, &perp_markets, &[SpotMarket::default()]).await;
+
+        let marketmap =
+            MarketMap::<PerpMarket>::new(CommitmentConfig::confirmed(), &provider.endpoint(), true);
+        marketmap.sync_with(&provider).await.unwrap();
+
+        let mut keys = marketmap.keys();
+        keys.sort();
+        assert_eq!(keys, vec![0, 1, 2]);
+        assert_eq!(marketmap.get(&1).unwrap().data.market_index, 1);
+        assert!(!marketmap.subscribed.load(Ordering::Relaxed));
     }
 
     #[tokio::test]
     async fn test_marketmap_spot() {
-        let endpoint = "https://api.devnet.solana.com".to_string();
-        let commitment = CommitmentConfig {
-            commitment: CommitmentLevel::Processed,
-        };
-
-        let marketmap = MarketMap::<SpotMarket>::new(commitment, &endpoint, true);
-        marketmap.subscribe().await.unwrap();
-
-        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-
-        dbg!(marketmap.size());
-        assert!(marketmap.size() == 13);
-
-        dbg!(marketmap.get_latest_slot());
-
-        marketmap.unsubscribe().await.unwrap();
-
-        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-
-        assert_eq!(marketmap.size(), 0);
-        assert_eq!(marketmap.subscribed.load(Ordering::Relaxed), false);
+        let spot_markets = [0, 1].map(|market_index| SpotMarket {
+            market_index,
+            ..Default::default()
+        });
+        let provider =
+            drift_program_fixtures(Context::MainNet, &[PerpMarket::default()], &spot_markets).await;
+
+        let marketmap =
+            MarketMap::<SpotMarket>::new(CommitmentConfig::confirmed(), &provider.endpoint(), true);
+        marketmap.sync_with(&provider).await.unwrap();
+
+        assert_eq!(marketmap.size(), 2);
+        assert!(marketmap.contains(&0) && marketmap.contains(&1));
     }
 }
@@ -2,106 +2,71 @@ use anchor_lang::prelude::AccountInfo;
 use drift::{
     ids::{pyth_program, switchboard_program},
     instructions::optional_accounts::AccountMaps,
-    state::{oracle::OracleSource, perp_market::PerpMarket, spot_market::SpotMarket, user::User},
+    state::{
+        oracle::OracleSource,
+        oracle_map::OracleMap,
+        perp_market::PerpMarket,
+        perp_market_map::{MarketSet, PerpMarketMap},
+        spot_market::SpotMarket,
+        spot_market_map::SpotMarketMap,
+        state::OracleGuardRails,
+        user::User,
+    },
 };
-use fnv::FnvHashSet;
+use fnv::FnvHashMap;
 use solana_sdk::{account::Account, pubkey::Pubkey};
 
 use crate::{
     constants, drift_client::DriftClient, utils::zero_account_to_bytes, AccountProvider, MarketId,
-    SdkResult,
+    SdkError, SdkResult,
 };
 
-/// Builds an AccountMap of relevant spot, perp, and oracle accounts from rpc
-#[derive(Default)]
-pub(crate) struct AccountMapBuilder {
-    /// placeholder account values populated with real market & oracle account data
-    accounts: Vec<(Pubkey, Account)>,
-}
+/// Gathers the spot, perp, and oracle accounts relevant to a user from the client's maps
+pub(crate) struct AccountMapBuilder;
 
 impl AccountMapBuilder {
-    /// Constructs the account map + drift state account
+    /// Collect the market and oracle accounts `user` has positions in, the USDC spot market is
+    /// always included
+    ///
+    /// Load drift's `AccountMaps` from the result with `account_infos()` then `load()`
     pub fn build<T: AccountProvider>(
-        &mut self,
         client: &DriftClient<T>,
         user: &User,
-    ) -> SdkResult<AccountMaps> {
-        let mut oracles = FnvHashSet::<Pubkey>::default();
+    ) -> SdkResult<AccountMapData> {
+        let mut oracles = FnvHashMap::<Pubkey, OracleSource>::default();
         let mut spot_markets = Vec::<SpotMarket>::with_capacity(user.spot_positions.len() + 1); // +1 incase missing USDC position
         let mut perp_markets = Vec::<PerpMarket>::with_capacity(user.perp_positions.len());
 
         for p in user.spot_positions.iter().filter(|p| !p.is_available()) {
             let market = client
                 .get_spot_market_account(p.market_index)
-                .expect("spot market");
-            oracles.insert(market.oracle);
-            self.accounts.push((market.pubkey, Account::default()));
+                .ok_or(SdkError::MarketNotFound(p.market_index))?;
+            oracles.insert(market.oracle, market.oracle_source);
             spot_markets.push(market);
         }
 
         let quote_market = client
             .get_spot_market_account(MarketId::QUOTE_SPOT.index)
-            .expect("spot market");
-        if oracles.insert(quote_market.oracle) {
+            .ok_or(SdkError::MarketNotFound(MarketId::QUOTE_SPOT.index))?;
+        if oracles
+            .insert(quote_market.oracle, quote_market.oracle_source)
+            .is_none()
+        {
             // ensure always include the spot USDC market
-            self.accounts
-                .push((quote_market.pubkey, Account::default()));
             spot_markets.push(quote_market);
         }
 
         for p in user.perp_positions.iter().filter(|p| !p.is_available()) {
             let market = client
                 .get_perp_market_account(p.market_index)
-                .expect("perp market");
-            oracles.insert(market.amm.oracle);
-            self.accounts.push((market.pubkey, Account::default()));
+                .ok_or(SdkError::MarketNotFound(p.market_index))?;
+            oracles.insert(market.amm.oracle, market.amm.oracle_source);
             perp_markets.push(market);
         }
 
-        self.accounts
-            .extend(oracles.iter().map(|x| (*x, Default::default())));
-        let mut accounts_iter = self.accounts.iter_mut();
-
-        let mut spot_accounts = Vec::<AccountInfo>::with_capacity(spot_markets.len());
-        for market in spot_markets.iter() {
-            let (pubkey, account) = accounts_iter.next().unwrap();
-            account.data = zero_account_to_bytes(*market);
-            spot_accounts.push(AccountInfo::new(
-                pubkey,
-                false,
-                false,
-                &mut account.lamports,
-                &mut account.data[..],
-                &constants::PROGRAM_ID,
-                false,
-                0,
-            ));
-        }
-
-        let mut perp_accounts = Vec::<AccountInfo>::with_capacity(perp_markets.len());
-        for market in perp_markets.iter() {
-            let (pubkey, account) = accounts_iter.next().unwrap();
-            account.data = zero_account_to_bytes(*market);
-            perp_accounts.push(AccountInfo::new(
-                pubkey,
-                false,
-                false,
-                &mut account.lamports,
-                &mut account.data[..],
-                &constants::PROGRAM_ID,
-                false,
-                0,
-            ));
-        }
-
-        let mut oracle_accounts = Vec::<AccountInfo>::with_capacity(oracles.len());
-        for oracle_key in oracles.iter() {
-            let oracle = client
-                .backend
-                .oracle_map
-                .get(oracle_key)
-                .expect("oracle exists");
-            let owner = match oracle.source {
+        let mut oracle_accounts = Vec::with_capacity(oracles.len());
+        for (oracle_key, source) in oracles.iter() {
+            let owner = match source {
                 OracleSource::Pyth
                 | OracleSource::Pyth1K
                 | OracleSource::Pyth1M
@@ -109,52 +74,281 @@ impl AccountMapBuilder {
                 | OracleSource::PythPull
                 | OracleSource::Pyth1KPull
                 | OracleSource::Pyth1MPull
-                | OracleSource::PythStableCoinPull => &pyth_program::ID,
-                OracleSource::Switchboard => &switchboard_program::ID,
-                OracleSource::QuoteAsset => &constants::DEFAULT_PUBKEY,
-                OracleSource::Prelaunch => &drift::ID,
+                | OracleSource::PythStableCoinPull => pyth_program::ID,
+                OracleSource::Switchboard => switchboard_program::ID,
+                // drift's oracle map prices the quote asset without an account
+                OracleSource::QuoteAsset => continue,
+                OracleSource::Prelaunch => drift::ID,
             };
-            let (pubkey, account) = accounts_iter.next().unwrap();
-            account.data.clone_from(&oracle.raw);
-            oracle_accounts.push(AccountInfo::new(
+            let oracle = client
+                .backend
+                .oracle_map
+                .get(oracle_key)
+                .ok_or(SdkError::InvalidOracle)?;
+            oracle_accounts.push((
+                *oracle_key,
+                Account {
+                    data: oracle.raw,
+                    owner,
+                    ..Default::default()
+                },
+            ));
+        }
+
+        let perp_slot = client.backend.perp_market_map.get_latest_slot();
+        let spot_slot = client.backend.spot_market_map.get_latest_slot();
+        let oracle_slot = client.backend.oracle_map.get_latest_slot();
+        let slot = std::cmp::max(oracle_slot, std::cmp::max(perp_slot, spot_slot));
+
+        let oracle_guard_rails = client
+            .backend
+            .state_account
+            .read()
+            .expect("state account lock")
+            .oracle_guard_rails;
+
+        Ok(AccountMapData::new(
+            &perp_markets,
+            &spot_markets,
+            oracle_accounts,
+            slot,
+            oracle_guard_rails,
+        ))
+    }
+}
+
+/// Owned market and oracle accounts, drift's `AccountMaps` borrow from them
+pub(crate) struct AccountMapData {
+    perp_markets: Vec<(Pubkey, Account)>,
+    spot_markets: Vec<(Pubkey, Account)>,
+    oracles: Vec<(Pubkey, Account)>,
+    slot: u64,
+    oracle_guard_rails: OracleGuardRails,
+}
+
+impl AccountMapData {
+    pub fn new(
+        perp_markets: &[PerpMarket],
+        spot_markets: &[SpotMarket],
+        oracles: Vec<(Pubkey, Account)>,
+        slot: u64,
+        oracle_guard_rails: OracleGuardRails,
+    ) -> Self {
+        let program_account = |data: Vec<u8>| Account {
+            data,
+            owner: constants::PROGRAM_ID,
+            ..Default::default()
+        };
+        Self {
+            perp_markets: perp_markets
+                .iter()
+                .map(|market| {
+                    (
+                        market.pubkey,
+                        program_account(zero_account_to_bytes(*market)),
+                    )
+                })
+                .collect(),
+            spot_markets: spot_markets
+                .iter()
+                .map(|market| {
+                    (
+                        market.pubkey,
+                        program_account(zero_account_to_bytes(*market)),
+                    )
+                })
+                .collect(),
+            oracles,
+            slot,
+            oracle_guard_rails,
+        }
+    }
+
+    /// `AccountInfo`s over the account data
+    pub fn account_infos(&mut self) -> AccountMapInfos<'_> {
+        AccountMapInfos {
+            perp_markets: account_infos(&mut self.perp_markets),
+            spot_markets: account_infos(&mut self.spot_markets),
+            oracles: account_infos(&mut self.oracles),
+            slot: self.slot,
+            oracle_guard_rails: self.oracle_guard_rails,
+        }
+    }
+}
+
+/// `AccountInfo`s of an `AccountMapData`
+pub(crate) struct AccountMapInfos<'a> {
+    perp_markets: Vec<AccountInfo<'a>>,
+    spot_markets: Vec<AccountInfo<'a>>,
+    oracles: Vec<AccountInfo<'a>>,
+    slot: u64,
+    oracle_guard_rails: OracleGuardRails,
+}
+
+impl<'a> AccountMapInfos<'a> {
+    /// Load drift's perp market, spot market, and oracle maps
+    pub fn load(&'a self) -> SdkResult<AccountMaps<'a>> {
+        let perp_market_map = PerpMarketMap::load(
+            &MarketSet::default(),
+            &mut self.perp_markets.iter().peekable(),
+        )
+        .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
+
+        let spot_market_map = SpotMarketMap::load(
+            &MarketSet::default(),
+            &mut self.spot_markets.iter().peekable(),
+        )
+        .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
+
+        let oracle_map = OracleMap::load(
+            &mut self.oracles.iter().peekable(),
+            self.slot,
+            Some(self.oracle_guard_rails),
+        )
+        .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
+
+        Ok(AccountMaps {
+            spot_market_map,
+            perp_market_map,
+            oracle_map,
+        })
+    }
+}
+
+fn account_infos(accounts: &mut [(Pubkey, Account)]) -> Vec<AccountInfo<'_>> {
+    accounts
+        .iter_mut()
+        .map(|(pubkey, account)| {
+            AccountInfo::new(
                 pubkey,
                 false,
                 false,
                 &mut account.lamports,
                 &mut account.data[..],
-                owner,
+                &account.owner,
                 false,
                 0,
-            ));
-        }
+            )
+        })
+        .collect()
+}
 
-        let perp_slot = client.backend.perp_market_map.get_latest_slot();
-        let spot_slot = client.backend.spot_market_map.get_latest_slot();
-        let oracle_slot = client.backend.oracle_map.get_latest_slot();
-        let _slot = std::cmp::max(oracle_slot, std::cmp::max(perp_slot, spot_slot));
-
-        todo!()
-
-        // let perp_market_map =
-        //     PerpMarketMap::load(&MarketSet::default(), &mut perp_accounts.iter().peekable())
-        //         .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
-
-        // let spot_market_map =
-        //     SpotMarketMap::load(&MarketSet::default(), &mut spot_accounts.iter().peekable())
-        //         .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
-
-        // let state_account = client.backend.state_account.read().unwrap();
-        // let oracle_map = OracleMap::load(
-        //     &mut oracle_accounts.iter().peekable(),
-        //     slot,
-        //     Some(state_account.oracle_guard_rails),
-        // )
-        // .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
-
-        // Ok(AccountMaps {
-        //     spot_market_map,
-        //     perp_market_map,
-        //     oracle_map,
-        // })
-    }
+/// Oracle of `margin_test_client`'s SOL perp market
+#[cfg(test)]
+pub(crate) const SOL_ORACLE: Pubkey =
+    solana_sdk::pubkey!("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix");
+
+/// Client serving the USDC spot market and a SOL perp market (10% initial, 5% maintenance
+/// margin) from fixtures, SOL is priced at `sol_price` (PRICE_PRECISION) by a prelaunch oracle
+#[cfg(test)]
+pub(crate) async fn margin_test_client(
+    sol_price: i64,
+) -> DriftClient<crate::fixture_account_provider::FixtureAccountProvider> {
+    use drift::{
+        math::constants::{
+            AMM_RESERVE_PRECISION, PEG_PRECISION, SPOT_BALANCE_PRECISION,
+            SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+        },
+        state::{
+            oracle::{HistoricalOracleData, PrelaunchOracle},
+            perp_market::{MarketStatus, AMM},
+        },
+    };
+
+    use crate::{
+        constants::{derive_perp_market_account, derive_spot_market_account},
+        fixture_account_provider::drift_program_fixtures,
+        oraclemap::Oracle,
+        Context, Wallet,
+    };
+
+    let usdc_spot_market = SpotMarket {
+        pubkey: derive_spot_market_account(0),
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        initial_liability_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_liability_weight: SPOT_WEIGHT_PRECISION,
+        deposit_balance: 100_000 * SPOT_BALANCE_PRECISION,
+        historical_oracle_data: HistoricalOracleData::default_quote_oracle(),
+        ..Default::default()
+    };
+    let sol_perp_market = PerpMarket {
+        pubkey: derive_perp_market_account(0),
+        amm: AMM {
+            base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            sqrt_k: 100 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 100 * PEG_PRECISION,
+            order_step_size: 10_000_000,
+            oracle: SOL_ORACLE,
+            oracle_source: OracleSource::Prelaunch,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price: sol_price,
+                last_oracle_price_twap: sol_price,
+                last_oracle_price_twap_5min: sol_price,
+                ..Default::default()
+            },
+            ..AMM::default()
+        },
+        market_index: 0,
+        margin_ratio_initial: 1_000,
+        margin_ratio_maintenance: 500,
+        unrealized_pnl_maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        status: MarketStatus::Active,
+        ..Default::default()
+    };
+    let provider =
+        drift_program_fixtures(Context::MainNet, &[sol_perp_market], &[usdc_spot_market]).await;
+    let client = DriftClient::new(
+        Context::MainNet,
+        provider,
+        &Wallet::read_only(Pubkey::new_unique()),
+    )
+    .await
+    .unwrap();
+
+    let sol_oracle = zero_account_to_bytes(PrelaunchOracle {
+        price: sol_price,
+        max_price: sol_price,
+        perp_market_index: 0,
+        ..Default::default()
+    });
+    client.backend.oracle_map.oraclemap.insert(
+        SOL_ORACLE,
+        Oracle::decode(SOL_ORACLE, OracleSource::Prelaunch, 0, &sol_oracle).unwrap(),
+    );
+
+    client
+}
+
+/// User with `usdc` deposited and `sol` SOL-PERP base (BASE_PRECISION) entered at `entry_price`
+/// (PRICE_PRECISION)
+#[cfg(test)]
+pub(crate) fn margin_test_user(usdc: u64, sol: i64, entry_price: i64) -> User {
+    use drift::{
+        math::constants::{BASE_PRECISION_I64, SPOT_BALANCE_PRECISION_U64},
+        state::user::{PerpPosition, SpotPosition},
+    };
+
+    let mut user = User::default();
+    user.spot_positions[0] = SpotPosition {
+        market_index: MarketId::QUOTE_SPOT.index,
+        scaled_balance: usdc * SPOT_BALANCE_PRECISION_U64,
+        ..Default::default()
+    };
+    // PRICE_PRECISION and QUOTE_PRECISION are both 1e6
+    user.perp_positions[0] = PerpPosition {
+        market_index: 0,
+        base_asset_amount: sol,
+        quote_asset_amount: -sol * entry_price / BASE_PRECISION_I64,
+        quote_entry_amount: -sol * entry_price / BASE_PRECISION_I64,
+        ..Default::default()
+    };
+
+    user
 }
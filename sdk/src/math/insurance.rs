@@ -0,0 +1,148 @@
+//! insurance fund staking helpers
+//!
+
+use drift::{
+    math::{helpers::on_the_hour_update, insurance::if_shares_to_vault_amount},
+    state::{insurance_fund_stake::InsuranceFundStake, spot_market::SpotMarket},
+};
+
+use crate::SdkResult;
+
+/// Calculate the token amount a staker's insurance fund shares are worth
+///
+/// `vault_balance` token balance of the market's insurance fund vault
+pub fn calculate_staker_value(
+    stake: &InsuranceFundStake,
+    spot_market: &SpotMarket,
+    vault_balance: u64,
+) -> SdkResult<u64> {
+    let if_shares = stake.checked_if_shares(spot_market)?;
+    let value = if_shares_to_vault_amount(
+        if_shares,
+        spot_market.insurance_fund.total_shares,
+        vault_balance,
+    )?;
+
+    Ok(value)
+}
+
+/// Calculate the fraction of the market's insurance fund owned by the staker, in [0, 1]
+pub fn calculate_staker_share(
+    stake: &InsuranceFundStake,
+    spot_market: &SpotMarket,
+) -> SdkResult<f64> {
+    let total_shares = spot_market.insurance_fund.total_shares;
+    if total_shares == 0 {
+        return Ok(0.0);
+    }
+    let if_shares = stake.checked_if_shares(spot_market)?;
+
+    Ok(if_shares as f64 / total_shares as f64)
+}
+
+/// Returns the unix timestamp the staker's pending unstake request can be removed at, None
+/// without a pending request
+pub fn unstake_available_ts(stake: &InsuranceFundStake, spot_market: &SpotMarket) -> Option<i64> {
+    if stake.last_withdraw_request_shares == 0 {
+        return None;
+    }
+
+    Some(stake.last_withdraw_request_ts + spot_market.insurance_fund.unstaking_period)
+}
+
+/// Returns the seconds until the market's revenue can next be settled to its insurance fund at
+/// unix timestamp `now`, 0 once it is due
+///
+/// Returns None if the market doesn't settle revenue to the insurance fund
+pub fn time_until_revenue_settle(spot_market: &SpotMarket, now: i64) -> SdkResult<Option<i64>> {
+    let insurance_fund = &spot_market.insurance_fund;
+    if insurance_fund.revenue_settle_period <= 0 {
+        return Ok(None);
+    }
+    let remaining = on_the_hour_update(
+        now,
+        insurance_fund.last_revenue_settle_ts,
+        insurance_fund.revenue_settle_period,
+    )?;
+
+    Ok(Some(remaining))
+}
+
+#[cfg(test)]
+mod tests {
+    use drift::state::spot_market::InsuranceFund;
+
+    use super::*;
+
+    const HOUR: i64 = 60 * 60;
+
+    fn market_with_shares(total_shares: u128) -> SpotMarket {
+        SpotMarket {
+            insurance_fund: InsuranceFund {
+                total_shares,
+                unstaking_period: 13 * 24 * HOUR,
+                revenue_settle_period: HOUR,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn stake_with_shares(if_shares: u128) -> InsuranceFundStake {
+        InsuranceFundStake {
+            if_shares,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn staker_value_and_share() {
+        let spot_market = market_with_shares(1_000);
+        let stake = stake_with_shares(250);
+
+        assert_eq!(
+            calculate_staker_value(&stake, &spot_market, 2_000_000).unwrap(),
+            500_000
+        );
+        assert_eq!(calculate_staker_share(&stake, &spot_market).unwrap(), 0.25);
+        assert_eq!(
+            calculate_staker_share(&stake_with_shares(0), &market_with_shares(0)).unwrap(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn unstake_request() {
+        let spot_market = market_with_shares(1_000);
+        let mut stake = stake_with_shares(250);
+        assert_eq!(unstake_available_ts(&stake, &spot_market), None);
+
+        stake.last_withdraw_request_shares = 100;
+        stake.last_withdraw_request_ts = 1_000;
+        assert_eq!(
+            unstake_available_ts(&stake, &spot_market),
+            Some(1_000 + 13 * 24 * HOUR)
+        );
+    }
+
+    #[test]
+    fn revenue_settle_period() {
+        let mut spot_market = market_with_shares(1_000);
+        spot_market.insurance_fund.last_revenue_settle_ts = 100 * HOUR;
+
+        assert_eq!(
+            time_until_revenue_settle(&spot_market, 100 * HOUR + 10).unwrap(),
+            Some(HOUR - 10)
+        );
+        assert_eq!(
+            time_until_revenue_settle(&spot_market, 101 * HOUR).unwrap(),
+            Some(0)
+        );
+
+        spot_market.insurance_fund.revenue_settle_period = 0;
+        assert_eq!(
+            time_until_revenue_settle(&spot_market, 101 * HOUR).unwrap(),
+            None
+        );
+    }
+}
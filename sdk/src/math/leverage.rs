@@ -9,8 +9,9 @@ use drift::state::margin_calculation::MarginContext;
 use drift::state::user::User;
 
 pub fn get_leverage<T: AccountProvider>(client: &DriftClient<T>, user: &User) -> SdkResult<u128> {
-    let mut accounts_builder = AccountMapBuilder::default();
-    let mut account_maps = accounts_builder.build(client, user)?;
+    let mut accounts = AccountMapBuilder::build(client, user)?;
+    let account_infos = accounts.account_infos();
+    let mut account_maps = account_infos.load()?;
 
     let AccountMaps {
         perp_market_map,
@@ -50,8 +51,9 @@ pub fn get_spot_asset_value<T: AccountProvider>(
     client: &DriftClient<T>,
     user: &User,
 ) -> SdkResult<i128> {
-    let mut accounts_builder = AccountMapBuilder::default();
-    let mut account_maps = accounts_builder.build(client, user)?;
+    let mut accounts = AccountMapBuilder::build(client, user)?;
+    let account_infos = accounts.account_infos();
+    let mut account_maps = account_infos.load()?;
 
     let AccountMaps {
         perp_market_map,
@@ -93,6 +95,28 @@ fn calculate_leverage(total_liability_value: u128, net_asset_value: i128) -> u12
     sign as u128 * (leverage * PRICE_PRECISION as f64) as u128
 }
 
+#[cfg(test)]
+mod tests {
+    use drift::math::constants::{BASE_PRECISION_I64, PRICE_PRECISION_I64, QUOTE_PRECISION_I128};
+
+    use super::*;
+    use crate::math::account_map_builder::{margin_test_client, margin_test_user};
+
+    #[tokio::test]
+    async fn leverage_and_spot_asset_value() {
+        let client = margin_test_client(100 * PRICE_PRECISION_I64).await;
+        // 1,000 USDC, short 2 SOL at $100
+        let user = margin_test_user(1_000, -2 * BASE_PRECISION_I64, 100 * PRICE_PRECISION_I64);
+
+        // $200 of liabilities on $1,000 of collateral
+        assert_eq!(get_leverage(&client, &user).unwrap(), PRICE_PRECISION / 5);
+        assert_eq!(
+            get_spot_asset_value(&client, &user).unwrap(),
+            1_000 * QUOTE_PRECISION_I128
+        );
+    }
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use super::*;
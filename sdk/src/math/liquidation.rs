@@ -43,8 +43,9 @@ pub fn calculate_liquidation_price_and_unrealized_pnl<T: AccountProvider>(
     user: &User,
     market_index: u16,
 ) -> SdkResult<LiquidationAndPnlInfo> {
-    let mut accounts_builder = AccountMapBuilder::default();
-    let mut account_maps = accounts_builder.build(client, user)?;
+    let mut accounts = AccountMapBuilder::build(client, user)?;
+    let account_infos = accounts.account_infos();
+    let mut account_maps = account_infos.load()?;
     let position = user
         .get_perp_position(market_index)
         .map_err(|_| SdkError::NoPosiiton(market_index))?;
@@ -65,14 +66,32 @@ pub fn calculate_unrealized_pnl<T: AccountProvider>(
     market_index: u16,
 ) -> SdkResult<i128> {
     if let Ok(position) = user.get_perp_position(market_index) {
-        let mut accounts_builder = AccountMapBuilder::default();
-        let mut account_maps = accounts_builder.build(client, user)?;
+        let mut accounts = AccountMapBuilder::build(client, user)?;
+        let account_infos = accounts.account_infos();
+        let mut account_maps = account_infos.load()?;
         calculate_unrealized_pnl_inner(position, market_index, &mut account_maps)
     } else {
         Err(SdkError::NoPosiiton(market_index))
     }
 }
 
+/// Calculate the pnl of a user perp position, given by `market_index`, that is not yet settled
+/// into their quote balance (QUOTE_PRECISION)
+///
+/// This is the unrealized pnl plus any realized pnl still held in the position's quote amount
+pub fn calculate_unsettled_pnl<T: AccountProvider>(
+    client: &DriftClient<T>,
+    user: &User,
+    market_index: u16,
+) -> SdkResult<i128> {
+    let unrealized_pnl = calculate_unrealized_pnl(client, user, market_index)?;
+    let position = user
+        .get_perp_position(market_index)
+        .map_err(|_| SdkError::NoPosiiton(market_index))?;
+
+    Ok(unrealized_pnl + position.quote_asset_amount as i128 - position.quote_entry_amount as i128)
+}
+
 pub fn calculate_unrealized_pnl_inner(
     position: &PerpPosition,
     market_index: u16,
@@ -107,8 +126,9 @@ pub fn calculate_liquidation_price<T: AccountProvider>(
     user: &User,
     market_index: u16,
 ) -> SdkResult<i64> {
-    let mut accounts_builder = AccountMapBuilder::default();
-    let mut account_maps = accounts_builder.build(client, user)?;
+    let mut accounts = AccountMapBuilder::build(client, user)?;
+    let account_infos = accounts.account_infos();
+    let mut account_maps = account_infos.load()?;
     calculate_liquidation_price_inner(user, market_index, &mut account_maps)
 }
 
@@ -275,8 +295,10 @@ pub fn calculate_margin_requirements<T: AccountProvider>(
     client: &DriftClient<T>,
     user: &User,
 ) -> SdkResult<MarginRequirementInfo> {
-    let mut accounts_builder = AccountMapBuilder::default();
-    calculate_margin_requirements_inner(user, &mut accounts_builder.build(client, user)?)
+    let mut accounts = AccountMapBuilder::build(client, user)?;
+    let account_infos = accounts.account_infos();
+    let mut account_maps = account_infos.load()?;
+    calculate_margin_requirements_inner(user, &mut account_maps)
 }
 
 /// Calculate the margin requirements of `user` (internal)
@@ -342,12 +364,10 @@ pub fn calculate_collateral<T: AccountProvider>(
     user: &User,
     margin_category: MarginCategory,
 ) -> SdkResult<CollateralInfo> {
-    let mut accounts_builder = AccountMapBuilder::default();
-    calculate_collateral_inner(
-        user,
-        &mut accounts_builder.build(client, user)?,
-        margin_category,
-    )
+    let mut accounts = AccountMapBuilder::build(client, user)?;
+    let account_infos = accounts.account_infos();
+    let mut account_maps = account_infos.load()?;
+    calculate_collateral_inner(user, &mut account_maps, margin_category)
 }
 
 fn calculate_collateral_inner(
@@ -376,6 +396,37 @@ fn calculate_collateral_inner(
     })
 }
 
+#[cfg(test)]
+mod tests {
+    use drift::math::constants::{BASE_PRECISION_I64, PRICE_PRECISION_I64, QUOTE_PRECISION};
+
+    use super::*;
+    use crate::math::account_map_builder::{margin_test_client, margin_test_user};
+
+    #[tokio::test]
+    async fn margin_requirements_and_collateral() {
+        let client = margin_test_client(100 * PRICE_PRECISION_I64).await;
+        // 1,000 USDC, short 2 SOL at $100
+        let user = margin_test_user(1_000, -2 * BASE_PRECISION_I64, 100 * PRICE_PRECISION_I64);
+
+        assert_eq!(
+            calculate_margin_requirements(&client, &user).unwrap(),
+            MarginRequirementInfo {
+                initial: 20 * QUOTE_PRECISION,
+                maintenance: 10 * QUOTE_PRECISION,
+            }
+        );
+        assert_eq!(
+            calculate_collateral(&client, &user, MarginCategory::Initial).unwrap(),
+            CollateralInfo {
+                total: 1_000 * QUOTE_PRECISION_I128,
+                free: 980 * QUOTE_PRECISION_I128,
+            }
+        );
+        assert_eq!(calculate_unrealized_pnl(&client, &user, 0).unwrap(), 0);
+    }
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use std::str::FromStr;
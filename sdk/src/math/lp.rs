@@ -0,0 +1,233 @@
+//! perp market LP (BLP) helpers
+//!
+//! Settling is done by the program's own `controller::lp::settle_lp_position` on a copy of the
+//! position and market, so results match what a `settle_lp` ix would do.
+
+use drift::{
+    controller::lp::settle_lp_position,
+    math::lp,
+    state::{
+        perp_market::{PerpMarket, AMM},
+        user::PerpPosition,
+    },
+};
+
+use crate::SdkResult;
+
+/// What settling an LP position moves into the perp position
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LpSettleMetrics {
+    /// base settled into the position, a multiple of the market's order step size
+    /// (BASE_PRECISION)
+    pub base_asset_amount: i128,
+    /// quote settled into the position (QUOTE_PRECISION)
+    pub quote_asset_amount: i128,
+    /// base below the order step size left on the position after settling (BASE_PRECISION)
+    pub remainder_base_asset_amount: i128,
+}
+
+/// Calculate the base and quote the position's LP shares took on since it last settled, from the
+/// AMM's base and quote per LP
+///
+/// Returns (base (BASE_PRECISION), quote (QUOTE_PRECISION))
+pub fn calculate_settled_lp_base_quote(
+    amm: &AMM,
+    position: &PerpPosition,
+) -> SdkResult<(i128, i128)> {
+    Ok(lp::calculate_settled_lp_base_quote(amm, position)?)
+}
+
+/// Calculate what settling the position's LP shares moves into the position
+///
+/// As on-chain, base is settled in multiples of the market's order step size and the rest added
+/// to the position's remainder, which is settled once it reaches a step
+pub fn calculate_settle_lp_metrics(
+    market: &PerpMarket,
+    position: &PerpPosition,
+) -> SdkResult<LpSettleMetrics> {
+    if position.lp_shares == 0 {
+        return Ok(LpSettleMetrics {
+            remainder_base_asset_amount: position.remainder_base_asset_amount as i128,
+            ..Default::default()
+        });
+    }
+    let (base_asset_amount, quote_asset_amount, settled) = settle(market, position)?;
+
+    Ok(LpSettleMetrics {
+        base_asset_amount,
+        quote_asset_amount,
+        remainder_base_asset_amount: settled.remainder_base_asset_amount as i128,
+    })
+}
+
+/// Returns the position as it would be after settling its LP shares
+///
+/// Funding and the LP's share of the AMM's open orders are not included
+pub fn calculate_position_with_lp_settle(
+    market: &PerpMarket,
+    position: &PerpPosition,
+) -> SdkResult<PerpPosition> {
+    if position.lp_shares == 0 {
+        return Ok(*position);
+    }
+    let (_, _, settled) = settle(market, position)?;
+
+    Ok(settled)
+}
+
+/// Settle copies of `position` and `market`, returns the base and quote moved into the position
+/// with the settled position
+fn settle(market: &PerpMarket, position: &PerpPosition) -> SdkResult<(i128, i128, PerpPosition)> {
+    let mut market = *market;
+    let mut settled = *position;
+    // the program requires funding to be settled first, it isn't included here
+    if settled.base_asset_amount > 0 {
+        settled.last_cumulative_funding_rate = market.amm.cumulative_funding_rate_long as i64;
+    } else if settled.base_asset_amount < 0 {
+        settled.last_cumulative_funding_rate = market.amm.cumulative_funding_rate_short as i64;
+    }
+
+    let (delta, _) = settle_lp_position(&mut settled, &mut market)?;
+
+    Ok((
+        delta.base_asset_amount as i128,
+        delta.quote_asset_amount as i128,
+        settled,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use drift::math::constants::{AMM_RESERVE_PRECISION_I128, BASE_PRECISION_I64};
+
+    use super::*;
+
+    const STEP_SIZE: u64 = BASE_PRECISION_I64 as u64 / 10;
+
+    fn market_per_lp(base_per_lp: i128, quote_per_lp: i128) -> PerpMarket {
+        PerpMarket {
+            amm: AMM {
+                base_asset_amount_per_lp: base_per_lp,
+                quote_asset_amount_per_lp: quote_per_lp,
+                order_step_size: STEP_SIZE,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn lp_position(lp_shares: u64) -> PerpPosition {
+        PerpPosition {
+            lp_shares,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn settled_base_quote_scales_with_shares() {
+        // each share took on 0.25 base and -5 quote
+        let market = market_per_lp(
+            AMM_RESERVE_PRECISION_I128 / 4,
+            -5 * AMM_RESERVE_PRECISION_I128,
+        );
+        let position = lp_position(2 * AMM_RESERVE_PRECISION_I128 as u64);
+
+        assert_eq!(
+            calculate_settled_lp_base_quote(&market.amm, &position).unwrap(),
+            (
+                AMM_RESERVE_PRECISION_I128 / 2,
+                -10 * AMM_RESERVE_PRECISION_I128
+            )
+        );
+
+        // nothing unsettled once the position caught up with the market
+        let caught_up = PerpPosition {
+            last_base_asset_amount_per_lp: (AMM_RESERVE_PRECISION_I128 / 4) as i64,
+            last_quote_asset_amount_per_lp: (-5 * AMM_RESERVE_PRECISION_I128) as i64,
+            ..position
+        };
+        assert_eq!(
+            calculate_settled_lp_base_quote(&market.amm, &caught_up).unwrap(),
+            (0, 0)
+        );
+
+        let rebased = PerpPosition {
+            per_lp_base: 1,
+            ..position
+        };
+        assert!(calculate_settled_lp_base_quote(&market.amm, &rebased).is_err());
+    }
+
+    #[test]
+    fn settle_metrics_standardize_to_step_size() {
+        // 0.25 base per share, 1 share
+        let market = market_per_lp(AMM_RESERVE_PRECISION_I128 / 4, 0);
+        let position = lp_position(AMM_RESERVE_PRECISION_I128 as u64);
+
+        let metrics = calculate_settle_lp_metrics(&market, &position).unwrap();
+        assert_eq!(metrics.base_asset_amount, 2 * STEP_SIZE as i128);
+        assert_eq!(metrics.remainder_base_asset_amount, STEP_SIZE as i128 / 2);
+
+        // the position's remainder is settled once it reaches a step
+        let with_remainder = PerpPosition {
+            remainder_base_asset_amount: STEP_SIZE as i32 / 2,
+            ..position
+        };
+        let metrics = calculate_settle_lp_metrics(&market, &with_remainder).unwrap();
+        assert_eq!(metrics.base_asset_amount, 3 * STEP_SIZE as i128);
+        assert_eq!(metrics.remainder_base_asset_amount, 0);
+
+        let with_remainder = PerpPosition {
+            remainder_base_asset_amount: STEP_SIZE as i32 / 2 + 1,
+            ..position
+        };
+        let metrics = calculate_settle_lp_metrics(&market, &with_remainder).unwrap();
+        assert_eq!(metrics.base_asset_amount, 3 * STEP_SIZE as i128);
+        assert_eq!(metrics.remainder_base_asset_amount, 1);
+
+        // short side keeps its sign
+        let short = market_per_lp(-AMM_RESERVE_PRECISION_I128 / 4, 0);
+        let metrics = calculate_settle_lp_metrics(&short, &position).unwrap();
+        assert_eq!(metrics.base_asset_amount, -2 * STEP_SIZE as i128);
+        assert_eq!(
+            metrics.remainder_base_asset_amount,
+            -(STEP_SIZE as i128) / 2
+        );
+    }
+
+    #[test]
+    fn position_with_lp_settle() {
+        let market = market_per_lp(AMM_RESERVE_PRECISION_I128 / 4, -AMM_RESERVE_PRECISION_I128);
+        let position = PerpPosition {
+            base_asset_amount: STEP_SIZE as i64,
+            quote_asset_amount: -1_000,
+            ..lp_position(AMM_RESERVE_PRECISION_I128 as u64)
+        };
+
+        let settled = calculate_position_with_lp_settle(&market, &position).unwrap();
+        assert_eq!(settled.base_asset_amount, 3 * STEP_SIZE as i64);
+        assert_eq!(
+            settled.quote_asset_amount,
+            -1_000 - AMM_RESERVE_PRECISION_I128 as i64
+        );
+        assert_eq!(settled.remainder_base_asset_amount, STEP_SIZE as i32 / 2);
+        assert_eq!(
+            settled.last_base_asset_amount_per_lp,
+            market.amm.base_asset_amount_per_lp as i64
+        );
+        // settling again is a no-op
+        assert_eq!(
+            calculate_settle_lp_metrics(&market, &settled).unwrap(),
+            LpSettleMetrics {
+                remainder_base_asset_amount: STEP_SIZE as i128 / 2,
+                ..Default::default()
+            }
+        );
+
+        let no_shares = lp_position(0);
+        assert_eq!(
+            calculate_position_with_lp_settle(&market, &no_shares).unwrap(),
+            no_shares
+        );
+    }
+}
@@ -1,7 +1,11 @@
 use drift::{
     controller::position::PositionDirection,
-    math::amm::calculate_price,
-    state::{oracle::OraclePriceData, perp_market::PerpMarket},
+    math::{amm::calculate_price, spot_balance::get_token_amount},
+    state::{
+        oracle::OraclePriceData,
+        perp_market::PerpMarket,
+        spot_market::{SpotBalanceType, SpotMarket},
+    },
 };
 
 use crate::types::SdkResult;
@@ -41,3 +45,18 @@ pub fn calculate_ask_price(
 
     Ok(price)
 }
+
+/// Calculates the quote token amount held in the market's pnl pool, i.e. the most positive pnl
+/// that can currently be settled in the market (QUOTE_PRECISION)
+pub fn calculate_pnl_pool_amount(
+    market: &PerpMarket,
+    quote_spot_market: &SpotMarket,
+) -> SdkResult<u128> {
+    let amount = get_token_amount(
+        market.pnl_pool.scaled_balance,
+        quote_spot_market,
+        &SpotBalanceType::Deposit,
+    )?;
+
+    Ok(amount)
+}
@@ -2,8 +2,10 @@ pub mod account_map_builder;
 pub mod amm;
 pub mod auction;
 pub mod exchange_status;
+pub mod insurance;
 pub mod leverage;
 pub mod liquidation;
+pub mod lp;
 pub mod market;
 pub mod oracle;
 pub mod order;
@@ -1,23 +1,21 @@
-use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 use anchor_lang::AccountDeserialize;
-use base64::Engine;
 use dashmap::DashMap;
+use drift::error::DriftResult;
 use drift::state::oracle::{get_oracle_price, OraclePriceData, OracleSource};
-use solana_account_decoder::{UiAccountData, UiAccountEncoding};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::RpcAccountInfoConfig;
-use solana_sdk::account_info::{AccountInfo, IntoAccountInfo};
+use solana_sdk::account_info::AccountInfo;
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
 use tokio::sync::RwLock;
 
 use crate::{
+    accounts::{AccountSubscriber, AccountSubscription, OnUpdate},
     error::SdkError,
-    event_emitter::EventEmitter,
-    utils::get_ws_url,
-    websocket_account_subscriber::{AccountUpdate, WebsocketAccountSubscriber},
+    types::DataAndSlot,
+    user_config::UserSubscriptionConfig,
     SdkResult,
 };
 
@@ -30,26 +28,74 @@ pub struct Oracle {
     pub raw: Vec<u8>,
 }
 
-impl AccountDeserialize for Oracle {
-    fn try_deserialize(_buf: &mut &[u8]) -> Result<Self, anchor_lang::error::Error> {
-        todo!()
+impl Oracle {
+    /// Decode the oracle account `data` using the price parser of `source`
+    pub fn decode(pubkey: Pubkey, source: OracleSource, slot: u64, data: &[u8]) -> SdkResult<Self> {
+        let price_data = decode_oracle_price(&pubkey, source, slot, data)
+            .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
+
+        Ok(Self {
+            pubkey,
+            data: price_data,
+            source,
+            slot,
+            raw: data.to_vec(),
+        })
     }
+}
+
+fn decode_oracle_price(
+    pubkey: &Pubkey,
+    source: OracleSource,
+    slot: u64,
+    data: &[u8],
+) -> DriftResult<OraclePriceData> {
+    let mut data = data.to_vec();
+    let mut lamports = 0;
+    let owner = Pubkey::default();
+    let account_info = AccountInfo::new(
+        pubkey,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    get_oracle_price(&source, &account_info, slot)
+}
 
-    fn try_deserialize_unchecked(_buf: &mut &[u8]) -> Result<Self, anchor_lang::error::Error> {
-        todo!()
+/// Oracle account data as received by a subscriber
+///
+/// Oracle layouts don't identify their source, `OracleMap` decodes updates with the market's
+/// `OracleSource`
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OracleAccountData(pub Vec<u8>);
+
+impl AccountDeserialize for OracleAccountData {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self, anchor_lang::error::Error> {
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self, anchor_lang::error::Error> {
+        let data = buf.to_vec();
+        *buf = &buf[buf.len()..];
+        Ok(Self(data))
     }
 }
 
 pub struct OracleMap {
     subscribed: AtomicBool,
     pub(crate) oraclemap: Arc<DashMap<Pubkey, Oracle>>,
-    event_emitter: &'static EventEmitter,
-    oracle_infos: DashMap<Pubkey, OracleSource>,
+    oracle_infos: Arc<DashMap<Pubkey, OracleSource>>,
     sync_lock: Option<Mutex<()>>,
     latest_slot: Arc<AtomicU64>,
     commitment: CommitmentConfig,
-    rpc: RpcClient,
-    oracle_subscribers: RwLock<Vec<WebsocketAccountSubscriber<Oracle>>>,
+    rpc: Arc<RpcClient>,
+    /// how oracle accounts are subscribed, websocket if unset
+    subscription: Option<UserSubscriptionConfig>,
+    oracle_subscribers: RwLock<Vec<AccountSubscription<OracleAccountData>>>,
     perp_oracles: DashMap<u16, Pubkey>,
     spot_oracles: DashMap<u16, Pubkey>,
 }
@@ -66,9 +112,7 @@ impl OracleMap {
     ) -> Self {
         let oraclemap = Arc::new(DashMap::new());
 
-        let event_emitter = EventEmitter::new();
-
-        let rpc = RpcClient::new_with_commitment(endpoint, commitment);
+        let rpc = Arc::new(RpcClient::new_with_commitment(endpoint, commitment));
 
         let sync_lock = if sync { Some(Mutex::new(())) } else { None };
 
@@ -82,7 +126,7 @@ impl OracleMap {
             .map(|(market_index, pubkey, _)| (*market_index, *pubkey))
             .collect();
 
-        let oracle_infos = DashMap::new();
+        let oracle_infos = Arc::new(DashMap::new());
         perp_oracles.iter().chain(spot_oracles.iter()).for_each(
             |(_market_index, pubkey, oracle_source)| {
                 oracle_infos.insert(*pubkey, *oracle_source);
@@ -96,46 +140,43 @@ impl OracleMap {
             sync_lock,
             latest_slot: Arc::new(AtomicU64::new(0)),
             commitment,
-            event_emitter: Box::leak(Box::new(event_emitter)),
             rpc,
+            subscription: None,
             oracle_subscribers: RwLock::new(vec![]),
             perp_oracles: perp_oracles_map,
             spot_oracles: spot_oracles_map,
         }
     }
 
+    /// Subscribe to oracle accounts with `subscription` rather than websocket
+    ///
+    /// `Custom` subscribers are for user accounts only and a `OneShot` oracle would go stale,
+    /// subscribing with either returns an error
+    pub fn with_subscription(mut self, subscription: UserSubscriptionConfig) -> Self {
+        self.subscription = Some(subscription);
+        self
+    }
+
     pub async fn subscribe(&self) -> SdkResult<()> {
         if self.sync_lock.is_some() {
             self.sync().await?;
         }
 
         if !self.subscribed.load(Ordering::Relaxed) {
-            let url = get_ws_url(&self.rpc.url())
-                .map_err(|e| SdkError::Generic(format!("valid url: {e}")))?;
-
             let mut oracle_subscribers = vec![];
             for oracle_info in self.oracle_infos.iter() {
-                let oracle_pubkey = oracle_info.key();
-                let oracle_subscriber = WebsocketAccountSubscriber::new(
-                    OracleMap::SUBSCRIPTION_ID,
-                    &url,
-                    *oracle_pubkey,
-                    self.commitment,
-                    self.event_emitter.clone(),
-                );
-                oracle_subscribers.push(oracle_subscriber);
+                if *oracle_info.value() == OracleSource::QuoteAsset {
+                    self.insert_quote_asset_oracle(*oracle_info.key())?;
+                    continue;
+                }
+                oracle_subscribers.push(self.oracle_subscriber(*oracle_info.key())?);
             }
 
             self.subscribed.store(true, Ordering::Relaxed);
 
-            let oracle_source_by_oracle_key = self.oracle_infos.clone();
-            let oracle_map = self.oraclemap.clone();
-
-            let mut subscribers_clone = oracle_subscribers.clone();
-
-            let subscribe_futures = subscribers_clone
+            let subscribe_futures = oracle_subscribers
                 .iter_mut()
-                .map(|subscriber| subscriber.subscribe())
+                .map(|subscriber| subscriber.subscribe(Some(self.on_update())))
                 .collect::<Vec<_>>();
             let results = futures_util::future::join_all(subscribe_futures).await;
             for result in results {
@@ -147,75 +188,6 @@ impl OracleMap {
                 }
             }
 
-            self.event_emitter
-                .subscribe(OracleMap::SUBSCRIPTION_ID, move |event| {
-                    if let Some(update) = event.as_any().downcast_ref::<AccountUpdate>() {
-                        let oracle_pubkey = Pubkey::from_str(&update.pubkey).expect("valid pubkey");
-                        let oracle_source_maybe = oracle_source_by_oracle_key.get(&oracle_pubkey);
-                        if let Some(oracle_source) = oracle_source_maybe {
-                            if let UiAccountData::Binary(blob, UiAccountEncoding::Base64) =
-                                &update.data.data
-                            {
-                                log::error!("Got oracle");
-                                let mut data = base64::engine::general_purpose::STANDARD
-                                    .decode(blob)
-                                    .expect("valid data");
-                                let owner =
-                                    Pubkey::from_str(&update.data.owner).expect("valid pubkey");
-                                let mut lamports = update.data.lamports;
-                                let oracle_account_info = AccountInfo::new(
-                                    &oracle_pubkey,
-                                    false,
-                                    false,
-                                    &mut lamports,
-                                    &mut data,
-                                    &owner,
-                                    false,
-                                    update.data.rent_epoch,
-                                );
-                                match get_oracle_price(
-                                    oracle_source.value(),
-                                    &oracle_account_info,
-                                    update.slot,
-                                ) {
-                                    Ok(price_data) => {
-                                        oracle_map.insert(
-                                            oracle_pubkey,
-                                            Oracle {
-                                                pubkey: oracle_pubkey,
-                                                data: price_data,
-                                                source: *oracle_source.value(),
-                                                slot: update.slot,
-                                                raw: data,
-                                            },
-                                        );
-                                    }
-                                    Err(err) => {
-                                        log::error!("Failed to get oracle price: {:?}", err)
-                                    }
-                                }
-                            }
-                        }
-                    }
-                });
-
-            // let mut subscribers_clone = oracle_subscribers.clone();
-
-            // let subscribe_futures = subscribers_clone
-            //     .iter_mut()
-            //     .map(|subscriber| subscriber.subscribe())
-            //     .collect::<Vec<_>>();
-            // let results = futures_util::future::join_all(subscribe_futures).await;
-            // for result in results {
-            //     match result {
-            //         Ok(()) => {}
-            //         Err(e) => {
-            //             log::error!("Error subscribing oraclemap: {e}");
-            //         }
-            //     }
-            // }
-            // let vecs = results.into_iter().collect::<Result<Vec<_>, _>>()?;
-
             let mut oracle_subscribers_mut = self.oracle_subscribers.write().await;
             *oracle_subscribers_mut = oracle_subscribers;
         }
@@ -242,6 +214,61 @@ impl OracleMap {
         Ok(())
     }
 
+    /// The quote asset price is fixed, there is no account to subscribe to
+    fn insert_quote_asset_oracle(&self, oracle: Pubkey) -> SdkResult<()> {
+        let oracle_data = Oracle::decode(oracle, OracleSource::QuoteAsset, 0, &[])?;
+        self.oraclemap.insert(oracle, oracle_data);
+        Ok(())
+    }
+
+    fn oracle_subscriber(
+        &self,
+        oracle: Pubkey,
+    ) -> SdkResult<AccountSubscription<OracleAccountData>> {
+        match self.subscription {
+            // oracle prices must stay live, a single fetch would go stale
+            Some(UserSubscriptionConfig::OneShot) => Err(SdkError::Generic(
+                "one-shot subscription is not supported for oracles".to_string(),
+            )),
+            Some(ref subscription) => {
+                subscription.subscriber(OracleMap::SUBSCRIPTION_ID, oracle, Arc::clone(&self.rpc))
+            }
+            None => AccountSubscription::websocket(
+                OracleMap::SUBSCRIPTION_ID,
+                oracle,
+                Arc::clone(&self.rpc),
+                self.commitment,
+            ),
+        }
+    }
+
+    /// Updates the map with oracle account updates, decoded with the market's source and the
+    /// update slot
+    fn on_update(&self) -> OnUpdate<OracleAccountData> {
+        let oracle_infos = Arc::clone(&self.oracle_infos);
+        let oracle_map = Arc::clone(&self.oraclemap);
+        Arc::new(
+            move |oracle_pubkey: &Pubkey, update: &DataAndSlot<OracleAccountData>| {
+                let Some(oracle_source) = oracle_infos.get(oracle_pubkey) else {
+                    return;
+                };
+                match Oracle::decode(
+                    *oracle_pubkey,
+                    *oracle_source.value(),
+                    update.slot,
+                    &update.data.0,
+                ) {
+                    Ok(oracle) => {
+                        oracle_map.insert(*oracle_pubkey, oracle);
+                    }
+                    Err(err) => {
+                        log::error!("Failed to get oracle price: {:?}", err)
+                    }
+                }
+            },
+        )
+    }
+
     #[allow(clippy::await_holding_lock)]
     async fn sync(&self) -> SdkResult<()> {
         let sync_lock = self.sync_lock.as_ref().expect("expected sync lock");
@@ -289,20 +316,9 @@ impl OracleMap {
         for (account, oracle_info) in response.value.iter().zip(oracle_infos.iter()) {
             if let Some(oracle_account) = account {
                 let oracle_pubkey = oracle_info.0;
-                let mut oracle_components = (oracle_pubkey, oracle_account.clone());
-                let account_info = oracle_components.into_account_info();
-                let price_data = get_oracle_price(&oracle_info.1, &account_info, slot)
-                    .map_err(|err| SdkError::Anchor(Box::new(err.into())))?;
-                self.oraclemap.insert(
-                    oracle_pubkey,
-                    Oracle {
-                        pubkey: oracle_pubkey,
-                        data: price_data,
-                        source: oracle_info.1,
-                        slot,
-                        raw: account.as_ref().expect("account").data.clone(),
-                    },
-                );
+                let oracle =
+                    Oracle::decode(oracle_pubkey, oracle_info.1, slot, &oracle_account.data)?;
+                self.oraclemap.insert(oracle_pubkey, oracle);
             }
         }
 
@@ -345,16 +361,14 @@ impl OracleMap {
         }
 
         self.oracle_infos.insert(oracle, source);
+        if source == OracleSource::QuoteAsset {
+            return self.insert_quote_asset_oracle(oracle);
+        }
 
-        let mut new_oracle_subscriber = WebsocketAccountSubscriber::new(
-            OracleMap::SUBSCRIPTION_ID,
-            &get_ws_url(&self.rpc.url()).expect("valid url"),
-            oracle,
-            self.commitment,
-            self.event_emitter.clone(),
-        );
-
-        new_oracle_subscriber.subscribe().await?;
+        let mut new_oracle_subscriber = self.oracle_subscriber(oracle)?;
+        new_oracle_subscriber
+            .subscribe(Some(self.on_update()))
+            .await?;
         let mut oracle_subscribers = self.oracle_subscribers.write().await;
         oracle_subscribers.push(new_oracle_subscriber);
 
@@ -378,11 +392,201 @@ impl OracleMap {
 mod tests {
     use std::collections::HashSet;
 
+    use anchor_lang::Discriminator;
+    use drift::state::oracle::PrelaunchOracle;
+
     use super::*;
     use crate::marketmap::MarketMap;
+    use crate::utils::zero_account_to_bytes;
+    use drift::math::constants::PRICE_PRECISION_I64;
     use drift::state::perp_market::PerpMarket;
     use drift::state::spot_market::SpotMarket;
 
+    /// Pyth price account magic number
+    const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+    /// Anchor discriminator of switchboard v2 `AggregatorAccountData`
+    const SWITCHBOARD_AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+    /// Pyth v2 price account with a single publisher
+    fn pyth_fixture(price: i64, conf: u64, expo: i32, valid_slot: u64) -> Vec<u8> {
+        let mut data = vec![0_u8; 3312];
+        data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        // version, account type (price), size, price type
+        data[4..8].copy_from_slice(&2_u32.to_le_bytes());
+        data[8..12].copy_from_slice(&3_u32.to_le_bytes());
+        data[12..16].copy_from_slice(&3312_u32.to_le_bytes());
+        data[16..20].copy_from_slice(&1_u32.to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        // num components, num quoting
+        data[24..28].copy_from_slice(&1_u32.to_le_bytes());
+        data[28..32].copy_from_slice(&1_u32.to_le_bytes());
+        data[32..40].copy_from_slice(&valid_slot.to_le_bytes());
+        data[40..48].copy_from_slice(&valid_slot.to_le_bytes());
+        // aggregate price info
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&conf.to_le_bytes());
+        data[224..228].copy_from_slice(&1_u32.to_le_bytes());
+        data[232..240].copy_from_slice(&valid_slot.to_le_bytes());
+        data
+    }
+
+    /// Switchboard v2 aggregator with a confirmed round (packed layout)
+    fn switchboard_fixture(mantissa: i128, std_dev: i128, scale: u32, round_slot: u64) -> Vec<u8> {
+        let mut data = vec![0_u8; 3851];
+        data[0..8].copy_from_slice(&SWITCHBOARD_AGGREGATOR_DISCRIMINATOR);
+        // min_oracle_results
+        data[236..240].copy_from_slice(&1_u32.to_le_bytes());
+        // latest_confirmed_round: num_success, round_open_slot, result, std_deviation
+        data[341..345].copy_from_slice(&3_u32.to_le_bytes());
+        data[350..358].copy_from_slice(&round_slot.to_le_bytes());
+        data[366..382].copy_from_slice(&mantissa.to_le_bytes());
+        data[382..386].copy_from_slice(&scale.to_le_bytes());
+        data[386..402].copy_from_slice(&std_dev.to_le_bytes());
+        data[402..406].copy_from_slice(&scale.to_le_bytes());
+        data
+    }
+
+    /// Anchor discriminator of pyth receiver `PriceUpdateV2`
+    const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+    /// Pyth pull oracle `PriceUpdateV2` account, fully verified
+    fn pyth_pull_fixture(price: i64, conf: u64, expo: i32, posted_slot: u64) -> Vec<u8> {
+        let mut data = vec![0_u8; 134];
+        data[0..8].copy_from_slice(&PRICE_UPDATE_V2_DISCRIMINATOR);
+        // write authority, verification level (full)
+        data[40] = 1;
+        // price message: feed id, price, conf, exponent
+        data[73..81].copy_from_slice(&price.to_le_bytes());
+        data[81..89].copy_from_slice(&conf.to_le_bytes());
+        data[89..93].copy_from_slice(&expo.to_le_bytes());
+        data[125..133].copy_from_slice(&posted_slot.to_le_bytes());
+        data
+    }
+
+    fn prelaunch_fixture(price: i64, confidence: u64, last_update_slot: u64) -> Vec<u8> {
+        zero_account_to_bytes(PrelaunchOracle {
+            price,
+            confidence,
+            amm_last_update_slot: last_update_slot,
+            ..PrelaunchOracle::default()
+        })
+    }
+
+    #[test]
+    fn test_decode_pyth() {
+        let pubkey = Pubkey::new_unique();
+        // 150.00 +/- 0.01
+        let data = pyth_fixture(15_000_000_000, 1_000_000, -8, 100);
+
+        let oracle = Oracle::decode(pubkey, OracleSource::Pyth, 105, &data).unwrap();
+        assert_eq!(oracle.pubkey, pubkey);
+        assert_eq!(oracle.source, OracleSource::Pyth);
+        assert_eq!(oracle.slot, 105);
+        assert_eq!(oracle.data.price, 150 * PRICE_PRECISION_I64);
+        assert_eq!(oracle.data.confidence, 10_000);
+        assert_eq!(oracle.data.delay, 5);
+        assert!(oracle.data.has_sufficient_number_of_data_points);
+        assert_eq!(oracle.raw, data);
+
+        let oracle = Oracle::decode(pubkey, OracleSource::Pyth1K, 105, &data).unwrap();
+        assert_eq!(oracle.data.price, 150 * 1_000 * PRICE_PRECISION_I64);
+
+        let oracle = Oracle::decode(pubkey, OracleSource::Pyth1M, 105, &data).unwrap();
+        assert_eq!(oracle.data.price, 150 * 1_000_000 * PRICE_PRECISION_I64);
+    }
+
+    #[test]
+    fn test_decode_pyth_stable_coin() {
+        let data = pyth_fixture(100_000_000, 10_000, -8, 100);
+
+        let oracle = Oracle::decode(
+            Pubkey::new_unique(),
+            OracleSource::PythStableCoin,
+            100,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(oracle.data.price, PRICE_PRECISION_I64);
+    }
+
+    #[test]
+    fn test_decode_switchboard() {
+        // 150.0 +/- 1.0
+        let data = switchboard_fixture(150_000_000_000, 1_000_000_000, 9, 200);
+
+        let oracle =
+            Oracle::decode(Pubkey::new_unique(), OracleSource::Switchboard, 202, &data).unwrap();
+        assert_eq!(oracle.data.price, 150 * PRICE_PRECISION_I64);
+        assert_eq!(oracle.data.confidence, PRICE_PRECISION_I64 as u64);
+        assert_eq!(oracle.data.delay, 2);
+        assert!(oracle.data.has_sufficient_number_of_data_points);
+        assert_eq!(oracle.raw, data);
+    }
+
+    #[test]
+    fn test_decode_prelaunch() {
+        let data = prelaunch_fixture(5 * PRICE_PRECISION_I64, 1_000, 10);
+
+        let oracle =
+            Oracle::decode(Pubkey::new_unique(), OracleSource::Prelaunch, 12, &data).unwrap();
+        assert_eq!(oracle.data.price, 5 * PRICE_PRECISION_I64);
+        assert_eq!(oracle.data.confidence, 1_000);
+        assert_eq!(oracle.data.delay, 2);
+        assert_eq!(oracle.raw, data);
+    }
+
+    #[test]
+    fn test_decode_quote_asset() {
+        let oracle =
+            Oracle::decode(Pubkey::new_unique(), OracleSource::QuoteAsset, 1, &[]).unwrap();
+        assert_eq!(oracle.data.price, PRICE_PRECISION_I64);
+    }
+
+    #[test]
+    fn test_decode_pyth_pull() {
+        let pubkey = Pubkey::new_unique();
+        // 150.00 +/- 0.01
+        let data = pyth_pull_fixture(15_000_000_000, 1_000_000, -8, 100);
+
+        let oracle = Oracle::decode(pubkey, OracleSource::PythPull, 105, &data).unwrap();
+        assert_eq!(oracle.pubkey, pubkey);
+        assert_eq!(oracle.source, OracleSource::PythPull);
+        assert_eq!(oracle.slot, 105);
+        assert_eq!(oracle.data.price, 150 * PRICE_PRECISION_I64);
+        assert_eq!(oracle.data.confidence, 10_000);
+        assert_eq!(oracle.data.delay, 5);
+        assert_eq!(oracle.raw, data);
+
+        let oracle = Oracle::decode(pubkey, OracleSource::Pyth1KPull, 105, &data).unwrap();
+        assert_eq!(oracle.data.price, 150 * 1_000 * PRICE_PRECISION_I64);
+
+        let oracle = Oracle::decode(pubkey, OracleSource::Pyth1MPull, 105, &data).unwrap();
+        assert_eq!(oracle.data.price, 150 * 1_000_000 * PRICE_PRECISION_I64);
+    }
+
+    #[test]
+    fn test_decode_pyth_stable_coin_pull() {
+        let data = pyth_pull_fixture(100_000_000, 10_000, -8, 100);
+
+        let oracle = Oracle::decode(
+            Pubkey::new_unique(),
+            OracleSource::PythStableCoinPull,
+            100,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(oracle.data.price, PRICE_PRECISION_I64);
+    }
+
+    #[test]
+    fn test_decode_with_wrong_source() {
+        let data = pyth_pull_fixture(15_000_000_000, 1_000_000, -8, 100);
+        assert!(Oracle::decode(Pubkey::new_unique(), OracleSource::Pyth, 100, &data).is_err());
+        assert!(
+            Oracle::decode(Pubkey::new_unique(), OracleSource::Switchboard, 100, &data).is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_oracle_map() {
         let commitment = CommitmentConfig::processed();
@@ -527,4 +731,114 @@ mod tests {
 
         let _ = oracle_map.unsubscribe().await;
     }
+
+    #[tokio::test]
+    async fn oracle_subscriber_follows_subscription() {
+        use solana_sdk::commitment_config::CommitmentLevel;
+        use tokio::time::Duration;
+
+        use crate::{accounts::BulkAccountLoader, grpc::GrpcConnectionOpts};
+
+        let oracle_map = |subscription: Option<UserSubscriptionConfig>| {
+            let oracle_map = OracleMap::new(
+                CommitmentConfig::confirmed(),
+                "http://127.0.0.1:8899".to_string(),
+                false,
+                &[],
+                &[],
+            );
+            match subscription {
+                Some(subscription) => oracle_map.with_subscription(subscription),
+                None => oracle_map,
+            }
+        };
+        let oracle = Pubkey::new_unique();
+
+        assert!(matches!(
+            oracle_map(None).oracle_subscriber(oracle),
+            Ok(AccountSubscription::WebSocket(_))
+        ));
+        assert!(matches!(
+            oracle_map(Some(UserSubscriptionConfig::WebSocket {
+                resub_timeout_ms: 1_000,
+                log_resub_messages: false,
+                commitment: CommitmentLevel::Confirmed,
+            }))
+            .oracle_subscriber(oracle),
+            Ok(AccountSubscription::WebSocket(_))
+        ));
+        assert!(matches!(
+            oracle_map(Some(UserSubscriptionConfig::Grpc {
+                connection: GrpcConnectionOpts::new("http://127.0.0.1:10000", None),
+                commitment: CommitmentLevel::Confirmed,
+            }))
+            .oracle_subscriber(oracle),
+            Ok(AccountSubscription::Grpc(_))
+        ));
+
+        let rpc_client = Arc::new(RpcClient::new("http://127.0.0.1:8899".to_string()));
+        let account_loader = BulkAccountLoader::new(
+            Arc::clone(&rpc_client),
+            CommitmentConfig::confirmed(),
+            Duration::from_secs(1),
+        );
+        assert!(matches!(
+            oracle_map(Some(UserSubscriptionConfig::Polling { account_loader }))
+                .oracle_subscriber(oracle),
+            Ok(AccountSubscription::Polling(_))
+        ));
+
+        // oracles would go stale after a single fetch
+        assert!(matches!(
+            oracle_map(Some(UserSubscriptionConfig::OneShot)).oracle_subscriber(oracle),
+            Err(SdkError::Generic(_))
+        ));
+        assert!(matches!(
+            oracle_map(Some(UserSubscriptionConfig::Custom {
+                user_account_subscriber: AccountSubscription::one_shot(oracle, rpc_client),
+            }))
+            .oracle_subscriber(oracle),
+            Err(SdkError::Generic(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn subscribe_decodes_updates_with_market_oracle_source() {
+        use tokio::time::Duration;
+
+        use crate::accounts::{bulk_account_loader::tests::mock_rpc, BulkAccountLoader};
+
+        // 150.00 +/- 0.01, pull oracles don't carry a pyth magic number to infer the source from
+        let (url, _) = mock_rpc(pyth_pull_fixture(15_000_000_000, 1_000_000, -8, 1)).await;
+        let pull_oracle = Pubkey::new_unique();
+        let quote_oracle = Pubkey::new_unique();
+        let account_loader = BulkAccountLoader::new(
+            Arc::new(RpcClient::new(url.clone())),
+            CommitmentConfig::confirmed(),
+            Duration::from_millis(10),
+        );
+        let oracle_map = OracleMap::new(
+            CommitmentConfig::confirmed(),
+            url,
+            false,
+            &[(0, pull_oracle, OracleSource::Pyth1KPull)],
+            &[(0, quote_oracle, OracleSource::QuoteAsset)],
+        )
+        .with_subscription(UserSubscriptionConfig::Polling { account_loader });
+
+        oracle_map.subscribe().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let oracle = oracle_map.get(&pull_oracle).expect("pull oracle");
+        assert_eq!(oracle.source, OracleSource::Pyth1KPull);
+        assert_eq!(oracle.data.price, 150 * 1_000 * PRICE_PRECISION_I64);
+        assert!(oracle.slot > 1);
+        assert_eq!(oracle.data.delay, oracle.slot as i64 - 1);
+
+        let oracle = oracle_map.get(&quote_oracle).expect("quote oracle");
+        assert_eq!(oracle.source, OracleSource::QuoteAsset);
+        assert_eq!(oracle.data.price, PRICE_PRECISION_I64);
+
+        oracle_map.unsubscribe().await.unwrap();
+    }
 }
@@ -9,12 +9,15 @@ use crate::{
 
 use super::{
     average_over_slots_strategy::AverageOverSlotsStrategy,
-    drift_priority_fee_method::{fetch_drift_priority_fee, DriftMarketInfo},
+    drift_priority_fee_method::{
+        fetch_drift_priority_fee, DriftMarketInfo, DriftPriorityFeeResponse,
+    },
     helius_priority_fee_method::{
-        fetch_helius_priority_fee, HeliusPriorityFeeLevels, HeliusPriorityLevel,
+        fetch_helius_priority_fee, HeliusPriorityFeeLevels, HeliusPriorityFeeResponse,
+        HeliusPriorityLevel,
     },
     max_over_slots_strategy::MaxOverSlotsStrategy,
-    solana_priority_fee_method::fetch_solana_priority_fee,
+    solana_priority_fee_method::{fetch_solana_priority_fee, SolanaPriorityFeeResponse},
     types::{
         PriorityFeeMethod, PriorityFeeResponse, PriorityFeeStrategy, PriorityFeeSubscriberConfig,
     },
@@ -136,115 +139,67 @@ impl<T: AccountProvider> PriorityFeeSubscriber<T> {
         Ok(())
     }
 
-    async fn load_for_solana(&mut self) -> SdkResult<()> {
-        match &self.drift_client {
-            Some(client) => {
-                let samples =
-                    fetch_solana_priority_fee(client, self.lookback_distance, &self.addresses)
-                        .await?;
-
-                if let Some(first) = samples.first() {
-                    self.latest_priority_fee = first.prioritization_fee;
-                    self.last_slot_seen = first.slot;
-
-                    self.last_avg_strategy_result = self
-                        .average_strategy
-                        .calculate(PriorityFeeResponse::Solana(&samples));
-                    self.last_max_strategy_result = self
-                        .max_strategy
-                        .calculate(PriorityFeeResponse::Solana(&samples));
-
-                    if let Some(custom_strategy) = &self.custom_strategy {
-                        self.last_custom_strategy_result =
-                            custom_strategy.calculate(PriorityFeeResponse::Solana(&samples));
-                    }
-                }
-
-                Ok(())
+    fn apply_solana_sample(&mut self, samples: &[SolanaPriorityFeeResponse]) {
+        if let Some(first) = samples.first() {
+            self.latest_priority_fee = first.prioritization_fee;
+            self.last_slot_seen = first.slot;
+
+            self.last_avg_strategy_result = self
+                .average_strategy
+                .calculate(PriorityFeeResponse::Solana(samples));
+            self.last_max_strategy_result = self
+                .max_strategy
+                .calculate(PriorityFeeResponse::Solana(samples));
+
+            if let Some(custom_strategy) = &self.custom_strategy {
+                self.last_custom_strategy_result =
+                    custom_strategy.calculate(PriorityFeeResponse::Solana(samples));
             }
-            None => Err(SdkError::Generic(
-                "Could not find the drift client".to_string(),
-            )),
         }
     }
 
-    async fn load_for_helius(&mut self) -> SdkResult<()> {
-        match &self.helius_rpc_url {
-            Some(helius_rpc_url) => {
-                let result = fetch_helius_priority_fee(
-                    helius_rpc_url,
-                    self.lookback_distance,
-                    &self.addresses,
-                )
-                .await;
-
-                match result {
-                    Ok(res) => {
-                        self.last_helius_sample = res.result.priority_fee_levels.clone();
+    fn apply_helius_sample(&mut self, sample: Option<HeliusPriorityFeeResponse>) {
+        match sample {
+            Some(res) => {
+                self.last_helius_sample = res.result.priority_fee_levels.clone();
 
-                        if let Some(sample) = &self.last_helius_sample {
-                            self.last_avg_strategy_result =
-                                *sample.0.get(&HeliusPriorityLevel::Medium).unwrap();
+                if let Some(sample) = &self.last_helius_sample {
+                    self.last_avg_strategy_result =
+                        *sample.0.get(&HeliusPriorityLevel::Medium).unwrap();
 
-                            self.last_max_strategy_result =
-                                *sample.0.get(&HeliusPriorityLevel::UnsafeMax).unwrap();
-                        }
+                    self.last_max_strategy_result =
+                        *sample.0.get(&HeliusPriorityLevel::UnsafeMax).unwrap();
+                }
 
-                        if let Some(custom_strategy) = &self.custom_strategy {
-                            self.last_custom_strategy_result =
-                                custom_strategy.calculate(PriorityFeeResponse::Helius(res));
-                        }
-                    }
-                    Err(_e) => {
-                        self.last_helius_sample = None;
-                    }
+                if let Some(custom_strategy) = &self.custom_strategy {
+                    self.last_custom_strategy_result =
+                        custom_strategy.calculate(PriorityFeeResponse::Helius(res));
                 }
-                Ok(())
             }
-
-            None => Err(SdkError::Generic(
-                "Could not find helius rpc url".to_string(),
-            )),
+            None => {
+                self.last_helius_sample = None;
+            }
         }
     }
 
-    async fn load_for_drift(&mut self) -> SdkResult<()> {
-        match &self.drift_priority_fee_endpoint {
-            Some(endpoint) => {
-                if let Some(drift_market) = &self.drift_markets {
-                    let market_types: Vec<&str> = drift_market
-                        .iter()
-                        .map(|market| market.market_type.as_str())
-                        .collect();
-                    let market_indexes: Vec<u16> = drift_market
-                        .iter()
-                        .map(|market| market.market_index)
-                        .collect();
-                    let sample =
-                        fetch_drift_priority_fee(endpoint, &market_types, &market_indexes).await?;
-
-                    if !sample.0.is_empty() {
-                        if let Some(sample) = &self.last_helius_sample {
-                            self.last_avg_strategy_result =
-                                *sample.0.get(&HeliusPriorityLevel::Medium).unwrap();
-
-                            self.last_max_strategy_result =
-                                *sample.0.get(&HeliusPriorityLevel::UnsafeMax).unwrap();
-                        }
+    fn apply_drift_sample(&mut self, sample: Option<DriftPriorityFeeResponse>) {
+        let Some(sample) = sample else {
+            return;
+        };
 
-                        if let Some(custom_strategy) = &self.custom_strategy {
-                            self.last_custom_strategy_result =
-                                custom_strategy.calculate(PriorityFeeResponse::Drift(sample));
-                        }
-                    }
-                }
+        if !sample.0.is_empty() {
+            if let Some(sample) = &self.last_helius_sample {
+                self.last_avg_strategy_result =
+                    *sample.0.get(&HeliusPriorityLevel::Medium).unwrap();
 
-                Ok(())
+                self.last_max_strategy_result =
+                    *sample.0.get(&HeliusPriorityLevel::UnsafeMax).unwrap();
             }
 
-            None => Err(SdkError::Generic(
-                "Could not find drift priority fee endpoint".to_string(),
-            )),
+            if let Some(custom_strategy) = &self.custom_strategy {
+                self.last_custom_strategy_result =
+                    custom_strategy.calculate(PriorityFeeResponse::Drift(sample));
+            }
         }
     }
 
@@ -315,15 +270,34 @@ impl<T: AccountProvider> PriorityFeeSubscriber<T> {
     }
 
     pub async fn load(&mut self) -> SdkResult<()> {
-        match self.priority_fee_method {
-            PriorityFeeMethod::Solana => self.load_for_solana().await?,
-            PriorityFeeMethod::Helius => self.load_for_helius().await?,
-            PriorityFeeMethod::Drift => self.load_for_drift().await?,
-        }
+        let sample = self.query().fetch().await?;
+        self.apply(sample);
 
         Ok(())
     }
 
+    /// Snapshot of what `load` fetches, so samples can be fetched without holding `self`
+    pub fn query(&self) -> PriorityFeeQuery<T> {
+        PriorityFeeQuery {
+            drift_client: self.drift_client.clone(),
+            priority_fee_method: self.priority_fee_method.clone(),
+            lookback_distance: self.lookback_distance,
+            addresses: self.addresses.clone(),
+            drift_markets: self.drift_markets.clone(),
+            drift_priority_fee_endpoint: self.drift_priority_fee_endpoint.clone(),
+            helius_rpc_url: self.helius_rpc_url.clone(),
+        }
+    }
+
+    /// Update the strategy results from a sample returned by `PriorityFeeQuery::fetch`
+    pub fn apply(&mut self, sample: PriorityFeeSample) {
+        match sample.0 {
+            Sample::Solana(samples) => self.apply_solana_sample(&samples),
+            Sample::Helius(sample) => self.apply_helius_sample(sample),
+            Sample::Drift(sample) => self.apply_drift_sample(sample),
+        }
+    }
+
     pub async fn unsubscribe(&mut self) {}
 
     pub fn update_addresses(&mut self, addresses: &[Pubkey]) {
@@ -334,3 +308,97 @@ impl<T: AccountProvider> PriorityFeeSubscriber<T> {
         self.drift_markets = Some(drift_markets.to_vec());
     }
 }
+
+/// Priority fee samples fetched by a `PriorityFeeQuery`
+pub struct PriorityFeeSample(Sample);
+
+enum Sample {
+    Solana(Vec<SolanaPriorityFeeResponse>),
+    /// `None` if the helius request failed
+    Helius(Option<HeliusPriorityFeeResponse>),
+    /// `None` if no drift markets are set
+    Drift(Option<DriftPriorityFeeResponse>),
+}
+
+/// Fetches priority fee samples for a `PriorityFeeSubscriber`, see `PriorityFeeSubscriber::query`
+pub struct PriorityFeeQuery<T: AccountProvider> {
+    drift_client: Option<Arc<DriftClient<T>>>,
+    priority_fee_method: PriorityFeeMethod,
+    lookback_distance: u8,
+    addresses: Vec<Pubkey>,
+    drift_markets: Option<Vec<DriftMarketInfo>>,
+    drift_priority_fee_endpoint: Option<String>,
+    helius_rpc_url: Option<String>,
+}
+
+impl<T: AccountProvider> PriorityFeeQuery<T> {
+    pub async fn fetch(&self) -> SdkResult<PriorityFeeSample> {
+        let sample = match self.priority_fee_method {
+            PriorityFeeMethod::Solana => self.fetch_solana().await?,
+            PriorityFeeMethod::Helius => self.fetch_helius().await?,
+            PriorityFeeMethod::Drift => self.fetch_drift().await?,
+        };
+
+        Ok(PriorityFeeSample(sample))
+    }
+
+    async fn fetch_solana(&self) -> SdkResult<Sample> {
+        match &self.drift_client {
+            Some(client) => {
+                let samples =
+                    fetch_solana_priority_fee(client, self.lookback_distance, &self.addresses)
+                        .await?;
+
+                Ok(Sample::Solana(samples))
+            }
+            None => Err(SdkError::Generic(
+                "Could not find the drift client".to_string(),
+            )),
+        }
+    }
+
+    async fn fetch_helius(&self) -> SdkResult<Sample> {
+        match &self.helius_rpc_url {
+            Some(helius_rpc_url) => {
+                let result = fetch_helius_priority_fee(
+                    helius_rpc_url,
+                    self.lookback_distance,
+                    &self.addresses,
+                )
+                .await;
+
+                Ok(Sample::Helius(result.ok()))
+            }
+
+            None => Err(SdkError::Generic(
+                "Could not find helius rpc url".to_string(),
+            )),
+        }
+    }
+
+    async fn fetch_drift(&self) -> SdkResult<Sample> {
+        match &self.drift_priority_fee_endpoint {
+            Some(endpoint) => {
+                let Some(drift_market) = &self.drift_markets else {
+                    return Ok(Sample::Drift(None));
+                };
+                let market_types: Vec<&str> = drift_market
+                    .iter()
+                    .map(|market| market.market_type.as_str())
+                    .collect();
+                let market_indexes: Vec<u16> = drift_market
+                    .iter()
+                    .map(|market| market.market_index)
+                    .collect();
+                let sample =
+                    fetch_drift_priority_fee(endpoint, &market_types, &market_indexes).await?;
+
+                Ok(Sample::Drift(Some(sample)))
+            }
+
+            None => Err(SdkError::Generic(
+                "Could not find drift priority fee endpoint".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use anchor_lang::AccountDeserialize;
+
+use crate::{
+    event_emitter::EventEmitter,
+    grpc::GrpcProgramAccountSubscriber,
+    types::SdkResult,
+    websocket_program_account_subscriber::{
+        WebsocketProgramAccountOptions, WebsocketProgramAccountSubscriber,
+    },
+};
+
+/// Program account subscription over either websocket or gRPC transport
+///
+/// Both emit `ProgramAccountUpdate`s on the event emitter
+#[derive(Clone)]
+pub enum ProgramAccountSubscriber {
+    Websocket(WebsocketProgramAccountSubscriber),
+    Grpc(GrpcProgramAccountSubscriber),
+}
+
+impl ProgramAccountSubscriber {
+    pub async fn subscribe<T>(&mut self) -> SdkResult<()>
+    where
+        T: AccountDeserialize + Clone + Send + 'static,
+    {
+        match self {
+            Self::Websocket(subscriber) => subscriber.subscribe::<T>().await,
+            Self::Grpc(subscriber) => subscriber.subscribe::<T>().await,
+        }
+    }
+
+    pub async fn unsubscribe(&mut self) -> SdkResult<()> {
+        match self {
+            Self::Websocket(subscriber) => subscriber.unsubscribe().await,
+            Self::Grpc(subscriber) => subscriber.unsubscribe().await,
+        }
+    }
+
+    pub fn event_emitter(&self) -> &EventEmitter {
+        match self {
+            Self::Websocket(subscriber) => &subscriber.event_emitter,
+            Self::Grpc(subscriber) => &subscriber.event_emitter,
+        }
+    }
+
+    pub(crate) fn options(&self) -> &WebsocketProgramAccountOptions {
+        match self {
+            Self::Websocket(subscriber) => &subscriber.options,
+            Self::Grpc(subscriber) => &subscriber.options,
+        }
+    }
+}
+
+impl From<WebsocketProgramAccountSubscriber> for ProgramAccountSubscriber {
+    fn from(subscriber: WebsocketProgramAccountSubscriber) -> Self {
+        Self::Websocket(subscriber)
+    }
+}
+
+impl From<GrpcProgramAccountSubscriber> for ProgramAccountSubscriber {
+    fn from(subscriber: GrpcProgramAccountSubscriber) -> Self {
+        Self::Grpc(subscriber)
+    }
+}
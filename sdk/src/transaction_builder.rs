@@ -3,11 +3,12 @@ use std::borrow::Cow;
 use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
 use drift::{
     controller::position::PositionDirection,
-    instructions::SpotFulfillmentType,
+    instructions::{SpotFulfillmentType, SwapReduceOnly},
     math::constants::QUOTE_SPOT_MARKET_INDEX,
     state::{
         order_params::{ModifyOrderParams, OrderParams},
         perp_market::PerpMarket,
+        settle_pnl_mode::SettlePnlMode,
         spot_market::SpotMarket,
         state::State,
         user::{MarketType, Order, User},
@@ -20,6 +21,7 @@ use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     message::{v0, Message, VersionedMessage},
     pubkey::Pubkey,
+    system_program, sysvar,
 };
 
 use crate::{
@@ -28,9 +30,12 @@ use crate::{
         self, derive_perp_market_account, derive_spot_market_account, state_account, ProgramData,
     },
     types::{MakerInfo, MarketId, ReferrerInfo, RemainingAccount, SdkResult, TxParams},
-    Wallet,
+    utils, Wallet,
 };
 
+/// Maximum compute unit limit a tx may request
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+
 /// Composable Tx builder for Drift program
 ///
 /// Prefer `DriftClient::init_tx`
@@ -69,6 +74,8 @@ pub struct TransactionBuilder<'a> {
     legacy: bool,
     /// add additional lookup tables (v0 only)
     lookup_tables: Vec<AddressLookupTableAccount>,
+    /// compute unit limit and price of the tx
+    pub(crate) tx_params: TxParams,
 }
 
 impl<'a> TransactionBuilder<'a> {
@@ -99,6 +106,7 @@ impl<'a> TransactionBuilder<'a> {
             ixs: Default::default(),
             lookup_tables: vec![program_data.lookup_table.clone()],
             legacy: false,
+            tx_params: Default::default(),
         }
     }
 
@@ -135,6 +143,69 @@ impl<'a> TransactionBuilder<'a> {
         self
     }
 
+    /// Initialize the authority's stats account, required before initializing its first user account
+    pub fn initialize_user_stats(mut self) -> Self {
+        let accounts = drift::accounts::InitializeUserStats {
+            user_stats: Wallet::derive_stats_account(&self.authority, &constants::PROGRAM_ID),
+            state: *state_account(),
+            authority: self.authority,
+            payer: self.authority,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None);
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::InitializeUserStats {}),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Initialize the (sub)account
+    ///
+    /// `sub_account_id` id the builder's sub-account address was derived from
+    ///
+    /// `name` display name of the account, truncated to 32 bytes
+    ///
+    /// `referrer` referrer of the authority, only applies to sub-account 0
+    pub fn initialize_user(
+        mut self,
+        sub_account_id: u16,
+        name: &str,
+        referrer: Option<ReferrerInfo>,
+    ) -> Self {
+        let mut accounts = drift::accounts::InitializeUser {
+            user: self.sub_account,
+            user_stats: Wallet::derive_stats_account(&self.authority, &constants::PROGRAM_ID),
+            state: *state_account(),
+            authority: self.authority,
+            payer: self.authority,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None);
+        if let Some(referrer) = referrer {
+            accounts.push(AccountMeta::new(referrer.referrer, false));
+            accounts.push(AccountMeta::new(referrer.referrer_stats, false));
+        }
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::InitializeUser {
+                sub_account_id,
+                name: utils::encode_name(name),
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
     /// Deposit collateral into account
     pub fn deposit(
         mut self,
@@ -213,6 +284,75 @@ impl<'a> TransactionBuilder<'a> {
         self
     }
 
+    /// Swap `amount_in` tokens of spot market `in_market_index` into spot market
+    /// `out_market_index` with `swap_ixs`, e.g. a Jupiter route
+    ///
+    /// The swap ixs are wrapped between `begin_swap`, withdrawing `amount_in` to
+    /// `in_token_account`, and `end_swap`, depositing the tokens received in `out_token_account`
+    /// and returning any unused input. Both token accounts must be owned by the authority.
+    ///
+    /// `limit_price` the worst in/out price accepted (PRICE_PRECISION)
+    ///
+    /// `reduce_only` only allow the swap to reduce the in market deposit or the out market borrow
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap(
+        mut self,
+        in_market_index: u16,
+        out_market_index: u16,
+        amount_in: u64,
+        in_token_account: Pubkey,
+        out_token_account: Pubkey,
+        swap_ixs: Vec<Instruction>,
+        limit_price: Option<u64>,
+        reduce_only: Option<SwapReduceOnly>,
+    ) -> Self {
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::Swap {
+                state: *state_account(),
+                user: self.sub_account,
+                user_stats: Wallet::derive_stats_account(&self.authority, &constants::PROGRAM_ID),
+                authority: self.authority,
+                out_spot_market_vault: constants::derive_spot_market_vault(out_market_index),
+                in_spot_market_vault: constants::derive_spot_market_vault(in_market_index),
+                out_token_account,
+                in_token_account,
+                token_program: constants::TOKEN_PROGRAM_ID,
+                drift_signer: constants::derive_drift_signer(),
+                instructions: sysvar::instructions::ID,
+            },
+            &[self.account_data.as_ref()],
+            &[],
+            &[
+                MarketId::spot(out_market_index),
+                MarketId::spot(in_market_index),
+            ],
+        );
+
+        self.ixs.push(Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts: accounts.clone(),
+            data: InstructionData::data(&drift::instruction::BeginSwap {
+                in_market_index,
+                out_market_index,
+                amount_in,
+            }),
+        });
+        self.ixs.extend(swap_ixs);
+        self.ixs.push(Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::EndSwap {
+                in_market_index,
+                out_market_index,
+                limit_price,
+                reduce_only,
+            }),
+        });
+
+        self
+    }
+
     /// Place new orders for account
     pub fn place_orders(mut self, orders: Vec<OrderParams>) -> Self {
         let readable_accounts: Vec<MarketId> = orders
@@ -737,12 +877,655 @@ impl<'a> TransactionBuilder<'a> {
         self
     }
 
-    pub fn tx_params(self, _tx_params: TxParams) -> Self {
+    /// Add a spot fill instruction
+    ///
+    /// `user_account_pubkey` address of the taker's sub-account
+    ///
+    /// `maker_info` makers to match the taker order against
+    ///
+    /// `fulfillment_type` type of fill, only `Match` is supported (external market accounts are not added)
+    pub fn fill_spot_order(
+        mut self,
+        user_account_pubkey: Pubkey,
+        user_account: &User,
+        order: &Order,
+        maker_info: &[MakerInfo],
+        referrer_info: &Option<ReferrerInfo>,
+        fulfillment_type: Option<SpotFulfillmentType>,
+    ) -> Self {
+        let user_stats_pubkey =
+            get_user_stats_account_pubkey(&constants::PROGRAM_ID, user_account.authority);
+
+        let filler = self.account_data.authority;
+        let filler_stats_pubkey = get_user_stats_account_pubkey(&constants::PROGRAM_ID, filler);
+
+        let market_index = order.market_index;
+
+        let mut user_accounts = vec![user_account];
+        for maker in maker_info {
+            user_accounts.push(&maker.maker_user_account);
+        }
+
+        let mut accounts = build_accounts(
+            self.program_data,
+            drift::accounts::FillOrder {
+                state: *state_account(),
+                authority: self.authority,
+                filler,
+                filler_stats: filler_stats_pubkey,
+                user: user_account_pubkey,
+                user_stats: user_stats_pubkey,
+            },
+            &user_accounts,
+            &[],
+            &[MarketId::spot(market_index), MarketId::QUOTE_SPOT],
+        );
+
+        for maker in maker_info {
+            accounts.push(AccountMeta::new(maker.maker, false));
+            accounts.push(AccountMeta::new(maker.maker_stats, false));
+        }
+
+        if let Some(referrer_info) = referrer_info {
+            if !maker_info.iter().any(|m| m.maker == referrer_info.referrer) {
+                accounts.push(AccountMeta::new(referrer_info.referrer, false));
+                accounts.push(AccountMeta::new(referrer_info.referrer_stats, false));
+            }
+        }
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::FillSpotOrder {
+                order_id: Some(order.order_id),
+                fulfillment_type,
+                maker_order_id: None,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Liquidate a user's perp position, taking it over into the liquidator's sub-account
+    ///
+    /// `user_info` liquidatee account address and data
+    ///
+    /// `max_base_asset_amount` max base amount the liquidator will take over (BASE_PRECISION)
+    ///
+    /// `limit_price` worst price the liquidator will take the position at
+    pub fn liquidate_perp(
+        mut self,
+        market_index: u16,
+        user_info: &(Pubkey, User),
+        max_base_asset_amount: u64,
+        limit_price: Option<u64>,
+    ) -> Self {
+        let (user, user_account) = user_info;
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::LiquidatePerp {
+                state: *state_account(),
+                authority: self.authority,
+                liquidator: self.sub_account,
+                liquidator_stats: Wallet::derive_stats_account(
+                    &self.authority,
+                    &constants::PROGRAM_ID,
+                ),
+                user: *user,
+                user_stats: Wallet::derive_stats_account(
+                    &user_account.authority,
+                    &constants::PROGRAM_ID,
+                ),
+            },
+            &[self.account_data.as_ref(), user_account],
+            &[],
+            &[MarketId::perp(market_index)],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::LiquidatePerp {
+                market_index,
+                liquidator_max_base_asset_amount: max_base_asset_amount,
+                limit_price,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Liquidate a user's spot borrow in exchange for one of their deposits
+    ///
+    /// `max_liability_transfer` max liability token amount the liquidator will take over
+    ///
+    /// `limit_price` worst asset/liability price the liquidator will accept
+    pub fn liquidate_spot(
+        mut self,
+        asset_market_index: u16,
+        liability_market_index: u16,
+        user_info: &(Pubkey, User),
+        max_liability_transfer: u128,
+        limit_price: Option<u64>,
+    ) -> Self {
+        let (user, user_account) = user_info;
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::LiquidateSpot {
+                state: *state_account(),
+                authority: self.authority,
+                liquidator: self.sub_account,
+                liquidator_stats: Wallet::derive_stats_account(
+                    &self.authority,
+                    &constants::PROGRAM_ID,
+                ),
+                user: *user,
+                user_stats: Wallet::derive_stats_account(
+                    &user_account.authority,
+                    &constants::PROGRAM_ID,
+                ),
+            },
+            &[self.account_data.as_ref(), user_account],
+            &[],
+            &[
+                MarketId::spot(asset_market_index),
+                MarketId::spot(liability_market_index),
+            ],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::LiquidateSpot {
+                asset_market_index,
+                liability_market_index,
+                liquidator_max_liability_transfer: max_liability_transfer,
+                limit_price,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Liquidate a user's spot borrow in exchange for their positive perp pnl
+    pub fn liquidate_borrow_for_perp_pnl(
+        mut self,
+        perp_market_index: u16,
+        spot_market_index: u16,
+        user_info: &(Pubkey, User),
+        max_liability_transfer: u128,
+        limit_price: Option<u64>,
+    ) -> Self {
+        let (user, user_account) = user_info;
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::LiquidateBorrowForPerpPnl {
+                state: *state_account(),
+                authority: self.authority,
+                liquidator: self.sub_account,
+                liquidator_stats: Wallet::derive_stats_account(
+                    &self.authority,
+                    &constants::PROGRAM_ID,
+                ),
+                user: *user,
+                user_stats: Wallet::derive_stats_account(
+                    &user_account.authority,
+                    &constants::PROGRAM_ID,
+                ),
+            },
+            &[self.account_data.as_ref(), user_account],
+            &[],
+            &[
+                MarketId::perp(perp_market_index),
+                MarketId::spot(spot_market_index),
+            ],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::LiquidateBorrowForPerpPnl {
+                perp_market_index,
+                spot_market_index,
+                liquidator_max_liability_transfer: max_liability_transfer,
+                limit_price,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Liquidate a user's negative perp pnl in exchange for one of their deposits
+    pub fn liquidate_perp_pnl_for_deposit(
+        mut self,
+        perp_market_index: u16,
+        spot_market_index: u16,
+        user_info: &(Pubkey, User),
+        max_pnl_transfer: u128,
+        limit_price: Option<u64>,
+    ) -> Self {
+        let (user, user_account) = user_info;
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::LiquidatePerpPnlForDeposit {
+                state: *state_account(),
+                authority: self.authority,
+                liquidator: self.sub_account,
+                liquidator_stats: Wallet::derive_stats_account(
+                    &self.authority,
+                    &constants::PROGRAM_ID,
+                ),
+                user: *user,
+                user_stats: Wallet::derive_stats_account(
+                    &user_account.authority,
+                    &constants::PROGRAM_ID,
+                ),
+            },
+            &[self.account_data.as_ref(), user_account],
+            &[],
+            &[
+                MarketId::perp(perp_market_index),
+                MarketId::spot(spot_market_index),
+            ],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::LiquidatePerpPnlForDeposit {
+                perp_market_index,
+                spot_market_index,
+                liquidator_max_pnl_transfer: max_pnl_transfer,
+                limit_price,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Settle a user's unrealized pnl in a perp market into their quote spot balance
+    ///
+    /// Settling is permissionless, `user_info` may be any account. Positive pnl is paid out of
+    /// the market's pnl pool so can be settled at most up to the pool balance.
+    pub fn settle_pnl(mut self, user_info: &(Pubkey, User), market_index: u16) -> Self {
+        let (user, user_account) = user_info;
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::SettlePNL {
+                state: *state_account(),
+                user: *user,
+                authority: self.authority,
+                spot_market_vault: constants::derive_spot_market_vault(QUOTE_SPOT_MARKET_INDEX),
+            },
+            &[user_account],
+            &[],
+            &[
+                MarketId::perp(market_index),
+                MarketId::spot(QUOTE_SPOT_MARKET_INDEX),
+            ],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::SettlePnl { market_index }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Settle a user's unrealized pnl in several perp markets with one ix
+    ///
+    /// With `SettlePnlMode::TrySettle` markets that can't be settled are skipped rather than
+    /// failing the ix
+    pub fn settle_multiple_pnls(
+        mut self,
+        user_info: &(Pubkey, User),
+        market_indexes: &[u16],
+        mode: SettlePnlMode,
+    ) -> Self {
+        let (user, user_account) = user_info;
+        let mut writable_markets: Vec<MarketId> =
+            market_indexes.iter().map(|i| MarketId::perp(*i)).collect();
+        writable_markets.push(MarketId::spot(QUOTE_SPOT_MARKET_INDEX));
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::SettlePNL {
+                state: *state_account(),
+                user: *user,
+                authority: self.authority,
+                spot_market_vault: constants::derive_spot_market_vault(QUOTE_SPOT_MARKET_INDEX),
+            },
+            &[user_account],
+            &[],
+            writable_markets.as_slice(),
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::SettleMultiplePnls {
+                market_indexes: market_indexes.to_vec(),
+                mode,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Provide liquidity to a perp market's AMM by minting `n_shares` LP shares
+    /// (AMM_RESERVE_PRECISION)
+    pub fn add_perp_lp_shares(mut self, n_shares: u64, market_index: u16) -> Self {
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::AddRemoveLiquidity {
+                state: *state_account(),
+                user: self.sub_account,
+                authority: self.authority,
+            },
+            &[self.account_data.as_ref()],
+            &[],
+            &[MarketId::perp(market_index)],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::AddPerpLpShares {
+                n_shares,
+                market_index,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Burn `shares_to_burn` LP shares of a perp market (AMM_RESERVE_PRECISION)
+    ///
+    /// The LP position is settled before the shares are burnt
+    pub fn remove_perp_lp_shares(mut self, shares_to_burn: u64, market_index: u16) -> Self {
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::AddRemoveLiquidity {
+                state: *state_account(),
+                user: self.sub_account,
+                authority: self.authority,
+            },
+            &[self.account_data.as_ref()],
+            &[],
+            &[MarketId::perp(market_index)],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::RemovePerpLpShares {
+                shares_to_burn,
+                market_index,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Settle a user's LP shares in a perp market into their perp position
+    ///
+    /// Settling is permissionless, `user_info` may be any account
+    pub fn settle_lp(mut self, user_info: &(Pubkey, User), market_index: u16) -> Self {
+        let (user, user_account) = user_info;
+        let accounts = build_accounts(
+            self.program_data,
+            drift::accounts::SettleLP {
+                state: *state_account(),
+                user: *user,
+            },
+            &[user_account],
+            &[],
+            &[MarketId::perp(market_index)],
+        );
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::SettleLp { market_index }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Initialize the authority's insurance fund stake account for a spot market
+    pub fn initialize_insurance_fund_stake(mut self, market_index: u16) -> Self {
+        let accounts = drift::accounts::InitializeInsuranceFundStake {
+            spot_market: derive_spot_market_account(market_index),
+            insurance_fund_stake: constants::derive_insurance_fund_stake(
+                &self.authority,
+                market_index,
+            ),
+            user_stats: Wallet::derive_stats_account(&self.authority, &constants::PROGRAM_ID),
+            state: *state_account(),
+            authority: self.authority,
+            payer: self.authority,
+            rent: sysvar::rent::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None);
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::InitializeInsuranceFundStake {
+                market_index,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Stake `amount` tokens of the spot market into its insurance fund
+    ///
+    /// `user_token_account` token account the stake is transferred from
+    pub fn add_insurance_fund_stake(
+        mut self,
+        market_index: u16,
+        amount: u64,
+        user_token_account: Pubkey,
+    ) -> Self {
+        let accounts = drift::accounts::AddInsuranceFundStake {
+            state: *state_account(),
+            spot_market: derive_spot_market_account(market_index),
+            insurance_fund_stake: constants::derive_insurance_fund_stake(
+                &self.authority,
+                market_index,
+            ),
+            user_stats: Wallet::derive_stats_account(&self.authority, &constants::PROGRAM_ID),
+            authority: self.authority,
+            spot_market_vault: constants::derive_spot_market_vault(market_index),
+            insurance_fund_vault: constants::derive_insurance_fund_vault(market_index),
+            drift_signer: constants::derive_drift_signer(),
+            user_token_account,
+            token_program: constants::TOKEN_PROGRAM_ID,
+        }
+        .to_account_metas(None);
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::AddInsuranceFundStake {
+                market_index,
+                amount,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Request to unstake `amount` tokens from the spot market's insurance fund, they can be
+    /// removed once the market's unstaking period has passed
+    pub fn request_remove_insurance_fund_stake(mut self, market_index: u16, amount: u64) -> Self {
+        let accounts = self.request_remove_insurance_fund_stake_accounts(market_index);
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::RequestRemoveInsuranceFundStake {
+                market_index,
+                amount,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Cancel a pending unstake request from the spot market's insurance fund
+    pub fn cancel_request_remove_insurance_fund_stake(mut self, market_index: u16) -> Self {
+        let accounts = self.request_remove_insurance_fund_stake_accounts(market_index);
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(
+                &drift::instruction::CancelRequestRemoveInsuranceFundStake { market_index },
+            ),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Remove the requested unstake from the spot market's insurance fund
+    ///
+    /// `user_token_account` token account the unstaked tokens are transferred to
+    pub fn remove_insurance_fund_stake(
+        mut self,
+        market_index: u16,
+        user_token_account: Pubkey,
+    ) -> Self {
+        let accounts = drift::accounts::RemoveInsuranceFundStake {
+            state: *state_account(),
+            spot_market: derive_spot_market_account(market_index),
+            insurance_fund_stake: constants::derive_insurance_fund_stake(
+                &self.authority,
+                market_index,
+            ),
+            user_stats: Wallet::derive_stats_account(&self.authority, &constants::PROGRAM_ID),
+            authority: self.authority,
+            insurance_fund_vault: constants::derive_insurance_fund_vault(market_index),
+            drift_signer: constants::derive_drift_signer(),
+            user_token_account,
+            token_program: constants::TOKEN_PROGRAM_ID,
+        }
+        .to_account_metas(None);
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::RemoveInsuranceFundStake {
+                market_index,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Move the spot market's revenue pool into its insurance fund, permissionless once the
+    /// market's revenue settle period has elapsed
+    pub fn settle_revenue_to_insurance_fund(mut self, market_index: u16) -> Self {
+        let accounts = drift::accounts::SettleRevenueToInsuranceFund {
+            state: *state_account(),
+            spot_market: derive_spot_market_account(market_index),
+            spot_market_vault: constants::derive_spot_market_vault(market_index),
+            drift_signer: constants::derive_drift_signer(),
+            insurance_fund_vault: constants::derive_insurance_fund_vault(market_index),
+            token_program: constants::TOKEN_PROGRAM_ID,
+        }
+        .to_account_metas(None);
+
+        let ix = Instruction {
+            program_id: constants::PROGRAM_ID,
+            accounts,
+            data: InstructionData::data(&drift::instruction::SettleRevenueToInsuranceFund {
+                spot_market_index: market_index,
+            }),
+        };
+        self.ixs.push(ix);
+
+        self
+    }
+
+    /// Accounts of the request and cancel unstake ixs
+    fn request_remove_insurance_fund_stake_accounts(&self, market_index: u16) -> Vec<AccountMeta> {
+        drift::accounts::RequestRemoveInsuranceFundStake {
+            spot_market: derive_spot_market_account(market_index),
+            insurance_fund_stake: constants::derive_insurance_fund_stake(
+                &self.authority,
+                market_index,
+            ),
+            user_stats: Wallet::derive_stats_account(&self.authority, &constants::PROGRAM_ID),
+            authority: self.authority,
+            insurance_fund_vault: constants::derive_insurance_fund_vault(market_index),
+        }
+        .to_account_metas(None)
+    }
+
+    /// Set the compute unit limit and price of the tx, replacing any set before
+    ///
+    /// `tx_params.processing` is only applied when built with `DriftClient::build_tx`
+    pub fn tx_params(mut self, tx_params: TxParams) -> Self {
+        self.tx_params = tx_params;
         self
     }
 
     /// Build the transaction message ready for signing and sending
-    pub fn build(self) -> VersionedMessage {
+    pub fn build(mut self) -> VersionedMessage {
+        self.apply_tx_params();
+        self.compile()
+    }
+
+    /// Add the compute budget ixs requested by `tx_params`
+    pub(crate) fn apply_tx_params(&mut self) {
+        let TxParams { base, processing } = &self.tx_params;
+        let compute_units = base.compute_units;
+        let compute_units_price = base.compute_units_price.map(u64::from).or_else(|| {
+            processing
+                .get_cu_price_from_compute_units
+                .zip(compute_units)
+                .map(|(cu_price, compute_units)| cu_price(compute_units as u64))
+        });
+
+        if let Some(price) = compute_units_price {
+            self.set_compute_budget_ix(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+        if let Some(units) = compute_units {
+            self.set_compute_budget_ix(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+    }
+
+    /// Replace the compute budget ix of the same kind as `ix`, or prepend it if there is none
+    fn set_compute_budget_ix(&mut self, ix: Instruction) {
+        // compute budget ixs are identified by their first data byte
+        match self.ixs.iter_mut().find(|existing| {
+            existing.program_id == ix.program_id && existing.data.first() == ix.data.first()
+        }) {
+            Some(existing) => *existing = ix,
+            None => self.ixs.insert(0, ix),
+        }
+    }
+
+    pub(crate) fn compile(&self) -> VersionedMessage {
         if self.legacy {
             let message = Message::new(self.ixs.as_ref(), Some(&self.authority));
             VersionedMessage::Legacy(message)
@@ -904,3 +1687,387 @@ pub async fn get_market_accounts(
 
     Ok((spot_markets, perp_markets))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BaseTxParams, ProcessingTxParams};
+
+    fn new_account_tx(program_data: &ProgramData, authority: Pubkey) -> TransactionBuilder {
+        let account_data = User {
+            authority,
+            ..Default::default()
+        };
+        TransactionBuilder::new(
+            program_data,
+            Wallet::derive_user_account(&authority, 0, &constants::PROGRAM_ID),
+            Cow::Owned(account_data),
+            false,
+        )
+    }
+
+    /// `ProgramData` with `spot` spot markets and `perp` perp markets, for ixs that include market
+    /// accounts
+    fn program_data_with_markets(spot: u16, perp: u16) -> ProgramData {
+        let spot_markets = (0..spot)
+            .map(|market_index| SpotMarket {
+                market_index,
+                pubkey: Pubkey::new_unique(),
+                oracle: Pubkey::new_unique(),
+                ..Default::default()
+            })
+            .collect();
+        let perp_markets = (0..perp)
+            .map(|market_index| {
+                let mut market = PerpMarket {
+                    market_index,
+                    pubkey: Pubkey::new_unique(),
+                    ..Default::default()
+                };
+                market.amm.oracle = Pubkey::new_unique();
+                market
+            })
+            .collect();
+
+        ProgramData::new(
+            spot_markets,
+            perp_markets,
+            ProgramData::uninitialized().lookup_table,
+        )
+    }
+
+    #[test]
+    fn initialize_user_stats_and_user() {
+        let program_data = ProgramData::uninitialized();
+        let authority = Pubkey::new_unique();
+        let builder = new_account_tx(&program_data, authority)
+            .initialize_user_stats()
+            .initialize_user(0, "Main Account", None);
+
+        let [init_stats, init_user] = builder.instructions() else {
+            panic!("expected 2 ixs");
+        };
+        let stats = Wallet::derive_stats_account(&authority, &constants::PROGRAM_ID);
+        assert_eq!(init_stats.accounts[0].pubkey, stats);
+        assert_eq!(
+            init_user.accounts[0].pubkey,
+            Wallet::derive_user_account(&authority, 0, &constants::PROGRAM_ID)
+        );
+        assert_eq!(init_user.accounts[1].pubkey, stats);
+        assert!(init_user
+            .accounts
+            .iter()
+            .filter(|a| a.pubkey == authority)
+            .all(|a| a.is_signer));
+        assert_eq!(
+            init_user.data,
+            InstructionData::data(&drift::instruction::InitializeUser {
+                sub_account_id: 0,
+                name: utils::encode_name("Main Account"),
+            })
+        );
+    }
+
+    #[test]
+    fn tx_params_replace_compute_budget_ixs() {
+        let program_data = ProgramData::uninitialized();
+        let mut builder = new_account_tx(&program_data, Pubkey::new_unique())
+            .with_priority_fee(1, Some(MAX_COMPUTE_UNITS))
+            .initialize_user_stats()
+            .tx_params(TxParams {
+                base: BaseTxParams {
+                    compute_units: Some(200_000),
+                    compute_units_price: Some(5_000),
+                },
+                ..Default::default()
+            });
+        builder.apply_tx_params();
+
+        assert_eq!(
+            builder.instructions(),
+            &[
+                ComputeBudgetInstruction::set_compute_unit_price(5_000),
+                ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+                builder.instructions()[2].clone(),
+            ]
+        );
+    }
+
+    #[test]
+    fn tx_params_cu_price_from_compute_units() {
+        let program_data = ProgramData::uninitialized();
+        let mut builder = new_account_tx(&program_data, Pubkey::new_unique())
+            .initialize_user_stats()
+            .tx_params(TxParams {
+                base: BaseTxParams {
+                    compute_units: Some(100_000),
+                    compute_units_price: None,
+                },
+                processing: ProcessingTxParams {
+                    get_cu_price_from_compute_units: Some(|units| 1_000_000_000 / units),
+                    ..Default::default()
+                },
+            });
+        builder.apply_tx_params();
+
+        assert_eq!(
+            &builder.instructions()[..2],
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn initialize_user_with_referrer() {
+        let program_data = ProgramData::uninitialized();
+        let referrer = ReferrerInfo {
+            referrer: Pubkey::new_unique(),
+            referrer_stats: Pubkey::new_unique(),
+        };
+        let builder = new_account_tx(&program_data, Pubkey::new_unique()).initialize_user(
+            0,
+            "Main Account",
+            Some(ReferrerInfo {
+                referrer: referrer.referrer,
+                referrer_stats: referrer.referrer_stats,
+            }),
+        );
+
+        let accounts = &builder.instructions()[0].accounts;
+        let remaining = &accounts[accounts.len() - 2..];
+        assert_eq!(remaining[0], AccountMeta::new(referrer.referrer, false));
+        assert_eq!(
+            remaining[1],
+            AccountMeta::new(referrer.referrer_stats, false)
+        );
+    }
+
+    #[test]
+    fn insurance_fund_stake_lifecycle() {
+        let program_data = ProgramData::uninitialized();
+        let authority = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let builder = new_account_tx(&program_data, authority)
+            .initialize_insurance_fund_stake(1)
+            .add_insurance_fund_stake(1, 1_000, token_account)
+            .request_remove_insurance_fund_stake(1, 500)
+            .cancel_request_remove_insurance_fund_stake(1)
+            .remove_insurance_fund_stake(1, token_account);
+
+        let stake = constants::derive_insurance_fund_stake(&authority, 1);
+        let vault = constants::derive_insurance_fund_vault(1);
+        let ixs = builder.instructions();
+        assert_eq!(ixs.len(), 5);
+        for ix in ixs {
+            assert!(ix.accounts.iter().any(|a| a.pubkey == stake));
+            assert!(ix
+                .accounts
+                .iter()
+                .any(|a| a.pubkey == authority && a.is_signer));
+        }
+        for ix in &ixs[1..] {
+            assert!(ix.accounts.iter().any(|a| a.pubkey == vault));
+        }
+        assert!(ixs[1]
+            .accounts
+            .contains(&AccountMeta::new(token_account, false)));
+        assert!(ixs[4]
+            .accounts
+            .contains(&AccountMeta::new(token_account, false)));
+        assert_eq!(
+            ixs[2].data,
+            InstructionData::data(&drift::instruction::RequestRemoveInsuranceFundStake {
+                market_index: 1,
+                amount: 500,
+            })
+        );
+        // request and cancel share accounts
+        assert_eq!(ixs[2].accounts, ixs[3].accounts);
+    }
+
+    #[test]
+    fn settle_revenue_to_insurance_fund_accounts() {
+        let program_data = ProgramData::uninitialized();
+        let builder =
+            new_account_tx(&program_data, Pubkey::new_unique()).settle_revenue_to_insurance_fund(0);
+
+        let accounts = &builder.instructions()[0].accounts;
+        assert!(accounts.contains(&AccountMeta::new(
+            constants::derive_spot_market_vault(0),
+            false
+        )));
+        assert!(accounts.contains(&AccountMeta::new(
+            constants::derive_insurance_fund_vault(0),
+            false
+        )));
+        assert!(accounts.iter().all(|a| !a.is_signer));
+    }
+
+    #[test]
+    fn swap_wraps_swap_ixs() {
+        let program_data = program_data_with_markets(2, 0);
+        let in_token_account = Pubkey::new_unique();
+        let out_token_account = Pubkey::new_unique();
+        let route_ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![AccountMeta::new(in_token_account, false)],
+            data: vec![1, 2, 3],
+        };
+        let builder = new_account_tx(&program_data, Pubkey::new_unique()).swap(
+            0,
+            1,
+            1_000,
+            in_token_account,
+            out_token_account,
+            vec![route_ix.clone()],
+            None,
+            Some(SwapReduceOnly::In),
+        );
+
+        let [begin_swap, swap, end_swap] = builder.instructions() else {
+            panic!("expected 3 ixs");
+        };
+        assert_eq!(swap, &route_ix);
+        assert_eq!(
+            begin_swap.data,
+            InstructionData::data(&drift::instruction::BeginSwap {
+                in_market_index: 0,
+                out_market_index: 1,
+                amount_in: 1_000,
+            })
+        );
+        assert_eq!(
+            end_swap.data,
+            InstructionData::data(&drift::instruction::EndSwap {
+                in_market_index: 0,
+                out_market_index: 1,
+                limit_price: None,
+                reduce_only: Some(SwapReduceOnly::In),
+            })
+        );
+        assert_eq!(begin_swap.accounts, end_swap.accounts);
+        for market in program_data.spot_market_configs() {
+            assert!(begin_swap
+                .accounts
+                .contains(&AccountMeta::new(market.pubkey, false)));
+        }
+        assert!(begin_swap
+            .accounts
+            .contains(&AccountMeta::new_readonly(sysvar::instructions::ID, false)));
+    }
+
+    #[test]
+    fn perp_lp_shares_and_settle_lp() {
+        let program_data = program_data_with_markets(1, 1);
+        let authority = Pubkey::new_unique();
+        let settlee = (
+            Pubkey::new_unique(),
+            User {
+                authority: Pubkey::new_unique(),
+                ..Default::default()
+            },
+        );
+        let builder = new_account_tx(&program_data, authority)
+            .add_perp_lp_shares(1_000, 0)
+            .remove_perp_lp_shares(500, 0)
+            .settle_lp(&settlee, 0);
+
+        let [add, remove, settle] = builder.instructions() else {
+            panic!("expected 3 ixs");
+        };
+        let perp_market = AccountMeta::new(program_data.perp_market_configs()[0].pubkey, false);
+        for ix in [add, remove, settle] {
+            assert!(ix.accounts.contains(&perp_market));
+        }
+        assert_eq!(add.accounts, remove.accounts);
+        assert!(add
+            .accounts
+            .iter()
+            .any(|a| a.pubkey == authority && a.is_signer));
+        assert_eq!(
+            remove.data,
+            InstructionData::data(&drift::instruction::RemovePerpLpShares {
+                shares_to_burn: 500,
+                market_index: 0,
+            })
+        );
+        // settling is permissionless
+        assert_eq!(settle.accounts[1], AccountMeta::new(settlee.0, false));
+        assert!(settle.accounts.iter().all(|a| !a.is_signer));
+    }
+
+    #[test]
+    fn liquidate_ix_accounts() {
+        let program_data = program_data_with_markets(2, 1);
+        let liquidator = Pubkey::new_unique();
+        let liquidatee = (
+            Pubkey::new_unique(),
+            User {
+                authority: Pubkey::new_unique(),
+                ..Default::default()
+            },
+        );
+        let builder = new_account_tx(&program_data, liquidator)
+            .liquidate_perp(0, &liquidatee, 1_000, None)
+            .liquidate_spot(0, 1, &liquidatee, 2_000, Some(5))
+            .liquidate_borrow_for_perp_pnl(0, 1, &liquidatee, 3_000, None)
+            .liquidate_perp_pnl_for_deposit(0, 1, &liquidatee, 4_000, None);
+
+        let [perp, spot, borrow_for_pnl, pnl_for_deposit] = builder.instructions() else {
+            panic!("expected 4 ixs");
+        };
+        let liquidator_stats = Wallet::derive_stats_account(&liquidator, &constants::PROGRAM_ID);
+        let liquidatee_stats =
+            Wallet::derive_stats_account(&liquidatee.1.authority, &constants::PROGRAM_ID);
+        for ix in [perp, spot, borrow_for_pnl, pnl_for_deposit] {
+            assert_eq!(ix.accounts[0].pubkey, *state_account());
+            assert_eq!(ix.accounts[1], AccountMeta::new_readonly(liquidator, true));
+            assert_eq!(
+                ix.accounts[2],
+                AccountMeta::new(
+                    Wallet::derive_user_account(&liquidator, 0, &constants::PROGRAM_ID),
+                    false
+                )
+            );
+            assert_eq!(ix.accounts[3], AccountMeta::new(liquidator_stats, false));
+            assert_eq!(ix.accounts[4], AccountMeta::new(liquidatee.0, false));
+            assert_eq!(ix.accounts[5], AccountMeta::new(liquidatee_stats, false));
+        }
+
+        let perp_market = AccountMeta::new(program_data.perp_market_configs()[0].pubkey, false);
+        let spot_markets: Vec<AccountMeta> = program_data
+            .spot_market_configs()
+            .iter()
+            .map(|m| AccountMeta::new(m.pubkey, false))
+            .collect();
+        assert!(perp.accounts.contains(&perp_market));
+        for market in &spot_markets {
+            assert!(spot.accounts.contains(market));
+        }
+        for ix in [borrow_for_pnl, pnl_for_deposit] {
+            assert!(ix.accounts.contains(&perp_market));
+            assert!(ix.accounts.contains(&spot_markets[1]));
+        }
+
+        assert_eq!(
+            spot.data,
+            InstructionData::data(&drift::instruction::LiquidateSpot {
+                asset_market_index: 0,
+                liability_market_index: 1,
+                liquidator_max_liability_transfer: 2_000,
+                limit_price: Some(5),
+            })
+        );
+        assert_eq!(
+            pnl_for_deposit.data,
+            InstructionData::data(&drift::instruction::LiquidatePerpPnlForDeposit {
+                perp_market_index: 0,
+                spot_market_index: 1,
+                liquidator_max_pnl_transfer: 4_000,
+                limit_price: None,
+            })
+        );
+    }
+}
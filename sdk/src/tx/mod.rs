@@ -1 +1,2 @@
 pub mod priority_fee_calculator;
+pub mod tx_sender;
@@ -0,0 +1,391 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, signature::Signature, transaction::VersionedTransaction,
+};
+
+use crate::{SdkError, SdkResult};
+
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_millis(2_000);
+const DEFAULT_RETRY_TIMEOUT: Duration = Duration::from_millis(30_000);
+
+/// Strategy for getting a signed tx landed
+pub trait TxSender: Send + Sync {
+    /// Send `tx`, signed against a blockhash valid up to `last_valid_block_height`
+    ///
+    /// Returns the signature on success
+    fn send_tx(
+        &self,
+        tx: VersionedTransaction,
+        last_valid_block_height: u64,
+    ) -> BoxFuture<SdkResult<Signature>>;
+}
+
+/// RPC connections shared by all tx senders
+#[derive(Clone)]
+pub struct TxSenderConnections {
+    rpc_client: Arc<RpcClient>,
+    /// txs are also fanned out to these, errors are only logged
+    additional_connections: Vec<Arc<RpcClient>>,
+    /// used to confirm txs and track block height
+    confirmation_client: Arc<RpcClient>,
+    send_config: RpcSendTransactionConfig,
+}
+
+impl TxSenderConnections {
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        additional_send_tx_endpoints: &[String],
+        tx_confirmation_endpoint: Option<&str>,
+        send_config: RpcSendTransactionConfig,
+    ) -> Self {
+        let commitment = rpc_client.commitment();
+        let additional_connections = additional_send_tx_endpoints
+            .iter()
+            .map(|endpoint| {
+                Arc::new(RpcClient::new_with_commitment(
+                    endpoint.to_string(),
+                    commitment,
+                ))
+            })
+            .collect();
+        let confirmation_client = match tx_confirmation_endpoint {
+            Some(endpoint) => Arc::new(RpcClient::new_with_commitment(
+                endpoint.to_string(),
+                commitment,
+            )),
+            None => rpc_client.clone(),
+        };
+
+        Self {
+            rpc_client,
+            additional_connections,
+            confirmation_client,
+            send_config,
+        }
+    }
+
+    /// Send `tx` to the main connection and fan it out to the additional connections
+    async fn send_to_all(&self, tx: &VersionedTransaction) -> SdkResult<Signature> {
+        for connection in &self.additional_connections {
+            let connection = connection.clone();
+            let tx = tx.clone();
+            let send_config = self.send_config;
+            tokio::spawn(async move {
+                if let Err(e) = connection
+                    .send_transaction_with_config(&tx, send_config)
+                    .await
+                {
+                    log::debug!("failed to send tx to {}: {e}", connection.url());
+                }
+            });
+        }
+
+        self.rpc_client
+            .send_transaction_with_config(tx, self.send_config)
+            .await
+            .map_err(|err| {
+                let err = SdkError::from(err);
+                err.to_out_of_sol_error().unwrap_or(err)
+            })
+    }
+
+    /// Returns `true` once `signature` is confirmed, an error if the tx failed
+    async fn is_confirmed(&self, signature: &Signature) -> SdkResult<bool> {
+        let statuses = self
+            .confirmation_client
+            .get_signature_statuses(&[*signature])
+            .await?;
+
+        match statuses.value.into_iter().next().flatten() {
+            Some(status) => {
+                if let Some(err) = status.err {
+                    return Err(SdkError::Generic(format!("tx {signature} failed: {err}")));
+                }
+                Ok(status.satisfies_commitment(CommitmentConfig::confirmed()))
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Resend `tx` every `retry_interval` until it is confirmed or `should_stop` returns true
+    async fn resend_until_confirmed<F>(
+        &self,
+        tx: &VersionedTransaction,
+        retry_interval: Duration,
+        mut should_stop: F,
+    ) -> SdkResult<Signature>
+    where
+        F: FnMut() -> BoxFuture<'static, SdkResult<bool>>,
+    {
+        let signature = self.send_to_all(tx).await?;
+        let mut interval = tokio::time::interval(retry_interval);
+        // first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            if self.is_confirmed(&signature).await? {
+                return Ok(signature);
+            }
+
+            if should_stop().await? {
+                return Err(SdkError::TxNotConfirmed(signature));
+            }
+
+            if let Err(e) = self.send_to_all(tx).await {
+                log::debug!("failed to resend tx {signature}: {e}");
+            }
+        }
+    }
+}
+
+/// Sends the tx once and does not wait for confirmation
+pub struct FastTxSender {
+    connections: TxSenderConnections,
+}
+
+impl FastTxSender {
+    pub fn new(connections: TxSenderConnections) -> Self {
+        Self { connections }
+    }
+}
+
+impl TxSender for FastTxSender {
+    fn send_tx(
+        &self,
+        tx: VersionedTransaction,
+        _last_valid_block_height: u64,
+    ) -> BoxFuture<SdkResult<Signature>> {
+        async move { self.connections.send_to_all(&tx).await }.boxed()
+    }
+}
+
+/// Resends the tx on an interval until it is confirmed or `timeout` elapses
+pub struct RetryTxSender {
+    connections: TxSenderConnections,
+    timeout: Duration,
+    retry_interval: Duration,
+}
+
+impl RetryTxSender {
+    pub fn new(
+        connections: TxSenderConnections,
+        timeout: Option<Duration>,
+        retry_interval: Option<Duration>,
+    ) -> Self {
+        Self {
+            connections,
+            timeout: timeout.unwrap_or(DEFAULT_RETRY_TIMEOUT),
+            retry_interval: retry_interval.unwrap_or(DEFAULT_RETRY_INTERVAL),
+        }
+    }
+}
+
+impl TxSender for RetryTxSender {
+    fn send_tx(
+        &self,
+        tx: VersionedTransaction,
+        _last_valid_block_height: u64,
+    ) -> BoxFuture<SdkResult<Signature>> {
+        async move {
+            let deadline = Instant::now() + self.timeout;
+            self.connections
+                .resend_until_confirmed(&tx, self.retry_interval, || {
+                    async move { Ok(Instant::now() >= deadline) }.boxed()
+                })
+                .await
+        }
+        .boxed()
+    }
+}
+
+/// Resends the tx on an interval until it is confirmed or its blockhash expires
+pub struct WhileValidTxSender {
+    connections: TxSenderConnections,
+    retry_interval: Duration,
+}
+
+impl WhileValidTxSender {
+    pub fn new(connections: TxSenderConnections, retry_interval: Option<Duration>) -> Self {
+        Self {
+            connections,
+            retry_interval: retry_interval.unwrap_or(DEFAULT_RETRY_INTERVAL),
+        }
+    }
+}
+
+impl TxSender for WhileValidTxSender {
+    fn send_tx(
+        &self,
+        tx: VersionedTransaction,
+        last_valid_block_height: u64,
+    ) -> BoxFuture<SdkResult<Signature>> {
+        async move {
+            let confirmation_client = self.connections.confirmation_client.clone();
+            self.connections
+                .resend_until_confirmed(&tx, self.retry_interval, || {
+                    let confirmation_client = confirmation_client.clone();
+                    async move {
+                        let block_height = confirmation_client.get_block_height().await?;
+                        Ok(block_height > last_valid_block_height)
+                    }
+                    .boxed()
+                })
+                .await
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::{json, Value};
+    use solana_sdk::{
+        hash::Hash, message::VersionedMessage, signature::Keypair, signer::Signer,
+        system_instruction, transaction::Transaction,
+    };
+
+    use super::*;
+    use crate::http_stub;
+
+    /// Mock JSON-RPC node, txs are reported confirmed once sent `confirm_after` times
+    struct MockRpc {
+        url: String,
+        sends: Arc<AtomicUsize>,
+    }
+
+    async fn mock_rpc_server(
+        signature: Signature,
+        confirm_after: usize,
+        block_height: u64,
+    ) -> MockRpc {
+        let sends = Arc::new(AtomicUsize::new(0));
+        let sends_ref = sends.clone();
+
+        let url = http_stub::serve_json_rpc(move |method, _params| match method {
+            "getVersion" => json!({ "solana-core": "1.16.0", "feature-set": 0 }),
+            "sendTransaction" => {
+                sends_ref.fetch_add(1, Ordering::Relaxed);
+                json!(signature.to_string())
+            }
+            "getSignatureStatuses" => {
+                let status = if sends_ref.load(Ordering::Relaxed) >= confirm_after {
+                    json!({
+                        "slot": 1,
+                        "confirmations": null,
+                        "err": null,
+                        "status": { "Ok": null },
+                        "confirmationStatus": "confirmed"
+                    })
+                } else {
+                    Value::Null
+                };
+                json!({ "context": { "slot": 1 }, "value": [status] })
+            }
+            "getBlockHeight" => json!(block_height),
+            method => panic!("unexpected method {method}"),
+        })
+        .await;
+
+        MockRpc { url, sends }
+    }
+
+    fn test_tx() -> VersionedTransaction {
+        let payer = Keypair::new();
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &Keypair::new().pubkey(),
+                1,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::new_unique(),
+        );
+        VersionedTransaction {
+            signatures: tx.signatures,
+            message: VersionedMessage::Legacy(tx.message),
+        }
+    }
+
+    fn connections(url: &str, additional: &[String]) -> TxSenderConnections {
+        TxSenderConnections::new(
+            Arc::new(RpcClient::new(url.to_string())),
+            additional,
+            None,
+            RpcSendTransactionConfig {
+                skip_preflight: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fast_tx_sender_fans_out() {
+        let tx = test_tx();
+        let rpc = mock_rpc_server(tx.signatures[0], 1, 0).await;
+        let additional = mock_rpc_server(tx.signatures[0], 1, 0).await;
+
+        let sender = FastTxSender::new(connections(&rpc.url, &[additional.url.clone()]));
+        let sig = sender.send_tx(tx.clone(), 0).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(sig, tx.signatures[0]);
+        assert_eq!(rpc.sends.load(Ordering::Relaxed), 1);
+        assert_eq!(additional.sends.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_tx_sender_resends_until_confirmed() {
+        let tx = test_tx();
+        let rpc = mock_rpc_server(tx.signatures[0], 3, 0).await;
+
+        let sender = RetryTxSender::new(
+            connections(&rpc.url, &[]),
+            Some(Duration::from_secs(5)),
+            Some(Duration::from_millis(50)),
+        );
+        let sig = sender.send_tx(tx.clone(), 0).await.unwrap();
+
+        assert_eq!(sig, tx.signatures[0]);
+        assert_eq!(rpc.sends.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_tx_sender_times_out() {
+        let tx = test_tx();
+        let rpc = mock_rpc_server(tx.signatures[0], usize::MAX, 0).await;
+
+        let sender = RetryTxSender::new(
+            connections(&rpc.url, &[]),
+            Some(Duration::from_millis(200)),
+            Some(Duration::from_millis(50)),
+        );
+        let result = sender.send_tx(tx, 0).await;
+
+        assert!(matches!(result, Err(SdkError::TxNotConfirmed(_))));
+        assert!(rpc.sends.load(Ordering::Relaxed) > 1);
+    }
+
+    #[tokio::test]
+    async fn test_while_valid_tx_sender_stops_after_last_valid_block_height() {
+        let tx = test_tx();
+        let rpc = mock_rpc_server(tx.signatures[0], usize::MAX, 101).await;
+
+        let sender =
+            WhileValidTxSender::new(connections(&rpc.url, &[]), Some(Duration::from_millis(50)));
+        let result = sender.send_tx(tx, 100).await;
+
+        assert!(matches!(result, Err(SdkError::TxNotConfirmed(_))));
+        assert_eq!(rpc.sends.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,9 +1,9 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, time::Duration};
 
 use anchor_lang::AccountDeserialize;
 use drift::state::user::{MarketType, Order, User, UserStats};
 use serde::Deserialize;
-use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, transaction::TransactionError};
 
 use crate::{error::SdkError, event_emitter::Event};
 
@@ -185,15 +185,23 @@ pub enum OracleSource {
 
 #[derive(Default)]
 pub struct BaseTxParams {
+    /// compute unit limit of the tx
     pub compute_units: Option<u32>,
+    /// compute unit price of the tx in µ-lamports
     pub compute_units_price: Option<u32>,
 }
 
+/// Params applied by `DriftClient::build_tx`
 #[derive(Default)]
 pub struct ProcessingTxParams {
+    /// size the compute unit limit by simulating the tx
     pub use_simulated_compute_units: Option<bool>,
-    pub compute_units_buffer_multipler: Option<u64>,
+    /// multiplier applied to the simulated compute units, default: 1.0
+    pub compute_units_buffer_multipler: Option<f64>,
+    /// derive the compute unit price from the simulated compute units rather than
+    /// `BaseTxParams::compute_units`
     pub use_simulated_compute_units_for_cu_price_calculation: Option<bool>,
+    /// derive the compute unit price (µ-lamports) from the tx compute units
     pub get_cu_price_from_compute_units: Option<fn(u64) -> u64>,
 }
 
@@ -203,6 +211,19 @@ pub struct TxParams {
     pub processing: ProcessingTxParams,
 }
 
+/// Result of simulating a tx
+#[derive(Debug, Clone)]
+pub struct SimulatedTx {
+    /// compute units consumed by the tx
+    pub compute_units: u64,
+    /// program logs of the tx
+    pub logs: Vec<String>,
+    /// error the tx failed with, if any
+    pub err: Option<TransactionError>,
+    /// time taken by the simulation request
+    pub duration: Duration,
+}
+
 #[derive(Debug, Clone)]
 pub struct MakerInfo {
     pub maker: Pubkey,
@@ -1,80 +1,74 @@
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 use drift::state::user::User;
 use solana_sdk::pubkey::Pubkey;
 
 use crate::{
+    accounts::{AccountSubscriber, AccountSubscription, UserAccountSubscriber},
     drift_client::DriftClient,
-    error::SdkError,
-    event_emitter::EventEmitter,
-    utils::{decode, get_ws_url},
-    websocket_account_subscriber::{AccountUpdate, WebsocketAccountSubscriber},
+    user_config::UserSubscriptionConfig,
     AccountProvider, DataAndSlot, SdkResult,
 };
 
 #[derive(Clone)]
 pub struct DriftUser {
     pub pubkey: Pubkey,
-    subscription: WebsocketAccountSubscriber<User>,
-    data_and_slot: Arc<RwLock<DataAndSlot<User>>>,
+    subscription: UserAccountSubscriber,
     pub sub_account: Option<u16>,
 }
 
 impl DriftUser {
     pub const SUBSCRIPTION_ID: &'static str = "user";
 
+    /// Create a user with the client's `user_account_subscription_config`, websocket if unset
+    ///
+    /// A `Custom` subscriber is used as is, it must be subscribing to `pubkey`
     pub async fn new<A: AccountProvider>(
         pubkey: Pubkey,
         drift_client: &DriftClient<A>,
         sub_account: Option<u16>,
     ) -> SdkResult<Self> {
-        let url = get_ws_url(&drift_client.inner().url())
-            .map_err(|e| SdkError::Generic(format!("valid url: {e}")))?;
-        let subscription = WebsocketAccountSubscriber::new(
-            DriftUser::SUBSCRIPTION_ID,
-            &url,
-            pubkey,
-            drift_client.inner().commitment(),
-            EventEmitter::new(),
-        );
+        let rpc_client = Arc::clone(&drift_client.backend.rpc_client);
+        let subscription = match drift_client.user_account_subscription_config {
+            Some(UserSubscriptionConfig::Custom {
+                ref user_account_subscriber,
+            }) => user_account_subscriber.clone(),
+            Some(ref config) => {
+                config.subscriber(DriftUser::SUBSCRIPTION_ID, pubkey, rpc_client)?
+            }
+            None => {
+                let commitment = rpc_client.commitment();
+                AccountSubscription::websocket(
+                    DriftUser::SUBSCRIPTION_ID,
+                    pubkey,
+                    rpc_client,
+                    commitment,
+                )?
+            }
+        };
 
         let user = drift_client.get_user_account(&pubkey).await?;
-        let data_and_slot = Arc::new(RwLock::new(DataAndSlot {
-            data: user,
-            slot: 0,
-        }));
+        subscription.set_data(user, 0);
 
         Ok(Self {
             pubkey,
             subscription,
-            data_and_slot,
             sub_account,
         })
     }
 
     pub async fn subscribe(&mut self) -> SdkResult<()> {
-        let current_data_and_slot = self.data_and_slot.clone();
-        self.subscription
-            .event_emitter
-            .subscribe(DriftUser::SUBSCRIPTION_ID, move |event| {
-                if let Some(update) = event.as_any().downcast_ref::<AccountUpdate>() {
-                    let new_data =
-                        decode::<User>(update.data.data.clone()).expect("valid user data");
-                    let slot = update.slot;
-                    let mut data_and_slot = current_data_and_slot.write().unwrap();
-                    *data_and_slot = DataAndSlot {
-                        data: new_data,
-                        slot,
-                    };
-                }
-            });
-        self.subscription.subscribe().await?;
-        Ok(())
+        self.subscription.subscribe(None).await
+    }
+
+    pub async fn unsubscribe(&mut self) -> SdkResult<()> {
+        self.subscription.unsubscribe().await
     }
 
     pub fn get_user_account_and_slot(&self) -> DataAndSlot<User> {
-        let reader = self.data_and_slot.read().expect("reader");
-        reader.clone()
+        self.subscription
+            .data_and_slot()
+            .expect("user account fetched on creation")
     }
 
     pub fn get_user_account(&self) -> User {
@@ -117,3 +111,130 @@ impl DriftUser {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use drift::state::{perp_market::PerpMarket, spot_market::SpotMarket};
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+    use tokio::time::Duration;
+
+    use super::*;
+    use crate::{
+        accounts::BulkAccountLoader,
+        drift_client_config::{ClientOpts, DriftClientSubscriptionConfig},
+        fixture_account_provider::{drift_account, drift_program_fixtures},
+        grpc::GrpcConnectionOpts,
+        utils::zero_account_to_bytes,
+        Context, Wallet,
+    };
+
+    /// Create a user of a client subscribing with `subscription`, `custom` replaces the
+    /// client's user subscription config
+    async fn drift_user(
+        subscription: Option<DriftClientSubscriptionConfig>,
+        custom: Option<UserSubscriptionConfig>,
+    ) -> SdkResult<DriftUser> {
+        let provider = drift_program_fixtures(
+            Context::MainNet,
+            &[PerpMarket::default()],
+            &[SpotMarket::default()],
+        )
+        .await;
+        let wallet = Wallet::read_only(Pubkey::new_unique());
+        let pubkey = wallet.sub_account(0);
+        let user = User {
+            authority: *wallet.authority(),
+            ..Default::default()
+        };
+        provider
+            .insert(pubkey, drift_account(zero_account_to_bytes(user)))
+            .await;
+        let mut client = DriftClient::new_with_opts(
+            Context::MainNet,
+            provider,
+            &wallet,
+            ClientOpts::new(0, None, subscription),
+        )
+        .await?;
+        if custom.is_some() {
+            client.user_account_subscription_config = custom;
+        }
+
+        DriftUser::new(pubkey, &client, Some(0)).await
+    }
+
+    #[tokio::test]
+    async fn subscribes_with_client_config() {
+        let account_loader = BulkAccountLoader::new(
+            Arc::new(RpcClient::new("http://127.0.0.1:8899".to_string())),
+            CommitmentConfig::confirmed(),
+            Duration::from_secs(1),
+        );
+
+        let user = drift_user(None, None).await.unwrap();
+        assert!(matches!(
+            user.subscription,
+            AccountSubscription::WebSocket(_)
+        ));
+        assert_eq!(user.get_user_account_and_slot().slot, 0);
+
+        let user = drift_user(
+            Some(DriftClientSubscriptionConfig::WebSocket {
+                resub_timeout_ms: 1_000,
+                log_resub_messages: false,
+                commitment: CommitmentLevel::Confirmed,
+            }),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            user.subscription,
+            AccountSubscription::WebSocket(_)
+        ));
+
+        let user = drift_user(
+            Some(DriftClientSubscriptionConfig::Grpc {
+                connection: GrpcConnectionOpts::new("http://127.0.0.1:10000", None),
+                commitment: CommitmentLevel::Confirmed,
+            }),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(user.subscription, AccountSubscription::Grpc(_)));
+
+        let user = drift_user(
+            Some(DriftClientSubscriptionConfig::Polling { account_loader }),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(user.subscription, AccountSubscription::Polling(_)));
+
+        let user = drift_user(Some(DriftClientSubscriptionConfig::OneShot), None)
+            .await
+            .unwrap();
+        assert!(matches!(user.subscription, AccountSubscription::OneShot(_)));
+    }
+
+    #[tokio::test]
+    async fn uses_custom_subscriber_as_is() {
+        let user_account_subscriber = AccountSubscription::one_shot(
+            Pubkey::new_unique(),
+            Arc::new(RpcClient::new("http://127.0.0.1:8899".to_string())),
+        );
+
+        let user = drift_user(
+            None,
+            Some(UserSubscriptionConfig::Custom {
+                user_account_subscriber,
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(user.subscription, AccountSubscription::OneShot(_)));
+    }
+}
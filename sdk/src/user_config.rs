@@ -1,9 +1,18 @@
-use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey};
+use std::sync::Arc;
+
+use anchor_lang::AccountDeserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+};
 
 use crate::{
-    accounts::{BulkAccountLoader, UserAccountSubscriber},
+    accounts::{AccountSubscription, BulkAccountLoader, UserAccountSubscriber},
     drift_client::DriftClient,
-    AccountProvider,
+    error::SdkError,
+    grpc::GrpcConnectionOpts,
+    AccountProvider, SdkResult,
 };
 
 pub struct UserConfig<T>
@@ -22,10 +31,66 @@ pub enum UserSubscriptionConfig {
         log_resub_messages: bool,
         commitment: CommitmentLevel,
     },
+    Grpc {
+        connection: GrpcConnectionOpts,
+        commitment: CommitmentLevel,
+    },
     Polling {
         account_loader: BulkAccountLoader,
     },
+    /// Fetch accounts once on subscribe
+    OneShot,
     Custom {
         user_account_subscriber: UserAccountSubscriber,
     },
 }
+
+impl UserSubscriptionConfig {
+    /// Build the subscriber of `pubkey` for this config
+    ///
+    /// `rpc_client` fetches the account. `Custom` subscribers are for user accounts only and
+    /// return an error.
+    pub(crate) fn subscriber<T>(
+        &self,
+        subscription_name: &'static str,
+        pubkey: Pubkey,
+        rpc_client: Arc<RpcClient>,
+    ) -> SdkResult<AccountSubscription<T>>
+    where
+        T: AccountDeserialize + Clone + Send + Sync + 'static,
+    {
+        let subscriber = match self {
+            Self::WebSocket { commitment, .. } => AccountSubscription::websocket(
+                subscription_name,
+                pubkey,
+                rpc_client,
+                CommitmentConfig {
+                    commitment: *commitment,
+                },
+            )?,
+            Self::Grpc {
+                connection,
+                commitment,
+            } => AccountSubscription::grpc(
+                subscription_name,
+                pubkey,
+                connection.clone(),
+                rpc_client,
+                CommitmentConfig {
+                    commitment: *commitment,
+                },
+            ),
+            Self::Polling { account_loader } => {
+                AccountSubscription::polling(pubkey, account_loader.clone())
+            }
+            Self::OneShot => AccountSubscription::one_shot(pubkey, rpc_client),
+            Self::Custom { .. } => {
+                return Err(SdkError::Generic(format!(
+                    "custom subscriber is not supported for {subscription_name} accounts"
+                )));
+            }
+        };
+
+        Ok(subscriber)
+    }
+}
@@ -1,15 +1,10 @@
 use std::sync::Arc;
 
-use anchor_client::{Client, Cluster};
 pub use drift::ID as PROGRAM_ID;
 use solana_sdk::pubkey::Pubkey;
 
 use crate::{
-    accounts::{
-        polling_user_stats_account_subscriber::PollingUserStatsAccountSubscriber,
-        web_socket_user_stats_account_subscriber::WebSocketUserStatsAccountSubscriber, ResubOpts,
-        UserStatsAccountSubscriber,
-    },
+    accounts::{AccountSubscriber, AccountSubscription, UserStatsAccountSubscriber},
     addresses::pda::{get_user_account_pubkey, get_user_stats_account_pubkey},
     drift_client::DriftClient,
     error::SdkError,
@@ -26,38 +21,45 @@ pub struct UserStats<T: AccountProvider> {
 }
 
 impl<T: AccountProvider> UserStats<T> {
+    pub const SUBSCRIPTION_ID: &'static str = "user_stats";
+
     pub fn new(config: UserStatsConfig<T>) -> SdkResult<Self> {
-        let client = Client::new(Cluster::Devnet, config.drift_client.wallet().signer.clone());
+        let pubkey = config.user_stats_account_public_key;
+        let rpc_client = Arc::clone(&config.drift_client.backend.rpc_client);
 
         let account_subscriber = match config.account_subscription {
             Some(account_sub) => match account_sub {
                 UserStatsSubscriptionConfig::Polling { account_loader } => {
-                    UserStatsAccountSubscriber::Polling(PollingUserStatsAccountSubscriber::new(
-                        Arc::new(client.program(PROGRAM_ID)),
-                        config.user_stats_account_public_key,
-                        account_loader,
-                    ))
+                    AccountSubscription::polling(pubkey, account_loader)
+                }
+                UserStatsSubscriptionConfig::WebSocket { commitment, .. } => {
+                    let commitment = commitment.unwrap_or(rpc_client.commitment());
+                    AccountSubscription::websocket(
+                        Self::SUBSCRIPTION_ID,
+                        pubkey,
+                        rpc_client,
+                        commitment,
+                    )?
                 }
-                UserStatsSubscriptionConfig::WebSocket {
-                    resub_timeout_ms,
-                    log_resub_messages,
+                UserStatsSubscriptionConfig::Grpc {
+                    connection,
                     commitment,
                 } => {
-                    UserStatsAccountSubscriber::WebSocket(WebSocketUserStatsAccountSubscriber::new(
-                        client.program(PROGRAM_ID),
-                        config.user_stats_account_public_key,
-                        Some(ResubOpts {
-                            resub_timeout_ms,
-                            log_resub_messages,
-                        }),
+                    let commitment = commitment.unwrap_or(rpc_client.commitment());
+                    AccountSubscription::grpc(
+                        Self::SUBSCRIPTION_ID,
+                        pubkey,
+                        connection,
+                        rpc_client,
                         commitment,
-                    ))
+                    )
                 }
-                UserStatsSubscriptionConfig::Custom => {
-                    return Err(SdkError::Generic(
-                        "Unknown user stats account subscription type".to_string(),
-                    ));
+                UserStatsSubscriptionConfig::OneShot => {
+                    AccountSubscription::one_shot(pubkey, rpc_client)
                 }
+                UserStatsSubscriptionConfig::Custom {
+                    user_stats_account_subscriber,
+                } => user_stats_account_subscriber,
             },
             None => {
                 return Err(SdkError::Generic(
@@ -68,20 +70,22 @@ impl<T: AccountProvider> UserStats<T> {
 
         Ok(Self {
             drift_client: config.drift_client,
-            user_stats_account_pubkey: config.user_stats_account_public_key,
+            user_stats_account_pubkey: pubkey,
             account_subscriber,
             is_subscribed: false,
         })
     }
 
+    /// Subscribe to the account, `user_stats_account` is its current value if already known
     pub async fn subscribe(
         &mut self,
         user_stats_account: Option<UserStatsAccount>,
     ) -> SdkResult<bool> {
-        self.is_subscribed = self
-            .account_subscriber
-            .subscribe(user_stats_account)
-            .await?;
+        if let Some(user_stats_account) = user_stats_account {
+            self.account_subscriber.set_data(user_stats_account, 0);
+        }
+        self.account_subscriber.subscribe(None).await?;
+        self.is_subscribed = self.account_subscriber.is_subscribed();
 
         Ok(self.is_subscribed)
     }
@@ -98,12 +102,11 @@ impl<T: AccountProvider> UserStats<T> {
     }
 
     pub fn get_account_and_slot(&self) -> SdkResult<Option<DataAndSlot<UserStatsAccount>>> {
-        let user_stats_account = self.account_subscriber.get_user_stats_account_and_slot()?;
-        Ok(user_stats_account)
+        Ok(self.account_subscriber.data_and_slot())
     }
 
     pub fn get_account(&self) -> SdkResult<Option<UserStatsAccount>> {
-        let account_and_slot = self.account_subscriber.get_user_stats_account_and_slot()?;
+        let account_and_slot = self.account_subscriber.data_and_slot();
 
         log::debug!("get account and slot: {:?}", account_and_slot);
         if let Some(account) = account_and_slot {
@@ -145,3 +148,119 @@ impl<T: AccountProvider> UserStats<T> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use drift::state::{perp_market::PerpMarket, spot_market::SpotMarket};
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use tokio::time::Duration;
+
+    use super::*;
+    use crate::{
+        accounts::BulkAccountLoader,
+        fixture_account_provider::{drift_program_fixtures, FixtureAccountProvider},
+        grpc::GrpcConnectionOpts,
+        Context, Wallet,
+    };
+
+    fn user_stats(
+        drift_client: &Arc<DriftClient<FixtureAccountProvider>>,
+        account_subscription: Option<UserStatsSubscriptionConfig>,
+    ) -> SdkResult<UserStats<FixtureAccountProvider>> {
+        UserStats::new(UserStatsConfig {
+            account_subscription,
+            drift_client: Arc::clone(drift_client),
+            user_stats_account_public_key: Pubkey::new_unique(),
+        })
+    }
+
+    #[tokio::test]
+    async fn subscribes_with_config() {
+        let provider = drift_program_fixtures(
+            Context::MainNet,
+            &[PerpMarket::default()],
+            &[SpotMarket::default()],
+        )
+        .await;
+        let drift_client = Arc::new(
+            DriftClient::new(
+                Context::MainNet,
+                provider,
+                &Wallet::read_only(Pubkey::new_unique()),
+            )
+            .await
+            .unwrap(),
+        );
+        let rpc_client = Arc::new(RpcClient::new("http://127.0.0.1:8899".to_string()));
+
+        let stats = user_stats(
+            &drift_client,
+            Some(UserStatsSubscriptionConfig::WebSocket {
+                resub_timeout_ms: None,
+                log_resub_messages: None,
+                commitment: None,
+            }),
+        )
+        .unwrap();
+        assert!(matches!(
+            stats.account_subscriber,
+            AccountSubscription::WebSocket(_)
+        ));
+
+        let stats = user_stats(
+            &drift_client,
+            Some(UserStatsSubscriptionConfig::Grpc {
+                connection: GrpcConnectionOpts::new("http://127.0.0.1:10000", None),
+                commitment: Some(CommitmentConfig::confirmed()),
+            }),
+        )
+        .unwrap();
+        assert!(matches!(
+            stats.account_subscriber,
+            AccountSubscription::Grpc(_)
+        ));
+
+        let stats = user_stats(
+            &drift_client,
+            Some(UserStatsSubscriptionConfig::Polling {
+                account_loader: BulkAccountLoader::new(
+                    Arc::clone(&rpc_client),
+                    CommitmentConfig::confirmed(),
+                    Duration::from_secs(1),
+                ),
+            }),
+        )
+        .unwrap();
+        assert!(matches!(
+            stats.account_subscriber,
+            AccountSubscription::Polling(_)
+        ));
+
+        let stats = user_stats(&drift_client, Some(UserStatsSubscriptionConfig::OneShot)).unwrap();
+        assert!(matches!(
+            stats.account_subscriber,
+            AccountSubscription::OneShot(_)
+        ));
+
+        let stats = user_stats(
+            &drift_client,
+            Some(UserStatsSubscriptionConfig::Custom {
+                user_stats_account_subscriber: AccountSubscription::one_shot(
+                    Pubkey::new_unique(),
+                    rpc_client,
+                ),
+            }),
+        )
+        .unwrap();
+        assert!(matches!(
+            stats.account_subscriber,
+            AccountSubscription::OneShot(_)
+        ));
+
+        assert!(matches!(
+            user_stats(&drift_client, None),
+            Err(SdkError::Generic(_))
+        ));
+    }
+}
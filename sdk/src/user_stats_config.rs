@@ -2,7 +2,12 @@ use std::sync::Arc;
 
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
 
-use crate::{accounts::BulkAccountLoader, drift_client::DriftClient, AccountProvider};
+use crate::{
+    accounts::{BulkAccountLoader, UserStatsAccountSubscriber},
+    drift_client::DriftClient,
+    grpc::GrpcConnectionOpts,
+    AccountProvider,
+};
 
 pub struct UserStatsConfig<T>
 where
@@ -20,8 +25,16 @@ pub enum UserStatsSubscriptionConfig {
         log_resub_messages: Option<bool>,
         commitment: Option<CommitmentConfig>,
     },
+    Grpc {
+        connection: GrpcConnectionOpts,
+        commitment: Option<CommitmentConfig>,
+    },
     Polling {
         account_loader: BulkAccountLoader,
     },
-    Custom,
+    /// Fetch the account once on subscribe
+    OneShot,
+    Custom {
+        user_stats_account_subscriber: UserStatsAccountSubscriber,
+    },
 }
@@ -1,41 +1,69 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use anchor_lang::AccountDeserialize;
 use dashmap::DashMap;
 use drift::state::events::OrderRecord;
-use drift::state::user::User;
-use serde_json::json;
+use drift::state::user::{MarketType, User};
+use futures_util::future::BoxFuture;
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
 use solana_client::rpc_filter::RpcFilterType;
-use solana_client::rpc_request::RpcRequest;
-use solana_client::rpc_response::{OptionalContext, RpcKeyedAccount};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
 
-use crate::dlob::dlob::DLOB;
 use crate::event_emitter::EventEmitter;
+use crate::grpc::{GrpcConnectionOpts, GrpcProgramAccountSubscriber};
 use crate::memcmp::{get_non_idle_user_filter, get_user_filter};
-use crate::utils::{decode, get_ws_url};
+use crate::program_account_subscriber::ProgramAccountSubscriber;
+use crate::utils::get_ws_url;
 use crate::websocket_program_account_subscriber::{
     ProgramAccountUpdate, WebsocketProgramAccountOptions, WebsocketProgramAccountSubscriber,
 };
-use crate::SdkResult;
+use crate::{AccountProvider, RpcAccountProvider, SdkError, SdkResult};
 
+use self::indexes::{insert_user, UserIndexes};
+
+mod indexes;
 pub mod user_stats_map;
 
+/// Identifies a `UserMap` snapshot file
+///
+/// Snapshots are zstd compressed: magic, version (u8), user size (u32), slot (u64), user count
+/// (u64) then each user's pubkey and account bytes, integers little endian
+const SNAPSHOT_MAGIC: &[u8; 4] = b"DUMS";
+const SNAPSHOT_VERSION: u8 = 1;
+const SNAPSHOT_HEADER_LEN: usize = 4 + 1 + 4 + 8 + 8;
+/// zstd level, users are mostly zeroed orders and positions so compress well at a low level
+const SNAPSHOT_COMPRESSION_LEVEL: i32 = 3;
+/// attempts to fetch users when reconciling a loaded snapshot
+const SNAPSHOT_RESYNC_ATTEMPTS: u32 = 3;
+
 #[derive(Clone)]
 pub struct UserMap {
     subscribed: bool,
-    subscription: WebsocketProgramAccountSubscriber,
+    subscription: ProgramAccountSubscriber,
     pub(crate) usermap: Arc<DashMap<String, User>>,
+    /// secondary indexes of `usermap`, updated with it
+    indexes: Arc<RwLock<UserIndexes>>,
     sync_lock: Arc<Option<Mutex<()>>>,
     latest_slot: Arc<AtomicU64>,
     commitment: CommitmentConfig,
-    rpc: Arc<RpcClient>,
+    /// fetches users when syncing and on a cache miss
+    accounts: Arc<dyn AccountSource>,
+    /// slot of the snapshot loaded by `load_snapshot`, reconciled on `subscribe`
+    snapshot_slot: Option<u64>,
+    /// slots of subscription updates received while a snapshot is reconciled
+    resync_updates: Arc<Mutex<Option<HashMap<String, u64>>>>,
 }
 
 impl UserMap {
@@ -47,27 +75,64 @@ impl UserMap {
         sync: bool,
         additional_filters: Option<Vec<RpcFilterType>>,
     ) -> Self {
-        let mut filters = vec![get_user_filter(), get_non_idle_user_filter()];
-        filters.extend(additional_filters.unwrap_or_default());
-        let options = WebsocketProgramAccountOptions {
-            filters,
-            commitment,
-            encoding: UiAccountEncoding::Base64,
-        };
-        let event_emitter = EventEmitter::new();
-
         let url = get_ws_url(endpoint).unwrap();
 
         let subscription = WebsocketProgramAccountSubscriber::new(
             UserMap::SUBSCRIPTION_ID,
             url,
-            options,
-            event_emitter,
+            Self::options(commitment, additional_filters),
+            EventEmitter::new(),
+        );
+
+        Self::with_subscription(commitment, endpoint, sync, subscription.into())
+    }
+
+    /// Create a new UserMap streaming user account updates over Yellowstone gRPC
+    ///
+    /// `endpoint` RPC endpoint used for syncing and fetching missing users
+    pub fn new_grpc(
+        commitment: CommitmentConfig,
+        endpoint: &str,
+        grpc: GrpcConnectionOpts,
+        sync: bool,
+        additional_filters: Option<Vec<RpcFilterType>>,
+    ) -> Self {
+        let subscription = GrpcProgramAccountSubscriber::new(
+            UserMap::SUBSCRIPTION_ID,
+            grpc,
+            Arc::new(RpcClient::new_with_commitment(
+                endpoint.to_string(),
+                commitment,
+            )),
+            Self::options(commitment, additional_filters),
+            EventEmitter::new(),
         );
 
+        Self::with_subscription(commitment, endpoint, sync, subscription.into())
+    }
+
+    fn options(
+        commitment: CommitmentConfig,
+        additional_filters: Option<Vec<RpcFilterType>>,
+    ) -> WebsocketProgramAccountOptions {
+        let mut filters = vec![get_user_filter(), get_non_idle_user_filter()];
+        filters.extend(additional_filters.unwrap_or_default());
+        WebsocketProgramAccountOptions {
+            filters,
+            commitment,
+            encoding: UiAccountEncoding::Base64,
+        }
+    }
+
+    fn with_subscription(
+        commitment: CommitmentConfig,
+        endpoint: &str,
+        sync: bool,
+        subscription: ProgramAccountSubscriber,
+    ) -> Self {
         let usermap = Arc::new(DashMap::new());
 
-        let rpc = RpcClient::new_with_commitment(endpoint.to_string(), commitment);
+        let rpc = RpcAccountProvider::with_commitment(endpoint, commitment);
 
         let sync_lock = if sync { Some(Mutex::new(())) } else { None };
 
@@ -75,16 +140,36 @@ impl UserMap {
             subscribed: false,
             subscription,
             usermap,
+            indexes: Default::default(),
             sync_lock: Arc::new(sync_lock),
             latest_slot: Arc::new(AtomicU64::new(0)),
             commitment,
-            rpc: Arc::new(rpc),
+            accounts: Arc::new(rpc),
+            snapshot_slot: None,
+            resync_updates: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Fetch users with `provider` rather than from RPC at the map's endpoint
+    pub fn with_account_provider(mut self, provider: impl AccountProvider) -> Self {
+        self.accounts = Arc::new(provider);
+        self
+    }
+
+    /// Sync users and subscribe to their updates
+    ///
+    /// After `load_snapshot` the map is usable immediately, the snapshot is reconciled with the
+    /// chain in the background instead of blocking on a full sync
     pub async fn subscribe(&mut self) -> SdkResult<()> {
+        let mut resync_slot = None;
         if self.sync_lock.is_some() {
-            self.sync().await?;
+            match self.snapshot_slot.take() {
+                Some(snapshot_slot) => {
+                    *self.resync_updates.lock().expect("lock") = Some(HashMap::new());
+                    resync_slot = Some(snapshot_slot);
+                }
+                None => self.sync().await?,
+            }
         }
 
         if !self.subscribed {
@@ -92,10 +177,12 @@ impl UserMap {
             self.subscribed = true;
 
             let usermap = self.usermap.clone();
+            let indexes = self.indexes.clone();
             let latest_slot = self.latest_slot.clone();
+            let resync_updates = self.resync_updates.clone();
 
             self.subscription
-                .event_emitter
+                .event_emitter()
                 .subscribe(UserMap::SUBSCRIPTION_ID, move |event| {
                     if let Some(update) =
                         event.as_any().downcast_ref::<ProgramAccountUpdate<User>>()
@@ -105,18 +192,30 @@ impl UserMap {
                         if update.data_and_slot.slot > latest_slot.load(Ordering::Relaxed) {
                             latest_slot.store(update.data_and_slot.slot, Ordering::Relaxed);
                         }
-                        usermap.insert(user_pubkey, user_data_and_slot.data);
+                        if let Some(updates) = resync_updates.lock().expect("lock").as_mut() {
+                            updates.insert(user_pubkey.clone(), user_data_and_slot.slot);
+                        }
+                        insert_user(&usermap, &indexes, user_pubkey, user_data_and_slot.data);
                     }
                 });
         }
 
+        if let Some(snapshot_slot) = resync_slot {
+            let usermap = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = usermap.resync_snapshot(snapshot_slot).await {
+                    log::error!("usermap snapshot resync failed: {e}");
+                }
+            });
+        }
+
         Ok(())
     }
 
     pub async fn add_pubkey(&mut self, user_account_pubkey: &Pubkey) -> SdkResult<()> {
-        let user_data = self.rpc.get_account_data(user_account_pubkey).await?;
+        let user_data = self.accounts.account(*user_account_pubkey).await?.data;
         let user = User::try_deserialize(&mut user_data.as_slice()).unwrap();
-        self.usermap.insert(user_account_pubkey.to_string(), user);
+        self.insert(user_account_pubkey.to_string(), user);
 
         Ok(())
     }
@@ -126,6 +225,7 @@ impl UserMap {
             self.subscription.unsubscribe().await?;
             self.subscribed = false;
             self.usermap.clear();
+            self.indexes.write().expect("lock").clear();
             self.latest_slot.store(0, Ordering::Relaxed);
         }
         Ok(())
@@ -143,17 +243,64 @@ impl UserMap {
         self.usermap.get(pubkey).map(|user| *user.value())
     }
 
+    /// Return a snapshot of all users, keyed by user account pubkey
+    pub fn values(&self) -> Vec<(Pubkey, User)> {
+        self.usermap
+            .iter()
+            .filter_map(|x| Pubkey::from_str(x.key()).ok().map(|key| (key, *x.value())))
+            .collect()
+    }
+
+    /// Return the users of `authority`, keyed by user account pubkey
+    pub fn users_by_authority(&self, authority: &Pubkey) -> Vec<(Pubkey, User)> {
+        let users = self.indexes.read().expect("lock").by_authority(authority);
+        self.get_all(users)
+    }
+
+    /// Return the users with an open order or position in the market, keyed by user account pubkey
+    pub fn users_in_market(
+        &self,
+        market_type: MarketType,
+        market_index: u16,
+    ) -> Vec<(Pubkey, User)> {
+        let users = self
+            .indexes
+            .read()
+            .expect("lock")
+            .by_market(market_type, market_index);
+        self.get_all(users)
+    }
+
+    /// Return the users with an order auction in progress at `slot`, keyed by user account pubkey
+    pub fn users_in_auction(&self, slot: u64) -> Vec<(Pubkey, User)> {
+        let users = self.indexes.read().expect("lock").in_auction(slot);
+        self.get_all(users)
+    }
+
+    fn get_all(&self, pubkeys: Vec<Pubkey>) -> Vec<(Pubkey, User)> {
+        pubkeys
+            .into_iter()
+            .filter_map(|pubkey| self.get(&pubkey.to_string()).map(|user| (pubkey, user)))
+            .collect()
+    }
+
+    /// Insert or replace a user, keeping the indexes in sync
+    fn insert(&self, pubkey: String, user: User) {
+        insert_user(&self.usermap, &self.indexes, pubkey, user);
+    }
+
     /// Get the User for a particular user_acount_pubkey, if no User exists, new one is created
     pub async fn must_get(&self, pubkey: &str) -> SdkResult<User> {
         if let Some(user) = self.get(pubkey) {
             Ok(user)
         } else {
             let user_data = self
-                .rpc
-                .get_account_data(&Pubkey::from_str(pubkey).unwrap())
-                .await?;
+                .accounts
+                .account(Pubkey::from_str(pubkey).unwrap())
+                .await?
+                .data;
             let user = User::try_deserialize(&mut user_data.as_slice()).unwrap();
-            self.usermap.insert(pubkey.to_string(), user);
+            self.insert(pubkey.to_string(), user);
             Ok(self.get(pubkey).unwrap())
         }
     }
@@ -167,11 +314,12 @@ impl UserMap {
             Ok((user, latest_slot))
         } else {
             let user_data = self
-                .rpc
-                .get_account_data(&Pubkey::from_str(pubkey).unwrap())
-                .await?;
+                .accounts
+                .account(Pubkey::from_str(pubkey).unwrap())
+                .await?
+                .data;
             let user = User::try_deserialize(&mut user_data.as_slice()).unwrap();
-            self.usermap.insert(pubkey.to_string(), user);
+            self.insert(pubkey.to_string(), user);
             Ok((self.get(pubkey).unwrap(), latest_slot))
         }
     }
@@ -186,43 +334,168 @@ impl UserMap {
                 Err(_) => return Ok(()),
             };
 
-            let account_config = RpcAccountInfoConfig {
-                commitment: Some(self.commitment),
-                encoding: Some(self.subscription.options.encoding),
-                ..RpcAccountInfoConfig::default()
-            };
+            let (slot, users) = self.fetch_users(None).await?;
+            for (pubkey, user) in users {
+                self.insert(pubkey, user);
+            }
+            self.latest_slot.store(slot, Ordering::Relaxed);
 
-            let gpa_config = RpcProgramAccountsConfig {
-                filters: Some(self.subscription.options.filters.clone()),
-                account_config,
-                with_context: Some(true),
-            };
+            drop(lock);
+        }
+        Ok(())
+    }
 
-            let response = self
-                .rpc
-                .send::<OptionalContext<Vec<RpcKeyedAccount>>>(
-                    RpcRequest::GetProgramAccounts,
-                    json!([drift::id().to_string(), gpa_config]),
-                )
-                .await?;
-
-            if let OptionalContext::Context(accounts) = response {
-                for account in accounts.value {
-                    let pubkey = account.pubkey;
-                    let user_data = account.account.data;
-                    let data = decode::<User>(user_data)?;
-                    self.usermap.insert(pubkey, data);
-                }
+    /// Fetch all users matching the subscription filters, returns (context slot, users)
+    ///
+    /// `min_context_slot` fail unless the RPC node has reached this slot
+    async fn fetch_users(
+        &self,
+        min_context_slot: Option<u64>,
+    ) -> SdkResult<(u64, Vec<(String, User)>)> {
+        let account_config = RpcAccountInfoConfig {
+            commitment: Some(self.commitment),
+            encoding: Some(self.subscription.options().encoding),
+            min_context_slot,
+            ..RpcAccountInfoConfig::default()
+        };
+
+        let gpa_config = RpcProgramAccountsConfig {
+            filters: Some(self.subscription.options().filters.clone()),
+            account_config,
+            with_context: Some(true),
+        };
+
+        let (slot, accounts) = self.accounts.program_accounts(gpa_config).await?;
+        let users = accounts
+            .into_iter()
+            .map(|(pubkey, account)| {
+                let user = User::try_deserialize(&mut account.data.as_slice())
+                    .map_err(|err| SdkError::Anchor(Box::new(err)))?;
+                Ok((pubkey.to_string(), user))
+            })
+            .collect::<SdkResult<Vec<_>>>()?;
+
+        Ok((slot, users))
+    }
 
-                self.latest_slot
-                    .store(accounts.context.slot, Ordering::Relaxed);
+    /// Reconcile users loaded from a snapshot taken at `snapshot_slot` with the chain
+    async fn resync_snapshot(&self, snapshot_slot: u64) -> SdkResult<()> {
+        let mut result = self.fetch_users(Some(snapshot_slot)).await;
+        for _ in 1..SNAPSHOT_RESYNC_ATTEMPTS {
+            if result.is_ok() {
+                break;
             }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            result = self.fetch_users(Some(snapshot_slot)).await;
+        }
 
-            drop(lock);
+        match result {
+            Ok((slot, users)) => {
+                self.reconcile(slot, users);
+                Ok(())
+            }
+            Err(err) => {
+                // keep the snapshot users, the subscription updates them from here
+                self.resync_updates.lock().expect("lock").take();
+                Err(err)
+            }
         }
+    }
+
+    /// Replace the map with `users` fetched at `slot`
+    ///
+    /// Users the subscription updated after `slot` are kept, they are newer than the fetch
+    fn reconcile(&self, slot: u64, users: Vec<(String, User)>) {
+        // held while applying so updates are either recorded here or applied after
+        let mut resync_updates = self.resync_updates.lock().expect("lock");
+        let updates = resync_updates.take().unwrap_or_default();
+        let is_newer = |pubkey: &str| updates.get(pubkey).is_some_and(|s| *s > slot);
+
+        let mut fetched = HashSet::with_capacity(users.len());
+        for (pubkey, user) in users {
+            if !is_newer(&pubkey) {
+                self.insert(pubkey.clone(), user);
+            }
+            fetched.insert(pubkey);
+        }
+        // users that went idle or were deleted since the snapshot
+        let mut indexes = self.indexes.write().expect("lock");
+        self.usermap.retain(|pubkey, user| {
+            let keep = fetched.contains(pubkey) || is_newer(pubkey);
+            if let (false, Ok(pubkey)) = (keep, Pubkey::from_str(pubkey)) {
+                indexes.update(pubkey, Some(user), None);
+            }
+            keep
+        });
+
+        self.latest_slot.fetch_max(slot, Ordering::Relaxed);
+    }
+
+    /// Save the users and latest slot to a zstd compressed snapshot at `path`
+    ///
+    /// The snapshot is written to a temporary file then renamed, so `path` is never partially
+    /// written
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> SdkResult<()> {
+        let path = path.as_ref();
+        let slot = self.get_latest_slot();
+        let users = self.values();
+
+        let tmp_path = path.with_extension("tmp");
+        let file = File::create(&tmp_path)?;
+        let mut writer = zstd::Encoder::new(BufWriter::new(file), SNAPSHOT_COMPRESSION_LEVEL)?;
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&[SNAPSHOT_VERSION])?;
+        writer.write_all(&(std::mem::size_of::<User>() as u32).to_le_bytes())?;
+        writer.write_all(&slot.to_le_bytes())?;
+        writer.write_all(&(users.len() as u64).to_le_bytes())?;
+        for (pubkey, user) in users.iter() {
+            writer.write_all(pubkey.as_ref())?;
+            writer.write_all(bytemuck::bytes_of(user))?;
+        }
+        writer.finish()?.flush()?;
+        std::fs::rename(tmp_path, path)?;
+
         Ok(())
     }
 
+    /// Load users from a snapshot saved by `save_snapshot`, returns the snapshot slot
+    ///
+    /// Call before `subscribe`, which reconciles the snapshot with the chain when syncing
+    pub fn load_snapshot(&mut self, path: impl AsRef<Path>) -> SdkResult<u64> {
+        let file = File::open(path)?;
+        let mut reader = zstd::Decoder::new(BufReader::new(file))?;
+
+        let mut header = [0_u8; SNAPSHOT_HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        let user_size = u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize;
+        if &header[..4] != SNAPSHOT_MAGIC
+            || header[4] != SNAPSHOT_VERSION
+            || user_size != std::mem::size_of::<User>()
+        {
+            return Err(SdkError::Generic(
+                "unsupported usermap snapshot".to_string(),
+            ));
+        }
+        let slot = u64::from_le_bytes(header[9..17].try_into().unwrap());
+        let count = u64::from_le_bytes(header[17..25].try_into().unwrap());
+
+        // read every user first so a truncated snapshot leaves the map untouched
+        let mut users = Vec::new();
+        let mut entry = vec![0_u8; 32 + user_size];
+        for _ in 0..count {
+            reader.read_exact(&mut entry)?;
+            let pubkey = Pubkey::try_from(&entry[..32]).expect("32 bytes");
+            users.push((pubkey, bytemuck::pod_read_unaligned::<User>(&entry[32..])));
+        }
+        for (pubkey, user) in users {
+            self.insert(pubkey.to_string(), user);
+        }
+        self.latest_slot.fetch_max(slot, Ordering::Relaxed);
+        self.snapshot_slot = Some(slot);
+
+        Ok(slot)
+    }
+
     pub fn get_latest_slot(&self) -> u64 {
         self.latest_slot.load(Ordering::Relaxed)
     }
@@ -234,11 +507,26 @@ impl UserMap {
 
         Ok(())
     }
+}
 
-    pub fn get_dlob(&self, slot: u64) -> DLOB {
-        let mut dlob = DLOB::new();
-        dlob.build_from_usermap(self, slot);
-        dlob
+/// Object safe `AccountProvider`, so `UserMap` can fetch from any provider without being generic
+trait AccountSource: Send + Sync {
+    fn account(&self, pubkey: Pubkey) -> BoxFuture<SdkResult<Account>>;
+    fn program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>>;
+}
+
+impl<T: AccountProvider> AccountSource for T {
+    fn account(&self, pubkey: Pubkey) -> BoxFuture<SdkResult<Account>> {
+        self.get_account(pubkey)
+    }
+    fn program_accounts(
+        &self,
+        config: RpcProgramAccountsConfig,
+    ) -> BoxFuture<SdkResult<(Slot, Vec<(Pubkey, Account)>)>> {
+        self.get_program_accounts(config)
     }
 }
 
@@ -247,30 +535,261 @@ mod tests {
 
     #[tokio::test]
     async fn test_usermap() {
+        use crate::fixture_account_provider::{drift_account, TEST_FIXTURES_DIR};
         use crate::usermap::UserMap;
+        use crate::utils::zero_account_to_bytes;
+        use crate::{AccountProvider, FixtureAccountProvider};
         use solana_sdk::commitment_config::CommitmentConfig;
-        use solana_sdk::commitment_config::CommitmentLevel;
+        use solana_sdk::pubkey::Pubkey;
 
-        let endpoint = "https://api.devnet.solana.com".to_string();
-        let commitment = CommitmentConfig {
-            commitment: CommitmentLevel::Processed,
-        };
+        let provider = FixtureAccountProvider::new(TEST_FIXTURES_DIR);
+        let (user_1, user_2, idle_user) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (authority_1, authority_2) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for (pubkey, user) in [
+            (user_1, user_with_authority(authority_1)),
+            (user_2, user_with_authority(authority_2)),
+            (
+                idle_user,
+                drift::state::user::User {
+                    idle: true,
+                    ..user_with_authority(authority_1)
+                },
+            ),
+        ] {
+            provider
+                .insert(pubkey, drift_account(zero_account_to_bytes(user)))
+                .await;
+        }
 
-        let mut usermap = UserMap::new(commitment, &endpoint, true, None);
-        usermap.subscribe().await.unwrap();
+        let mut usermap = UserMap::new(
+            CommitmentConfig::confirmed(),
+            &provider.endpoint(),
+            true,
+            None,
+        )
+        .with_account_provider(provider);
+        usermap.sync().await.unwrap();
+
+        // idle users are filtered out
+        assert_eq!(usermap.size(), 2);
+        assert_eq!(
+            usermap.get(&user_2.to_string()).unwrap().authority,
+            authority_2
+        );
+        assert_eq!(usermap.users_by_authority(&authority_1).len(), 1);
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+        // users missing from the map are fetched with the provider
+        assert!(!usermap.contains(&idle_user.to_string()));
+        let user = usermap.must_get(&idle_user.to_string()).await.unwrap();
+        assert!(user.idle);
+        assert_eq!(usermap.size(), 3);
+    }
 
-        dbg!(usermap.size());
-        assert!(usermap.size() > 500);
+    fn user_with_authority(authority: solana_sdk::pubkey::Pubkey) -> drift::state::user::User {
+        drift::state::user::User {
+            authority,
+            ..Default::default()
+        }
+    }
 
-        dbg!(usermap.get_latest_slot());
+    #[tokio::test]
+    async fn snapshot_round_trip() {
+        use crate::usermap::UserMap;
+        use solana_sdk::commitment_config::CommitmentConfig;
+        use solana_sdk::pubkey::Pubkey;
+        use std::sync::atomic::Ordering;
+
+        let path = std::env::temp_dir().join(format!("usermap-{}.snapshot", uuid::Uuid::new_v4()));
+        let users = [
+            (Pubkey::new_unique(), Pubkey::new_unique()),
+            (Pubkey::new_unique(), Pubkey::new_unique()),
+        ];
+
+        let usermap = UserMap::new(
+            CommitmentConfig::confirmed(),
+            "http://127.0.0.1:1",
+            true,
+            None,
+        );
+        for (pubkey, authority) in users {
+            usermap
+                .usermap
+                .insert(pubkey.to_string(), user_with_authority(authority));
+        }
+        usermap.latest_slot.store(100, Ordering::Relaxed);
+        usermap.save_snapshot(&path).unwrap();
+
+        let mut loaded = UserMap::new(
+            CommitmentConfig::confirmed(),
+            "http://127.0.0.1:1",
+            true,
+            None,
+        );
+        assert_eq!(loaded.load_snapshot(&path).unwrap(), 100);
+        assert_eq!(loaded.size(), 2);
+        assert_eq!(loaded.get_latest_slot(), 100);
+        assert_eq!(loaded.snapshot_slot, Some(100));
+        for (pubkey, authority) in users {
+            assert_eq!(
+                loaded.get(&pubkey.to_string()).unwrap().authority,
+                authority
+            );
+            // loaded users are indexed
+            let by_authority = loaded.users_by_authority(&authority);
+            assert_eq!(by_authority.len(), 1);
+            assert_eq!(by_authority[0].0, pubkey);
+        }
 
-        usermap.unsubscribe().await.unwrap();
+        std::fs::write(
+            &path,
+            zstd::encode_all(&b"not a snapshot at all"[..], 0).unwrap(),
+        )
+        .unwrap();
+        assert!(loaded.load_snapshot(&path).is_err());
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+        std::fs::remove_file(path).unwrap();
+    }
 
+    #[tokio::test]
+    async fn truncated_snapshot_loads_nothing() {
+        use crate::usermap::UserMap;
+        use solana_sdk::commitment_config::CommitmentConfig;
+        use solana_sdk::pubkey::Pubkey;
+        use std::sync::atomic::Ordering;
+
+        let path = std::env::temp_dir().join(format!("usermap-{}.snapshot", uuid::Uuid::new_v4()));
+        let usermap = UserMap::new(
+            CommitmentConfig::confirmed(),
+            "http://127.0.0.1:1",
+            true,
+            None,
+        );
+        for _ in 0..2 {
+            usermap.usermap.insert(
+                Pubkey::new_unique().to_string(),
+                user_with_authority(Pubkey::new_unique()),
+            );
+        }
+        usermap.latest_slot.store(100, Ordering::Relaxed);
+        usermap.save_snapshot(&path).unwrap();
+
+        // cut the last user short
+        let snapshot = zstd::decode_all(std::fs::File::open(&path).unwrap()).unwrap();
+        let truncated = &snapshot[..snapshot.len() - 100];
+        std::fs::write(&path, zstd::encode_all(truncated, 0).unwrap()).unwrap();
+
+        let mut loaded = UserMap::new(
+            CommitmentConfig::confirmed(),
+            "http://127.0.0.1:1",
+            true,
+            None,
+        );
+        let existing = Pubkey::new_unique();
+        loaded.insert(existing.to_string(), user_with_authority(existing));
+        assert!(loaded.load_snapshot(&path).is_err());
+        assert_eq!(loaded.size(), 1);
+        assert!(loaded.contains(&existing.to_string()));
+        assert_eq!(loaded.get_latest_slot(), 0);
+        assert_eq!(loaded.snapshot_slot, None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reconcile_keeps_newer_subscription_updates() {
+        use crate::usermap::UserMap;
+        use solana_sdk::commitment_config::CommitmentConfig;
+        use solana_sdk::pubkey::Pubkey;
+        use std::collections::HashMap;
+
+        let usermap = UserMap::new(
+            CommitmentConfig::confirmed(),
+            "http://127.0.0.1:1",
+            true,
+            None,
+        );
+        let [refetched, went_idle, updated, created] =
+            [0; 4].map(|_| Pubkey::new_unique().to_string());
+        let (old, new) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        // loaded from a snapshot at slot 90
+        for pubkey in [&refetched, &went_idle, &updated] {
+            usermap
+                .usermap
+                .insert(pubkey.clone(), user_with_authority(old));
+        }
+        // the subscription updated users after the fetch
+        usermap
+            .usermap
+            .insert(updated.clone(), user_with_authority(new));
+        usermap
+            .usermap
+            .insert(created.clone(), user_with_authority(new));
+        *usermap.resync_updates.lock().unwrap() = Some(HashMap::from([
+            (updated.clone(), 105),
+            (created.clone(), 105),
+        ]));
+
+        usermap.reconcile(
+            100,
+            vec![
+                (refetched.clone(), user_with_authority(new)),
+                (updated.clone(), user_with_authority(old)),
+            ],
+        );
+
+        assert_eq!(usermap.size(), 3);
+        assert_eq!(usermap.get(&refetched).unwrap().authority, new);
+        assert!(!usermap.contains(&went_idle));
+        assert_eq!(usermap.get(&updated).unwrap().authority, new);
+        assert_eq!(usermap.get(&created).unwrap().authority, new);
+        assert_eq!(usermap.get_latest_slot(), 100);
+        assert!(usermap.resync_updates.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_usermap_grpc() {
+        use crate::grpc::stub::{account_update, serve, user_account_data};
+        use crate::grpc::GrpcConnectionOpts;
+        use crate::usermap::UserMap;
+        use solana_sdk::commitment_config::CommitmentConfig;
+        use solana_sdk::pubkey::Pubkey;
+
+        let user = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let (url, _requests) = serve(vec![vec![account_update(
+            &user,
+            &drift::ID,
+            user_account_data(authority),
+            10,
+            1,
+        )]])
+        .await;
+
+        let mut usermap = UserMap::new_grpc(
+            CommitmentConfig::confirmed(),
+            "http://127.0.0.1:1",
+            GrpcConnectionOpts::new(&url, None),
+            false,
+            None,
+        );
+        usermap.subscribe().await.unwrap();
+
+        tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
+            while usermap.size() == 0 {
+                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("user update");
+        assert_eq!(usermap.get(&user.to_string()).unwrap().authority, authority);
+        assert_eq!(usermap.get_latest_slot(), 10);
+
+        usermap.unsubscribe().await.unwrap();
         assert_eq!(usermap.size(), 0);
-        assert_eq!(usermap.subscribed, false);
     }
 }
@@ -0,0 +1,226 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::RwLock,
+};
+
+use dashmap::DashMap;
+use drift::state::user::{MarketType, OrderStatus, User};
+use solana_sdk::{clock::Slot, pubkey::Pubkey};
+
+/// Secondary indexes over the users of a `UserMap`
+#[derive(Default)]
+pub(crate) struct UserIndexes {
+    /// authority to its user (sub)accounts
+    by_authority: HashMap<Pubkey, HashSet<Pubkey>>,
+    /// perp market index to users with an open order or position in it
+    by_perp_market: HashMap<u16, HashSet<Pubkey>>,
+    /// spot market index to users with an open order or position in it
+    by_spot_market: HashMap<u16, HashSet<Pubkey>>,
+    /// users with open auction orders, to the last slot of their latest ending auction
+    auction_end_slots: HashMap<Pubkey, Slot>,
+}
+
+impl UserIndexes {
+    /// Replace the index entries of `pubkey` for its `old` account with those for `new`
+    ///
+    /// `new` None removes the user
+    pub fn update(&mut self, pubkey: Pubkey, old: Option<&User>, new: Option<&User>) {
+        if let Some(old) = old {
+            remove_from(&mut self.by_authority, &old.authority, &pubkey);
+            let (perp_markets, spot_markets) = markets(old);
+            for market_index in perp_markets {
+                remove_from(&mut self.by_perp_market, &market_index, &pubkey);
+            }
+            for market_index in spot_markets {
+                remove_from(&mut self.by_spot_market, &market_index, &pubkey);
+            }
+            self.auction_end_slots.remove(&pubkey);
+        }
+
+        if let Some(new) = new {
+            self.by_authority
+                .entry(new.authority)
+                .or_default()
+                .insert(pubkey);
+            let (perp_markets, spot_markets) = markets(new);
+            for market_index in perp_markets {
+                self.by_perp_market
+                    .entry(market_index)
+                    .or_default()
+                    .insert(pubkey);
+            }
+            for market_index in spot_markets {
+                self.by_spot_market
+                    .entry(market_index)
+                    .or_default()
+                    .insert(pubkey);
+            }
+            if let Some(end_slot) = auction_end_slot(new) {
+                self.auction_end_slots.insert(pubkey, end_slot);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn by_authority(&self, authority: &Pubkey) -> Vec<Pubkey> {
+        self.by_authority
+            .get(authority)
+            .map(|users| users.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn by_market(&self, market_type: MarketType, market_index: u16) -> Vec<Pubkey> {
+        let index = match market_type {
+            MarketType::Perp => &self.by_perp_market,
+            MarketType::Spot => &self.by_spot_market,
+        };
+        index
+            .get(&market_index)
+            .map(|users| users.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn in_auction(&self, slot: Slot) -> Vec<Pubkey> {
+        self.auction_end_slots
+            .iter()
+            .filter(|(_, end_slot)| **end_slot >= slot)
+            .map(|(pubkey, _)| *pubkey)
+            .collect()
+    }
+}
+
+/// Insert or replace the user at `pubkey` in `usermap`, keeping `indexes` in sync
+pub(crate) fn insert_user(
+    usermap: &DashMap<String, User>,
+    indexes: &RwLock<UserIndexes>,
+    pubkey: String,
+    user: User,
+) {
+    // held across the map update so concurrent updates of a user are indexed in order
+    let mut indexes = indexes.write().expect("lock");
+    let key = Pubkey::from_str(&pubkey);
+    let old = usermap.insert(pubkey, user);
+    if let Ok(key) = key {
+        indexes.update(key, old.as_ref(), Some(&user));
+    }
+}
+
+/// Remove `pubkey` from the index set of `key`, dropping the set once empty
+fn remove_from<K: Eq + std::hash::Hash>(
+    index: &mut HashMap<K, HashSet<Pubkey>>,
+    key: &K,
+    pubkey: &Pubkey,
+) {
+    if let Some(users) = index.get_mut(key) {
+        users.remove(pubkey);
+        if users.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+/// Returns the (perp, spot) market indexes the user has an open order or position in
+fn markets(user: &User) -> (HashSet<u16>, HashSet<u16>) {
+    let mut perp_markets: HashSet<u16> = user
+        .perp_positions
+        .iter()
+        .filter(|p| !p.is_available())
+        .map(|p| p.market_index)
+        .collect();
+    let mut spot_markets: HashSet<u16> = user
+        .spot_positions
+        .iter()
+        .filter(|p| !p.is_available())
+        .map(|p| p.market_index)
+        .collect();
+    for order in user.orders.iter().filter(|o| o.status == OrderStatus::Open) {
+        match order.market_type {
+            MarketType::Perp => perp_markets.insert(order.market_index),
+            MarketType::Spot => spot_markets.insert(order.market_index),
+        };
+    }
+
+    (perp_markets, spot_markets)
+}
+
+/// Returns the last slot of the user's latest ending auction, None without open auction orders
+fn auction_end_slot(user: &User) -> Option<Slot> {
+    user.orders
+        .iter()
+        .filter(|o| o.status == OrderStatus::Open && o.auction_duration > 0)
+        .map(|o| o.slot + o.auction_duration as Slot)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use drift::state::user::{Order, PerpPosition, SpotPosition};
+
+    use super::*;
+
+    fn open_order(market_type: MarketType, market_index: u16, slot: Slot, auction: u8) -> Order {
+        Order {
+            status: OrderStatus::Open,
+            market_type,
+            market_index,
+            slot,
+            auction_duration: auction,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn indexes_follow_user_updates() {
+        let mut indexes = UserIndexes::default();
+        let (pubkey, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut user = User {
+            authority,
+            ..Default::default()
+        };
+        user.perp_positions[0] = PerpPosition {
+            market_index: 1,
+            base_asset_amount: 1_000,
+            ..Default::default()
+        };
+        user.spot_positions[0] = SpotPosition {
+            market_index: 0,
+            scaled_balance: 1_000,
+            ..Default::default()
+        };
+        user.orders[0] = open_order(MarketType::Perp, 2, 100, 10);
+        user.orders[1] = open_order(MarketType::Spot, 3, 105, 0);
+        indexes.update(pubkey, None, Some(&user));
+
+        assert_eq!(indexes.by_authority(&authority), vec![pubkey]);
+        assert_eq!(indexes.by_market(MarketType::Perp, 1), vec![pubkey]);
+        assert_eq!(indexes.by_market(MarketType::Perp, 2), vec![pubkey]);
+        assert_eq!(indexes.by_market(MarketType::Spot, 0), vec![pubkey]);
+        assert_eq!(indexes.by_market(MarketType::Spot, 3), vec![pubkey]);
+        assert!(indexes.by_market(MarketType::Spot, 1).is_empty());
+        assert_eq!(indexes.in_auction(110), vec![pubkey]);
+        assert!(indexes.in_auction(111).is_empty());
+
+        // orders filled and the perp position closed
+        let mut updated = user;
+        updated.perp_positions[0] = PerpPosition::default();
+        updated.orders[0] = Order::default();
+        updated.orders[1] = Order::default();
+        indexes.update(pubkey, Some(&user), Some(&updated));
+
+        assert!(indexes.by_market(MarketType::Perp, 1).is_empty());
+        assert!(indexes.by_market(MarketType::Perp, 2).is_empty());
+        assert!(indexes.by_market(MarketType::Spot, 3).is_empty());
+        assert_eq!(indexes.by_market(MarketType::Spot, 0), vec![pubkey]);
+        assert!(indexes.in_auction(100).is_empty());
+
+        indexes.update(pubkey, Some(&updated), None);
+        assert!(indexes.by_authority(&authority).is_empty());
+        assert!(indexes.by_market(MarketType::Spot, 0).is_empty());
+        assert!(indexes.by_authority.is_empty() && indexes.by_spot_market.is_empty());
+    }
+}
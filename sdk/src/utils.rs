@@ -5,8 +5,14 @@ use base64::Engine;
 use drift::state::user::MarketType;
 use serde_json::json;
 use solana_account_decoder::UiAccountData;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_request::RpcRequest,
+    rpc_response::{OptionalContext, RpcKeyedAccount},
+};
 use solana_sdk::{
-    account::Account, address_lookup_table_account::AddressLookupTableAccount, bs58,
+    account::Account, address_lookup_table_account::AddressLookupTableAccount, bs58, clock::Slot,
     pubkey::Pubkey, signature::Keypair,
 };
 
@@ -104,7 +110,7 @@ pub fn get_ws_url(url: &str) -> Result<String, &'static str> {
 pub fn dlob_subscribe_ws_json(market: &str) -> String {
     json!({
         "type": "subscribe",
-        "marketType": if market.ends_with("perp") {
+        "marketType": if market.to_lowercase().ends_with("perp") {
             "perp"
         } else {
             "spot"
@@ -131,6 +137,44 @@ where
     T::try_deserialize(&mut decoded_data_slice).map_err(|err| SdkError::Anchor(Box::new(err)))
 }
 
+/// Fetch the drift program accounts matching `config`, returns (context slot, accounts)
+pub async fn get_program_accounts(
+    rpc_client: &RpcClient,
+    mut config: RpcProgramAccountsConfig,
+) -> SdkResult<(Slot, Vec<(Pubkey, Account)>)> {
+    config.with_context = Some(true);
+    let response = rpc_client
+        .send::<OptionalContext<Vec<RpcKeyedAccount>>>(
+            RpcRequest::GetProgramAccounts,
+            json!([drift::id().to_string(), config]),
+        )
+        .await?;
+    let OptionalContext::Context(accounts) = response else {
+        return Err(SdkError::Generic(
+            "getProgramAccounts response without context".to_string(),
+        ));
+    };
+
+    let slot = accounts.context.slot;
+    let accounts = accounts
+        .value
+        .into_iter()
+        .map(|keyed| {
+            let pubkey = keyed
+                .pubkey
+                .parse::<Pubkey>()
+                .map_err(|_| SdkError::Deserializing)?;
+            let account = keyed
+                .account
+                .decode::<Account>()
+                .ok_or(SdkError::Deserializing)?;
+            Ok((pubkey, account))
+        })
+        .collect::<SdkResult<Vec<_>>>()?;
+
+    Ok((slot, accounts))
+}
+
 pub(crate) fn market_type_to_string(market_type: &MarketType) -> String {
     match market_type {
         MarketType::Perp => "perp".to_string(),
@@ -143,6 +187,14 @@ pub fn deserialize_account<T: anchor_lang::AccountDeserialize>(data: &mut &[u8])
     T::try_deserialize(data).ok()
 }
 
+/// Encode a user account name, space padded (and truncated) to 32 bytes as the program expects
+pub fn encode_name(name: &str) -> [u8; 32] {
+    let mut encoded = [b' '; 32];
+    let len = name.len().min(32);
+    encoded[..len].copy_from_slice(&name.as_bytes()[..len]);
+    encoded
+}
+
 pub(crate) fn zero_account_to_bytes<T: bytemuck::Pod + anchor_lang::Discriminator>(
     account: T,
 ) -> Vec<u8> {
@@ -213,4 +265,14 @@ mod tests {
         let http_url = "http://dlob.drift.trade";
         assert!(http_to_ws(http_url).unwrap() == "ws://dlob.drift.trade/ws")
     }
+
+    #[test]
+    fn test_encode_name() {
+        let encoded = encode_name("Main Account");
+        assert_eq!(&encoded[..12], b"Main Account");
+        assert!(encoded[12..].iter().all(|b| *b == b' '));
+
+        let long_name = "a".repeat(40);
+        assert_eq!(encode_name(&long_name), [b'a'; 32]);
+    }
 }
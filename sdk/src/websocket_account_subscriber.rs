@@ -1,12 +1,16 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use anchor_lang::AccountDeserialize;
-use futures_util::StreamExt;
+use futures_util::{future::BoxFuture, FutureExt, StreamExt};
 use solana_account_decoder::{UiAccount, UiAccountEncoding};
-use solana_client::{nonblocking::pubsub_client::PubsubClient, rpc_config::RpcAccountInfoConfig};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::RpcAccountInfoConfig,
+};
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
 
 use crate::{
+    accounts::{AccountSubscriber, OnUpdate, SubscribedAccount},
     error::SdkError,
     event_emitter::{Event, EventEmitter},
     types::DataAndSlot,
@@ -50,12 +54,15 @@ where
 
     unsubscriber: Option<tokio::sync::mpsc::Sender<()>>,
 
-    pub(crate) data_and_slot: Arc<Mutex<Option<DataAndSlot<T>>>>,
+    account: SubscribedAccount<T>,
+
+    /// client for `fetch`es, fetching is a no-op without one
+    rpc_client: Option<Arc<RpcClient>>,
 }
 
 impl<T> WebsocketAccountSubscriber<T>
 where
-    T: AccountDeserialize + Send + 'static,
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
 {
     pub fn new(
         subscription_name: &'static str,
@@ -72,10 +79,17 @@ where
             subscribed: false,
             event_emitter,
             unsubscriber: None,
-            data_and_slot: Arc::new(Mutex::new(None)),
+            account: SubscribedAccount::new(pubkey),
+            rpc_client: None,
         }
     }
 
+    /// Set the RPC client used to `fetch` the account
+    pub fn with_rpc_client(mut self, rpc_client: Arc<RpcClient>) -> Self {
+        self.rpc_client = Some(rpc_client);
+        self
+    }
+
     pub async fn subscribe(&mut self) -> SdkResult<()> {
         if self.subscribed {
             return Ok(());
@@ -100,7 +114,7 @@ where
         let base_delay = tokio::time::Duration::from_secs(2);
 
         let url = self.url.clone();
-        let data_and_slot = self.data_and_slot.clone();
+        let account = self.account.clone();
 
         tokio::spawn({
             let event_emitter = self.event_emitter.clone();
@@ -131,10 +145,14 @@ where
                                                     slot,
                                                 };
                                                 event_emitter.emit(subscription_name, Box::new(account_update));
-                                                let new_data = decode::<T>(message.value.data.clone()).expect("valid state data");
-                                                let mut data_and_slot = data_and_slot.lock().unwrap();
-                                                *data_and_slot = Some(DataAndSlot {slot, data: new_data } );
-                                                drop(data_and_slot);
+                                                match decode::<T>(message.value.data.clone()) {
+                                                    Ok(new_data) => {
+                                                        account.update(new_data, slot);
+                                                    }
+                                                    Err(e) => {
+                                                        log::error!("{subscription_name}: Error decoding account data {e}");
+                                                    }
+                                                }
                                             }
                                         }
                                         None => {
@@ -192,6 +210,41 @@ where
     }
 
     pub async fn fetch(&mut self) -> SdkResult<()> {
-        Ok(())
+        match self.rpc_client {
+            Some(ref rpc_client) => self.account.fetch(rpc_client).await,
+            None => Ok(()),
+        }
+    }
+
+    /// The latest account value, None until an update is received
+    pub fn data_and_slot(&self) -> Option<DataAndSlot<T>> {
+        self.account.get()
+    }
+}
+
+impl<T> AccountSubscriber<T> for WebsocketAccountSubscriber<T>
+where
+    T: AccountDeserialize + Clone + Send + Sync + 'static,
+{
+    fn subscribe(&mut self, on_update: Option<OnUpdate<T>>) -> BoxFuture<SdkResult<()>> {
+        if !self.subscribed {
+            self.account.set_on_update(on_update);
+        }
+        WebsocketAccountSubscriber::subscribe(self).boxed()
+    }
+    fn fetch(&mut self) -> BoxFuture<SdkResult<()>> {
+        WebsocketAccountSubscriber::fetch(self).boxed()
+    }
+    fn unsubscribe(&mut self) -> BoxFuture<SdkResult<()>> {
+        WebsocketAccountSubscriber::unsubscribe(self).boxed()
+    }
+    fn set_data(&self, data: T, slot: u64) {
+        self.account.update(data, slot);
+    }
+    fn data_and_slot(&self) -> Option<DataAndSlot<T>> {
+        self.account.get()
+    }
+    fn is_subscribed(&self) -> bool {
+        self.subscribed
     }
 }